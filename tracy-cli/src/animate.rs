@@ -0,0 +1,248 @@
+//! The `animate` command.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+};
+
+use anyhow::{anyhow, Context};
+use clap::ArgMatches;
+use rayon::ThreadPoolBuilder;
+use tracy::{
+    math::{Matrix, Point3, Vec3},
+    rendering::{Camera, ToneMapping},
+};
+
+use crate::{
+    args::{parse_number, parse_size},
+    config::Config,
+    output, progress, render, scene, Failure,
+};
+
+/// Options of the `animate` command.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Scene file to animate.
+    pub scene: PathBuf,
+    /// Number of frames to render.
+    pub frames: u32,
+    /// Frame rate of the animation.
+    pub fps: u32,
+    /// Degrees of camera rotation around the scene over the whole animation.
+    pub orbit: Option<f32>,
+    /// Directory to write the numbered frames to, `None` to skip writing them.
+    pub output: Option<PathBuf>,
+    /// Video file to encode the frames to with `ffmpeg`.
+    pub video: Option<PathBuf>,
+    /// Resolution of the frames, `None` to keep the scene camera's.
+    pub size: Option<(u32, u32)>,
+    /// Supersampling grid size along each axis.
+    pub samples: u32,
+    /// Maximum recursion depth, `None` to keep the scene's.
+    pub depth: Option<u32>,
+    /// Number of rendering threads, `None` to use all cores.
+    pub threads: Option<usize>,
+    /// Display transform applied to the frames before they are saved.
+    pub tone_mapping: ToneMapping,
+}
+
+impl Options {
+    /// Reads the options from the command line, falling back to the user's `config`.
+    pub fn parse(matches: &ArgMatches, config: &Config) -> Result<Self, Failure> {
+        let orbit = if matches.is_present("orbit") {
+            Some(parse_number(matches, "orbit")?.unwrap_or(360.0))
+        } else {
+            None
+        };
+
+        let video = matches.value_of("video").map(PathBuf::from);
+
+        // Frames are only kept on disk by default when they are not encoded to a video
+        let output = match matches.value_of("output") {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None if video.is_none() => Some(config.output_path("frames")),
+            None => None,
+        };
+
+        Ok(Self {
+            scene: PathBuf::from(matches.value_of("scene").unwrap()),
+            frames: parse_number(matches, "frames")?.unwrap_or(120).max(1),
+            fps: parse_number(matches, "fps")?.unwrap_or(24).max(1),
+            orbit,
+            output,
+            video,
+            size: matches
+                .value_of("size")
+                .map(parse_size)
+                .transpose()?
+                .or_else(|| config.size()),
+            samples: parse_number(matches, "samples")?
+                .or(config.samples)
+                .unwrap_or(1)
+                .max(1),
+            depth: parse_number(matches, "depth")?,
+            threads: parse_number(matches, "threads")?.or(config.threads),
+            tone_mapping: config.tone_mapping(),
+        })
+    }
+}
+
+/// Renders every frame of the animation, writing them as a numbered PNG sequence and/or piping
+/// them to `ffmpeg`.
+pub fn run(options: &Options) -> Result<(), Failure> {
+    let orbit = options.orbit.ok_or_else(|| {
+        Failure::Usage(
+            "scene files cannot describe keyframes yet, use --orbit to animate the camera".into(),
+        )
+    })?;
+
+    let (world, mut camera) = scene::load(&options.scene)?;
+
+    if let Some((width, height)) = options.size {
+        camera.set_size(width, height);
+    }
+    if let Some(depth) = options.depth {
+        camera.set_recursion_limit(depth);
+    }
+    render::set_samples(&mut camera, options.samples);
+
+    if let Some(dir) = &options.output {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))
+            .map_err(Failure::Output)?;
+    }
+
+    let mut encoder = options
+        .video
+        .as_ref()
+        .map(|path| Encoder::spawn(path, options.fps))
+        .transpose()
+        .map_err(Failure::Unavailable)?;
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(options.threads.unwrap_or(0))
+        .build()
+        .expect("failed to create rendering thread pool");
+
+    let turntable = Turntable::new(&camera);
+    let title = options.scene.display().to_string();
+
+    for frame in 0..options.frames {
+        let angle = orbit.to_radians() * frame as f32 / options.frames as f32;
+        camera.set_view_transform(turntable.view_transform(angle));
+
+        let progress = render::progress_of(&camera);
+
+        let label = format!("frame {}/{}", frame + 1, options.frames);
+        let canvas = progress::report(&label, &progress, || {
+            pool.install(|| render::render_with_progress(&world, &camera, &progress))
+        });
+        let canvas = options.tone_mapping.apply(&canvas);
+
+        if let Some(dir) = &options.output {
+            let path = dir.join(format!("frame_{:04}.png", frame));
+
+            let mut metadata = output::metadata(&title, &world, &camera);
+            metadata.push(("Frame", frame.to_string()));
+            metadata.push(("Frames per second", options.fps.to_string()));
+
+            output::save(&canvas, &path, &metadata)
+                .with_context(|| format!("failed to save {}", path.display()))
+                .map_err(Failure::Output)?;
+        }
+
+        if let Some(encoder) = &mut encoder {
+            encoder
+                .write(&canvas.convert_to_binary_ppm())
+                .map_err(Failure::Output)?;
+        }
+    }
+
+    match encoder {
+        Some(encoder) => encoder.finish().map_err(Failure::Output),
+        None => Ok(()),
+    }
+}
+
+/// A camera rotating around the vertical axis through its target.
+#[derive(Debug)]
+struct Turntable {
+    view: Matrix,
+    target: Point3,
+}
+
+impl Turntable {
+    /// Creates a turntable starting from the current view of `camera`.
+    ///
+    /// The target is placed along the view direction, at the point closest to the origin.
+    fn new(camera: &Camera) -> Self {
+        let inv = camera.view_transform().inverse().unwrap();
+
+        let eye = &inv * Point3::new(0.0, 0.0, 0.0);
+        let fwd = (&inv * Vec3::new(0.0, 0.0, -1.0)).normalize();
+        let distance = (Point3::default() - eye).dot(&fwd).max(1.0);
+
+        Self {
+            view: camera.view_transform().clone(),
+            target: eye + fwd * distance,
+        }
+    }
+
+    /// Returns the view transform of the camera rotated by `angle` radians around the target.
+    fn view_transform(&self, angle: f32) -> Matrix {
+        let Point3 { x, y, z, .. } = self.target;
+
+        // Rotating the camera around the target is the same as rotating the world the other way
+        &self.view
+            * Matrix::from_translation(x, y, z)
+            * Matrix::from_rotation_y(-angle)
+            * Matrix::from_translation(-x, -y, -z)
+    }
+}
+
+/// A running `ffmpeg` process encoding a video from the frames written to its input.
+#[derive(Debug)]
+struct Encoder {
+    child: Child,
+    stdin: ChildStdin,
+}
+
+impl Encoder {
+    fn spawn(path: &Path, fps: u32) -> anyhow::Result<Self> {
+        let mut child = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "image2pipe", "-c:v", "ppm"])
+            .args(["-framerate", &fps.to_string()])
+            .args(["-i", "-", "-pix_fmt", "yuv420p"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to start ffmpeg")?;
+
+        let stdin = child.stdin.take().unwrap();
+
+        Ok(Self { child, stdin })
+    }
+
+    fn write(&mut self, frame: &[u8]) -> anyhow::Result<()> {
+        self.stdin
+            .write_all(frame)
+            .context("failed to write frame to ffmpeg")
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        let Self { mut child, stdin } = self;
+
+        // Closing the input signals the end of the video
+        drop(stdin);
+
+        let status = child.wait().context("failed to wait for ffmpeg")?;
+        if !status.success() {
+            return Err(anyhow!("ffmpeg failed with {}", status));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! Parsing of command line values.
+
+use clap::ArgMatches;
+
+use crate::Failure;
+
+/// Parses the optional numeric argument `name`.
+pub fn parse_number<T: std::str::FromStr>(
+    matches: &ArgMatches,
+    name: &str,
+) -> Result<Option<T>, Failure> {
+    matches
+        .value_of(name)
+        .map(|s| {
+            s.parse()
+                .map_err(|_| Failure::Usage(format!("invalid value for --{}: {}", name, s)))
+        })
+        .transpose()
+}
+
+/// Parses a resolution in the `<WIDTH>x<HEIGHT>` format.
+pub fn parse_size(s: &str) -> Result<(u32, u32), Failure> {
+    let invalid = || Failure::Usage(format!("invalid size {}, expected <WIDTH>x<HEIGHT>", s));
+
+    let (w, h) = s.split_once('x').ok_or_else(invalid)?;
+    let width = w.parse::<u32>().map_err(|_| invalid())?;
+    let height = h.parse::<u32>().map_err(|_| invalid())?;
+
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{App, Arg};
+
+    use super::*;
+
+    fn matches(args: &[&str]) -> ArgMatches<'static> {
+        App::new("test")
+            .arg(Arg::with_name("threads").long("threads").takes_value(true))
+            .get_matches_from(std::iter::once("test").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn parsing_sizes() {
+        assert_eq!(parse_size("640x480").unwrap(), (640, 480));
+        assert_eq!(parse_size("1x1").unwrap(), (1, 1));
+
+        for s in &[
+            "640",
+            "640x",
+            "x480",
+            "640x480x2",
+            "0x480",
+            "640x0",
+            "-1x480",
+            "a x b",
+        ] {
+            assert!(matches!(parse_size(s), Err(Failure::Usage(_))), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parsing_numbers() {
+        assert_eq!(
+            parse_number::<usize>(&matches(&[]), "threads").unwrap(),
+            None
+        );
+        assert_eq!(
+            parse_number::<usize>(&matches(&["--threads", "4"]), "threads").unwrap(),
+            Some(4)
+        );
+
+        match parse_number::<usize>(&matches(&["--threads", "four"]), "threads") {
+            Err(Failure::Usage(msg)) => assert_eq!(msg, "invalid value for --threads: four"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
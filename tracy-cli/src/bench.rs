@@ -0,0 +1,300 @@
+//! The `bench` command.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+use rayon::ThreadPoolBuilder;
+use serde::Serialize;
+
+use crate::{
+    args::{parse_number, parse_size},
+    config::Config,
+    render,
+    scene::{self, Format},
+    Failure,
+};
+
+/// Reference scenes embedded in the binary, benchmarked when no scene file is given.
+const BUNDLED_SCENES: [(&str, &str); 12] = [
+    ("chapter5", include_str!("../../scenes/ch05.yml")),
+    ("chapter6", include_str!("../../scenes/ch06.yml")),
+    ("chapter7", include_str!("../../scenes/ch07.yml")),
+    ("chapter8", include_str!("../../scenes/ch08.yml")),
+    ("chapter9", include_str!("../../scenes/ch09.yml")),
+    ("chapter10a", include_str!("../../scenes/ch10a.yml")),
+    ("chapter10b", include_str!("../../scenes/ch10b.yml")),
+    ("chapter10c", include_str!("../../scenes/ch10c.yml")),
+    ("chapter11", include_str!("../../scenes/ch11.yml")),
+    ("chapter12", include_str!("../../scenes/ch12.yml")),
+    ("chapter13", include_str!("../../scenes/ch13.yml")),
+    ("cover", include_str!("../../scenes/cover.yml")),
+];
+
+/// Format of the benchmark results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Csv,
+}
+
+/// Options of the `bench` command.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Scene files to benchmark, empty for the bundled scenes.
+    pub scenes: Vec<PathBuf>,
+    /// Number of untimed runs before the timed ones.
+    pub warmup: u32,
+    /// Number of timed runs.
+    pub runs: u32,
+    /// Resolution of the renders, `None` to keep each scene camera's.
+    pub size: Option<(u32, u32)>,
+    /// Number of rendering threads, `None` to use all cores.
+    pub threads: Option<usize>,
+    /// Format of the results.
+    pub format: ReportFormat,
+    /// File to write the results to, `None` for the standard output.
+    pub output: Option<PathBuf>,
+}
+
+impl Options {
+    /// Reads the options from the command line, falling back to the user's `config`.
+    ///
+    /// Only the thread count is taken from `config`, so that results stay comparable between
+    /// users.
+    pub fn parse(matches: &ArgMatches, config: &Config) -> Result<Self, Failure> {
+        let format = match matches.value_of("format") {
+            Some("csv") => ReportFormat::Csv,
+            _ => ReportFormat::Json,
+        };
+
+        Ok(Self {
+            scenes: matches
+                .values_of("scenes")
+                .map(|v| v.map(PathBuf::from).collect())
+                .unwrap_or_default(),
+            warmup: parse_number(matches, "warmup")?.unwrap_or(1),
+            runs: parse_number(matches, "runs")?.unwrap_or(5).max(1),
+            size: matches.value_of("size").map(parse_size).transpose()?,
+            threads: parse_number(matches, "threads")?.or(config.threads),
+            format,
+            output: matches.value_of("output").map(PathBuf::from),
+        })
+    }
+}
+
+/// Timing statistics of a single stage of the rendering pipeline.
+#[derive(Debug, Serialize)]
+pub struct StageTimes {
+    /// Name of the stage.
+    pub stage: &'static str,
+    /// Fastest run, in milliseconds.
+    pub min_ms: f64,
+    /// Average run, in milliseconds.
+    pub mean_ms: f64,
+    /// Median run, in milliseconds.
+    pub median_ms: f64,
+    /// Slowest run, in milliseconds.
+    pub max_ms: f64,
+    /// All the timed runs, in milliseconds.
+    pub runs_ms: Vec<f64>,
+}
+
+impl StageTimes {
+    fn new(stage: &'static str, runs: &[Duration]) -> Self {
+        let runs_ms = runs
+            .iter()
+            .map(|d| d.as_secs_f64() * 1e3)
+            .collect::<Vec<_>>();
+
+        let mut sorted = runs_ms.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let n = sorted.len();
+        let median_ms = if n % 2 == 0 {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+        } else {
+            sorted[n / 2]
+        };
+
+        Self {
+            stage,
+            min_ms: sorted[0],
+            mean_ms: sorted.iter().sum::<f64>() / n as f64,
+            median_ms,
+            max_ms: sorted[n - 1],
+            runs_ms,
+        }
+    }
+}
+
+/// Benchmark results of a single scene.
+#[derive(Debug, Serialize)]
+pub struct SceneReport {
+    /// Name of the scene.
+    pub scene: String,
+    /// Resolution of the renders.
+    pub width: u32,
+    /// Resolution of the renders.
+    pub height: u32,
+    /// Timings of the parsing, building and rendering stages.
+    pub stages: Vec<StageTimes>,
+}
+
+/// Results of a whole benchmark session.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// Version of the `tracy` crate being benchmarked.
+    pub version: &'static str,
+    /// Number of rendering threads.
+    pub threads: usize,
+    /// Number of untimed runs of each scene.
+    pub warmup: u32,
+    /// Number of timed runs of each scene.
+    pub runs: u32,
+    /// Results of each scene.
+    pub scenes: Vec<SceneReport>,
+}
+
+impl Report {
+    /// Formats the report as CSV, with one row per scene and stage.
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            String::from("scene,stage,width,height,runs,min_ms,mean_ms,median_ms,max_ms\n");
+
+        for scene in &self.scenes {
+            for stage in &scene.stages {
+                csv += &format!(
+                    "{},{},{},{},{},{:.3},{:.3},{:.3},{:.3}\n",
+                    scene.scene,
+                    stage.stage,
+                    scene.width,
+                    scene.height,
+                    stage.runs_ms.len(),
+                    stage.min_ms,
+                    stage.mean_ms,
+                    stage.median_ms,
+                    stage.max_ms,
+                );
+            }
+        }
+
+        csv
+    }
+}
+
+/// Benchmarks the scenes and writes the report.
+pub fn run(options: &Options) -> Result<(), Failure> {
+    let scenes = if options.scenes.is_empty() {
+        BUNDLED_SCENES
+            .iter()
+            .map(|&(name, data)| (name.to_string(), data.to_string(), Format::Yaml))
+            .collect::<Vec<_>>()
+    } else {
+        options
+            .scenes
+            .iter()
+            .map(|path| read_scene(path))
+            .collect::<Result<_, _>>()?
+    };
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(options.threads.unwrap_or(0))
+        .build()
+        .expect("failed to create rendering thread pool");
+
+    let reports = pool.install(|| {
+        scenes
+            .iter()
+            .map(|(name, data, format)| bench_scene(name, data, *format, options))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    let report = Report {
+        version: tracy::VERSION,
+        threads: pool.current_num_threads(),
+        warmup: options.warmup,
+        runs: options.runs,
+        scenes: reports,
+    };
+
+    let text = match options.format {
+        ReportFormat::Json => serde_json::to_string_pretty(&report).unwrap() + "\n",
+        ReportFormat::Csv => report.to_csv(),
+    };
+
+    match &options.output {
+        Some(path) => fs::write(path, text)
+            .with_context(|| format!("failed to write {}", path.display()))
+            .map_err(Failure::Output),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+/// Reads a scene file, returning its name, contents and format.
+fn read_scene(path: &Path) -> Result<(String, String, Format), Failure> {
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))
+        .map_err(Failure::Input)?;
+
+    Ok((path.display().to_string(), data, Format::of(path)))
+}
+
+/// Times each stage of the rendering of a scene.
+fn bench_scene(
+    name: &str,
+    data: &str,
+    format: Format,
+    options: &Options,
+) -> Result<SceneReport, Failure> {
+    let mut parse_times = Vec::new();
+    let mut build_times = Vec::new();
+    let mut render_times = Vec::new();
+    let mut size = (0, 0);
+
+    for run in 0..options.warmup + options.runs {
+        let start = Instant::now();
+        let prefab = scene::parse(data, format)
+            .with_context(|| format!("invalid scene {}", name))
+            .map_err(Failure::InvalidData)?;
+        let parsed = Instant::now();
+        let (world, mut camera) = prefab
+            .try_build()
+            .with_context(|| format!("invalid scene {}", name))
+            .map_err(Failure::InvalidData)?;
+        let built = Instant::now();
+
+        if let Some((width, height)) = options.size {
+            camera.set_size(width, height);
+        }
+        size = (camera.horizontal_size(), camera.vertical_size());
+
+        let render_start = Instant::now();
+        render::render(&world, &camera);
+        let rendered = Instant::now();
+
+        if run >= options.warmup {
+            parse_times.push(parsed - start);
+            build_times.push(built - parsed);
+            render_times.push(rendered - render_start);
+        }
+    }
+
+    Ok(SceneReport {
+        scene: name.to_string(),
+        width: size.0,
+        height: size.1,
+        stages: vec![
+            StageTimes::new("parse", &parse_times),
+            StageTimes::new("build", &build_times),
+            StageTimes::new("render", &render_times),
+        ],
+    })
+}
@@ -0,0 +1,181 @@
+//! Default render settings, read from the user's configuration file.
+//!
+//! The file is looked up, in order, at the path given with `--config`, at the path in the
+//! `TRACY_CONFIG` environment variable, and at `tracy/config.toml` within the user's configuration
+//! directory, eg. `~/.config/tracy/config.toml`. It is shared with `tracy-ui`:
+//!
+//! ```toml
+//! size = "1920x1080"
+//! samples = 2
+//! threads = 8
+//! output_dir = "renders"
+//!
+//! [tonemap]
+//! operator = "Aces"
+//! exposure = 0.5
+//! gamma = 2.2
+//! ```
+//!
+//! Every setting is optional, and options given on the command line take precedence.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use serde::Deserialize;
+use tracy::rendering::ToneMapping;
+
+use crate::{args::parse_size, Failure};
+
+/// Name of the configuration file, within the user's configuration directory.
+const CONFIG_FILE: &str = "tracy/config.toml";
+
+/// Environment variable overriding the location of the configuration file.
+const CONFIG_ENV_VAR: &str = "TRACY_CONFIG";
+
+/// Default render settings.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Resolution of the output images, in the `<WIDTH>x<HEIGHT>` format.
+    size: Option<String>,
+    /// Supersampling grid size along each axis.
+    pub samples: Option<u32>,
+    /// Number of rendering threads.
+    pub threads: Option<usize>,
+    /// Directory where output images are written, unless given a path on the command line.
+    pub output_dir: Option<PathBuf>,
+    /// Display transform applied to the images before they are saved.
+    pub tonemap: Option<ToneMapping>,
+}
+
+impl Config {
+    /// Loads the configuration from `path` if given, or from its default location otherwise.
+    ///
+    /// A missing file at the default location is not an error, while a file given explicitly
+    /// must exist.
+    pub fn load(path: Option<&Path>) -> Result<Self, Failure> {
+        let (path, required) = match (path, env::var_os(CONFIG_ENV_VAR)) {
+            (Some(path), _) => (path.to_path_buf(), true),
+            (None, Some(path)) => (PathBuf::from(path), true),
+            (None, None) => match config_dir() {
+                Some(dir) => (dir.join(CONFIG_FILE), false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Self::default())
+            }
+            Err(e) => {
+                return Err(Failure::Input(anyhow!(
+                    "failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+
+        let config: Self = toml::from_str(&data)
+            .map_err(|e| Failure::InvalidData(anyhow!("{}: {}", path.display(), e)))?;
+
+        if let Some(size) = &config.size {
+            parse_size(size)
+                .map_err(|e| Failure::InvalidData(anyhow!("{}: {}", path.display(), e)))?;
+        }
+
+        Ok(config)
+    }
+
+    /// Returns the resolution of the output images, if any.
+    pub fn size(&self) -> Option<(u32, u32)> {
+        self.size.as_deref().and_then(|s| parse_size(s).ok())
+    }
+
+    /// Returns the display transform to apply to the images before they are saved.
+    pub fn tone_mapping(&self) -> ToneMapping {
+        self.tonemap.unwrap_or_default()
+    }
+
+    /// Returns the path of an output file named `name`, within the output directory if any.
+    pub fn output_path(&self, name: &str) -> PathBuf {
+        match &self.output_dir {
+            Some(dir) => dir.join(name),
+            None => PathBuf::from(name),
+        }
+    }
+}
+
+/// Returns the user's configuration directory, if any.
+fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| env::var_os("APPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    /// Writes `data` to a configuration file unique to this process and test.
+    fn config_file(name: &str, data: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("tracy-cli-{}-{}.toml", process::id(), name));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn loading_a_configuration_file() {
+        let path = config_file(
+            "load",
+            "size = \"640x480\"\nsamples = 2\noutput_dir = \"renders\"\n",
+        );
+        let config = Config::load(Some(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.size(), Some((640, 480)));
+        assert_eq!(config.samples, Some(2));
+        assert_eq!(config.threads, None);
+        assert_eq!(
+            config.output_path("a.png"),
+            Path::new("renders").join("a.png")
+        );
+    }
+
+    #[test]
+    fn falling_back_to_the_defaults() {
+        let config = Config::default();
+
+        assert_eq!(config.size(), None);
+        assert_eq!(config.tone_mapping(), ToneMapping::default());
+        assert_eq!(config.output_path("a.png"), PathBuf::from("a.png"));
+    }
+
+    #[test]
+    fn rejecting_invalid_configuration_files() {
+        let missing = env::temp_dir().join(format!("tracy-cli-{}-missing.toml", process::id()));
+        assert!(matches!(
+            Config::load(Some(&missing)),
+            Err(Failure::Input(_))
+        ));
+
+        for (name, data) in &[
+            ("size", "size = \"640\"\n"),
+            ("unknown", "resolution = \"640x480\"\n"),
+            ("syntax", "samples = \n"),
+        ] {
+            let path = config_file(name, data);
+            let result = Config::load(Some(&path));
+            fs::remove_file(&path).unwrap();
+
+            assert!(matches!(result, Err(Failure::InvalidData(_))), "{}", name);
+        }
+    }
+}
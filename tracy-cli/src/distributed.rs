@@ -0,0 +1,699 @@
+//! The `coordinator` and `worker` commands, spreading a render across several processes.
+//!
+//! The coordinator splits the frame into tiles and waits for workers to connect over TCP. Each
+//! worker receives the scene, then renders the tiles handed out by the coordinator one at a time,
+//! sending back their pixels. Tiles assigned to a worker that disconnects are handed out again,
+//! so workers are only dismissed, and the image saved, once every tile has been received.
+//!
+//! Messages are exchanged as single lines of JSON of at most [`MAX_LINE`] bytes, with the pixels
+//! of a tile following their [`Message::Pixels`] header as little-endian `f32` RGB triples.
+//!
+//! Workers which take longer than the timeout of the coordinator to send back a tile are dropped,
+//! and their tile is handed out again. Writes on either side fail after [`WRITE_TIMEOUT`].
+
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{mpsc, Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ArgMatches;
+use rayon::{
+    iter::{IntoParallelIterator, ParallelIterator},
+    ThreadPoolBuilder,
+};
+use serde::{Deserialize, Serialize};
+use tracy::rendering::{Canvas, Color, ToneMapping};
+
+use crate::{
+    args::{parse_number, parse_size},
+    config::Config,
+    output, render,
+    scene::{self, Format},
+    Failure,
+};
+
+/// Longest message line accepted, including its newline, large enough for most scene files.
+const MAX_LINE: u64 = 64 * 1024 * 1024;
+
+/// How long writing a message may block before the connection is considered lost.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a worker waits for the scene after connecting, and for tiles beyond the time the
+/// coordinator may keep it idle.
+const WORKER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options of the `coordinator` command.
+#[derive(Debug, Clone)]
+pub struct CoordinatorOptions {
+    /// Scene file to render.
+    pub scene: PathBuf,
+    /// Output image.
+    pub output: PathBuf,
+    /// Address to listen on for workers.
+    pub bind: String,
+    /// Side of the square tiles handed out to workers, in pixels.
+    pub tile_size: u32,
+    /// How long a worker may take to render a tile before it is handed to another worker.
+    pub timeout: Duration,
+    /// Resolution of the output image, `None` to keep the scene camera's.
+    pub size: Option<(u32, u32)>,
+    /// Supersampling grid size along each axis.
+    pub samples: u32,
+    /// Maximum recursion depth, `None` to keep the scene's.
+    pub depth: Option<u32>,
+    /// Display transform applied to the image before it is saved.
+    pub tone_mapping: ToneMapping,
+}
+
+impl CoordinatorOptions {
+    /// Reads the options from the command line, falling back to the user's `config`.
+    pub fn parse(matches: &ArgMatches, config: &Config) -> Result<Self, Failure> {
+        Ok(Self {
+            scene: PathBuf::from(matches.value_of("scene").unwrap()),
+            output: matches
+                .value_of("output")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| config.output_path("render.png")),
+            bind: matches.value_of("bind").unwrap().to_string(),
+            tile_size: parse_number(matches, "tile-size")?.unwrap_or(32).max(1),
+            timeout: Duration::from_secs(parse_number(matches, "timeout")?.unwrap_or(300).max(1)),
+            size: matches
+                .value_of("size")
+                .map(parse_size)
+                .transpose()?
+                .or_else(|| config.size()),
+            samples: parse_number(matches, "samples")?
+                .or(config.samples)
+                .unwrap_or(1)
+                .max(1),
+            depth: parse_number(matches, "depth")?,
+            tone_mapping: config.tone_mapping(),
+        })
+    }
+}
+
+/// Options of the `worker` command.
+#[derive(Debug, Clone)]
+pub struct WorkerOptions {
+    /// Address of the coordinator.
+    pub coordinator: String,
+    /// Number of rendering threads, `None` to use all cores.
+    pub threads: Option<usize>,
+}
+
+impl WorkerOptions {
+    /// Reads the options from the command line, falling back to the user's `config`.
+    pub fn parse(matches: &ArgMatches, config: &Config) -> Result<Self, Failure> {
+        Ok(Self {
+            coordinator: matches.value_of("coordinator").unwrap().to_string(),
+            threads: parse_number(matches, "threads")?.or(config.threads),
+        })
+    }
+}
+
+/// A rectangular region of the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tile {
+    /// Column of the top-left pixel.
+    pub x: u32,
+    /// Row of the top-left pixel.
+    pub y: u32,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+}
+
+impl Tile {
+    fn pixels(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+}
+
+/// Messages exchanged between the coordinator and its workers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Message {
+    /// The scene to render, sent to workers when they connect.
+    Scene {
+        /// Contents of the scene file.
+        data: String,
+        /// Whether the scene is in RON rather than YAML format.
+        ron: bool,
+        /// Resolution of the frame.
+        width: u32,
+        /// Resolution of the frame.
+        height: u32,
+        /// Supersampling grid size along each axis.
+        samples: u32,
+        /// Maximum recursion depth.
+        depth: u32,
+        /// How long the coordinator waits for the pixels of a tile, in milliseconds.
+        timeout: u64,
+    },
+    /// A tile for the worker to render.
+    Tile(Tile),
+    /// The rendered pixels of a tile, followed by their binary data.
+    Pixels(Tile),
+    /// There are no more tiles to render.
+    Done,
+}
+
+/// Progress of the frame being assembled by the coordinator.
+#[derive(Debug)]
+struct Frame {
+    canvas: Canvas,
+    pending: VecDeque<Tile>,
+    remaining: usize,
+}
+
+/// The frame being assembled, shared between the threads serving workers.
+#[derive(Debug)]
+struct SharedFrame {
+    frame: Mutex<Frame>,
+    /// Signaled when a tile is returned to the queue or the last tile is received.
+    changed: Condvar,
+}
+
+/// Hands out tiles of the scene to workers and saves the assembled image.
+pub fn coordinate(options: &CoordinatorOptions) -> Result<(), Failure> {
+    let data = fs::read_to_string(&options.scene)
+        .with_context(|| format!("failed to read {}", options.scene.display()))
+        .map_err(Failure::Input)?;
+    let format = Format::of(&options.scene);
+
+    let (world, mut camera) = scene::parse(&data, format)
+        .and_then(|prefab| Ok(prefab.try_build()?))
+        .with_context(|| format!("invalid scene {}", options.scene.display()))
+        .map_err(Failure::InvalidData)?;
+
+    if let Some((width, height)) = options.size {
+        camera.set_size(width, height);
+    }
+    if let Some(depth) = options.depth {
+        camera.set_recursion_limit(depth);
+    }
+
+    let (width, height) = (camera.horizontal_size(), camera.vertical_size());
+    let tiles = split(width, height, options.tile_size);
+
+    render::set_samples(&mut camera, options.samples);
+
+    let hello = Message::Scene {
+        data,
+        ron: format == Format::Ron,
+        width,
+        height,
+        samples: options.samples,
+        depth: camera.recursion_limit_for(&world),
+        timeout: options.timeout.as_millis() as u64,
+    };
+
+    let listener = TcpListener::bind(&options.bind)
+        .with_context(|| format!("failed to listen on {}", options.bind))
+        .map_err(Failure::Unavailable)?;
+
+    eprintln!(
+        "waiting for workers on {}, {} tiles to render",
+        options.bind,
+        tiles.len()
+    );
+
+    let canvas = assemble(
+        listener,
+        hello,
+        Canvas::new(width, height),
+        tiles,
+        options.timeout,
+    );
+
+    let canvas = options.tone_mapping.apply(&canvas);
+    let metadata = output::metadata(&options.scene.display().to_string(), &world, &camera);
+
+    output::save(&canvas, &options.output, &metadata)
+        .with_context(|| format!("failed to save {}", options.output.display()))
+        .map_err(Failure::Output)
+}
+
+/// Hands out `tiles` to the workers connecting to `listener`, returning `canvas` once they have
+/// drawn every tile onto it.
+///
+/// Workers are sent `hello` first, and are dropped if they take longer than `timeout` to send
+/// back a tile.
+fn assemble(
+    listener: TcpListener,
+    hello: Message,
+    canvas: Canvas,
+    tiles: Vec<Tile>,
+    timeout: Duration,
+) -> Canvas {
+    let total = tiles.len();
+    let shared = Arc::new(SharedFrame {
+        frame: Mutex::new(Frame {
+            canvas,
+            pending: tiles.into(),
+            remaining: total,
+        }),
+        changed: Condvar::new(),
+    });
+
+    let (done_tx, done_rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let (shared, hello, done_tx) = (shared.clone(), hello.clone(), done_tx.clone());
+
+            thread::spawn(move || {
+                let peer = stream
+                    .peer_addr()
+                    .map(|a| a.to_string())
+                    .unwrap_or_default();
+
+                if let Err(e) = serve_worker(stream, &hello, &shared, total, timeout) {
+                    eprintln!("worker {} dropped: {:#}", peer, e);
+                }
+
+                let frame = shared.frame.lock().unwrap();
+                if frame.remaining == 0 {
+                    let _ = done_tx.send(frame.canvas.clone());
+                }
+            });
+        }
+    });
+
+    done_rx
+        .recv()
+        .expect("coordinator stopped accepting workers")
+}
+
+/// Feeds tiles to a single worker until every tile of the frame has been received, returning
+/// tiles it failed to render to the queue.
+///
+/// Workers finding the queue empty are kept waiting rather than dismissed, since tiles of other
+/// workers may still be returned to the queue if they disconnect.
+fn serve_worker(
+    stream: TcpStream,
+    hello: &Message,
+    shared: &SharedFrame,
+    total: usize,
+    timeout: Duration,
+) -> Result<()> {
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    send(&mut writer, hello)?;
+
+    loop {
+        let tile = {
+            let mut frame = shared.frame.lock().unwrap();
+            loop {
+                if let Some(tile) = frame.pending.pop_front() {
+                    break tile;
+                }
+                if frame.remaining == 0 {
+                    return send(&mut writer, &Message::Done);
+                }
+                frame = shared.changed.wait(frame).unwrap();
+            }
+        };
+
+        let pixels = send(&mut writer, &Message::Tile(tile))
+            .and_then(|_| receive_pixels(&mut reader, tile))
+            .with_context(|| format!("failed to render the tile at ({}, {})", tile.x, tile.y));
+
+        let mut frame = shared.frame.lock().unwrap();
+        match pixels {
+            Ok(pixels) => {
+                for (i, color) in pixels.into_iter().enumerate() {
+                    let i = i as u32;
+                    frame
+                        .canvas
+                        .put(tile.x + i % tile.width, tile.y + i / tile.width, color);
+                }
+
+                frame.remaining -= 1;
+                eprint!("\rreceived {}/{} tiles", total - frame.remaining, total);
+                if frame.remaining == 0 {
+                    eprintln!();
+                    shared.changed.notify_all();
+                }
+            }
+            Err(e) => {
+                frame.pending.push_back(tile);
+                shared.changed.notify_one();
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// Connects to a coordinator and renders the tiles it hands out.
+pub fn work(options: &WorkerOptions) -> Result<(), Failure> {
+    let stream = TcpStream::connect(&options.coordinator)
+        .with_context(|| format!("failed to connect to {}", options.coordinator))
+        .map_err(Failure::Unavailable)?;
+
+    stream
+        .set_read_timeout(Some(WORKER_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(WRITE_TIMEOUT)))
+        .map_err(|e| Failure::Unavailable(e.into()))?;
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(options.threads.unwrap_or(0))
+        .build()
+        .expect("failed to create rendering thread pool");
+
+    let mut reader = BufReader::new(
+        stream
+            .try_clone()
+            .map_err(|e| Failure::Unavailable(e.into()))?,
+    );
+    let mut writer = stream;
+
+    let (data, ron, width, height, samples, depth, timeout) = match receive(&mut reader) {
+        Ok(Message::Scene {
+            data,
+            ron,
+            width,
+            height,
+            samples,
+            depth,
+            timeout,
+        }) => (data, ron, width, height, samples, depth, timeout),
+        Ok(msg) => {
+            return Err(Failure::InvalidData(anyhow!(
+                "unexpected message {:?}",
+                msg
+            )))
+        }
+        Err(e) => return Err(Failure::Unavailable(e)),
+    };
+
+    let format = if ron { Format::Ron } else { Format::Yaml };
+    let (world, mut camera) = scene::parse(&data, format)
+        .and_then(|prefab| Ok(prefab.try_build()?))
+        .context("invalid scene received from the coordinator")
+        .map_err(Failure::InvalidData)?;
+
+    camera.set_size(width, height);
+    camera.set_recursion_limit(depth);
+    render::set_samples(&mut camera, samples);
+
+    // The coordinator may keep workers idle until the tiles of other workers time out
+    reader
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_millis(timeout) + WORKER_TIMEOUT))
+        .map_err(|e| Failure::Unavailable(e.into()))?;
+
+    let mut rendered = 0;
+
+    loop {
+        let tile = match receive(&mut reader) {
+            Ok(Message::Tile(tile)) => tile,
+            Ok(Message::Done) => break,
+            Ok(msg) => {
+                return Err(Failure::InvalidData(anyhow!(
+                    "unexpected message {:?}",
+                    msg
+                )))
+            }
+            Err(e) => return Err(Failure::Unavailable(e)),
+        };
+
+        let pixels = pool.install(|| {
+            (0..tile.pixels() as u32)
+                .into_par_iter()
+                .map(|i| {
+                    let (x, y) = (tile.x + i % tile.width, tile.y + i / tile.width);
+                    camera.render_pixel(&world, x, y).0
+                })
+                .collect::<Vec<_>>()
+        });
+
+        send_pixels(&mut writer, tile, &pixels).map_err(Failure::Unavailable)?;
+
+        rendered += 1;
+        eprint!("\rrendered {} tiles", rendered);
+    }
+
+    eprintln!();
+    Ok(())
+}
+
+/// Splits a frame into tiles of at most `size`×`size` pixels, in scanline order.
+fn split(width: u32, height: u32, size: u32) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+
+    for y in (0..height).step_by(size as usize) {
+        for x in (0..width).step_by(size as usize) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: size.min(width - x),
+                height: size.min(height - y),
+            });
+        }
+    }
+
+    tiles
+}
+
+fn send<W: Write>(w: &mut W, msg: &Message) -> Result<()> {
+    let mut line = serde_json::to_vec(msg)?;
+    line.push(b'\n');
+    if line.len() as u64 > MAX_LINE {
+        bail!("message exceeds the limit of {} bytes", MAX_LINE);
+    }
+
+    w.write_all(&line)?;
+    Ok(w.flush()?)
+}
+
+fn receive<R: BufRead>(r: &mut R) -> Result<Message> {
+    receive_at_most(r, MAX_LINE)
+}
+
+/// Reads a message from a line of at most `max_len` bytes, including its newline.
+fn receive_at_most<R: BufRead>(r: &mut R, max_len: u64) -> Result<Message> {
+    let mut line = Vec::new();
+    let len = r.take(max_len).read_until(b'\n', &mut line)?;
+
+    match line.last() {
+        Some(b'\n') => Ok(serde_json::from_slice(&line)?),
+        _ if len == 0 => bail!("connection closed"),
+        _ if len as u64 == max_len => bail!("message exceeds the limit of {} bytes", max_len),
+        _ => bail!("connection closed in the middle of a message"),
+    }
+}
+
+fn send_pixels<W: Write>(w: &mut W, tile: Tile, pixels: &[Color]) -> Result<()> {
+    let mut data = Vec::with_capacity(pixels.len() * 12);
+    for c in pixels {
+        for v in &[c.r, c.g, c.b] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    send(w, &Message::Pixels(tile))?;
+    w.write_all(&data)?;
+    Ok(w.flush()?)
+}
+
+fn receive_pixels<R: BufRead>(r: &mut R, expected: Tile) -> Result<Vec<Color>> {
+    match receive(r)? {
+        Message::Pixels(tile) if tile == expected => (),
+        msg => bail!("unexpected message {:?}", msg),
+    }
+
+    let mut data = vec![0; expected.pixels() * 12];
+    r.read_exact(&mut data)?;
+
+    let f32_at = |i: usize| f32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+
+    Ok((0..expected.pixels())
+        .map(|i| Color::new(f32_at(i * 12), f32_at(i * 12 + 4), f32_at(i * 12 + 8)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const SCENE: &str = "
+camera:
+  width: 8
+  height: 6
+  fov: 60
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+
+lights:
+  - position: [-10, 10, -10]
+
+objects:
+  - shape:
+      Sphere:
+    material:
+      pattern:
+        kind:
+          solid: [1, 0.2, 1]
+";
+
+    const TILE: Tile = Tile {
+        x: 2,
+        y: 1,
+        width: 2,
+        height: 3,
+    };
+
+    fn hello(timeout: Duration) -> Message {
+        Message::Scene {
+            data: SCENE.to_string(),
+            ron: false,
+            width: 8,
+            height: 6,
+            samples: 1,
+            depth: 5,
+            timeout: timeout.as_millis() as u64,
+        }
+    }
+
+    /// Renders the scene of `hello` in a single process.
+    fn expected() -> Canvas {
+        let (world, mut camera) = scene::parse(SCENE, Format::Yaml)
+            .unwrap()
+            .try_build()
+            .unwrap();
+        camera.set_recursion_limit(5);
+        camera.render(&world)
+    }
+
+    /// Starts a worker rendering the tiles handed out on `listener`.
+    fn spawn_worker(listener: &TcpListener) -> thread::JoinHandle<Result<(), Failure>> {
+        let options = WorkerOptions {
+            coordinator: listener.local_addr().unwrap().to_string(),
+            threads: Some(1),
+        };
+
+        thread::spawn(move || work(&options))
+    }
+
+    #[test]
+    fn sending_and_receiving_messages() {
+        let messages = [
+            hello(Duration::from_secs(1)),
+            Message::Tile(TILE),
+            Message::Done,
+        ];
+
+        let mut data = Vec::new();
+        for msg in &messages {
+            send(&mut data, msg).unwrap();
+        }
+
+        let mut r = Cursor::new(data);
+        for msg in &messages {
+            assert_eq!(&receive(&mut r).unwrap(), msg);
+        }
+
+        assert_eq!(
+            receive(&mut r).unwrap_err().to_string(),
+            "connection closed"
+        );
+    }
+
+    #[test]
+    fn rejecting_long_or_truncated_lines() {
+        let mut data = Vec::new();
+        send(&mut data, &Message::Tile(TILE)).unwrap();
+
+        let len = data.len() as u64;
+        assert!(receive_at_most(&mut Cursor::new(&data), len).is_ok());
+        assert!(receive_at_most(&mut Cursor::new(&data), len - 1).is_err());
+        assert!(receive(&mut Cursor::new(&data[..data.len() - 1])).is_err());
+        assert!(receive(&mut Cursor::new(b"{\"tile\":\n")).is_err());
+    }
+
+    #[test]
+    fn sending_and_receiving_pixels() {
+        let pixels = (0..TILE.pixels())
+            .map(|i| Color::new(i as f32, -0.5, f32::MAX))
+            .collect::<Vec<_>>();
+
+        let mut data = Vec::new();
+        send_pixels(&mut data, TILE, &pixels).unwrap();
+
+        assert_eq!(
+            receive_pixels(&mut Cursor::new(&data), TILE).unwrap(),
+            pixels
+        );
+
+        let other = Tile { x: 0, ..TILE };
+        assert!(receive_pixels(&mut Cursor::new(&data), other).is_err());
+        assert!(receive_pixels(&mut Cursor::new(&data[..data.len() - 1]), TILE).is_err());
+    }
+
+    #[test]
+    fn rendering_a_frame_across_workers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let workers = [spawn_worker(&listener), spawn_worker(&listener)];
+
+        let timeout = Duration::from_secs(60);
+        let canvas = assemble(
+            listener,
+            hello(timeout),
+            Canvas::new(8, 6),
+            split(8, 6, 3),
+            timeout,
+        );
+
+        assert!(canvas.iter().eq(expected().iter()));
+        for worker in workers {
+            assert!(worker.join().unwrap().is_ok());
+        }
+    }
+
+    #[test]
+    fn reassigning_the_tiles_of_stalled_workers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let timeout = Duration::from_millis(200);
+        let coordinator = thread::spawn(move || {
+            assemble(
+                listener,
+                hello(timeout),
+                Canvas::new(8, 6),
+                split(8, 6, 4),
+                timeout,
+            )
+        });
+
+        // Take a tile and never send it back
+        let mut stalled = BufReader::new(TcpStream::connect(addr).unwrap());
+        assert!(matches!(receive(&mut stalled), Ok(Message::Scene { .. })));
+        assert!(matches!(receive(&mut stalled), Ok(Message::Tile(_))));
+
+        let options = WorkerOptions {
+            coordinator: addr.to_string(),
+            threads: Some(1),
+        };
+        let worker = thread::spawn(move || work(&options));
+
+        assert!(coordinator.join().unwrap().iter().eq(expected().iter()));
+        assert!(worker.join().unwrap().is_ok());
+    }
+}
@@ -0,0 +1,445 @@
+//! Batch rendering of scenes from the command line.
+
+#![deny(missing_debug_implementations)]
+#![warn(missing_docs)]
+
+use std::{fmt, path::Path, process};
+
+use clap::{crate_version, App, AppSettings, Arg, ArgMatches, ErrorKind, SubCommand};
+
+use config::Config;
+
+mod animate;
+mod args;
+mod bench;
+mod config;
+mod distributed;
+mod output;
+#[cfg(feature = "profiling")]
+mod profile;
+mod progress;
+mod render;
+mod scene;
+#[cfg(feature = "server")]
+mod server;
+
+/// Reasons for the program to fail, each reported with its own exit code.
+#[derive(Debug)]
+pub enum Failure {
+    /// The command line is invalid.
+    Usage(String),
+    /// An input file could not be read.
+    Input(anyhow::Error),
+    /// An input file could be read, but its contents are invalid.
+    InvalidData(anyhow::Error),
+    /// An output file could not be written.
+    Output(anyhow::Error),
+    /// A service or external program the command relies on is unavailable.
+    Unavailable(anyhow::Error),
+}
+
+impl Failure {
+    /// Returns the exit code of the process for this failure, following `sysexits.h`.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Usage(_) => 64,
+            Failure::InvalidData(_) => 65,
+            Failure::Input(_) => 66,
+            Failure::Unavailable(_) => 69,
+            Failure::Output(_) => 73,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Usage(msg) => write!(f, "{}", msg),
+            Failure::Input(e)
+            | Failure::InvalidData(e)
+            | Failure::Output(e)
+            | Failure::Unavailable(e) => write!(f, "{:#}", e),
+        }
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    let app = App::new("tracy-cli")
+        .version(crate_version!())
+        .about("Renders Tracy scenes without a user interface")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("config")
+                .help("Default render settings [default: ~/.config/tracy/config.toml]")
+                .long("config")
+                .value_name("FILE")
+                .takes_value(true)
+                .global(true),
+        )
+        .subcommand(
+            SubCommand::with_name("render")
+                .about("Renders a scene file to an image")
+                .arg(
+                    Arg::with_name("scene")
+                        .help("Scene file to render, in YAML or RON format")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help(
+                            "Output image, in PNG or PPM format depending on its extension \
+                             [default: render.png]",
+                        )
+                        .short("o")
+                        .long("output")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("size")
+                        .help("Resolution of the output image [default: the scene camera's]")
+                        .short("s")
+                        .long("size")
+                        .value_name("WIDTHxHEIGHT")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("samples")
+                        .help("Supersampling level, casting N×N rays per pixel [default: 1]")
+                        .long("samples")
+                        .value_name("N")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .help("Maximum number of reflections and refractions of a ray")
+                        .long("depth")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .help("Number of rendering threads [default: all cores]")
+                        .short("j")
+                        .long("threads")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("watch")
+                        .help("Renders the scene again every time its file is modified")
+                        .short("w")
+                        .long("watch"),
+                )
+                .arg(
+                    Arg::with_name("stream")
+                        .help(
+                            "Writes the image as it is rendered, without holding it in memory, \
+                             for very large renders",
+                        )
+                        .long("stream"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("animate")
+                .about("Renders an animation of a scene to a sequence of frames")
+                .arg(
+                    Arg::with_name("scene")
+                        .help("Scene file to animate, in YAML or RON format")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("frames")
+                        .help("Number of frames to render")
+                        .long("frames")
+                        .value_name("N")
+                        .takes_value(true)
+                        .default_value("120"),
+                )
+                .arg(
+                    Arg::with_name("fps")
+                        .help("Frame rate of the animation")
+                        .long("fps")
+                        .value_name("N")
+                        .takes_value(true)
+                        .default_value("24"),
+                )
+                .arg(
+                    Arg::with_name("orbit")
+                        .help("Orbits the camera around the scene by DEGREES over the animation")
+                        .long("orbit")
+                        .value_name("DEGREES")
+                        .takes_value(true)
+                        .min_values(0)
+                        .require_equals(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("Directory to write the numbered PNG frames to [default: frames]")
+                        .short("o")
+                        .long("output")
+                        .value_name("DIR")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("video")
+                        .help("Encodes the frames to a video file with ffmpeg")
+                        .long("video")
+                        .value_name("FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("size")
+                        .help("Resolution of the frames [default: the scene camera's]")
+                        .short("s")
+                        .long("size")
+                        .value_name("WIDTHxHEIGHT")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("samples")
+                        .help("Supersampling level, casting N×N rays per pixel [default: 1]")
+                        .long("samples")
+                        .value_name("N")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .help("Maximum number of reflections and refractions of a ray")
+                        .long("depth")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .help("Number of rendering threads [default: all cores]")
+                        .short("j")
+                        .long("threads")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Measures the time taken to load and render scenes")
+                .arg(
+                    Arg::with_name("scenes")
+                        .help("Scene files to benchmark [default: the bundled reference scenes]")
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::with_name("warmup")
+                        .help("Number of untimed runs of each scene")
+                        .long("warmup")
+                        .value_name("N")
+                        .takes_value(true)
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::with_name("runs")
+                        .help("Number of timed runs of each scene")
+                        .short("n")
+                        .long("runs")
+                        .value_name("N")
+                        .takes_value(true)
+                        .default_value("5"),
+                )
+                .arg(
+                    Arg::with_name("size")
+                        .help("Resolution of the renders [default: the scene camera's]")
+                        .short("s")
+                        .long("size")
+                        .value_name("WIDTHxHEIGHT")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .help("Format of the results")
+                        .short("f")
+                        .long("format")
+                        .possible_values(&["json", "csv"])
+                        .default_value("json"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("File to write the results to [default: standard output]")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .help("Number of rendering threads [default: all cores]")
+                        .short("j")
+                        .long("threads")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("coordinator")
+                .about("Renders a scene file with the help of worker processes")
+                .arg(
+                    Arg::with_name("scene")
+                        .help("Scene file to render, in YAML or RON format")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help(
+                            "Output image, in PNG or PPM format depending on its extension \
+                             [default: render.png]",
+                        )
+                        .short("o")
+                        .long("output")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("bind")
+                        .help("Address to listen on for workers")
+                        .short("b")
+                        .long("bind")
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .default_value("0.0.0.0:7878"),
+                )
+                .arg(
+                    Arg::with_name("tile-size")
+                        .help("Side of the square tiles handed out to workers, in pixels")
+                        .long("tile-size")
+                        .value_name("N")
+                        .takes_value(true)
+                        .default_value("32"),
+                )
+                .arg(
+                    Arg::with_name("timeout")
+                        .help(
+                            "Seconds a worker may take to render a tile before the tile is handed \
+                             to another worker",
+                        )
+                        .long("timeout")
+                        .value_name("SECONDS")
+                        .takes_value(true)
+                        .default_value("300"),
+                )
+                .arg(
+                    Arg::with_name("size")
+                        .help("Resolution of the output image [default: the scene camera's]")
+                        .short("s")
+                        .long("size")
+                        .value_name("WIDTHxHEIGHT")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("samples")
+                        .help("Supersampling level, casting N×N rays per pixel [default: 1]")
+                        .long("samples")
+                        .value_name("N")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .help("Maximum number of reflections and refractions of a ray")
+                        .long("depth")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("worker")
+                .about("Renders tiles handed out by a coordinator")
+                .arg(
+                    Arg::with_name("coordinator")
+                        .help("Address of the coordinator")
+                        .value_name("ADDRESS")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .help("Number of rendering threads [default: all cores]")
+                        .short("j")
+                        .long("threads")
+                        .takes_value(true),
+                ),
+        );
+
+    #[cfg(feature = "profiling")]
+    let app = app.arg(
+        Arg::with_name("profile")
+            .help("Prints the time spent in each phase of the renderer")
+            .long("profile")
+            .global(true),
+    );
+
+    #[cfg(feature = "server")]
+    let app = app.subcommand(
+        SubCommand::with_name("serve")
+            .about("Renders scenes submitted over HTTP")
+            .arg(
+                Arg::with_name("bind")
+                    .help("Address to listen on")
+                    .short("b")
+                    .long("bind")
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .default_value("127.0.0.1:8000"),
+            )
+            .arg(
+                Arg::with_name("threads")
+                    .help("Number of rendering threads [default: all cores]")
+                    .short("j")
+                    .long("threads")
+                    .takes_value(true),
+            ),
+    );
+
+    app
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Failure> {
+    let config = match matches.subcommand() {
+        (_, Some(matches)) => Config::load(matches.value_of("config").map(Path::new))?,
+        _ => Config::default(),
+    };
+
+    match matches.subcommand() {
+        ("render", Some(matches)) => render::run(&render::Options::parse(matches, &config)?),
+        ("animate", Some(matches)) => animate::run(&animate::Options::parse(matches, &config)?),
+        ("bench", Some(matches)) => bench::run(&bench::Options::parse(matches, &config)?),
+        ("coordinator", Some(matches)) => {
+            distributed::coordinate(&distributed::CoordinatorOptions::parse(matches, &config)?)
+        }
+        ("worker", Some(matches)) => {
+            distributed::work(&distributed::WorkerOptions::parse(matches, &config)?)
+        }
+        #[cfg(feature = "server")]
+        ("serve", Some(matches)) => server::run(&server::Options::parse(matches, &config)?),
+        _ => unreachable!("subcommand is required"),
+    }
+}
+
+fn main() {
+    let matches = app().get_matches_safe().unwrap_or_else(|e| match e.kind {
+        ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => e.exit(),
+        _ => {
+            eprintln!("{}", e.message);
+            process::exit(Failure::Usage(e.message).exit_code());
+        }
+    });
+
+    #[cfg(feature = "profiling")]
+    let profiling = matches
+        .subcommand()
+        .1
+        .is_some_and(|m| m.is_present("profile"));
+
+    #[cfg(feature = "profiling")]
+    if profiling {
+        profile::start();
+    }
+
+    let result = run(&matches);
+
+    #[cfg(feature = "profiling")]
+    if profiling {
+        profile::report();
+    }
+
+    if let Err(failure) = result {
+        eprintln!("error: {}", failure);
+        process::exit(failure.exit_code());
+    }
+}
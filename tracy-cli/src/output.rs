@@ -0,0 +1,317 @@
+//! Saving of rendered canvases to image files.
+
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Result};
+use deflate::{write::ZlibEncoder, Compression};
+use tracy::{
+    query::World,
+    rendering::{Camera, Canvas, Color, PpmFormat, PpmWriter},
+};
+
+/// Size of the compressed image data chunks of streamed PNG files.
+const IDAT_CHUNK_SIZE: usize = 1 << 16;
+
+/// Returns the metadata describing a render of the scene `title`, made of `world` as seen through
+/// `camera`.
+pub fn metadata(title: &str, world: &World, camera: &Camera) -> Vec<(&'static str, String)> {
+    vec![
+        ("Title", title.to_string()),
+        (
+            "Software",
+            format!(
+                "tracy-cli {} (tracy {})",
+                env!("CARGO_PKG_VERSION"),
+                tracy::VERSION
+            ),
+        ),
+        (
+            "Resolution",
+            format!("{}x{}", camera.horizontal_size(), camera.vertical_size()),
+        ),
+        (
+            "Field of view",
+            format!("{:.2}°", camera.fov().to_degrees()),
+        ),
+        ("Samples per pixel", camera.samples_per_pixel().to_string()),
+        (
+            "Recursion depth",
+            camera.recursion_limit_for(world).to_string(),
+        ),
+    ]
+}
+
+/// Writes `canvas` to `path`, in PNG or PPM format depending on its extension.
+///
+/// `metadata` is stored as key-value pairs in PNG text chunks, and is left out of PPM files.
+pub fn save<P: AsRef<Path>>(canvas: &Canvas, path: P, metadata: &[(&str, String)]) -> Result<()> {
+    let path = path.as_ref();
+
+    match path.extension().and_then(OsStr::to_str) {
+        Some("png") => write_png(canvas, BufWriter::new(File::create(path)?), metadata),
+        Some("ppm") => Ok(fs::write(path, canvas.convert_to_binary_ppm())?),
+        _ => bail!("unsupported output format: {}", path.display()),
+    }
+}
+
+/// Sink receiving the rows of an image, from top to bottom.
+pub type RowSink<'a> = dyn FnMut(&[Color]) -> io::Result<()> + Send + 'a;
+
+/// Writes a `width`×`height` image to `path` like [`save`], without ever holding it in memory.
+///
+/// `render` is given a sink to pass each row of the image to, in order, as soon as it is ready,
+/// and must return the first error reported by the sink.
+pub fn save_rows<P, F>(
+    path: P,
+    width: u32,
+    height: u32,
+    metadata: &[(&str, String)],
+    render: F,
+) -> Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut RowSink) -> io::Result<()>,
+{
+    let path = path.as_ref();
+    let mut file = match path.extension().and_then(OsStr::to_str) {
+        Some("png") | Some("ppm") => BufWriter::new(File::create(path)?),
+        _ => bail!("unsupported output format: {}", path.display()),
+    };
+
+    if path.extension() == Some(OsStr::new("png")) {
+        write_png_rows(&mut file, width, height, metadata, render)?;
+    } else {
+        let mut ppm = PpmWriter::new(&mut file, width, height, PpmFormat::Binary)?;
+        render(&mut |row| ppm.write_row(row))?;
+        ppm.finish()?;
+    }
+
+    Ok(file.flush()?)
+}
+
+/// Encodes `canvas` as a PNG image, storing `metadata` in its text chunks.
+#[cfg(feature = "server")]
+pub fn encode_png(canvas: &Canvas, metadata: &[(&str, String)]) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    write_png(canvas, &mut png, metadata)?;
+    Ok(png)
+}
+
+fn write_png<W: Write>(canvas: &Canvas, w: W, metadata: &[(&str, String)]) -> Result<()> {
+    let data = canvas
+        .iter()
+        .flat_map(|c| {
+            let (r, g, b) = c.to_rgb888();
+            vec![r, g, b]
+        })
+        .collect::<Vec<_>>();
+
+    let mut encoder = png::Encoder::new(w, canvas.width(), canvas.height());
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    for (key, value) in metadata {
+        writer.write_chunk(*b"tEXt", &text_chunk(key, value))?;
+    }
+    writer.write_image_data(&data)?;
+
+    Ok(())
+}
+
+/// Writes a PNG image whose rows are produced by `render`, as described in [`save_rows`].
+///
+/// The image data is compressed as it comes, and written out in chunks of [`IDAT_CHUNK_SIZE`]
+/// bytes.
+fn write_png_rows<W, F>(
+    w: W,
+    width: u32,
+    height: u32,
+    metadata: &[(&str, String)],
+    render: F,
+) -> Result<()>
+where
+    W: Write + Send,
+    F: FnOnce(&mut RowSink) -> io::Result<()>,
+{
+    let mut encoder = png::Encoder::new(w, width, height);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    for (key, value) in metadata {
+        writer.write_chunk(*b"tEXt", &text_chunk(key, value))?;
+    }
+
+    let mut rows = 0;
+    let mut raw = Vec::with_capacity(width as usize * 3);
+    let mut filtered = Vec::with_capacity(width as usize * 3 + 1);
+    let mut zlib = ZlibEncoder::new(
+        IdatWriter {
+            png: &mut writer,
+            buffer: Vec::with_capacity(IDAT_CHUNK_SIZE),
+        },
+        Compression::Default,
+    );
+
+    render(&mut |row| {
+        if row.len() != width as usize || rows >= height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "row does not fit in the image",
+            ));
+        }
+
+        raw.clear();
+        for c in row {
+            let (r, g, b) = c.to_rgb888();
+            raw.extend_from_slice(&[r, g, b]);
+        }
+
+        // Each byte is stored as its difference with the same channel of the previous pixel,
+        // which compresses well for the smooth gradients of most renders
+        filtered.clear();
+        filtered.push(1);
+        filtered.extend(raw.iter().enumerate().map(|(i, &x)| {
+            if i < 3 {
+                x
+            } else {
+                x.wrapping_sub(raw[i - 3])
+            }
+        }));
+
+        rows += 1;
+        zlib.write_all(&filtered)
+    })?;
+
+    if rows != height {
+        bail!(
+            "only {} of the {} rows of the image were written",
+            rows,
+            height
+        );
+    }
+
+    zlib.finish()?.flush()?;
+    Ok(())
+}
+
+/// Writer splitting compressed image data into PNG `IDAT` chunks.
+struct IdatWriter<'a, W: Write> {
+    png: &'a mut png::Writer<W>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> Write for IdatWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= IDAT_CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.png.write_chunk(*b"IDAT", &self.buffer)?;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Encodes a key-value pair as the data of a PNG `tEXt` chunk, replacing the characters that do
+/// not fit in Latin-1.
+fn text_chunk(key: &str, value: &str) -> Vec<u8> {
+    let latin1 = |s: &str| {
+        s.chars()
+            .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+            .collect::<Vec<_>>()
+    };
+
+    let mut data = latin1(key);
+    data.push(0);
+    data.extend(latin1(value));
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canvas() -> Canvas {
+        let mut canvas = Canvas::new(3, 2);
+        for (x, y, pixel) in canvas.enumerate_pixels_mut() {
+            *pixel = Color::new(x as f32 / 2.0, y as f32, 0.5);
+        }
+        canvas
+    }
+
+    /// Decodes a PNG image into its size and RGB data.
+    fn decode(data: &[u8]) -> (u32, u32, Vec<u8>) {
+        let (info, mut reader) = png::Decoder::new(data).read_info().unwrap();
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        (info.width, info.height, pixels)
+    }
+
+    #[test]
+    fn encoding_png_images() {
+        let mut png = Vec::new();
+        write_png(&canvas(), &mut png, &[("Title", "test".to_string())]).unwrap();
+
+        let (width, height, pixels) = decode(&png);
+        assert_eq!((width, height), (3, 2));
+        assert_eq!(
+            pixels,
+            canvas()
+                .iter()
+                .flat_map(|c| {
+                    let (r, g, b) = c.to_rgb888();
+                    vec![r, g, b]
+                })
+                .collect::<Vec<_>>()
+        );
+
+        assert!(png.windows(10).any(|w| w == b"Title\0test"));
+    }
+
+    #[test]
+    fn streaming_png_images() {
+        let canvas = canvas();
+
+        let mut expected = Vec::new();
+        write_png(&canvas, &mut expected, &[]).unwrap();
+
+        let mut png = Vec::new();
+        write_png_rows(&mut png, 3, 2, &[], |sink| canvas.rows().try_for_each(sink)).unwrap();
+        assert_eq!(decode(&png), decode(&expected));
+
+        // Streams must provide exactly as many rows as the image has
+        let too_few = |sink: &mut RowSink| canvas.rows().take(1).try_for_each(sink);
+        assert!(write_png_rows(Vec::new(), 3, 2, &[], too_few).is_err());
+
+        let too_many = |sink: &mut RowSink| canvas.rows().chain(canvas.rows()).try_for_each(sink);
+        assert!(write_png_rows(Vec::new(), 3, 2, &[], too_many).is_err());
+    }
+
+    #[test]
+    fn encoding_metadata_in_latin1() {
+        assert_eq!(text_chunk("Fov", "60°"), b"Fov\x0060\xb0");
+        assert_eq!(text_chunk("Title", "日本"), b"Title\0??");
+    }
+
+    #[test]
+    fn rejecting_unknown_output_formats() {
+        let path = std::env::temp_dir().join(format!("tracy-cli-{}.jpg", std::process::id()));
+
+        assert!(save(&canvas(), &path, &[]).is_err());
+        assert!(save_rows(&path, 3, 2, &[], |_| Ok(())).is_err());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,26 @@
+//! Report of the time spent in each phase of the renderer, enabled with `--profile`.
+
+use tracy::profile::{self, Counters, Phase};
+
+static COUNTERS: Counters = Counters::new();
+
+/// Starts counting the time spent in each phase of the renderer.
+pub fn start() {
+    profile::set_profiler(&COUNTERS).expect("profiler started twice");
+}
+
+/// Prints the time spent in each phase so far on the standard error.
+///
+/// Phases nest into each other, so the times are not meant to add up.
+pub fn report() {
+    eprintln!("{:<16} {:>12} {:>12}", "phase", "calls", "total");
+
+    for &phase in &Phase::ALL {
+        eprintln!(
+            "{:<16} {:>12} {:>12.2?}",
+            phase.name(),
+            COUNTERS.calls(phase),
+            COUNTERS.total_time(phase)
+        );
+    }
+}
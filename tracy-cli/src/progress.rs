@@ -0,0 +1,154 @@
+//! Progress reporting of long renders on the standard error.
+
+use std::{
+    fs,
+    io::{self, IsTerminal, Write},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the progress bar is redrawn on a terminal.
+const BAR_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often a progress line is logged when the standard error is not a terminal.
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Width of the progress bar, in characters.
+const BAR_WIDTH: usize = 30;
+
+/// Counter of the scanlines completed by a render.
+#[derive(Debug, Default)]
+pub struct Progress {
+    total_lines: u32,
+    rays_per_line: u64,
+    completed_lines: AtomicU32,
+}
+
+impl Progress {
+    /// Creates a counter for a render of `total_lines` scanlines, casting `rays_per_line` primary
+    /// rays each.
+    pub fn new(total_lines: u32, rays_per_line: u64) -> Self {
+        Self {
+            total_lines,
+            rays_per_line,
+            completed_lines: AtomicU32::new(0),
+        }
+    }
+
+    /// Marks `n` more scanlines as completed.
+    pub fn add_lines(&self, n: u32) {
+        self.completed_lines.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the number of scanlines completed so far.
+    pub fn completed_lines(&self) -> u32 {
+        self.completed_lines
+            .load(Ordering::Relaxed)
+            .min(self.total_lines)
+    }
+}
+
+/// Runs `f`, reporting the progress it records in `progress` on the standard error.
+///
+/// On a terminal, a progress bar is kept up to date until `f` returns. Otherwise, a plain line is
+/// logged every few seconds, so that the output stays readable in log files.
+pub fn report<T, F>(label: &str, progress: &Progress, f: F) -> T
+where
+    F: FnOnce() -> T + Send,
+    T: Send,
+{
+    let tty = io::stderr().is_terminal();
+    let start = Instant::now();
+    let finished = AtomicBool::new(false);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            let interval = if tty { BAR_INTERVAL } else { LOG_INTERVAL };
+            let mut last = Instant::now();
+
+            while !finished.load(Ordering::Relaxed) {
+                // Wake up often, so that short renders are not delayed by a long log interval
+                thread::sleep(BAR_INTERVAL.min(interval));
+
+                if last.elapsed() >= interval && !finished.load(Ordering::Relaxed) {
+                    print(label, progress, start.elapsed(), tty);
+                    last = Instant::now();
+                }
+            }
+        });
+
+        let result = f();
+        finished.store(true, Ordering::Relaxed);
+
+        print(label, progress, start.elapsed(), tty);
+        if tty {
+            eprintln!();
+        }
+
+        result
+    })
+}
+
+fn print(label: &str, progress: &Progress, elapsed: Duration, tty: bool) {
+    let done = progress.completed_lines();
+    let total = progress.total_lines.max(1);
+    let fraction = done as f64 / total as f64;
+
+    let secs = elapsed.as_secs_f64().max(1e-3);
+    let lines_per_sec = done as f64 / secs;
+    let rays_per_sec = done as f64 * progress.rays_per_line as f64 / secs;
+
+    let mut stats = format!(
+        "{}/{} lines, {:.0} lines/s, {} primary rays/s",
+        done,
+        progress.total_lines,
+        lines_per_sec,
+        si(rays_per_sec),
+    );
+
+    if let Some(rss) = resident_memory() {
+        stats += &format!(", {:.1} MiB", rss as f64 / (1024. * 1024.));
+    }
+
+    if tty {
+        let filled = (fraction * BAR_WIDTH as f64) as usize;
+        eprint!(
+            "\r{} [{}{}] {:3.0}% {}\x1b[K",
+            label,
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            fraction * 100.,
+            stats
+        );
+        let _ = io::stderr().flush();
+    } else {
+        eprintln!("{}: {:.0}% ({})", label, fraction * 100., stats);
+    }
+}
+
+/// Formats `value` with an SI prefix, eg. `1.2M`.
+fn si(value: f64) -> String {
+    match value {
+        v if v >= 1e9 => format!("{:.1}G", v / 1e9),
+        v if v >= 1e6 => format!("{:.1}M", v / 1e6),
+        v if v >= 1e3 => format!("{:.1}k", v / 1e3),
+        v => format!("{:.0}", v),
+    }
+}
+
+/// Returns the resident memory of the process in bytes, where the platform exposes it.
+fn resident_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kib * 1024)
+}
@@ -0,0 +1,275 @@
+//! The `render` command.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+use rayon::ThreadPoolBuilder;
+use tracy::{
+    query::World,
+    rendering::{Camera, Canvas, Color, ToneMapping},
+};
+
+use crate::{
+    args::{parse_number, parse_size},
+    config::Config,
+    output,
+    progress::{self, Progress},
+    scene, Failure,
+};
+
+/// Options of the `render` command.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Scene file to render.
+    pub scene: PathBuf,
+    /// Output image.
+    pub output: PathBuf,
+    /// Resolution of the output image, `None` to keep the scene camera's.
+    pub size: Option<(u32, u32)>,
+    /// Number of rays cast along each axis of a pixel.
+    pub samples: u32,
+    /// Maximum recursion depth, `None` to keep the scene's.
+    pub depth: Option<u32>,
+    /// Number of rendering threads, `None` to use all cores.
+    pub threads: Option<usize>,
+    /// Whether to render again every time the scene file changes.
+    pub watch: bool,
+    /// Whether to write the image as it is rendered, rather than once complete.
+    pub stream: bool,
+    /// Display transform applied to the image before it is saved.
+    pub tone_mapping: ToneMapping,
+}
+
+/// How often the scene file is checked for changes in watch mode.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+impl Options {
+    /// Reads the options from the command line, falling back to the user's `config`.
+    pub fn parse(matches: &ArgMatches, config: &Config) -> Result<Self, Failure> {
+        Ok(Self {
+            scene: PathBuf::from(matches.value_of("scene").unwrap()),
+            output: matches
+                .value_of("output")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| config.output_path("render.png")),
+            size: matches
+                .value_of("size")
+                .map(parse_size)
+                .transpose()?
+                .or_else(|| config.size()),
+            samples: parse_number(matches, "samples")?
+                .or(config.samples)
+                .unwrap_or(1)
+                .max(1),
+            depth: parse_number(matches, "depth")?,
+            threads: parse_number(matches, "threads")?.or(config.threads),
+            watch: matches.is_present("watch"),
+            stream: matches.is_present("stream"),
+            tone_mapping: config.tone_mapping(),
+        })
+    }
+}
+
+/// Renders the scene and saves it to the output image.
+///
+/// In watch mode, this never returns: failures are reported and the scene is rendered again once
+/// its file is modified.
+pub fn run(options: &Options) -> Result<(), Failure> {
+    if options.watch {
+        watch(options)
+    } else {
+        render_to_file(options)
+    }
+}
+
+fn watch(options: &Options) -> ! {
+    let mut modified = last_modified(&options.scene);
+
+    loop {
+        let start = Instant::now();
+        match render_to_file(options) {
+            Ok(()) => eprintln!(
+                "rendered {} in {:.2?}",
+                options.output.display(),
+                start.elapsed()
+            ),
+            Err(failure) => eprintln!("error: {}", failure),
+        }
+        eprintln!("watching {} for changes...", options.scene.display());
+
+        // Only retry a broken file once it's modified again
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+
+            let now = last_modified(&options.scene);
+            if now != modified {
+                modified = now;
+                break;
+            }
+        }
+    }
+}
+
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn render_to_file(options: &Options) -> Result<(), Failure> {
+    let (world, mut camera) = scene::load(&options.scene)?;
+
+    if let Some((width, height)) = options.size {
+        camera.set_size(width, height);
+    }
+    if let Some(depth) = options.depth {
+        camera.set_recursion_limit(depth);
+    }
+    set_samples(&mut camera, options.samples);
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(options.threads.unwrap_or(0))
+        .build()
+        .expect("failed to create rendering thread pool");
+
+    let progress = progress_of(&camera);
+    let metadata = output::metadata(&options.scene.display().to_string(), &world, &camera);
+
+    let saved = if options.stream {
+        let (width, height) = (camera.horizontal_size(), camera.vertical_size());
+
+        output::save_rows(&options.output, width, height, &metadata, |sink| {
+            let mut mapped = Vec::with_capacity(width as usize);
+
+            progress::report("rendering", &progress, || {
+                pool.install(|| {
+                    render_rows(&world, &camera, &progress, |row| {
+                        mapped.clear();
+                        mapped.extend(row.iter().map(|&c| options.tone_mapping.map_color(c)));
+                        sink(&mapped)
+                    })
+                })
+            })
+        })
+    } else {
+        let canvas = progress::report("rendering", &progress, || {
+            pool.install(|| render_with_progress(&world, &camera, &progress))
+        });
+
+        output::save(
+            &options.tone_mapping.apply(&canvas),
+            &options.output,
+            &metadata,
+        )
+    };
+
+    saved
+        .with_context(|| format!("failed to save {}", options.output.display()))
+        .map_err(Failure::Output)
+}
+
+/// Renders `world` through `camera`.
+pub fn render(world: &World, camera: &Camera) -> Canvas {
+    render_with_progress(world, camera, &Progress::default())
+}
+
+/// Renders `world` through `camera` like [`render`], recording completed scanlines in `progress`.
+pub fn render_with_progress(world: &World, camera: &Camera, progress: &Progress) -> Canvas {
+    let mut stream = camera.stream(world);
+    let mut completed = 0;
+
+    while stream.advance() {
+        progress.add_lines(stream.completed_lines() - completed);
+        completed = stream.completed_lines();
+    }
+
+    stream.finalize()
+}
+
+/// Renders `world` through `camera` like [`render_with_progress`], passing each row of the image
+/// to `f` as soon as it is complete instead of holding the whole image in memory.
+///
+/// Rendering stops at the first error returned by `f`.
+pub fn render_rows<F>(
+    world: &World,
+    camera: &Camera,
+    progress: &Progress,
+    mut f: F,
+) -> io::Result<()>
+where
+    F: FnMut(&[Color]) -> io::Result<()>,
+{
+    camera.render_rows(world, |row| {
+        f(row)?;
+        progress.add_lines(1);
+        Ok(())
+    })
+}
+
+/// Casts `samples`×`samples` rays through each pixel rendered by `camera`.
+pub fn set_samples(camera: &mut Camera, samples: u32) {
+    camera.set_samples_per_pixel(samples.max(1).saturating_mul(samples.max(1)));
+}
+
+/// Returns a counter for the scanlines rendered by `camera`.
+pub fn progress_of(camera: &Camera) -> Progress {
+    Progress::new(
+        camera.vertical_size(),
+        camera.horizontal_size() as u64 * camera.samples_per_pixel() as u64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str], config: &str) -> Options {
+        let matches = crate::app()
+            .get_matches_from_safe(["tracy-cli", "render", "scene.yml"].iter().chain(args))
+            .unwrap();
+        let config = toml::from_str(config).unwrap();
+
+        Options::parse(matches.subcommand_matches("render").unwrap(), &config).unwrap()
+    }
+
+    #[test]
+    fn falling_back_to_the_configuration() {
+        let config = "size = \"64x32\"\nsamples = 3\nthreads = 2\noutput_dir = \"renders\"\n";
+
+        let opts = options(&[], config);
+        assert_eq!(opts.output, Path::new("renders").join("render.png"));
+        assert_eq!(opts.size, Some((64, 32)));
+        assert_eq!(opts.samples, 3);
+        assert_eq!(opts.threads, Some(2));
+
+        let opts = options(
+            &["-o", "out.ppm", "-s", "8x8", "--samples", "2", "-j", "1"],
+            config,
+        );
+        assert_eq!(opts.output, PathBuf::from("out.ppm"));
+        assert_eq!(opts.size, Some((8, 8)));
+        assert_eq!(opts.samples, 2);
+        assert_eq!(opts.threads, Some(1));
+
+        let opts = options(&[], "");
+        assert_eq!(opts.output, PathBuf::from("render.png"));
+        assert_eq!(opts.size, None);
+        assert_eq!(opts.samples, 1);
+        assert_eq!(opts.threads, None);
+    }
+
+    #[test]
+    fn casting_samples_along_each_axis() {
+        let mut camera = Camera::new(4, 2, 1.0);
+
+        set_samples(&mut camera, 3);
+        assert_eq!(camera.samples_per_pixel(), 9);
+
+        set_samples(&mut camera, 0);
+        assert_eq!(camera.samples_per_pixel(), 1);
+    }
+}
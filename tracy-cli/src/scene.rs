@@ -0,0 +1,84 @@
+//! Loading of scene files.
+
+use std::{ffi::OsStr, fs, path::Path};
+
+use anyhow::{Context, Result};
+use tracy::{
+    query::World,
+    rendering::{Camera, ScenePrefab},
+};
+
+use crate::Failure;
+
+/// Syntax of a scene file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Ron,
+}
+
+impl Format {
+    /// Guesses the syntax of the file at `path`: RON for a `.ron` extension, YAML otherwise.
+    pub fn of<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(OsStr::to_str) {
+            Some("ron") => Format::Ron,
+            _ => Format::Yaml,
+        }
+    }
+}
+
+/// Parses the prefab of a scene from `data`.
+pub fn parse(data: &str, format: Format) -> Result<ScenePrefab> {
+    Ok(match format {
+        Format::Yaml => serde_yaml::from_str(data)?,
+        Format::Ron => ron::from_str(data)?,
+    })
+}
+
+/// Loads the scene described by the prefab at `path`.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<(World, Camera), Failure> {
+    let path = path.as_ref();
+
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))
+        .map_err(Failure::Input)?;
+
+    let prefab = parse(&data, Format::of(path))
+        .with_context(|| format!("invalid scene {}", path.display()))
+        .map_err(Failure::InvalidData)?;
+
+    prefab
+        .try_build()
+        .with_context(|| format!("invalid scene {}", path.display()))
+        .map_err(Failure::InvalidData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detecting_scene_formats() {
+        assert_eq!(Format::of("scenes/ch07.ron"), Format::Ron);
+        assert_eq!(Format::of("scenes/ch07.yml"), Format::Yaml);
+        assert_eq!(Format::of("scenes/ch07.yaml"), Format::Yaml);
+        assert_eq!(Format::of("scenes/ron"), Format::Yaml);
+        assert_eq!(Format::of("ch07"), Format::Yaml);
+    }
+
+    #[test]
+    fn parsing_scenes_in_each_format() {
+        let yaml = "camera: { width: 4, height: 2, fov: 60, from: [0, 0, -5], to: [0, 0, 0], \
+                    up: [0, 1, 0] }\nlights: []\nobjects: []\n";
+        let ron = "(camera: (width: 4, height: 2, fov: 60, from: (0, 0, -5), to: (0, 0, 0), \
+                   up: (0, 1, 0)), lights: [], objects: [])";
+
+        for (data, format) in &[(yaml, Format::Yaml), (ron, Format::Ron)] {
+            let (_, camera) = parse(data, *format).unwrap().try_build().unwrap();
+            assert_eq!((camera.horizontal_size(), camera.vertical_size()), (4, 2));
+        }
+
+        assert!(parse(yaml, Format::Ron).is_err());
+        assert!(parse(ron, Format::Yaml).is_err());
+    }
+}
@@ -0,0 +1,524 @@
+//! The `serve` command, rendering scenes submitted over HTTP.
+//!
+//! The server exposes the following endpoints:
+//!
+//! - `POST /render`: renders the scene file in the request body and responds with a PNG image.
+//!   The scene is parsed as RON if the `format=ron` query parameter is given, as YAML otherwise,
+//!   and `size=WIDTHxHEIGHT` overrides the resolution of its camera. With the `async` parameter,
+//!   the server immediately responds with `202 Accepted` and the ID of the render in a JSON body.
+//! - `GET /progress/:id`: reports the progress of a render as JSON.
+//! - `GET /render/:id`: responds with the PNG image of a finished render, which is then
+//!   forgotten, or with `202 Accepted` while it is still in progress.
+//!
+//! Requests are handled by a fixed number of threads, and renders by a shared pool of rendering
+//! threads. Scene files larger than [`MAX_SCENE_SIZE`] are rejected with `413 Payload Too Large`
+//! and renders of more than [`MAX_PIXELS`] pixels with `400 Bad Request`. At most [`MAX_JOBS`]
+//! renders may be in progress at once, new ones are rejected with `503 Service Unavailable` until
+//! some complete. Finished renders which are not fetched within [`JOB_TTL`] are forgotten.
+
+use std::{
+    collections::HashMap,
+    io::Read,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracy::{
+    query::World,
+    rendering::{Camera, Canvas},
+};
+
+use crate::{
+    args::{parse_number, parse_size},
+    config::Config,
+    output,
+    scene::{self, Format},
+    Failure,
+};
+
+/// Largest scene file accepted by the server, in bytes.
+const MAX_SCENE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Largest number of pixels of a render accepted by the server.
+const MAX_PIXELS: u64 = 4096 * 4096;
+
+/// How long the result of a finished render is kept for clients to fetch it.
+const JOB_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Largest number of renders in progress at the same time.
+const MAX_JOBS: usize = 16;
+
+/// Number of threads handling requests.
+const HANDLER_THREADS: usize = 8;
+
+/// Options of the `serve` command.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Address to listen on.
+    pub bind: String,
+    /// Number of rendering threads, `None` to use all cores.
+    pub threads: Option<usize>,
+}
+
+impl Options {
+    /// Reads the options from the command line, falling back to the user's `config`.
+    pub fn parse(matches: &ArgMatches, config: &Config) -> Result<Self, Failure> {
+        Ok(Self {
+            bind: matches.value_of("bind").unwrap().to_string(),
+            threads: parse_number(matches, "threads")?.or(config.threads),
+        })
+    }
+}
+
+/// State of a render submitted to the server.
+#[derive(Debug, Default)]
+struct Job {
+    completed_lines: u32,
+    total_lines: u32,
+    result: Option<Result<Vec<u8>, String>>,
+    finished: Option<Instant>,
+}
+
+/// State shared by all the request handlers.
+#[derive(Debug)]
+struct Shared {
+    pool: ThreadPool,
+    jobs: Mutex<HashMap<u64, Job>>,
+    next_id: AtomicU64,
+}
+
+impl Shared {
+    fn new(threads: Option<usize>) -> Self {
+        Self {
+            pool: ThreadPoolBuilder::new()
+                .num_threads(threads.unwrap_or(0))
+                .build()
+                .expect("failed to create rendering thread pool"),
+            jobs: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+}
+
+type Reply = Response<std::io::Cursor<Vec<u8>>>;
+
+/// Serves render requests until the process is killed.
+pub fn run(options: &Options) -> Result<(), Failure> {
+    let server = Server::http(&options.bind).map_err(|e| {
+        Failure::Unavailable(anyhow!("failed to listen on {}: {}", options.bind, e))
+    })?;
+
+    let server = Arc::new(server);
+    let shared = Arc::new(Shared::new(options.threads));
+
+    eprintln!("listening on http://{}", options.bind);
+
+    let handlers = (0..HANDLER_THREADS)
+        .map(|_| {
+            let (server, shared) = (server.clone(), shared.clone());
+
+            thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let reply = handle(&shared, &mut request);
+
+                    // The client may have gone away in the meantime, there is no one left to
+                    // report errors to
+                    let _ = request.respond(reply);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for handler in handlers {
+        let _ = handler.join();
+    }
+
+    Ok(())
+}
+
+/// Routes `request` to its handler, returning the reply to send back.
+fn handle(shared: &Arc<Shared>, request: &mut Request) -> Reply {
+    evict_expired_jobs(shared);
+
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+
+    let params = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.split_once('=').unwrap_or((p, "")))
+        .collect::<HashMap<_, _>>();
+
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+
+    match (request.method(), segments.as_slice()) {
+        (Method::Post, ["render"]) => submit(shared, request, &params),
+        (Method::Get, ["progress", id]) => progress(shared, id),
+        (Method::Get, ["render", id]) => fetch(shared, id),
+        (_, ["render"]) | (_, ["progress", _]) | (_, ["render", _]) => {
+            error(405, "method not allowed")
+        }
+        _ => error(404, "not found"),
+    }
+}
+
+/// Handles `POST /render`.
+fn submit(shared: &Arc<Shared>, request: &mut Request, params: &HashMap<&str, &str>) -> Reply {
+    // Read one byte past the limit to tell scenes of exactly the maximum size from larger ones
+    let mut data = String::new();
+    if let Err(e) = request
+        .as_reader()
+        .take(MAX_SCENE_SIZE + 1)
+        .read_to_string(&mut data)
+    {
+        return error(400, &format!("failed to read scene: {}", e));
+    }
+    if data.len() as u64 > MAX_SCENE_SIZE {
+        return error(
+            413,
+            &format!("scene exceeds the limit of {} bytes", MAX_SCENE_SIZE),
+        );
+    }
+
+    let format = match params.get("format") {
+        Some(&"ron") => Format::Ron,
+        _ => Format::Yaml,
+    };
+
+    let (world, mut camera) =
+        match scene::parse(&data, format).and_then(|prefab| Ok(prefab.try_build()?)) {
+            Ok(scene) => scene,
+            Err(e) => return error(400, &format!("invalid scene: {:#}", e)),
+        };
+
+    if let Some(size) = params.get("size") {
+        match parse_size(size) {
+            Ok((width, height)) => camera.set_size(width, height),
+            Err(e) => return error(400, &e.to_string()),
+        }
+    }
+
+    let (width, height) = (camera.horizontal_size(), camera.vertical_size());
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return error(
+            400,
+            &format!(
+                "resolution of {}x{} exceeds the limit of {} pixels",
+                width, height, MAX_PIXELS
+            ),
+        );
+    }
+
+    let id = {
+        let mut jobs = shared.jobs.lock().unwrap();
+
+        if jobs.values().filter(|job| job.result.is_none()).count() >= MAX_JOBS {
+            return error(503, "too many renders in progress, try again later");
+        }
+
+        let id = shared.next_id.fetch_add(1, Ordering::Relaxed);
+        jobs.insert(
+            id,
+            Job {
+                total_lines: camera.vertical_size(),
+                ..Default::default()
+            },
+        );
+        id
+    };
+
+    let render = {
+        let shared = shared.clone();
+
+        move || {
+            let canvas = shared.pool.install(|| {
+                let mut stream = camera.stream(&world);
+
+                while stream.advance() {
+                    if let Some(job) = shared.jobs.lock().unwrap().get_mut(&id) {
+                        job.completed_lines = stream.completed_lines();
+                    }
+                }
+
+                stream.finalize()
+            });
+
+            let png = encode(&canvas, &world, &camera);
+            if let Some(job) = shared.jobs.lock().unwrap().get_mut(&id) {
+                job.result = Some(png);
+                job.finished = Some(Instant::now());
+            }
+        }
+    };
+
+    if params.contains_key("async") {
+        shared.pool.spawn(render);
+
+        return json_reply(202, &json!({ "id": id }))
+            .with_header(header("Location", &format!("/render/{}", id)));
+    }
+
+    render();
+    fetch(shared, &id.to_string())
+}
+
+/// Forgets the finished renders which have not been fetched in time.
+fn evict_expired_jobs(shared: &Shared) {
+    shared
+        .jobs
+        .lock()
+        .unwrap()
+        .retain(|_, job| job.finished.is_none_or(|t| t.elapsed() < JOB_TTL));
+}
+
+/// Handles `GET /progress/:id`.
+fn progress(shared: &Shared, id: &str) -> Reply {
+    let jobs = shared.jobs.lock().unwrap();
+
+    match id.parse().ok().and_then(|id| jobs.get(&id)) {
+        Some(job) => json_reply(200, &progress_json(job)),
+        None => error(404, "unknown render"),
+    }
+}
+
+/// Handles `GET /render/:id`.
+fn fetch(shared: &Shared, id: &str) -> Reply {
+    let mut jobs = shared.jobs.lock().unwrap();
+
+    let id = match id.parse().ok().filter(|id| jobs.contains_key(id)) {
+        Some(id) => id,
+        None => return error(404, "unknown render"),
+    };
+
+    if jobs[&id].result.is_none() {
+        return json_reply(202, &progress_json(&jobs[&id]));
+    }
+
+    match jobs.remove(&id).unwrap().result.unwrap() {
+        Ok(png) => Response::from_data(png).with_header(header("Content-Type", "image/png")),
+        Err(e) => error(500, &e),
+    }
+}
+
+fn encode(canvas: &Canvas, world: &World, camera: &Camera) -> Result<Vec<u8>, String> {
+    output::encode_png(canvas, &output::metadata("tracy-cli serve", world, camera))
+        .map_err(|e| format!("failed to encode image: {:#}", e))
+}
+
+fn progress_json(job: &Job) -> serde_json::Value {
+    let state = match &job.result {
+        None => "rendering",
+        Some(Ok(_)) => "done",
+        Some(Err(_)) => "failed",
+    };
+
+    json!({
+        "state": state,
+        "completed_lines": job.completed_lines,
+        "total_lines": job.total_lines,
+        "progress": job.completed_lines as f64 / job.total_lines.max(1) as f64,
+    })
+}
+
+fn json_reply(status: u16, value: &serde_json::Value) -> Reply {
+    Response::from_string(value.to_string())
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn error(status: u16, msg: &str) -> Reply {
+    Response::from_string(format!("{}\n", msg)).with_status_code(status)
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_http::TestRequest;
+
+    use super::*;
+
+    const SCENE: &str = "
+camera:
+  width: 8
+  height: 6
+  fov: 60
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+
+lights:
+  - position: [-10, 10, -10]
+
+objects:
+  - shape:
+      Sphere:
+    material:
+      pattern:
+        kind:
+          solid: [1, 0.2, 1]
+";
+
+    fn shared() -> Arc<Shared> {
+        Arc::new(Shared::new(Some(1)))
+    }
+
+    fn request(shared: &Arc<Shared>, method: Method, path: &str, body: &'static str) -> Reply {
+        let mut request = TestRequest::new()
+            .with_method(method)
+            .with_path(path)
+            .with_body(body)
+            .into();
+
+        handle(shared, &mut request)
+    }
+
+    fn status(reply: &Reply) -> u16 {
+        reply.status_code().0
+    }
+
+    fn body(reply: Reply) -> String {
+        let mut body = String::new();
+        reply.into_reader().read_to_string(&mut body).unwrap();
+        body
+    }
+
+    #[test]
+    fn routing_requests() {
+        let shared = shared();
+
+        assert_eq!(status(&request(&shared, Method::Get, "/", "")), 404);
+        assert_eq!(status(&request(&shared, Method::Get, "/render", "")), 405);
+        assert_eq!(
+            status(&request(&shared, Method::Post, "/progress/1", "")),
+            405
+        );
+        assert_eq!(status(&request(&shared, Method::Get, "/render/1", "")), 404);
+        assert_eq!(
+            status(&request(&shared, Method::Get, "/progress/x", "")),
+            404
+        );
+
+        let reply = request(&shared, Method::Post, "/render", SCENE);
+        assert_eq!(status(&reply), 200);
+        assert!(reply
+            .headers()
+            .iter()
+            .any(|h| h.field.equiv("Content-Type") && h.value == "image/png"));
+
+        let reply = request(&shared, Method::Post, "/render", "camera: [");
+        assert_eq!(status(&reply), 400);
+        assert!(body(reply).starts_with("invalid scene"));
+    }
+
+    #[test]
+    fn fetching_an_async_render() {
+        let shared = shared();
+
+        let reply = request(&shared, Method::Post, "/render?async&size=4x2", SCENE);
+        assert_eq!(status(&reply), 202);
+
+        let id = serde_json::from_str::<serde_json::Value>(&body(reply)).unwrap()["id"]
+            .as_u64()
+            .unwrap();
+
+        let progress = format!("/progress/{}", id);
+        while shared.jobs.lock().unwrap()[&id].result.is_none() {
+            let reply = request(&shared, Method::Get, &progress, "");
+            assert_eq!(status(&reply), 200);
+        }
+
+        let reply = request(&shared, Method::Get, &progress, "");
+        let json = serde_json::from_str::<serde_json::Value>(&body(reply)).unwrap();
+        assert_eq!(json["state"], "done");
+        assert_eq!(json["total_lines"], 2);
+
+        let fetch = format!("/render/{}", id);
+        assert_eq!(status(&request(&shared, Method::Get, &fetch, "")), 200);
+        assert_eq!(status(&request(&shared, Method::Get, &fetch, "")), 404);
+    }
+
+    #[test]
+    fn rejecting_oversized_renders() {
+        let shared = shared();
+
+        let reply = request(&shared, Method::Post, "/render?size=8192x8192", SCENE);
+        assert_eq!(status(&reply), 400);
+        assert!(body(reply).contains("exceeds the limit"));
+
+        let scene = format!("{}{}", SCENE, " ".repeat(MAX_SCENE_SIZE as usize));
+        let reply = request(&shared, Method::Post, "/render", Box::leak(scene.into()));
+        assert_eq!(status(&reply), 413);
+
+        assert!(shared.jobs.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejecting_renders_beyond_the_job_limit() {
+        let shared = shared();
+
+        shared
+            .jobs
+            .lock()
+            .unwrap()
+            .extend((0..MAX_JOBS as u64).map(|id| (id + 100, Job::default())));
+
+        let reply = request(&shared, Method::Post, "/render", SCENE);
+        assert_eq!(status(&reply), 503);
+
+        // Finished renders waiting to be fetched do not count
+        shared.jobs.lock().unwrap().get_mut(&100).unwrap().result = Some(Ok(Vec::new()));
+
+        let reply = request(&shared, Method::Post, "/render", SCENE);
+        assert_eq!(status(&reply), 200);
+    }
+
+    #[test]
+    fn evicting_expired_jobs() {
+        let shared = shared();
+        let expired = Instant::now() - JOB_TTL - Duration::from_secs(1);
+
+        shared.jobs.lock().unwrap().extend(vec![
+            (1, Job::default()),
+            (
+                2,
+                Job {
+                    result: Some(Ok(Vec::new())),
+                    finished: Some(Instant::now()),
+                    ..Default::default()
+                },
+            ),
+            (
+                3,
+                Job {
+                    result: Some(Ok(Vec::new())),
+                    finished: Some(expired),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        evict_expired_jobs(&shared);
+
+        let mut ids = shared
+            .jobs
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![1, 2]);
+    }
+}
@@ -0,0 +1,122 @@
+//! Camera animations over a sequence of frames.
+
+use std::{f32::consts::TAU, path::Path};
+
+use anyhow::Result;
+use imgui::Ui;
+use tracy::{math::Matrix, rendering::Camera};
+
+use crate::{
+    controls::OrbitControls,
+    export::{ImageFormat, Metadata},
+    scene::Scene,
+};
+
+/// Actions requested from the timeline.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TimelineInput {
+    /// The current frame was changed.
+    pub seek: bool,
+    /// The whole animation should be exported.
+    pub export: bool,
+}
+
+/// A camera orbiting a full turn around its target.
+#[derive(Debug, Clone)]
+pub struct Turntable {
+    /// Number of frames in a full turn.
+    pub frames: u32,
+    /// The frame currently shown.
+    pub frame: u32,
+    /// Whether frames are advanced automatically once rendered.
+    pub playing: bool,
+    origin: Option<OrbitControls>,
+}
+
+impl Default for Turntable {
+    fn default() -> Self {
+        Self {
+            frames: 36,
+            frame: 0,
+            playing: false,
+            origin: None,
+        }
+    }
+}
+
+impl Turntable {
+    /// Rewinds the animation, which will restart from the next camera it is applied to.
+    pub fn reset(&mut self) {
+        self.frame = 0;
+        self.playing = false;
+        self.origin = None;
+    }
+
+    /// Moves to the next frame, wrapping around at the end of the turn.
+    pub fn advance(&mut self) {
+        self.frame = (self.frame + 1) % self.frames.max(1);
+    }
+
+    /// Returns the view transform at `frame`, orbiting from the position `camera` had when the
+    /// animation was started.
+    pub fn view_transform(&mut self, camera: &Camera, frame: u32) -> Matrix {
+        let mut orbit = self
+            .origin
+            .get_or_insert_with(|| OrbitControls::from_camera(camera))
+            .clone();
+
+        orbit.rotate(TAU * frame as f32 / self.frames.max(1) as f32);
+        orbit.view_transform()
+    }
+
+    /// Draws the timeline widgets.
+    pub fn draw(&mut self, ui: &Ui) -> TimelineInput {
+        let mut input = TimelineInput::default();
+
+        if ui.button(if self.playing { "Pause" } else { "Play" }) {
+            self.playing = !self.playing;
+        }
+
+        ui.same_line();
+        input.seek |= ui.slider("Frame", 0, self.frames.max(1) - 1, &mut self.frame);
+
+        if ui.input_scalar("Frames per turn", &mut self.frames).build() {
+            self.frames = self.frames.max(1);
+            self.frame = self.frame.min(self.frames - 1);
+            input.seek = true;
+        }
+
+        input.export = ui.button("Export frames...");
+        input
+    }
+
+    /// Renders every frame of the animation of `scene` at the given size, writing them to `dir`
+    /// as a numbered PNG sequence.
+    ///
+    /// The scene's camera is left at the current frame.
+    pub fn export<P: AsRef<Path>>(
+        &mut self,
+        scene: &mut dyn Scene,
+        width: u32,
+        height: u32,
+        dir: P,
+    ) -> Result<()> {
+        for frame in 0..self.frames {
+            let camera = scene.camera_mut();
+            camera.set_view_transform(self.view_transform(camera, frame));
+
+            let (world, camera) = scene.render(width, height);
+            let path = dir.as_ref().join(format!("frame_{:04}.png", frame));
+
+            let metadata =
+                Metadata::new(&scene.name(), &world, &camera).with("Frame", frame.to_string());
+
+            ImageFormat::Png.save(&camera.render(&world), path, &metadata)?;
+        }
+
+        let camera = scene.camera_mut();
+        camera.set_view_transform(self.view_transform(camera, self.frame));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+//! Timing of the rendering of all the scenes.
+
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use imgui::{TableFlags, Ui};
+use tracy::{query::World, rendering::Camera};
+
+use crate::worker::Scheduling;
+
+/// A scene to be benchmarked.
+pub struct BenchmarkJob {
+    /// Name of the scene.
+    pub name: String,
+    /// The world to be rendered.
+    pub world: Arc<World>,
+    /// The camera to render through, already set to the benchmark resolution.
+    pub camera: Camera,
+}
+
+/// Timings collected for a single scene.
+#[derive(Debug, Clone)]
+pub struct BenchmarkResult {
+    /// Name of the scene.
+    pub name: String,
+    /// Duration of each render.
+    pub runs: Vec<Duration>,
+    /// Number of pixels in each render.
+    pub pixels: u64,
+}
+
+impl BenchmarkResult {
+    /// Returns the duration of the fastest render.
+    pub fn min(&self) -> Duration {
+        self.runs.iter().min().copied().unwrap_or_default()
+    }
+
+    /// Returns the average duration of a render.
+    pub fn avg(&self) -> Duration {
+        if self.runs.is_empty() {
+            Duration::default()
+        } else {
+            self.runs.iter().sum::<Duration>() / self.runs.len() as u32
+        }
+    }
+
+    /// Returns the number of primary rays cast per second, on average.
+    ///
+    /// Reflected, refracted and shadow rays are not accounted for.
+    pub fn rays_per_sec(&self) -> f64 {
+        self.pixels as f64 / self.avg().as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Actions requested from the benchmark panel.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BenchmarkInput {
+    /// A new benchmark should be started.
+    pub start: bool,
+    /// The results should be exported to CSV.
+    pub export: bool,
+}
+
+/// Benchmark rendering every scene a number of times at a fixed resolution.
+pub struct Benchmark {
+    /// Number of renders per scene.
+    pub runs: u32,
+    /// Width of the renders.
+    pub width: u32,
+    /// Height of the renders.
+    pub height: u32,
+    results: Vec<BenchmarkResult>,
+    total: usize,
+    rx: Option<Receiver<BenchmarkResult>>,
+    cancel: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Default for Benchmark {
+    fn default() -> Self {
+        Self {
+            runs: 3,
+            width: 256,
+            height: 256,
+            results: Vec::new(),
+            total: 0,
+            rx: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+}
+
+impl Benchmark {
+    /// Starts benchmarking `jobs` in the background, discarding any previous result.
+    pub fn start(&mut self, jobs: Vec<BenchmarkJob>, scheduling: Scheduling) {
+        self.stop();
+
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let runs = self.runs.max(1);
+
+        self.results.clear();
+        self.total = jobs.len();
+        self.rx = Some(rx);
+        self.cancel = cancel.clone();
+
+        let pool = scheduling.build_pool();
+
+        self.thread = Some(thread::spawn(move || {
+            pool.install(|| {
+                for job in jobs {
+                    let mut result = BenchmarkResult {
+                        name: job.name,
+                        runs: Vec::new(),
+                        pixels: job.camera.horizontal_size() as u64
+                            * job.camera.vertical_size() as u64,
+                    };
+
+                    for _ in 0..runs {
+                        if cancel.load(Ordering::Relaxed) {
+                            return;
+                        }
+
+                        let start = Instant::now();
+                        job.camera.render(&job.world);
+                        result.runs.push(start.elapsed());
+                    }
+
+                    if tx.send(result).is_err() {
+                        return;
+                    }
+                }
+            })
+        }));
+    }
+
+    /// Stops the benchmark in progress, if any.
+    pub fn stop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().expect("benchmark thread panicked");
+        }
+    }
+
+    /// Returns whether a benchmark is in progress.
+    pub fn is_running(&self) -> bool {
+        match &self.thread {
+            Some(thread) => !thread.is_finished(),
+            None => false,
+        }
+    }
+
+    /// Collects the results of the scenes completed since the last call.
+    pub fn poll(&mut self) {
+        if let Some(rx) = &self.rx {
+            self.results.extend(rx.try_iter());
+        }
+    }
+
+    /// Formats the results as CSV.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("scene,width,height,runs,min_ms,avg_ms,primary_rays_per_sec\n");
+
+        for r in &self.results {
+            writeln!(
+                csv,
+                "\"{}\",{},{},{},{:.3},{:.3},{:.0}",
+                r.name.replace('"', "\"\""),
+                self.width,
+                self.height,
+                r.runs.len(),
+                r.min().as_secs_f64() * 1e3,
+                r.avg().as_secs_f64() * 1e3,
+                r.rays_per_sec(),
+            )
+            .unwrap();
+        }
+
+        csv
+    }
+
+    /// Draws the benchmark settings and results.
+    pub fn draw(&mut self, ui: &Ui) -> BenchmarkInput {
+        let mut input = BenchmarkInput::default();
+        let running = self.is_running();
+
+        ui.input_scalar("Runs per scene", &mut self.runs).build();
+        ui.input_scalar("Width", &mut self.width).build();
+        ui.input_scalar("Height", &mut self.height).build();
+
+        if running {
+            if ui.button("Stop") {
+                self.stop();
+            }
+            ui.same_line();
+            ui.text(format!(
+                "Running... {}/{} scenes",
+                self.results.len(),
+                self.total
+            ));
+        } else {
+            input.start = ui.button("Run benchmark");
+
+            if !self.results.is_empty() {
+                ui.same_line();
+                input.export = ui.button("Export CSV...");
+            }
+        }
+
+        ui.separator();
+
+        if let Some(_table) = ui.begin_table_with_flags(
+            "Benchmark results",
+            4,
+            TableFlags::BORDERS | TableFlags::ROW_BG,
+        ) {
+            ui.table_setup_column("Scene");
+            ui.table_setup_column("Min (ms)");
+            ui.table_setup_column("Avg (ms)");
+            ui.table_setup_column("Primary rays/s");
+            ui.table_headers_row();
+
+            for r in &self.results {
+                ui.table_next_row();
+                ui.table_next_column();
+                ui.text(&r.name);
+                ui.table_next_column();
+                ui.text(format!("{:.1}", r.min().as_secs_f64() * 1e3));
+                ui.table_next_column();
+                ui.text(format!("{:.1}", r.avg().as_secs_f64() * 1e3));
+                ui.table_next_column();
+                ui.text(format!("{:.0}", r.rays_per_sec()));
+            }
+        }
+
+        input
+    }
+}
+
+impl Drop for Benchmark {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,274 @@
+//! Keyboard shortcuts and command palette.
+
+use imgui::{Key, Ui};
+
+/// Keys that can be bound to a command.
+const BINDABLE_KEYS: [Key; 43] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::Escape,
+    Key::Space,
+    Key::Enter,
+    Key::Delete,
+    Key::Backspace,
+];
+
+/// An action that can be triggered from the keyboard or the command palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Render,
+    Save,
+    Cancel,
+    Open,
+    ToggleCompare,
+    ToggleInspectPixels,
+    ShowPalette,
+}
+
+impl Command {
+    /// All the available commands, in the order they are presented to the user.
+    pub const ALL: [Command; 7] = [
+        Command::Render,
+        Command::Save,
+        Command::Cancel,
+        Command::Open,
+        Command::ToggleCompare,
+        Command::ToggleInspectPixels,
+        Command::ShowPalette,
+    ];
+
+    /// Returns a human-readable description of this command.
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Render => "Render current scene",
+            Command::Save => "Save current scene as...",
+            Command::Cancel => "Stop rendering",
+            Command::Open => "Open scene file...",
+            Command::ToggleCompare => "Toggle comparison with previous render",
+            Command::ToggleInspectPixels => "Toggle pixel inspector",
+            Command::ShowPalette => "Show command palette",
+        }
+    }
+}
+
+/// A key combination triggering a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shortcut {
+    /// The key to be pressed.
+    pub key: Key,
+    /// Whether Ctrl must be held.
+    pub ctrl: bool,
+}
+
+impl Shortcut {
+    const fn new(key: Key) -> Self {
+        Self { key, ctrl: false }
+    }
+
+    const fn ctrl(key: Key) -> Self {
+        Self { key, ctrl: true }
+    }
+
+    /// Returns whether this shortcut was pressed during the last frame.
+    fn is_pressed(&self, ui: &Ui) -> bool {
+        ui.io().key_ctrl == self.ctrl && ui.is_key_pressed_no_repeat(self.key)
+    }
+}
+
+impl std::fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+/// Bindings between shortcuts and commands.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Command, Option<Shortcut>)>,
+    capturing: Option<Command>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (Command::Render, Some(Shortcut::new(Key::R))),
+                (Command::Save, Some(Shortcut::new(Key::S))),
+                (Command::Cancel, Some(Shortcut::new(Key::Escape))),
+                (Command::Open, Some(Shortcut::ctrl(Key::O))),
+                (Command::ToggleCompare, Some(Shortcut::new(Key::C))),
+                (Command::ToggleInspectPixels, Some(Shortcut::new(Key::I))),
+                (Command::ShowPalette, Some(Shortcut::ctrl(Key::P))),
+            ],
+            capturing: None,
+        }
+    }
+}
+
+impl Keymap {
+    /// Returns the shortcut bound to `command`, if any.
+    pub fn shortcut(&self, command: Command) -> Option<Shortcut> {
+        self.bindings
+            .iter()
+            .find(|(c, _)| *c == command)
+            .and_then(|(_, s)| *s)
+    }
+
+    /// Binds `shortcut` to `command`, removing it from any other command.
+    pub fn bind(&mut self, command: Command, shortcut: Option<Shortcut>) {
+        for (c, s) in &mut self.bindings {
+            if *c == command {
+                *s = shortcut;
+            } else if shortcut.is_some() && *s == shortcut {
+                *s = None;
+            }
+        }
+    }
+
+    /// Returns the command whose shortcut was pressed during the last frame.
+    ///
+    /// Shortcuts are ignored while the user is typing in a text field or rebinding a command.
+    pub fn triggered(&self, ui: &Ui) -> Option<Command> {
+        if ui.io().want_text_input || self.capturing.is_some() {
+            return None;
+        }
+
+        self.bindings
+            .iter()
+            .find(|(_, s)| matches!(s, Some(s) if s.is_pressed(ui)))
+            .map(|(c, _)| *c)
+    }
+
+    /// Draws the table of shortcuts, allowing the user to rebind them.
+    pub fn draw(&mut self, ui: &Ui) {
+        if let Some(command) = self.capturing {
+            let key = BINDABLE_KEYS
+                .iter()
+                .find(|&&key| ui.is_key_pressed_no_repeat(key));
+
+            if let Some(&key) = key {
+                let ctrl = ui.io().key_ctrl;
+                self.bind(command, Some(Shortcut { key, ctrl }));
+                self.capturing = None;
+            }
+        }
+
+        for command in Command::ALL {
+            let label = if self.capturing == Some(command) {
+                "Press a key...".to_string()
+            } else {
+                match self.shortcut(command) {
+                    Some(shortcut) => shortcut.to_string(),
+                    None => "Unbound".to_string(),
+                }
+            };
+
+            if ui.button(format!("{}##{:?}", label, command)) {
+                self.capturing = Some(command);
+            }
+
+            ui.same_line();
+            ui.text(command.name());
+        }
+    }
+}
+
+/// A searchable list of all the available commands.
+#[derive(Debug, Default, Clone)]
+pub struct CommandPalette {
+    open: bool,
+    filter: String,
+}
+
+impl CommandPalette {
+    /// Shows the palette starting from the next frame.
+    pub fn open(&mut self) {
+        self.open = true;
+        self.filter.clear();
+    }
+
+    /// Draws the palette if open, returning the command chosen by the user.
+    pub fn draw(&mut self, ui: &Ui, keymap: &Keymap) -> Option<Command> {
+        if self.open {
+            ui.open_popup("Command palette");
+            self.open = false;
+        }
+
+        let _popup = ui.begin_popup("Command palette")?;
+
+        if ui.is_window_appearing() {
+            ui.set_keyboard_focus_here();
+        }
+
+        let confirmed = ui
+            .input_text("##filter", &mut self.filter)
+            .hint("Type a command...")
+            .enter_returns_true(true)
+            .build();
+
+        let filter = self.filter.to_lowercase();
+        let mut matches = Command::ALL
+            .iter()
+            .copied()
+            .filter(|c| c.name().to_lowercase().contains(&filter));
+
+        // Enter runs the first matching command
+        let mut chosen = if confirmed { matches.next() } else { None };
+
+        for command in matches {
+            let label = match keymap.shortcut(command) {
+                Some(shortcut) => format!("{} ({})", command.name(), shortcut),
+                None => command.name().to_string(),
+            };
+
+            if ui.selectable(label) {
+                chosen = Some(command);
+            }
+        }
+
+        if chosen.is_some() {
+            ui.close_current_popup();
+        }
+
+        chosen
+    }
+}
@@ -0,0 +1,100 @@
+//! Visual comparison between the current render and the previous one.
+
+use imgui::{Image, MouseButton, TextureId, Ui};
+
+/// How the previous render is overlaid on top of the current one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareMode {
+    /// The previous render is shown left of a vertical divider, the current one right of it.
+    Split,
+    /// The previous render is blended over the current one with a given opacity.
+    OnionSkin,
+}
+
+/// Settings of the A/B comparison view.
+#[derive(Debug, Clone)]
+pub struct Compare {
+    /// Whether the comparison overlay is shown.
+    pub enabled: bool,
+    /// The selected comparison mode.
+    pub mode: CompareMode,
+    /// Horizontal position of the divider, as a fraction of the canvas width.
+    pub split: f32,
+    /// Opacity of the previous render in onion skin mode.
+    pub opacity: f32,
+}
+
+impl Default for Compare {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: CompareMode::Split,
+            split: 0.5,
+            opacity: 0.5,
+        }
+    }
+}
+
+impl Compare {
+    /// Draws the widgets controlling the comparison view.
+    pub fn draw_controls(&mut self, ui: &Ui) {
+        ui.checkbox("Compare with previous", &mut self.enabled);
+
+        if self.enabled {
+            ui.same_line();
+            ui.radio_button("Split", &mut self.mode, CompareMode::Split);
+            ui.same_line();
+            ui.radio_button("Onion skin", &mut self.mode, CompareMode::OnionSkin);
+
+            if self.mode == CompareMode::OnionSkin {
+                ui.same_line();
+                ui.set_next_item_width(120.0);
+                ui.slider("Opacity", 0.0, 1.0, &mut self.opacity);
+            }
+        }
+    }
+
+    /// Draws the `reference` render over the current one, which is expected to occupy `size`
+    /// starting from the current cursor position.
+    pub fn draw_overlay(&self, ui: &Ui, reference: TextureId, size: [f32; 2]) {
+        let origin = ui.cursor_screen_pos();
+
+        match self.mode {
+            CompareMode::Split => {
+                Image::new(reference, [size[0] * self.split, size[1]])
+                    .uv1([self.split, 1.0])
+                    .build(ui);
+
+                let x = origin[0] + size[0] * self.split;
+                ui.get_window_draw_list()
+                    .add_line([x, origin[1]], [x, origin[1] + size[1]], [1.0, 1.0, 1.0])
+                    .thickness(2.0)
+                    .build();
+            }
+            CompareMode::OnionSkin => {
+                Image::new(reference, size)
+                    .tint_col([1.0, 1.0, 1.0, self.opacity])
+                    .build(ui);
+            }
+        }
+    }
+
+    /// Moves the divider while the last submitted item is left-dragged.
+    ///
+    /// Returns `true` if the mouse input was consumed by the comparison view.
+    pub fn drag_divider(&mut self, ui: &Ui) -> bool {
+        if !self.enabled || self.mode != CompareMode::Split {
+            return false;
+        }
+
+        if ui.is_item_active() && ui.is_mouse_down(MouseButton::Left) {
+            let min = ui.item_rect_min();
+            let width = ui.item_rect_size()[0];
+
+            self.split = ((ui.io().mouse_pos[0] - min[0]) / width).clamp(0.0, 1.0);
+            return true;
+        }
+
+        false
+    }
+}
@@ -0,0 +1,174 @@
+//! User configuration persisted across sessions, and default render settings.
+
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    export::ExportSettings, headless, resolution::Resolution, tonemap::ToneMapping,
+    worker::Scheduling,
+};
+
+/// Name of the configuration file, within the user's configuration directory.
+const CONFIG_FILE: &str = "tracy/tracy-ui.ron";
+
+/// Name of the file holding the default render settings, shared with `tracy-cli`.
+const DEFAULTS_FILE: &str = "tracy/config.toml";
+
+/// Environment variable overriding the location of the default render settings.
+pub const DEFAULTS_ENV_VAR: &str = "TRACY_CONFIG";
+
+/// Settings restored when the application is launched again.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Position and size of the windows, in imgui's .ini format.
+    pub layout: String,
+    /// Last-used render resolution.
+    pub resolution: Resolution,
+    /// Last-used export options.
+    pub export: ExportSettings,
+    /// Last-used rendering thread settings.
+    pub scheduling: Scheduling,
+    /// Directory where renders were last saved.
+    pub output_dir: Option<PathBuf>,
+    /// Last-used display transform.
+    pub tone_mapping: ToneMapping,
+    /// Whether to show a rasterized preview while ray tracing.
+    pub preview: bool,
+    /// Settings of each scene, indexed by name.
+    pub scenes: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            layout: String::new(),
+            resolution: Resolution::default(),
+            export: ExportSettings::default(),
+            scheduling: Scheduling::default(),
+            output_dir: None,
+            tone_mapping: ToneMapping::default(),
+            preview: true,
+            scenes: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Returns the location of the configuration file, if the user has a configuration directory.
+    pub fn path() -> Option<PathBuf> {
+        Some(config_dir()?.join(CONFIG_FILE))
+    }
+
+    /// Loads the configuration saved by the last session.
+    ///
+    /// Falls back to the default configuration if none was saved or it cannot be read.
+    pub fn load() -> Self {
+        let path = match Self::path() {
+            Some(path) if path.exists() => path,
+            _ => return Self::default(),
+        };
+
+        let result = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|s| Ok(ron::from_str(&s)?));
+
+        result.unwrap_or_else(|e| {
+            eprintln!("Failed to load {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    /// Saves the configuration for the next session.
+    pub fn save(&self) -> Result<()> {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, data)?;
+
+        Ok(())
+    }
+}
+
+/// Default render settings, written by the user in `~/.config/tracy/config.toml`.
+///
+/// The same file is read by `tracy-cli`. Settings given there take precedence over the ones
+/// restored from the last session. The number of samples per pixel is only used by `tracy-cli`,
+/// since the UI casts a single ray per pixel.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    /// Render resolution, in the `<WIDTH>x<HEIGHT>` format.
+    pub size: Option<String>,
+    /// Number of samples per pixel.
+    pub samples: Option<u32>,
+    /// Number of rendering threads.
+    pub threads: Option<usize>,
+    /// Directory where renders are saved.
+    pub output_dir: Option<PathBuf>,
+    /// Display transform.
+    pub tonemap: Option<ToneMapping>,
+}
+
+impl Defaults {
+    /// Loads the default render settings from `path` if given, from the file named by the
+    /// `TRACY_CONFIG` environment variable if set, or from the user's configuration directory.
+    ///
+    /// A missing file in the configuration directory means no defaults, while a file given
+    /// explicitly must exist.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match (path, env::var_os(DEFAULTS_ENV_VAR)) {
+            (Some(path), _) => (path.to_path_buf(), true),
+            (None, Some(path)) => (PathBuf::from(path), true),
+            (None, None) => match config_dir() {
+                Some(dir) => (dir.join(DEFAULTS_FILE), false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+
+        let defaults: Self =
+            toml::from_str(&data).with_context(|| format!("invalid {}", path.display()))?;
+
+        if let Some(size) = &defaults.size {
+            headless::parse_size(size).with_context(|| format!("invalid {}", path.display()))?;
+        }
+
+        Ok(defaults)
+    }
+
+    /// Returns the render resolution, if any.
+    pub fn size(&self) -> Option<(u32, u32)> {
+        self.size
+            .as_deref()
+            .and_then(|s| headless::parse_size(s).ok())
+    }
+}
+
+/// Returns the user's configuration directory, if any.
+fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| env::var_os("APPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+}
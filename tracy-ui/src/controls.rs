@@ -0,0 +1,162 @@
+//! Mouse-driven camera navigation.
+
+use std::f32::consts::FRAC_PI_2;
+
+use imgui::{MouseButton, Ui};
+use tracy::{
+    math::{Matrix, Point3, Vec3},
+    query::Aabb,
+    rendering::Camera,
+};
+
+/// Radians of rotation per pixel of mouse movement.
+const ORBIT_SPEED: f32 = 0.01;
+
+/// Fraction of the orbit distance moved per pixel of mouse movement.
+const PAN_SPEED: f32 = 0.002;
+
+/// Fraction of the orbit distance moved per mouse wheel step.
+const ZOOM_SPEED: f32 = 0.1;
+
+/// Maximum elevation of the camera, kept slightly below the poles to avoid a degenerate up vector.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// Extra distance kept around framed objects, relative to their size.
+const FRAME_MARGIN: f32 = 1.1;
+
+/// Camera movements requested by the user during the last frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CameraInput {
+    /// Mouse movement while orbiting.
+    pub orbit: [f32; 2],
+    /// Mouse movement while panning.
+    pub pan: [f32; 2],
+    /// Mouse wheel steps.
+    pub zoom: f32,
+}
+
+impl CameraInput {
+    /// Captures the camera movements performed on the last submitted item.
+    ///
+    /// Left-dragging orbits around the target, middle- or right-dragging pans and scrolling zooms.
+    pub fn capture(ui: &Ui) -> Option<Self> {
+        let io = ui.io();
+        let mut input = Self::default();
+
+        if ui.is_item_active() {
+            if ui.is_mouse_down(MouseButton::Left) {
+                input.orbit = io.mouse_delta;
+            } else if ui.is_mouse_down(MouseButton::Middle) || ui.is_mouse_down(MouseButton::Right)
+            {
+                input.pan = io.mouse_delta;
+            }
+        }
+
+        if ui.is_item_hovered() {
+            input.zoom = io.mouse_wheel;
+        }
+
+        if input == Self::default() {
+            None
+        } else {
+            Some(input)
+        }
+    }
+}
+
+/// Orbiting camera controller looking at a target point.
+#[derive(Debug, Clone)]
+pub struct OrbitControls {
+    target: Point3,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl OrbitControls {
+    /// Creates a controller matching the current view of `camera`.
+    ///
+    /// The orbit target is placed along the view direction, at the point closest to the origin.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let inv = camera.view_transform().inverse().unwrap();
+
+        let eye = &inv * Point3::new(0.0, 0.0, 0.0);
+        let fwd = (&inv * Vec3::new(0.0, 0.0, -1.0)).normalize();
+
+        let distance = (Point3::default() - eye).dot(&fwd).max(1.0);
+        let offset = -fwd;
+
+        Self {
+            target: eye + fwd * distance,
+            distance,
+            yaw: offset.x.atan2(offset.z),
+            pitch: offset.y.asin(),
+        }
+    }
+
+    /// Applies the user's input to this controller.
+    pub fn apply(&mut self, input: &CameraInput) {
+        self.orbit(input.orbit[0], input.orbit[1]);
+        self.pan(input.pan[0], input.pan[1]);
+        self.zoom(input.zoom);
+    }
+
+    /// Rotates the camera around its target by the given mouse movement.
+    pub fn orbit(&mut self, dx: f32, dy: f32) {
+        self.yaw -= dx * ORBIT_SPEED;
+        self.pitch = (self.pitch + dy * ORBIT_SPEED).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Rotates the camera around the vertical axis through its target by `angle` radians.
+    pub fn rotate(&mut self, angle: f32) {
+        self.yaw += angle;
+    }
+
+    /// Moves the camera and its target parallel to the view plane by the given mouse movement.
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let fwd = (self.target - self.eye()).normalize();
+        let left = fwd.cross(&Vec3::unit_y()).normalize();
+        let up = left.cross(&fwd);
+
+        // Drag the scene along with the cursor
+        let scale = self.distance * PAN_SPEED;
+        self.target = self.target + left * (dx * scale) + up * (dy * scale);
+    }
+
+    /// Moves the camera towards (positive `steps`) or away from its target.
+    pub fn zoom(&mut self, steps: f32) {
+        self.distance = (self.distance * (1.0 - steps * ZOOM_SPEED)).max(0.1);
+    }
+
+    /// Targets the center of `bounds` and moves the camera away until they fit in the view of
+    /// `camera`, keeping the current direction of view.
+    ///
+    /// Bounds that are empty or extend to infinity are left out of view.
+    pub fn frame(&mut self, bounds: &Aabb, camera: &Camera) {
+        if !bounds.is_finite() {
+            return;
+        }
+
+        // The field of view spans the longer side of the canvas
+        let aspect = camera.horizontal_size() as f32 / camera.vertical_size() as f32;
+        let half_view = (camera.fov() / 2.0).tan() * aspect.min(1.0 / aspect);
+        let radius = (bounds.max - bounds.min).length() / 2.0;
+
+        self.target = bounds.center();
+        self.distance = (radius / half_view.atan().sin() * FRAME_MARGIN).max(0.1);
+    }
+
+    /// Returns the view transform corresponding to the current camera position.
+    pub fn view_transform(&self) -> Matrix {
+        Matrix::look_at(self.eye(), self.target, Vec3::unit_y())
+    }
+
+    fn eye(&self) -> Point3 {
+        self.target
+            + Vec3::new(
+                self.pitch.cos() * self.yaw.sin(),
+                self.pitch.sin(),
+                self.pitch.cos() * self.yaw.cos(),
+            ) * self.distance
+    }
+}
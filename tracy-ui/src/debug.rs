@@ -0,0 +1,176 @@
+//! False-color visualizations of a scene's geometry, and overlays drawn on top of its renders.
+
+use imgui::Ui;
+use tracy::{
+    math::Point3,
+    query::{Aabb, Interference, Object, ObjectHandle, World},
+    rendering::{Camera, Color},
+};
+
+/// Distance at which the depth visualization fades to half brightness.
+const DEPTH_HALF_DISTANCE: f32 = 10.0;
+
+/// Distance of the plane in front of the camera against which box edges are clipped.
+const NEAR_PLANE: f32 = 1e-3;
+
+/// Color of the bounding box wireframes.
+const BOUNDS_COLOR: [f32; 3] = [1.0, 0.8, 0.0];
+
+/// A false-color view replacing the shaded scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    /// World-space normals of the first hit, mapped to RGB.
+    Normals,
+    /// Distance of the first hit, brighter when closer.
+    Depth,
+    /// A distinct color for each object.
+    ObjectId,
+}
+
+impl DebugView {
+    /// All the views, in the order they are presented to the user.
+    pub const ALL: [DebugView; 3] = [DebugView::Normals, DebugView::Depth, DebugView::ObjectId];
+
+    /// Returns a human-readable name for this view.
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Normals => "Normals",
+            DebugView::Depth => "Depth",
+            DebugView::ObjectId => "Object IDs",
+        }
+    }
+
+    /// Computes the color of a ray whose first hit is `hit` in this view.
+    pub fn color_of(self, world: &World, hit: Option<&Interference>) -> Color {
+        let hit = match hit {
+            Some(hit) => hit,
+            None => return Color::BLACK,
+        };
+
+        match self {
+            DebugView::Normals => {
+                let n = hit.normal;
+                Color::new(n.x + 1.0, n.y + 1.0, n.z + 1.0) * 0.5
+            }
+            DebugView::Depth => {
+                let v = DEPTH_HALF_DISTANCE / (DEPTH_HALF_DISTANCE + hit.toi);
+                Color::new(v, v, v)
+            }
+            DebugView::ObjectId => object_index(world, hit.handle)
+                .map(id_color)
+                .unwrap_or(Color::WHITE),
+        }
+    }
+}
+
+/// Returns the position of the object pointed to by `handle` in the world's object list.
+pub fn object_index(world: &World, handle: ObjectHandle) -> Option<usize> {
+    let obj = world.get(handle)?;
+    world.objects().position(|o| std::ptr::eq(o, obj))
+}
+
+/// Picks a color for the `n`-th object, spreading hues along the golden ratio so that
+/// neighboring IDs are easy to tell apart.
+fn id_color(n: usize) -> Color {
+    let hue = (n as f32 * 0.618_034).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+
+    match hue as u32 {
+        0 => Color::new(1.0, x, 0.0),
+        1 => Color::new(x, 1.0, 0.0),
+        2 => Color::new(0.0, 1.0, x),
+        3 => Color::new(0.0, x, 1.0),
+        4 => Color::new(x, 0.0, 1.0),
+        _ => Color::new(1.0, 0.0, x),
+    }
+}
+
+/// Wireframes of the bounding boxes of a scene's objects, drawn over the canvas.
+///
+/// Objects with unbounded extents, such as planes, are left out.
+#[derive(Debug, Default)]
+pub struct BoundsOverlay {
+    /// Whether the overlay is shown.
+    pub enabled: bool,
+    /// Edges of the boxes as seen through the camera, in fractions of the canvas size.
+    edges: Vec<[[f32; 2]; 2]>,
+}
+
+impl BoundsOverlay {
+    /// Projects the bounding boxes of the objects of `world` as seen through `camera`.
+    pub fn update(&mut self, world: &World, camera: &Camera) {
+        self.edges = world
+            .objects()
+            .map(Object::bounds)
+            .filter(Aabb::is_finite)
+            .flat_map(|bounds| box_edges(&bounds))
+            .filter_map(|(a, b)| project_edge(camera, a, b))
+            .collect();
+    }
+
+    /// Draws the wireframes over a canvas of `size` starting at the cursor position.
+    pub fn draw(&self, ui: &Ui, size: [f32; 2]) {
+        if !self.enabled {
+            return;
+        }
+
+        let origin = ui.cursor_screen_pos();
+        let to_screen = |p: [f32; 2]| [origin[0] + p[0] * size[0], origin[1] + p[1] * size[1]];
+        let draw_list = ui.get_window_draw_list();
+
+        for &[a, b] in &self.edges {
+            draw_list
+                .add_line(to_screen(a), to_screen(b), BOUNDS_COLOR)
+                .build();
+        }
+    }
+}
+
+/// Returns the twelve edges of `bounds`.
+fn box_edges(bounds: &Aabb) -> impl Iterator<Item = (Point3, Point3)> {
+    let (min, max) = (bounds.min, bounds.max);
+    let corner = move |i: usize| {
+        Point3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        )
+    };
+
+    // Each edge joins a corner to the one across a single axis
+    (0..8)
+        .flat_map(|i| (0..3).map(move |axis| (i, i | 1 << axis)))
+        .filter(|(i, j)| i != j)
+        .map(move |(i, j)| (corner(i), corner(j)))
+}
+
+/// Projects the segment from `a` to `b` onto the canvas of `camera`, in fractions of its size.
+///
+/// The segment is clipped to the part in front of the camera, and `None` is returned if there is
+/// none.
+fn project_edge(camera: &Camera, a: Point3, b: Point3) -> Option<[[f32; 2]; 2]> {
+    let view = camera.view_transform();
+    let (mut a, mut b) = (view * a, view * b);
+
+    // The camera looks down the -z axis of its own space
+    if a.z > -NEAR_PLANE && b.z > -NEAR_PLANE {
+        return None;
+    }
+    if a.z > -NEAR_PLANE {
+        a = a + (b - a) * ((-NEAR_PLANE - a.z) / (b.z - a.z));
+    } else if b.z > -NEAR_PLANE {
+        b = b + (a - b) * ((-NEAR_PLANE - b.z) / (a.z - b.z));
+    }
+
+    // Invert the mapping of `Camera::ray_through` from the canvas to the plane at z = -1
+    let half_width = camera.pixel_size() * camera.horizontal_size() as f32 / 2.0;
+    let half_height = camera.pixel_size() * camera.vertical_size() as f32 / 2.0;
+    let to_canvas = |p: Point3| {
+        [
+            (half_width + p.x / p.z) / (2.0 * half_width),
+            (half_height + p.y / p.z) / (2.0 * half_height),
+        ]
+    };
+
+    Some([to_canvas(a), to_canvas(b)])
+}
@@ -0,0 +1,205 @@
+//! Saving of rendered canvases to image files.
+
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracy::{
+    query::World,
+    rendering::{Camera, Canvas},
+};
+
+/// Description of how a render was produced, embedded in the saved images that support it.
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    entries: Vec<(String, String)>,
+}
+
+impl Metadata {
+    /// Describes a render of `scene`, made of `world` as seen by `camera`.
+    pub fn new(scene: &str, world: &World, camera: &Camera) -> Self {
+        let transform = camera.view_transform();
+        let rows = (0..4)
+            .map(|i| {
+                let row = (0..4).map(|j| transform[(i, j)].to_string());
+                format!("[{}]", row.collect::<Vec<_>>().join(", "))
+            })
+            .collect::<Vec<_>>();
+
+        Self::default()
+            .with("Title", scene)
+            .with(
+                "Software",
+                format!(
+                    "tracy-ui {} (tracy {})",
+                    env!("CARGO_PKG_VERSION"),
+                    tracy::VERSION
+                ),
+            )
+            .with(
+                "Resolution",
+                format!("{}x{}", camera.horizontal_size(), camera.vertical_size()),
+            )
+            .with("Field of view", format!("{:.2}°", camera.fov().to_degrees()))
+            .with("View transform", format!("[{}]", rows.join(", ")))
+            .with("Samples per pixel", "1")
+            .with("Recursion depth", camera.recursion_limit_for(world).to_string())
+    }
+
+    /// Adds an entry to the metadata.
+    pub fn with<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.entries.push((key.into(), value.into()));
+        self
+    }
+
+    /// Encodes an entry as the data of a PNG `tEXt` chunk, replacing the characters that do not
+    /// fit in Latin-1.
+    fn png_text_chunk(key: &str, value: &str) -> Vec<u8> {
+        let latin1 = |s: &str| {
+            s.chars()
+                .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+                .collect::<Vec<_>>()
+        };
+
+        let mut data = latin1(key);
+        data.push(0);
+        data.extend(latin1(value));
+        data
+    }
+}
+
+/// Image formats supported when saving a render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    PpmAscii,
+    PpmBinary,
+    Exr,
+}
+
+impl ImageFormat {
+    /// All the supported formats, in the order they are presented to the user.
+    pub const ALL: [ImageFormat; 4] = [
+        ImageFormat::Png,
+        ImageFormat::PpmAscii,
+        ImageFormat::PpmBinary,
+        ImageFormat::Exr,
+    ];
+
+    /// Returns a human-readable name for this format.
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::PpmAscii => "PPM (ASCII)",
+            ImageFormat::PpmBinary => "PPM (binary)",
+            ImageFormat::Exr => "OpenEXR",
+        }
+    }
+
+    /// Returns the file extension commonly used for this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::PpmAscii | ImageFormat::PpmBinary => "ppm",
+            ImageFormat::Exr => "exr",
+        }
+    }
+
+    /// Guesses the format of `path` from its extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| format.extension() == extension)
+    }
+
+    /// Writes `canvas` to `path` in this format.
+    ///
+    /// EXR files store the canvas' linear colors as they are, all the other formats are clamped
+    /// to 8 bits per channel.
+    ///
+    /// `metadata` is stored in PNG text chunks, and is left out of the other formats.
+    pub fn save<P: AsRef<Path>>(self, canvas: &Canvas, path: P, metadata: &Metadata) -> Result<()> {
+        let (width, height) = (canvas.width(), canvas.height());
+
+        match self {
+            ImageFormat::Png => {
+                let buf = canvas
+                    .iter()
+                    .flat_map(|p| {
+                        let (r, g, b) = p.to_rgb888();
+                        vec![r, g, b]
+                    })
+                    .collect::<Vec<u8>>();
+
+                let mut encoder =
+                    png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+                encoder.set_color(png::ColorType::RGB);
+                encoder.set_depth(png::BitDepth::Eight);
+
+                let mut writer = encoder.write_header()?;
+                for (key, value) in &metadata.entries {
+                    writer.write_chunk(*b"tEXt", &Metadata::png_text_chunk(key, value))?;
+                }
+                writer.write_image_data(&buf)?;
+            }
+            ImageFormat::PpmAscii => fs::write(path, canvas.convert_to_ppm())?,
+            ImageFormat::PpmBinary => fs::write(path, canvas.convert_to_binary_ppm())?,
+            ImageFormat::Exr => {
+                exr::prelude::write_rgb_file(path, width as usize, height as usize, |x, y| {
+                    let c = canvas.get(x as u32, y as u32).unwrap();
+                    (c.r, c.g, c.b)
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// User-selected options for saving a render.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    /// Index of the output format in [`ImageFormat::ALL`].
+    pub format: usize,
+    /// Whether to render at a custom resolution rather than the canvas size.
+    pub override_size: bool,
+    /// Custom output width.
+    pub width: u32,
+    /// Custom output height.
+    pub height: u32,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            format: 0,
+            override_size: false,
+            width: 1920,
+            height: 1080,
+        }
+    }
+}
+
+impl ExportSettings {
+    /// Returns the selected output format.
+    pub fn format(&self) -> ImageFormat {
+        ImageFormat::ALL[self.format]
+    }
+
+    /// Returns the output resolution, using `canvas_size` unless overridden.
+    pub fn size(&self, canvas_size: (u32, u32)) -> (u32, u32) {
+        if self.override_size {
+            (self.width.max(1), self.height.max(1))
+        } else {
+            canvas_size
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Rendering of scenes without opening a window.
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::{
+    config::Defaults,
+    export::{ImageFormat, Metadata},
+    scene::{self, Scene},
+    tonemap::ToneMapping,
+    worker::Scheduling,
+};
+
+/// Command line usage of the headless mode.
+pub const USAGE: &str = "\
+Usage: tracy-ui --headless --scene <SCENE> [--out <FILE>] [--size <WIDTH>x<HEIGHT>]
+
+Options:
+    --scene <SCENE>    Built-in scene (e.g. chapter11, appendixa1) or path to a scene file
+    --out <FILE>       Output image, its format deduced from the extension [default: render.png]
+    --size <WxH>       Resolution of the output image [default: 800x600]
+    --threads <N>      Number of rendering threads [default: all cores]
+    --config <FILE>    Default render settings [default: ~/.config/tracy/config.toml]
+    --list             List the built-in scenes and exit";
+
+/// Options of a headless render, parsed from the command line.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Name or path of the scene to render, `None` to list the built-in scenes.
+    pub scene: Option<String>,
+    /// Path of the output image.
+    pub out: PathBuf,
+    /// Resolution of the output image.
+    pub size: (u32, u32),
+    /// How the rendering work is scheduled.
+    pub scheduling: Scheduling,
+    /// Display transform applied to the image before it is saved.
+    pub tone_mapping: ToneMapping,
+}
+
+impl Options {
+    /// Parses the options from the program's arguments, excluding the program name.
+    ///
+    /// Options not given on the command line are taken from the user's default render settings.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut scene = None;
+        let mut out = None;
+        let mut size = None;
+        let mut threads = None;
+        let mut config = None;
+        let mut list = false;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| anyhow!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--headless" => (),
+                "--list" => list = true,
+                "--scene" => scene = Some(value()?),
+                "--out" => out = Some(PathBuf::from(value()?)),
+                "--size" => size = Some(parse_size(&value()?)?),
+                "--threads" => threads = Some(value()?.parse().context("invalid thread count")?),
+                "--config" => config = Some(PathBuf::from(value()?)),
+                _ => bail!("unknown argument: {}", arg),
+            }
+        }
+
+        let defaults = Defaults::load(config.as_deref())?;
+
+        let mut options = Self {
+            scene,
+            out: out.unwrap_or_else(|| {
+                defaults
+                    .output_dir
+                    .clone()
+                    .unwrap_or_default()
+                    .join("render.png")
+            }),
+            size: size.or_else(|| defaults.size()).unwrap_or((800, 600)),
+            scheduling: Scheduling::default(),
+            tone_mapping: defaults.tonemap.unwrap_or_default(),
+        };
+
+        if let Some(threads) = threads.or(defaults.threads) {
+            options.scheduling.threads = threads;
+        }
+
+        match (list, &options.scene) {
+            (true, _) => options.scene = None,
+            (false, None) => bail!("no scene given"),
+            (false, Some(_)) => (),
+        }
+
+        Ok(options)
+    }
+}
+
+/// Renders the scene chosen in `options` and saves it to the output image.
+pub fn run(options: &Options) -> Result<()> {
+    let name = match &options.scene {
+        Some(name) => name,
+        None => {
+            for scene in scene::get_scene_list()? {
+                println!("{:<16}{}", scene_key(&scene.name()), scene.name());
+            }
+            return Ok(());
+        }
+    };
+
+    let format = ImageFormat::from_path(&options.out)
+        .ok_or_else(|| anyhow!("unsupported output format: {}", options.out.display()))?;
+
+    let mut scene = find_scene(name)?;
+    let (width, height) = options.size;
+    let (world, camera) = scene.render(width, height);
+
+    let canvas = options
+        .scheduling
+        .build_pool()
+        .install(|| camera.render(&world));
+
+    let canvas = options.tone_mapping.apply(&canvas);
+    let metadata = Metadata::new(&scene.name(), &world, &camera);
+
+    format
+        .save(&canvas, &options.out, &metadata)
+        .with_context(|| format!("failed to save {}", options.out.display()))
+}
+
+/// Looks up a built-in scene by its key or full name, falling back to opening a scene file.
+fn find_scene(name: &str) -> Result<Box<dyn Scene>> {
+    let wanted = normalize(name);
+
+    for scene in scene::get_scene_list()? {
+        if scene_key(&scene.name()) == wanted || normalize(&scene.name()) == wanted {
+            return Ok(scene);
+        }
+    }
+
+    let path = PathBuf::from(name);
+
+    if scene::has_extension(&path, &scene::EXTENSIONS) {
+        scene::open_scene(&path).with_context(|| format!("failed to open {}", path.display()))
+    } else {
+        bail!("no such scene: {} (see --list)", name)
+    }
+}
+
+/// Returns the short name of a scene used on the command line, e.g. `chapter11` for
+/// "Chapter 11: Reflections".
+fn scene_key(name: &str) -> String {
+    normalize(name.split(':').next().unwrap_or(name))
+}
+
+/// Lowercases `s`, keeping only alphanumeric characters.
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Parses a resolution in the `<WIDTH>x<HEIGHT>` format.
+pub fn parse_size(s: &str) -> Result<(u32, u32)> {
+    let (w, h) = s
+        .split_once('x')
+        .ok_or_else(|| anyhow!("invalid size {}, expected <WIDTH>x<HEIGHT>", s))?;
+
+    let width = w.parse().with_context(|| format!("invalid width: {}", w))?;
+    let height = h
+        .parse()
+        .with_context(|| format!("invalid height: {}", h))?;
+
+    if width == 0 || height == 0 {
+        bail!("invalid size {}, expected a non-empty image", s);
+    }
+
+    Ok((width, height))
+}
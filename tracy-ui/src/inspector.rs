@@ -0,0 +1,171 @@
+//! Live editor for the objects and lights of a world.
+
+use imgui::Ui;
+use tracy::{
+    query::{LightHandle, World},
+    rendering::{Color, Material, Pattern, PatternKind, PointLight},
+};
+
+/// A change to a world requested from the inspector.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    /// Replaces the material of the `n`-th object.
+    Material(usize, Material),
+    /// Replaces the light identified by the handle.
+    Light(LightHandle, PointLight),
+}
+
+impl Edit {
+    /// Applies this change to `world`.
+    pub fn apply(self, world: &mut World) {
+        match self {
+            Edit::Material(n, material) => {
+                if let Some(obj) = world.objects_mut().nth(n) {
+                    obj.set_material(material);
+                }
+            }
+            Edit::Light(handle, light) => {
+                if let Some(l) = world.get_light_mut(handle) {
+                    *l = light;
+                }
+            }
+        }
+    }
+}
+
+/// Draws the editing widgets for all the objects and lights of `world`.
+///
+/// The world is not modified directly, since it may be in use by a render. Instead, the list of
+/// requested changes is returned so that they can be applied once the world is available.
+pub fn draw(ui: &Ui, world: &World) -> Vec<Edit> {
+    let mut edits = Vec::new();
+
+    for (i, obj) in world.objects().enumerate() {
+        let shape = format!("{:?}", obj.shape());
+        let name = shape.split(|c: char| !c.is_alphanumeric()).next().unwrap();
+
+        if let Some(_token) = ui.tree_node(format!("Object #{} ({})", i, name)) {
+            let mut material = obj.material().clone();
+
+            if draw_material(ui, i, &mut material) {
+                edits.push(Edit::Material(i, material));
+            }
+        }
+    }
+
+    for (i, (handle, light)) in world.light_handles().zip(world.lights()).enumerate() {
+        if let Some(_token) = ui.tree_node(format!("Light #{}", i)) {
+            let mut light = light.clone();
+
+            if draw_light(ui, i, &mut light) {
+                edits.push(Edit::Light(handle, light));
+            }
+        }
+    }
+
+    edits
+}
+
+fn draw_material(ui: &Ui, id: usize, material: &mut Material) -> bool {
+    let mut changed = false;
+
+    // Only solid colors can be edited, nested patterns are left alone
+    if let PatternKind::Solid(c) = material.pattern.kind() {
+        let mut color = [c.r, c.g, c.b];
+
+        if ui.color_edit3(format!("Color##obj{}", id), &mut color) {
+            material.pattern = Pattern::new(Color::from(color).into());
+            changed = true;
+        }
+    }
+
+    changed |= ui.slider(
+        format!("Ambient##obj{}", id),
+        0.0,
+        1.0,
+        &mut material.ambient,
+    );
+    changed |= ui.slider(
+        format!("Diffuse##obj{}", id),
+        0.0,
+        1.0,
+        &mut material.diffuse,
+    );
+    changed |= ui.slider(
+        format!("Specular##obj{}", id),
+        0.0,
+        1.0,
+        &mut material.specular,
+    );
+    changed |= ui.slider(
+        format!("Shininess##obj{}", id),
+        1.0,
+        400.0,
+        &mut material.shininess,
+    );
+    changed |= ui.slider(
+        format!("Reflective##obj{}", id),
+        0.0,
+        1.0,
+        &mut material.reflective,
+    );
+    changed |= ui.slider(
+        format!("Reflection roughness##obj{}", id),
+        0.0,
+        1.0,
+        &mut material.reflection_roughness,
+    );
+    changed |= ui.slider(
+        format!("Transparency##obj{}", id),
+        0.0,
+        1.0,
+        &mut material.transparency,
+    );
+    changed |= ui.slider(
+        format!("Refraction roughness##obj{}", id),
+        0.0,
+        1.0,
+        &mut material.refraction_roughness,
+    );
+    changed |= ui.slider(
+        format!("Refractive index##obj{}", id),
+        1.0,
+        3.0,
+        &mut material.refractive_index,
+    );
+    changed |= ui.checkbox(format!("Metallic##obj{}", id), &mut material.metallic);
+
+    changed
+}
+
+fn draw_light(ui: &Ui, id: usize, light: &mut PointLight) -> bool {
+    let mut changed = false;
+
+    let mut position = [light.position.x, light.position.y, light.position.z];
+    if ui
+        .input_float3(format!("Position##light{}", id), &mut position)
+        .build()
+    {
+        light.position = position.into();
+        changed = true;
+    }
+
+    let mut color = [light.color.r, light.color.g, light.color.b];
+    if ui.color_edit3(format!("Color##light{}", id), &mut color) {
+        light.color = color.into();
+        changed = true;
+    }
+
+    changed |= ui.slider(
+        format!("Intensity##light{}", id),
+        0.0,
+        2.0,
+        &mut light.intensity,
+    );
+    changed |= ui.checkbox(
+        format!("Cast shadows##light{}", id),
+        &mut light.casts_shadows,
+    );
+
+    changed
+}
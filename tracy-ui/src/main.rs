@@ -3,11 +3,45 @@
 #![deny(missing_debug_implementations)]
 #![warn(missing_docs)]
 
+use std::{env, process};
+
+use headless::Options;
 use ui::TracyUi;
 
+mod animation;
+mod benchmark;
+mod commands;
+mod compare;
+mod config;
+mod controls;
+mod debug;
+mod export;
+mod headless;
+mod inspector;
+mod pixel;
+mod preview;
+mod region;
+mod resolution;
 mod scene;
+mod tonemap;
 mod ui;
+mod worker;
 
 fn main() {
-    TracyUi::new("Tracy - a ray tracing renderer", 1280, 640).run();
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    if !args
+        .iter()
+        .any(|arg| arg == "--headless" || arg == "--list")
+    {
+        TracyUi::new("Tracy - a ray tracing renderer", 1280, 640).run();
+        return;
+    }
+
+    let result = Options::parse(args).and_then(|options| headless::run(&options));
+
+    if let Err(e) = result {
+        eprintln!("error: {:#}\n\n{}", e, headless::USAGE);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,89 @@
+//! Inspection of single pixels of a render.
+
+use imgui::{Image, TextureId, Ui};
+use tracy::{
+    query::World,
+    rendering::{Aov, Canvas, Color},
+};
+
+use crate::debug;
+
+/// Number of canvas pixels shown across the loupe.
+const LOUPE_PIXELS: f32 = 16.0;
+
+/// On-screen size of the loupe.
+const LOUPE_SIZE: f32 = 160.0;
+
+/// Information gathered about a single pixel of a render.
+#[derive(Debug, Clone)]
+pub struct PixelInfo {
+    /// Horizontal coordinate of the pixel.
+    pub x: u32,
+    /// Vertical coordinate of the pixel.
+    pub y: u32,
+    /// Linear color of the pixel.
+    pub color: Color,
+    /// Index of the first object hit by the primary ray through the pixel, with its distance.
+    pub hit: Option<(usize, f32)>,
+}
+
+impl PixelInfo {
+    /// Probes the pixel at `uv` (in normalized canvas coordinates) of a render of `world`, given
+    /// the `aovs` recorded along with its `canvas`.
+    ///
+    /// Returns `None` if the coordinates are out of the canvas.
+    pub fn probe(world: &World, canvas: &Canvas, aovs: &[Aov], uv: [f32; 2]) -> Option<Self> {
+        let x = (uv[0] * canvas.width() as f32) as u32;
+        let y = (uv[1] * canvas.height() as f32) as u32;
+
+        let color = *canvas.get(x, y)?;
+        let aov = aovs.get(y as usize * canvas.width() as usize + x as usize)?;
+        let hit = aov
+            .object
+            .and_then(|handle| Some((debug::object_index(world, handle)?, aov.depth)));
+
+        Some(Self { x, y, color, hit })
+    }
+
+    /// Draws a tooltip with a zoomed crop of `texture` around this pixel and its details.
+    pub fn draw_tooltip(&self, ui: &Ui, texture: TextureId, canvas: &Canvas) {
+        let (w, h) = (canvas.width() as f32, canvas.height() as f32);
+        let center = [(self.x as f32 + 0.5) / w, (self.y as f32 + 0.5) / h];
+        let radius = [LOUPE_PIXELS / 2.0 / w, LOUPE_PIXELS / 2.0 / h];
+
+        ui.tooltip(|| {
+            let origin = ui.cursor_screen_pos();
+
+            Image::new(texture, [LOUPE_SIZE, LOUPE_SIZE])
+                .uv0([center[0] - radius[0], center[1] - radius[1]])
+                .uv1([center[0] + radius[0], center[1] + radius[1]])
+                .border_col([1.0, 1.0, 1.0, 1.0])
+                .build(ui);
+
+            // Outline the inspected pixel
+            let cell = LOUPE_SIZE / LOUPE_PIXELS;
+            let min = [
+                origin[0] + LOUPE_SIZE / 2.0 - cell / 2.0,
+                origin[1] + LOUPE_SIZE / 2.0 - cell / 2.0,
+            ];
+            ui.get_window_draw_list()
+                .add_rect(min, [min[0] + cell, min[1] + cell], [1.0, 0.0, 0.0])
+                .build();
+
+            let Color { r, g, b } = self.color;
+            let (r8, g8, b8) = self.color.to_rgb888();
+
+            ui.text(format!("Pixel: ({}, {})", self.x, self.y));
+            ui.text(format!("Linear: ({:.4}, {:.4}, {:.4})", r, g, b));
+            ui.text(format!("8-bit: ({}, {}, {})", r8, g8, b8));
+
+            match self.hit {
+                Some((index, depth)) => {
+                    ui.text(format!("Object: #{}", index));
+                    ui.text(format!("Depth: {:.4}", depth));
+                }
+                None => ui.text("Object: none"),
+            }
+        });
+    }
+}
@@ -0,0 +1,428 @@
+//! Rasterized preview of a scene, shown while it is being ray traced.
+
+use std::f32::consts::PI;
+
+use tracy::{
+    math::Point3,
+    query::{Object, World},
+    rendering::{Camera, Canvas, Color},
+    shape::{Cone, Cube, Cylinder, Mesh, Plane, Quad, Sphere, Triangle},
+};
+use wgpu::util::DeviceExt;
+
+/// Format of the offscreen color target.
+const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Format of the offscreen depth buffer.
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Distance of the near clipping plane from the eye.
+const NEAR: f32 = 0.01;
+
+/// Distance of the far clipping plane from the eye.
+const FAR: f32 = 10_000.0;
+
+/// Half the side of the quad standing in for infinite planes and cylinders.
+const EXTENT: f32 = 1_000.0;
+
+/// Number of subdivisions around the Y axis of curved shapes.
+const SLICES: usize = 32;
+
+/// Number of subdivisions from pole to pole of spheres.
+const STACKS: usize = 16;
+
+/// Number of floats per vertex: clip-space position, view-space position and color.
+const VERTEX_FLOATS: usize = 4 + 3 + 3;
+
+const SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) clip: vec4<f32>,
+    @location(0) view: vec3<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+    @location(0) clip: vec4<f32>,
+    @location(1) view: vec3<f32>,
+    @location(2) color: vec3<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip = clip;
+    out.view = view;
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Flat shading from the face normal, lit by a headlight at the eye
+    let n = normalize(cross(dpdx(in.view), dpdy(in.view)));
+    let shade = 0.2 + 0.8 * abs(dot(n, normalize(-in.view)));
+    return vec4<f32>(in.color * shade, 1.0);
+}
+"#;
+
+/// Rasterizer drawing flat-shaded approximations of a scene's objects on the GPU.
+pub struct Preview {
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Preview {
+    /// Creates the rendering pipeline on `device`.
+    pub fn new(device: &wgpu::Device) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("preview shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("preview pipeline layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("preview pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: (VERTEX_FLOATS * std::mem::size_of::<f32>()) as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x3, 2 => Float32x3],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: COLOR_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: Default::default(),
+            multiview: None,
+        });
+
+        Self { pipeline }
+    }
+
+    /// Rasterizes `world` as seen by `camera`, blocking until the image is read back.
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &World,
+        camera: &Camera,
+    ) -> Canvas {
+        let (width, height) = (camera.horizontal_size(), camera.vertical_size());
+        let vertices = tessellate(world, camera);
+
+        let mut canvas = Canvas::new(width, height);
+        if vertices.is_empty() {
+            return canvas;
+        }
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let target = |format: wgpu::TextureFormat, usage: wgpu::TextureUsages| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("preview target"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+
+        let color = target(
+            COLOR_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        );
+        let depth = target(DEPTH_FORMAT, wgpu::TextureUsages::RENDER_ATTACHMENT);
+
+        let color_view = color.create_view(&Default::default());
+        let depth_view = depth.create_view(&Default::default());
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("preview vertices"),
+            contents: &vertices
+                .iter()
+                .flat_map(|f| f.to_ne_bytes())
+                .collect::<Vec<_>>(),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        // Rows copied out of a texture must be aligned
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let bytes_per_row = (width * 4 + align - 1) / align * align;
+
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("preview readback"),
+            size: (bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("preview encoder"),
+        });
+
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("preview pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &color_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            pass.set_pipeline(&self.pipeline);
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            pass.draw(0..(vertices.len() / VERTEX_FLOATS) as u32, 0..1);
+        }
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &color,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            size,
+        );
+
+        queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| ());
+        device.poll(wgpu::Maintain::Wait);
+
+        {
+            let data = slice.get_mapped_range();
+
+            for (y, row) in data.chunks(bytes_per_row as usize).enumerate() {
+                for (x, px) in row.chunks(4).take(width as usize).enumerate() {
+                    let [r, g, b] = [px[0], px[1], px[2]].map(|c| c as f32 / 255.0);
+                    canvas.put(x as u32, y as u32, Color::new(r, g, b));
+                }
+            }
+        }
+
+        readback.unmap();
+        canvas
+    }
+}
+
+/// Builds the vertices of the triangles approximating all the objects in `world`.
+///
+/// Positions are projected on the CPU, so that the vertex shader is a mere pass-through.
+fn tessellate(world: &World, camera: &Camera) -> Vec<f32> {
+    // Extent of the canvas, one unit in front of the eye
+    let half_width = camera.pixel_size() * camera.horizontal_size() as f32 / 2.0;
+    let half_height = camera.pixel_size() * camera.vertical_size() as f32 / 2.0;
+
+    let mut vertices = Vec::new();
+
+    for obj in world.objects() {
+        let to_view = camera.view_transform() * obj.transform();
+        let Color { r, g, b } = obj.material().color_at(&Point3::new(0.0, 0.0, 0.0));
+
+        for p in triangles(obj) {
+            let [x, y, z, _] = &to_view * [p[0], p[1], p[2], 1.0];
+
+            // The eye looks down -Z, with the canvas mirrored along X as in `Camera::ray_through`
+            let d = -z;
+            vertices.extend_from_slice(&[
+                -x / half_width,
+                y / half_height,
+                (d - NEAR) * FAR / (FAR - NEAR),
+                d,
+                x,
+                y,
+                z,
+                r,
+                g,
+                b,
+            ]);
+        }
+    }
+
+    vertices
+}
+
+/// Returns the object-space vertices of the triangles approximating `obj`'s shape.
+///
+/// Shapes without a known approximation are left out of the preview.
+fn triangles(obj: &Object) -> Vec<[f32; 3]> {
+    let shape = obj.shape().as_any();
+
+    if shape.is::<Sphere>() {
+        sphere()
+    } else if shape.is::<Cube>() {
+        cube()
+    } else if shape.is::<Plane>() {
+        quad(|u, v| [u * EXTENT, 0.0, v * EXTENT])
+    } else if let Some(q) = shape.downcast_ref::<Quad>() {
+        let (x, z) = (q.width() / 2.0, q.depth() / 2.0);
+        quad(|u, v| [u * x, 0.0, v * z])
+    } else if let Some(cylinder) = shape.downcast_ref::<Cylinder>() {
+        self::cylinder(cylinder)
+    } else if let Some(cone) = shape.downcast_ref::<Cone>() {
+        self::cone(cone)
+    } else if let Some(triangle) = shape.downcast_ref::<Triangle>() {
+        triangle.vertices().iter().map(|&p| p.into()).collect()
+    } else if let Some(mesh) = shape.downcast_ref::<Mesh>() {
+        mesh.triangles()
+            .iter()
+            .flat_map(|t| t.vertices())
+            .map(|&p| p.into())
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Returns the two triangles of the quad mapped by `f` from `[-1, 1]²`.
+fn quad<F: Fn(f32, f32) -> [f32; 3]>(f: F) -> Vec<[f32; 3]> {
+    vec![
+        f(-1.0, -1.0),
+        f(1.0, -1.0),
+        f(1.0, 1.0),
+        f(-1.0, -1.0),
+        f(1.0, 1.0),
+        f(-1.0, 1.0),
+    ]
+}
+
+/// Returns the triangles of a grid of `u` × `v` cells mapped by `f` from `[0, 1]²`.
+fn grid<F: Fn(f32, f32) -> [f32; 3]>(u: usize, v: usize, f: F) -> Vec<[f32; 3]> {
+    let mut vertices = Vec::with_capacity(u * v * 6);
+
+    for i in 0..u {
+        for j in 0..v {
+            let (u0, u1) = (i as f32 / u as f32, (i + 1) as f32 / u as f32);
+            let (v0, v1) = (j as f32 / v as f32, (j + 1) as f32 / v as f32);
+
+            vertices.extend_from_slice(&[
+                f(u0, v0),
+                f(u1, v0),
+                f(u1, v1),
+                f(u0, v0),
+                f(u1, v1),
+                f(u0, v1),
+            ]);
+        }
+    }
+
+    vertices
+}
+
+fn sphere() -> Vec<[f32; 3]> {
+    grid(SLICES, STACKS, |u, v| {
+        let (theta, phi) = (u * 2.0 * PI, v * PI);
+        [phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin()]
+    })
+}
+
+fn cube() -> Vec<[f32; 3]> {
+    let mut vertices = Vec::with_capacity(36);
+
+    for s in [-1.0, 1.0] {
+        vertices.extend(quad(|u, v| [s, u, v]));
+        vertices.extend(quad(|u, v| [u, s, v]));
+        vertices.extend(quad(|u, v| [u, v, s]));
+    }
+
+    vertices
+}
+
+fn cylinder(cylinder: &Cylinder) -> Vec<[f32; 3]> {
+    let bottom = cylinder.bottom().max(-EXTENT);
+    let top = cylinder.top().min(EXTENT);
+
+    let mut vertices = grid(SLICES, 1, |u, v| {
+        let theta = u * 2.0 * PI;
+        [theta.cos(), bottom + v * (top - bottom), theta.sin()]
+    });
+
+    if cylinder.closed() {
+        for y in [bottom, top] {
+            vertices.extend(grid(SLICES, 1, |u, v| {
+                let theta = u * 2.0 * PI;
+                [v * theta.cos(), y, v * theta.sin()]
+            }));
+        }
+    }
+
+    vertices
+}
+
+fn cone(cone: &Cone) -> Vec<[f32; 3]> {
+    let bottom = cone.bottom().max(-EXTENT);
+    let top = cone.top().min(EXTENT);
+    let mut vertices = Vec::new();
+
+    // Each nappe is drawn separately, since the radius is not linear in Y across the apex
+    for (y0, y1) in [(bottom, top.min(0.0)), (bottom.max(0.0), top)] {
+        if y0 < y1 {
+            vertices.extend(grid(SLICES, 1, |u, v| {
+                let theta = u * 2.0 * PI;
+                let y = y0 + v * (y1 - y0);
+                [y.abs() * theta.cos(), y, y.abs() * theta.sin()]
+            }));
+        }
+    }
+
+    if cone.closed() {
+        for y in [bottom, top] {
+            vertices.extend(grid(SLICES, 1, |u, v| {
+                let theta = u * 2.0 * PI;
+                [v * y.abs() * theta.cos(), y, v * y.abs() * theta.sin()]
+            }));
+        }
+    }
+
+    vertices
+}
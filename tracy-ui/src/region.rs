@@ -0,0 +1,128 @@
+//! Selective re-rendering of a region of the canvas.
+
+use std::ops::Range;
+
+use imgui::{MouseButton, Ui};
+use tracy::rendering::Camera;
+
+/// Quality settings used when re-rendering a region.
+#[derive(Debug, Clone, Copy)]
+pub struct RegionQuality {
+    /// Number of rays cast along each axis of a pixel.
+    pub samples: u32,
+    /// Maximum number of reflections/refractions per ray.
+    pub depth: u32,
+}
+
+impl Default for RegionQuality {
+    fn default() -> Self {
+        Self {
+            samples: 4,
+            depth: 10,
+        }
+    }
+}
+
+impl RegionQuality {
+    /// Draws the widgets controlling the region quality.
+    pub fn draw(&mut self, ui: &Ui) {
+        ui.slider("Region samples", 1, 8, &mut self.samples);
+        ui.slider("Region recursion depth", 1, 16, &mut self.depth);
+    }
+}
+
+/// A rectangular area of the canvas to be re-rendered.
+#[derive(Debug, Clone)]
+pub struct Region {
+    min: (u32, u32),
+    max: (u32, u32),
+    quality: RegionQuality,
+}
+
+impl Region {
+    /// Creates the region of a `width` x `height` canvas spanned by two opposite corners, given in
+    /// normalized canvas coordinates.
+    ///
+    /// Returns `None` if the region doesn't contain any pixel.
+    pub fn new(
+        a: [f32; 2],
+        b: [f32; 2],
+        width: u32,
+        height: u32,
+        quality: RegionQuality,
+    ) -> Option<Self> {
+        let to_pixel = |v: f32, size: u32| ((v.clamp(0.0, 1.0) * size as f32) as u32).min(size);
+
+        let min = (
+            to_pixel(a[0].min(b[0]), width),
+            to_pixel(a[1].min(b[1]), height),
+        );
+        let max = (
+            to_pixel(a[0].max(b[0]), width),
+            to_pixel(a[1].max(b[1]), height),
+        );
+
+        if min.0 == max.0 || min.1 == max.1 {
+            None
+        } else {
+            Some(Self { min, max, quality })
+        }
+    }
+
+    /// Returns the range of rows covered by this region.
+    pub fn rows(&self) -> Range<u32> {
+        self.min.1..self.max.1
+    }
+
+    /// Returns the range of columns covered by this region.
+    pub fn columns(&self) -> Range<u32> {
+        self.min.0..self.max.0
+    }
+
+    /// Returns a copy of `camera` rendering at the quality of this region.
+    pub fn camera(&self, camera: &Camera) -> Camera {
+        let n = self.quality.samples.max(1);
+
+        let mut camera = camera.clone();
+        camera.set_samples_per_pixel(n * n);
+        camera.set_recursion_limit(self.quality.depth);
+        camera
+    }
+}
+
+/// Tracks a rectangle being dragged over the last submitted item while Shift is held.
+///
+/// Returns the corners of the rectangle in normalized item coordinates once the mouse button is
+/// released, drawing the selection in the meantime.
+pub fn select(ui: &Ui) -> Option<([f32; 2], [f32; 2])> {
+    let io = ui.io();
+
+    if !io.key_shift {
+        return None;
+    }
+
+    let min = ui.item_rect_min();
+    let size = ui.item_rect_size();
+    let end = io.mouse_pos;
+    let delta = ui.mouse_drag_delta_with_button(MouseButton::Left);
+    let start = [end[0] - delta[0], end[1] - delta[1]];
+
+    if ui.is_item_active() && ui.is_mouse_dragging(MouseButton::Left) {
+        ui.get_window_draw_list()
+            .add_rect(start, end, [1.0, 1.0, 0.0])
+            .build();
+    }
+
+    if ui.is_item_deactivated() {
+        let normalize = |p: [f32; 2]| [(p[0] - min[0]) / size[0], (p[1] - min[1]) / size[1]];
+        Some((normalize(start), normalize(end)))
+    } else {
+        None
+    }
+}
+
+/// Returns whether a region selection may be in progress, in which case the mouse input over the
+/// canvas should not be used for anything else.
+pub fn is_selecting(ui: &Ui) -> bool {
+    ui.io().key_shift
+}
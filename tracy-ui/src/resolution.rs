@@ -0,0 +1,83 @@
+//! Render resolution presets.
+
+use imgui::Ui;
+use serde::{Deserialize, Serialize};
+
+/// Predefined render resolutions, `None` meaning the size of the canvas window.
+const PRESETS: [(&str, Option<(u32, u32)>); 8] = [
+    ("Canvas window", None),
+    ("640x480 (4:3)", Some((640, 480))),
+    ("720p (16:9)", Some((1280, 720))),
+    ("1080p (16:9)", Some((1920, 1080))),
+    ("4K UHD (16:9)", Some((3840, 2160))),
+    ("Square 512", Some((512, 512))),
+    ("Square 1024", Some((1024, 1024))),
+    ("Cinema (2.39:1)", Some((2048, 858))),
+];
+
+/// Index of the custom resolution entry, following the presets.
+const CUSTOM: usize = PRESETS.len();
+
+/// The user-selected render resolution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Resolution {
+    selection: usize,
+    custom: (u32, u32),
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Self {
+            selection: 0,
+            custom: (800, 600),
+        }
+    }
+}
+
+impl Resolution {
+    /// Returns the resolution to render at, given the size of the canvas window.
+    pub fn size(&self, canvas_size: (u32, u32)) -> (u32, u32) {
+        let (width, height) = match PRESETS.get(self.selection) {
+            Some((_, preset)) => preset.unwrap_or(canvas_size),
+            None => self.custom,
+        };
+
+        (width.max(1), height.max(1))
+    }
+
+    /// Selects a custom resolution of `size` pixels.
+    pub fn set_custom(&mut self, size: (u32, u32)) {
+        self.selection = CUSTOM;
+        self.custom = size;
+    }
+
+    /// Returns whether the resolution follows the size of the canvas window.
+    pub fn is_canvas(&self) -> bool {
+        self.selection == 0
+    }
+
+    /// Draws the resolution selector, returning `true` if the selection changed.
+    pub fn draw(&mut self, ui: &Ui) -> bool {
+        let labels = PRESETS
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(std::iter::once("Custom"))
+            .collect::<Vec<_>>();
+
+        let mut changed = ui.combo_simple_string("Resolution", &mut self.selection, &labels);
+
+        if self.selection == CUSTOM {
+            changed |= ui.input_scalar("Width", &mut self.custom.0).build();
+            changed |= ui.input_scalar("Height", &mut self.custom.1).build();
+        }
+
+        changed
+    }
+}
+
+/// Computes the largest size with the aspect ratio of `image` that fits within `area`.
+pub fn letterbox(image: (u32, u32), area: [f32; 2]) -> [f32; 2] {
+    let scale = (area[0] / image.0 as f32).min(area[1] / image.1 as f32);
+    [image.0 as f32 * scale, image.1 as f32 * scale]
+}
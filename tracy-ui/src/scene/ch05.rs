@@ -1,10 +1,10 @@
-use std::{f32, fs::File};
+use std::{f32, fs::File, sync::Arc};
 
 use anyhow::Result;
 use imgui::*;
 use tracy::{
     query::World,
-    rendering::{Camera, Color, Material, Pattern, ScenePrefab, Stream},
+    rendering::{Camera, Color, Material, Pattern, ScenePrefab},
 };
 
 use super::Scene;
@@ -40,7 +40,7 @@ impl Scene for FlatSphere {
         "Rendering of a sphere using flat shading.".to_string()
     }
 
-    fn render(&mut self, width: u32, height: u32) -> Stream {
+    fn render(&mut self, width: u32, height: u32) -> (Arc<World>, Camera) {
         let sphere = self.world.objects_mut().next().unwrap();
 
         sphere.set_material(Material {
@@ -49,10 +49,31 @@ impl Scene for FlatSphere {
         });
 
         self.camera.set_size(width, height);
-        self.camera.stream(&self.world)
+        (Arc::new(self.world.snapshot()), self.camera.clone())
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn world(&self) -> &World {
+        &self.world
+    }
+
+    fn world_mut(&mut self) -> &mut World {
+        &mut self.world
     }
 
     fn draw(&mut self, ui: &Ui) -> bool {
         ui.color_picker3(format!("Color##{}", self.name()), &mut self.color)
     }
+
+    fn save_settings(&self) -> Option<String> {
+        ron::to_string(&self.color).ok()
+    }
+
+    fn restore_settings(&mut self, settings: &str) -> Result<()> {
+        self.color = ron::from_str(settings)?;
+        Ok(())
+    }
 }
@@ -1,10 +1,10 @@
-use std::{f32, fs::File};
+use std::{f32, fs::File, sync::Arc};
 
 use anyhow::Result;
 use imgui::*;
 use tracy::{
     query::World,
-    rendering::{Camera, Color, Material, Pattern, ScenePrefab, Stream},
+    rendering::{Camera, Color, Material, Pattern, ScenePrefab},
 };
 
 use super::Scene;
@@ -50,7 +50,7 @@ impl Scene for PhongSphere {
         "Rendering of a sphere using Phong shading.".to_string()
     }
 
-    fn render(&mut self, width: u32, height: u32) -> Stream {
+    fn render(&mut self, width: u32, height: u32) -> (Arc<World>, Camera) {
         let sphere = self.world.objects_mut().next().unwrap();
 
         sphere.set_material(Material {
@@ -63,7 +63,19 @@ impl Scene for PhongSphere {
         });
 
         self.camera.set_size(width, height);
-        self.camera.stream(&self.world)
+        (Arc::new(self.world.snapshot()), self.camera.clone())
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn world(&self) -> &World {
+        &self.world
+    }
+
+    fn world_mut(&mut self) -> &mut World {
+        &mut self.world
     }
 
     fn draw(&mut self, ui: &Ui) -> bool {
@@ -101,4 +113,26 @@ impl Scene for PhongSphere {
 
         redraw
     }
+
+    fn save_settings(&self) -> Option<String> {
+        ron::to_string(&(
+            self.color,
+            self.ambient,
+            self.diffuse,
+            self.specular,
+            self.shininess,
+        ))
+        .ok()
+    }
+
+    fn restore_settings(&mut self, settings: &str) -> Result<()> {
+        (
+            self.color,
+            self.ambient,
+            self.diffuse,
+            self.specular,
+            self.shininess,
+        ) = ron::from_str(settings)?;
+        Ok(())
+    }
 }
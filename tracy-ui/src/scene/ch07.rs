@@ -1,10 +1,10 @@
-use std::fs::File;
+use std::{fs::File, sync::Arc};
 
 use anyhow::Result;
 use imgui::*;
 use tracy::{
     query::World,
-    rendering::{Camera, ScenePrefab, Stream},
+    rendering::{Camera, ScenePrefab},
 };
 
 use super::Scene;
@@ -39,13 +39,34 @@ impl Scene for ThreeSpheres {
         "Camera pointed at three spheres in a room.".to_string()
     }
 
-    fn render(&mut self, width: u32, height: u32) -> Stream {
+    fn render(&mut self, width: u32, height: u32) -> (Arc<World>, Camera) {
         self.camera.set_size(width, height);
         self.camera.set_fov(self.fov.to_radians());
-        self.camera.stream(&self.world)
+        (Arc::new(self.world.snapshot()), self.camera.clone())
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn world(&self) -> &World {
+        &self.world
+    }
+
+    fn world_mut(&mut self) -> &mut World {
+        &mut self.world
     }
 
     fn draw(&mut self, ui: &Ui) -> bool {
         ui.slider(&format!("FOV##{}", self.name()), 30.0, 180.0, &mut self.fov)
     }
+
+    fn save_settings(&self) -> Option<String> {
+        ron::to_string(&self.fov).ok()
+    }
+
+    fn restore_settings(&mut self, settings: &str) -> Result<()> {
+        self.fov = ron::from_str(settings)?;
+        Ok(())
+    }
 }
@@ -1,10 +1,10 @@
-use std::fs::File;
+use std::{fs::File, sync::Arc};
 
 use anyhow::Result;
 use imgui::*;
 use tracy::{
-    query::World,
-    rendering::{Camera, PointLight, ScenePrefab, Stream},
+    query::{LightHandle, World},
+    rendering::{Camera, PointLight, ScenePrefab},
 };
 
 use super::Scene;
@@ -19,6 +19,7 @@ pub struct ShadowSpheres {
     cast_shadows: bool,
     multiple_lights: bool,
     second_light: PointLight,
+    second_light_handle: Option<LightHandle>,
 }
 
 impl ShadowSpheres {
@@ -27,6 +28,7 @@ impl ShadowSpheres {
         let second_light = scene.lights[1].clone();
 
         let (world, camera) = scene.build();
+        let second_light_handle = world.light_handles().nth(1);
 
         Ok(Self {
             world,
@@ -35,6 +37,7 @@ impl ShadowSpheres {
             cast_shadows: true,
             multiple_lights: false,
             second_light,
+            second_light_handle,
         })
     }
 }
@@ -48,18 +51,37 @@ impl Scene for ShadowSpheres {
         "The three spheres in a room cast shadows now.".to_string()
     }
 
-    fn render(&mut self, width: u32, height: u32) -> Stream {
-        self.world.lights_mut().next().unwrap().casts_shadows = self.cast_shadows;
+    fn render(&mut self, width: u32, height: u32) -> (Arc<World>, Camera) {
+        let world = &mut self.world;
 
-        match (self.multiple_lights, self.world.lights().count()) {
-            (true, 1) => self.world.add_light(self.second_light.clone()),
-            (false, 2) => self.world.remove_light(&self.second_light),
+        world.lights_mut().next().unwrap().casts_shadows = self.cast_shadows;
+
+        match (self.multiple_lights, self.second_light_handle) {
+            (true, None) => {
+                self.second_light_handle = Some(world.add_light(self.second_light.clone()));
+            }
+            (false, Some(handle)) => {
+                world.remove_light(handle);
+                self.second_light_handle = None;
+            }
             _ => (),
         }
 
         self.camera.set_size(width, height);
         self.camera.set_fov(self.fov.to_radians());
-        self.camera.stream(&self.world)
+        (Arc::new(self.world.snapshot()), self.camera.clone())
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn world(&self) -> &World {
+        &self.world
+    }
+
+    fn world_mut(&mut self) -> &mut World {
+        &mut self.world
     }
 
     fn draw(&mut self, ui: &Ui) -> bool {
@@ -79,4 +101,13 @@ impl Scene for ShadowSpheres {
 
         redraw
     }
+
+    fn save_settings(&self) -> Option<String> {
+        ron::to_string(&(self.fov, self.cast_shadows, self.multiple_lights)).ok()
+    }
+
+    fn restore_settings(&mut self, settings: &str) -> Result<()> {
+        (self.fov, self.cast_shadows, self.multiple_lights) = ron::from_str(settings)?;
+        Ok(())
+    }
 }
@@ -1,11 +1,11 @@
-use std::fs::File;
+use std::{fs::File, sync::Arc};
 
 use anyhow::Result;
 use imgui::*;
 use tracy::{
     math::Matrix,
     query::World,
-    rendering::{Camera, ScenePrefab, Stream},
+    rendering::{Camera, ScenePrefab},
     shape::Plane,
 };
 
@@ -51,7 +51,7 @@ impl Scene for PlaneShape {
         "Three little spheres sitting on a plane.".to_string()
     }
 
-    fn render(&mut self, width: u32, height: u32) -> Stream {
+    fn render(&mut self, width: u32, height: u32) -> (Arc<World>, Camera) {
         for obj in self.world.objects_mut() {
             if obj.shape().as_any().is::<Plane>() {
                 obj.set_transform(
@@ -62,7 +62,19 @@ impl Scene for PlaneShape {
         }
 
         self.camera.set_size(width, height);
-        self.camera.stream(&self.world)
+        (Arc::new(self.world.snapshot()), self.camera.clone())
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn world(&self) -> &World {
+        &self.world
+    }
+
+    fn world_mut(&mut self) -> &mut World {
+        &mut self.world
     }
 
     fn draw(&mut self, ui: &Ui) -> bool {
@@ -73,4 +85,13 @@ impl Scene for PlaneShape {
             &mut self.plane_y,
         )
     }
+
+    fn save_settings(&self) -> Option<String> {
+        ron::to_string(&self.plane_y).ok()
+    }
+
+    fn restore_settings(&mut self, settings: &str) -> Result<()> {
+        self.plane_y = ron::from_str(settings)?;
+        Ok(())
+    }
 }
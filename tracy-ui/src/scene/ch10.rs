@@ -1,10 +1,10 @@
-use std::fs::File;
+use std::{fs::File, sync::Arc};
 
 use anyhow::Result;
 use imgui::*;
 use tracy::{
     query::World,
-    rendering::{Camera, ScenePrefab, Stream},
+    rendering::{Camera, ScenePrefab},
 };
 
 use super::Scene;
@@ -49,9 +49,21 @@ impl Scene for Patterns {
         "All four patterns in a scene.".to_string()
     }
 
-    fn render(&mut self, width: u32, height: u32) -> Stream {
+    fn render(&mut self, width: u32, height: u32) -> (Arc<World>, Camera) {
         self.camera.set_size(width, height);
-        self.camera.stream(&self.world)
+        (Arc::new(self.world.snapshot()), self.camera.clone())
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn world(&self) -> &World {
+        &self.world
+    }
+
+    fn world_mut(&mut self) -> &mut World {
+        &mut self.world
     }
 
     fn draw(&mut self, ui: &Ui) -> bool {
@@ -72,4 +84,18 @@ impl Scene for Patterns {
 
         redraw
     }
+
+    fn save_settings(&self) -> Option<String> {
+        ron::to_string(&self.selection).ok()
+    }
+
+    fn restore_settings(&mut self, settings: &str) -> Result<()> {
+        let selection: usize = ron::from_str(settings)?;
+
+        if selection != self.selection && selection < Self::SUBSCENES.len() {
+            *self = Self::load_scene(selection)?;
+        }
+
+        Ok(())
+    }
 }
@@ -1,10 +1,10 @@
-use std::fs::File;
+use std::{fs::File, sync::Arc};
 
 use anyhow::Result;
 use imgui::*;
 use tracy::{
     query::World,
-    rendering::{Camera, ScenePrefab, Stream},
+    rendering::{Camera, ScenePrefab},
 };
 
 use super::Scene;
@@ -34,9 +34,21 @@ impl Scene for Reflections {
         "Shiny shiny stuff.".to_string()
     }
 
-    fn render(&mut self, width: u32, height: u32) -> Stream {
+    fn render(&mut self, width: u32, height: u32) -> (Arc<World>, Camera) {
         self.camera.set_size(width, height);
-        self.camera.stream(&self.world)
+        (Arc::new(self.world.snapshot()), self.camera.clone())
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn world(&self) -> &World {
+        &self.world
+    }
+
+    fn world_mut(&mut self) -> &mut World {
+        &mut self.world
     }
 
     fn draw(&mut self, _: &Ui) -> bool {
@@ -0,0 +1,125 @@
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Result;
+use imgui::*;
+use tracy::{
+    query::World,
+    rendering::{Camera, ScenePrefab},
+};
+
+use super::Scene;
+
+/// Minimum time between two checks for changes to a scene file.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A scene loaded from a user-provided prefab file.
+///
+/// Files with a `.ron` extension are parsed as RON, anything else as YAML.
+///
+/// The file is watched for changes and automatically reloaded when modified.
+#[derive(Debug)]
+pub struct SceneFile {
+    path: PathBuf,
+    world: World,
+    camera: Camera,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    reloaded: bool,
+}
+
+impl SceneFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        let (world, camera) = Self::load(&path)?;
+
+        Ok(Self {
+            path,
+            world,
+            camera,
+            modified,
+            last_check: Instant::now(),
+            reloaded: false,
+        })
+    }
+
+    fn load(path: &Path) -> Result<(World, Camera)> {
+        let file = File::open(path)?;
+
+        let prefab: ScenePrefab = match path.extension().and_then(OsStr::to_str) {
+            Some("ron") => ron::de::from_reader(file)?,
+            _ => serde_yaml::from_reader(file)?,
+        };
+
+        Ok(prefab.try_build()?)
+    }
+}
+
+impl Scene for SceneFile {
+    fn name(&self) -> String {
+        let file_name = self.path.file_name().unwrap_or_default();
+        format!("File: {}", file_name.to_string_lossy())
+    }
+
+    fn description(&self) -> String {
+        format!("Scene loaded from {}.", self.path.display())
+    }
+
+    fn render(&mut self, width: u32, height: u32) -> (Arc<World>, Camera) {
+        self.camera.set_size(width, height);
+        (Arc::new(self.world.snapshot()), self.camera.clone())
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn world(&self) -> &World {
+        &self.world
+    }
+
+    fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    fn reload_if_changed(&mut self) -> Result<bool> {
+        if self.last_check.elapsed() < RELOAD_CHECK_INTERVAL {
+            return Ok(false);
+        }
+        self.last_check = Instant::now();
+
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified == self.modified {
+            return Ok(false);
+        }
+
+        // Only retry a broken file once it's modified again
+        self.modified = modified;
+
+        let (world, camera) = Self::load(&self.path)?;
+        self.world = world;
+        self.camera = camera;
+        self.reloaded = true;
+
+        Ok(true)
+    }
+
+    fn draw(&mut self, ui: &Ui) -> bool {
+        if self.reloaded {
+            ui.text_colored([1.0, 1.0, 0.0, 1.0], "Reloaded after changes on disk.");
+
+            if ui.button("Render changes") {
+                self.reloaded = false;
+                return true;
+            }
+        }
+
+        false
+    }
+}
@@ -1,8 +1,14 @@
 //! Generators for each chapter's exercises.
 
-use anyhow::Result;
+use std::{ffi::OsStr, path::Path, sync::Arc};
+
+use anyhow::{bail, Result};
 use imgui::Ui;
-use tracy::rendering::Stream;
+use tracy::{
+    io::{obj, stl},
+    query::{Aabb, World},
+    rendering::Camera,
+};
 
 mod ch05;
 mod ch06;
@@ -14,13 +20,33 @@ mod ch11;
 mod ch12;
 mod ch13;
 mod cover;
+mod file;
 
 /// Traits shared by all the renderable scenes.
 pub trait Scene {
     fn name(&self) -> String;
     fn description(&self) -> String;
-    fn render(&mut self, width: u32, height: u32) -> Stream;
+    /// Applies the current settings and returns the world and camera to be rendered.
+    fn render(&mut self, width: u32, height: u32) -> (Arc<World>, Camera);
+    fn camera_mut(&mut self) -> &mut Camera;
+    fn world(&self) -> &World;
+    fn world_mut(&mut self) -> &mut World;
     fn draw(&mut self, ui: &Ui) -> bool;
+
+    /// Reloads the scene if its source changed since it was last loaded, returning `true` if so.
+    fn reload_if_changed(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Returns the values of the scene's settings, to be restored in a later session.
+    fn save_settings(&self) -> Option<String> {
+        None
+    }
+
+    /// Restores the settings previously returned by [`Scene::save_settings`].
+    fn restore_settings(&mut self, _settings: &str) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Returns a list of all the available scenes.
@@ -38,3 +64,47 @@ pub fn get_scene_list() -> Result<Vec<Box<dyn Scene>>> {
         Box::new(cover::Cover::new()?),
     ])
 }
+
+/// Returns `true` if the extension of `path` is one of `extensions`, ignoring case.
+pub fn has_extension<P: AsRef<Path>>(path: P, extensions: &[&str]) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Extensions of the scene files that can be opened with [`open_scene`].
+pub const EXTENSIONS: [&str; 3] = ["yml", "yaml", "ron"];
+
+/// Loads a scene from a YAML or RON prefab file.
+pub fn open_scene<P: AsRef<Path>>(path: P) -> Result<Box<dyn Scene>> {
+    Ok(Box::new(file::SceneFile::open(path)?))
+}
+
+/// Extensions of the model files that can be added to a scene with [`import_model`].
+pub const MODEL_EXTENSIONS: [&str; 2] = ["obj", "stl"];
+
+/// Adds the model at `path` to `scene` with the default material, named after the file.
+///
+/// The format of the model is given by the extension of the file, one of [`MODEL_EXTENSIONS`].
+/// Returns the bounds of the model, to frame it with the camera.
+pub fn import_model<P: AsRef<Path>>(scene: &mut dyn Scene, path: P) -> Result<Aabb> {
+    let path = path.as_ref();
+
+    let mut object = if has_extension(path, &["obj"]) {
+        obj::load(path)?
+    } else if has_extension(path, &["stl"]) {
+        stl::load(path)?
+    } else {
+        bail!("unknown model format");
+    };
+    object.set_name(
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned()),
+    );
+
+    let bounds = object.bounds();
+    scene.world_mut().add(object);
+
+    Ok(bounds)
+}
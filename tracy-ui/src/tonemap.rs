@@ -0,0 +1,37 @@
+//! Tone mapping controls.
+//!
+//! The rendered canvas is left untouched and only mapped when displayed, so the transform can be
+//! changed without re-rendering.
+
+use imgui::Ui;
+
+pub use tracy::rendering::{ToneMapOperator, ToneMapping};
+
+/// Draws the tone mapping controls, returning `true` if any of them changed.
+pub fn draw(tone_mapping: &mut ToneMapping, ui: &Ui) -> bool {
+    let mut changed = false;
+
+    let mut operator = ToneMapOperator::ALL
+        .iter()
+        .position(|&op| op == tone_mapping.operator)
+        .unwrap_or(0);
+    let labels = ToneMapOperator::ALL
+        .iter()
+        .map(|op| op.name())
+        .collect::<Vec<_>>();
+
+    if ui.combo_simple_string("Tone mapping", &mut operator, &labels) {
+        tone_mapping.operator = ToneMapOperator::ALL[operator];
+        changed = true;
+    }
+
+    changed |= ui.slider("Exposure (EV)", -8.0, 8.0, &mut tone_mapping.exposure);
+    changed |= ui.slider("Gamma", 0.5, 3.0, &mut tone_mapping.gamma);
+
+    if ui.button("Reset display") {
+        *tone_mapping = ToneMapping::default();
+        changed = true;
+    }
+
+    changed
+}
@@ -1,14 +1,13 @@
 use std::{
-    path::Path,
-    time::{Duration, Instant},
+    path::{Path, PathBuf},
+    time::Instant,
 };
 
 use futures::executor::block_on;
-use image::{ImageBuffer, Rgb};
 use imgui::{self as im};
 use imgui_wgpu::{Renderer, RendererConfig, Texture, TextureConfig};
 use imgui_winit_support::WinitPlatform;
-use tracy::rendering::{Canvas, Stream};
+use tracy::rendering::{Aov, Camera, Canvas, DEFAULT_RECURSION_DEPTH};
 use winit::{
     dpi::{LogicalPosition, LogicalSize},
     event::{Event, WindowEvent},
@@ -17,13 +16,28 @@ use winit::{
     window::Window,
 };
 
-use crate::scene::{self, Scene};
+use crate::{
+    animation::{TimelineInput, Turntable},
+    benchmark::{Benchmark, BenchmarkInput, BenchmarkJob},
+    commands::{Command, CommandPalette, Keymap},
+    compare::Compare,
+    config::{Config, Defaults},
+    controls::{CameraInput, OrbitControls},
+    debug::{BoundsOverlay, DebugView},
+    export::{ExportSettings, ImageFormat, Metadata},
+    inspector::{self, Edit},
+    pixel::PixelInfo,
+    preview::Preview,
+    region::{self, Region, RegionQuality},
+    resolution::{self, Resolution},
+    scene::{self, Scene},
+    tonemap::{self, ToneMapping},
+    worker::{Progress, RenderWorker, Scheduling},
+};
 
 const DEFAULT_WIDTH: u32 = 512;
 const DEFAULT_HEIGHT: u32 = 512;
 
-const MAX_RENDER_BATCH_DURATION: Duration = Duration::from_millis(50);
-
 pub struct TracyUi {
     event_loop: EventLoop<()>,
     ctx: UiContext,
@@ -42,16 +56,115 @@ struct GfxBackend {
     surface: wgpu::Surface,
     renderer: imgui_wgpu::Renderer,
     texture_id: Option<im::TextureId>,
+    reference_id: Option<im::TextureId>,
+    preview: Preview,
+}
+
+/// User settings and tools persisting across frames.
+#[derive(Default)]
+struct Settings {
+    export: ExportSettings,
+    compare: Compare,
+    inspect_pixels: bool,
+    resolution: Resolution,
+    scheduling: Scheduling,
+    turntable: Turntable,
+    debug_view: Option<DebugView>,
+    bounds: BoundsOverlay,
+    region: RegionQuality,
+    keymap: Keymap,
+    palette: CommandPalette,
+    show_shortcuts: bool,
+    benchmark: Benchmark,
+    show_benchmark: bool,
+    output_dir: Option<PathBuf>,
+    tone_mapping: ToneMapping,
+    preview: bool,
+    recursion_limit: Option<u32>,
+}
+
+impl Settings {
+    /// Restores the settings saved in `config` by a previous session, overridden by the user's
+    /// `defaults`.
+    fn restore(config: &Config, defaults: &Defaults) -> Self {
+        let mut resolution = config.resolution.clone();
+        if let Some(size) = defaults.size() {
+            resolution.set_custom(size);
+        }
+
+        Self {
+            export: config.export.clone(),
+            resolution,
+            scheduling: Scheduling {
+                threads: defaults
+                    .threads
+                    .unwrap_or(config.scheduling.threads)
+                    .clamp(1, Scheduling::max_threads()),
+                ..config.scheduling
+            },
+            output_dir: defaults
+                .output_dir
+                .clone()
+                .or_else(|| config.output_dir.clone()),
+            tone_mapping: defaults.tonemap.unwrap_or(config.tone_mapping),
+            preview: config.preview,
+            ..Self::default()
+        }
+    }
+
+    /// Saves these settings for the next session, along with the window layout and the
+    /// settings of each scene.
+    fn save(&self, imgui: &mut im::Context, scenes: &[Box<dyn Scene>]) -> anyhow::Result<()> {
+        let mut layout = String::new();
+        imgui.save_ini_settings(&mut layout);
+
+        let scenes = scenes
+            .iter()
+            .filter_map(|scene| Some((scene.name(), scene.save_settings()?)))
+            .collect();
+
+        Config {
+            layout,
+            resolution: self.resolution.clone(),
+            export: self.export.clone(),
+            scheduling: self.scheduling,
+            output_dir: self.output_dir.clone(),
+            tone_mapping: self.tone_mapping,
+            preview: self.preview,
+            scenes,
+        }
+        .save()
+    }
+
+    /// Returns a file dialog starting from the directory where renders were last saved.
+    fn file_dialog(&self) -> rfd::FileDialog {
+        let dialog = rfd::FileDialog::new();
+
+        match &self.output_dir {
+            Some(dir) => dialog.set_directory(dir),
+            None => dialog,
+        }
+    }
 }
 
 #[derive(Default)]
 struct UiState {
     render_scene: Option<usize>,
     save_scene: Option<usize>,
+    open_scene: bool,
     canvas_width: u32,
     canvas_height: u32,
     stop_rendering: bool,
     freeze_canvas_size: bool,
+    progress: Option<Progress>,
+    camera_input: Option<CameraInput>,
+    hovered_pixel: Option<[f32; 2]>,
+    render_region: Option<([f32; 2], [f32; 2])>,
+    settings_changed: bool,
+    display_changed: bool,
+    timeline: TimelineInput,
+    benchmark: BenchmarkInput,
+    edits: Vec<Edit>,
 }
 
 impl TracyUi {
@@ -129,6 +242,7 @@ impl TracyUi {
         };
 
         let renderer = Renderer::new(&mut imgui, &device, &queue, renderer_config);
+        let preview = Preview::new(&device);
 
         // Build UI structure
         Self {
@@ -144,6 +258,8 @@ impl TracyUi {
                 surface,
                 renderer,
                 texture_id: None,
+                reference_id: None,
+                preview,
             },
         }
     }
@@ -157,7 +273,33 @@ impl TracyUi {
         } = self;
 
         let mut scenes = scene::get_scene_list().unwrap();
-        let mut current_render: Option<Stream> = None;
+        let mut current_render: Option<RenderWorker> = None;
+        let mut current_scene: Option<usize> = None;
+        let mut controls: Option<OrbitControls> = None;
+
+        // Restore the previous session
+        let config = Config::load();
+        ctx.imgui.load_ini_settings(&config.layout);
+
+        for scene in scenes.iter_mut() {
+            if let Some(values) = config.scenes.get(&scene.name()) {
+                if let Err(e) = scene.restore_settings(values) {
+                    eprintln!("Failed to restore settings of {}: {}", scene.name(), e);
+                }
+            }
+        }
+
+        let defaults = Defaults::load(None).unwrap_or_else(|e| {
+            eprintln!("Failed to load default settings: {:#}", e);
+            Defaults::default()
+        });
+
+        let mut settings = Settings::restore(&config, &defaults);
+        let mut last_canvas: Option<Canvas> = None;
+        let mut last_aovs: Vec<Aov> = Vec::new();
+        let mut reference_canvas: Option<Canvas> = None;
+        let mut files_to_open: Vec<PathBuf> = Vec::new();
+        let mut last_camera: Option<Camera> = None;
 
         let mut last_frame = Instant::now();
         let mut last_cursor = None;
@@ -194,8 +336,18 @@ impl TracyUi {
                     event: WindowEvent::CloseRequested,
                     ..
                 } => {
+                    if let Err(e) = settings.save(&mut ctx.imgui, &scenes) {
+                        eprintln!("Failed to save settings: {}", e);
+                    }
+
                     *control_flow = ControlFlow::Exit;
                 }
+                Event::WindowEvent {
+                    event: WindowEvent::DroppedFile(ref path),
+                    ..
+                } => {
+                    files_to_open.push(path.clone());
+                }
                 Event::MainEventsCleared => ctx.window.request_redraw(),
                 Event::RedrawEventsCleared => {
                     let now = Instant::now();
@@ -218,65 +370,345 @@ impl TracyUi {
                     // Draw UI and capture user's input
                     let ui = ctx.imgui.frame();
                     let mut state = UiState {
-                        freeze_canvas_size: current_render.is_some(),
+                        freeze_canvas_size: current_render.is_some()
+                            && settings.resolution.is_canvas(),
+                        progress: current_render.as_ref().map(RenderWorker::progress),
                         ..UiState::default()
                     };
-                    state.draw_ui(ui, &mut scenes[..], gfx.texture_id);
+                    state.draw_ui(
+                        ui,
+                        &mut scenes[..],
+                        current_scene,
+                        &mut settings,
+                        gfx.texture_id,
+                        gfx.reference_id,
+                    );
+
+                    // Scene files changed on disk, show the changes right away if on screen
+                    for (id, scene) in scenes.iter_mut().enumerate() {
+                        match scene.reload_if_changed() {
+                            Ok(true) if current_scene == Some(id) => {
+                                state.render_scene = Some(id);
+                            }
+                            Ok(_) => (),
+                            Err(e) => eprintln!("Failed to reload {}: {}", scene.name(), e),
+                        }
+                    }
+
+                    // Scene rendered from the picker, restart camera navigation from its camera
+                    if let Some(id) = state.render_scene {
+                        current_scene = Some(id);
+                        controls = None;
+                        settings.turntable.reset();
+                        state.edits.clear();
+                    }
+
+                    // Scene file requested, add it to the list of scenes
+                    if state.open_scene {
+                        let path = rfd::FileDialog::new()
+                            .add_filter("Scene files", &scene::EXTENSIONS)
+                            .pick_file();
+
+                        files_to_open.extend(path);
+                    }
+
+                    // Scene files picked or dropped onto the window, and models dropped into the
+                    // current scene, framed by the camera
+                    for path in files_to_open.drain(..) {
+                        if scene::has_extension(&path, &scene::EXTENSIONS) {
+                            match scene::open_scene(&path) {
+                                Ok(scene) => scenes.push(scene),
+                                Err(e) => eprintln!("Failed to open {}: {}", path.display(), e),
+                            }
+                        } else if scene::has_extension(&path, &scene::MODEL_EXTENSIONS) {
+                            let id = match current_scene {
+                                Some(id) => id,
+                                None => {
+                                    eprintln!(
+                                        "Cannot import {}: render a scene first",
+                                        path.display()
+                                    );
+                                    continue;
+                                }
+                            };
+
+                            match scene::import_model(scenes[id].as_mut(), &path) {
+                                Ok(bounds) => {
+                                    let camera = scenes[id].camera_mut();
+                                    let mut orbit = OrbitControls::from_camera(camera);
+                                    orbit.frame(&bounds, camera);
+                                    camera.set_view_transform(orbit.view_transform());
+
+                                    controls = Some(orbit);
+                                    settings.turntable.reset();
+                                    state.render_scene = Some(id);
+                                }
+                                Err(e) => eprintln!("Failed to import {}: {}", path.display(), e),
+                            }
+                        } else {
+                            eprintln!("Cannot open {}: unknown file type", path.display());
+                        }
+                    }
+
+                    // Camera moved from the canvas, re-render the scene from the new point of view
+                    if let (Some(input), Some(id)) = (state.camera_input, current_scene) {
+                        let camera = scenes[id].camera_mut();
+                        let controls =
+                            controls.get_or_insert_with(|| OrbitControls::from_camera(camera));
+
+                        controls.apply(&input);
+                        camera.set_view_transform(controls.view_transform());
+
+                        settings.turntable.reset();
+                        state.render_scene = Some(id);
+                    }
+
+                    // Turntable playing, move to the next frame once the previous one is rendered
+                    if settings.turntable.playing
+                        && current_render.is_none()
+                        && current_scene.is_some()
+                    {
+                        settings.turntable.advance();
+                        state.timeline.seek = true;
+                    }
+
+                    // Turntable frame changed, move the camera along its orbit
+                    if let (true, Some(id)) = (state.timeline.seek, current_scene) {
+                        let turntable = &mut settings.turntable;
+                        let camera = scenes[id].camera_mut();
+                        camera
+                            .set_view_transform(turntable.view_transform(camera, turntable.frame));
 
-                    // User has stopped the rendering
-                    if state.stop_rendering {
+                        controls = None;
+                        state.render_scene = Some(id);
+                    }
+
+                    // Scene edited from the inspector or settings changed, re-render it
+                    if !state.edits.is_empty() || state.settings_changed {
+                        state.render_scene = current_scene;
+                    }
+
+                    // User has stopped the rendering, or a new one is about to replace it
+                    if state.stop_rendering
+                        || state.render_scene.is_some()
+                        || state.save_scene.is_some()
+                    {
                         current_render = None;
                     }
 
                     // New render triggered/forced
                     if let Some(id) = state.render_scene {
-                        // SAFETY: because of some lifetime fuckery that I don't fully understand,
-                        // the closure we are in has an anonymous lifetime different from the one
-                        // on `Stream`, and AFAIK it cannot be changed. As a result of this, items
-                        // borrowed from `scenes` cannot fulfill both lifetimes, despite them
-                        // living (supposedly) longer than this closure. Anyway, make the borrow
-                        // checker happy by transmuting the lifetime to one it can accept.
-                        let scene = unsafe {
-                            std::mem::transmute::<&'_ mut Box<dyn Scene>, &'_ mut Box<dyn Scene>>(
-                                &mut scenes[id],
-                            )
+                        for edit in state.edits.drain(..) {
+                            edit.apply(scenes[id].world_mut());
+                        }
+
+                        let (width, height) = settings
+                            .resolution
+                            .size((state.canvas_width, state.canvas_height));
+                        let (world, mut camera) = scenes[id].render(width, height);
+                        if let Some(depth) = settings.recursion_limit {
+                            camera.set_recursion_limit(depth);
+                        }
+
+                        // Keep the last complete render around for comparison
+                        if let Some(canvas) = last_canvas.take() {
+                            gfx.render_to_reference(&canvas, &settings.tone_mapping);
+                            reference_canvas = Some(canvas);
+                        }
+
+                        // Show a rasterized preview until the ray traced scanlines come in
+                        let preview = match settings.debug_view {
+                            None if settings.preview => {
+                                Some(gfx.preview.render(&gfx.device, &gfx.queue, &world, &camera))
+                            }
+                            _ => None,
                         };
 
-                        current_render =
-                            Some(scene.render(state.canvas_width, state.canvas_height));
+                        settings.bounds.update(&world, &camera);
+                        last_camera = Some(camera.clone());
+
+                        let mut worker = RenderWorker::spawn(
+                            world,
+                            camera,
+                            settings.scheduling,
+                            settings.debug_view,
+                        );
+
+                        if let Some(preview) = preview {
+                            worker = worker.with_background(preview);
+                            gfx.render_to_texture(worker.canvas(), &settings.tone_mapping);
+                        }
+
+                        current_render = Some(worker);
+                    }
+
+                    // Region selected on the canvas, re-render it over the last complete render
+                    if let (Some((a, b)), Some(id), Some(camera)) =
+                        (state.render_region, current_scene, &last_camera)
+                    {
+                        let region = last_canvas.as_ref().and_then(|base| {
+                            Region::new(a, b, base.width(), base.height(), settings.region)
+                        });
+
+                        if let Some(region) = region {
+                            let base = last_canvas.take().unwrap();
+                            let base_aovs = std::mem::take(&mut last_aovs);
+                            let (world, _) = scenes[id].render(base.width(), base.height());
+
+                            gfx.render_to_reference(&base, &settings.tone_mapping);
+                            reference_canvas = Some(base.clone());
+
+                            current_render = Some(RenderWorker::spawn_region(
+                                world,
+                                camera.clone(),
+                                settings.scheduling,
+                                base,
+                                base_aovs,
+                                region,
+                            ));
+                        }
                     }
 
-                    // Render next batch of frames if a rendering is in progress
-                    if let Some(ref mut stream) = current_render {
-                        let mut render = false;
+                    // Upload any scanline completed by the rendering thread
+                    if let Some(ref mut worker) = current_render {
+                        let finished = worker.is_finished();
 
-                        // Render for MAX_RENDER_BATCH_DURATION or until we are done
-                        let start = Instant::now();
-                        while start.elapsed() <= MAX_RENDER_BATCH_DURATION && stream.advance() {
-                            render = true;
+                        if worker.poll() {
+                            gfx.render_to_texture(worker.canvas(), &settings.tone_mapping);
                         }
 
-                        if render {
-                            gfx.render_to_texture(
-                                state.canvas_width,
-                                state.canvas_height,
-                                stream.canvas(),
-                            )
-                        } else {
+                        if finished {
+                            last_canvas = Some(worker.canvas().clone());
+                            last_aovs = worker.aovs().to_vec();
                             current_render = None;
                         }
                     }
 
+                    // Benchmark every scene at the same resolution
+                    if state.benchmark.start {
+                        let (width, height) = (
+                            settings.benchmark.width.max(1),
+                            settings.benchmark.height.max(1),
+                        );
+
+                        let jobs = scenes
+                            .iter_mut()
+                            .map(|scene| {
+                                let (world, camera) = scene.render(width, height);
+                                BenchmarkJob {
+                                    name: scene.name(),
+                                    world,
+                                    camera,
+                                }
+                            })
+                            .collect();
+
+                        settings.benchmark.start(jobs, settings.scheduling);
+                    }
+
+                    settings.benchmark.poll();
+
+                    // Display settings changed, map the renders to the screen again
+                    if state.display_changed {
+                        let canvas = match &current_render {
+                            Some(worker) => Some(worker.canvas()),
+                            None => last_canvas.as_ref(),
+                        };
+
+                        if let Some(canvas) = canvas {
+                            gfx.render_to_texture(canvas, &settings.tone_mapping);
+                        }
+
+                        if let Some(canvas) = &reference_canvas {
+                            gfx.render_to_reference(canvas, &settings.tone_mapping);
+                        }
+                    }
+
+                    // Show the details of the pixel under the cursor
+                    if let (Some(uv), Some(id), Some(tid)) =
+                        (state.hovered_pixel, current_scene, gfx.texture_id)
+                    {
+                        let render = match &current_render {
+                            Some(worker) => Some((worker.canvas(), worker.aovs())),
+                            None => last_canvas.as_ref().map(|c| (c, last_aovs.as_slice())),
+                        };
+
+                        if let Some((canvas, aovs)) = render {
+                            let info = PixelInfo::probe(scenes[id].world(), canvas, aovs, uv);
+
+                            if let Some(info) = info {
+                                info.draw_tooltip(ui, tid, canvas);
+                            }
+                        }
+                    }
+
                     // Image save requested
                     if let Some(id) = state.save_scene {
-                        let path = format!("{}.png", scenes.get(id).unwrap().name());
+                        let format = settings.export.format();
+                        let file_name = format!("{}.{}", scenes[id].name(), format.extension());
+
+                        let path = settings
+                            .file_dialog()
+                            .add_filter(format.name(), &[format.extension()])
+                            .set_file_name(&file_name)
+                            .save_file();
+
+                        if let Some(path) = path {
+                            settings.output_dir = path.parent().map(Path::to_path_buf);
+
+                            let (width, height) = settings.export.size(
+                                settings
+                                    .resolution
+                                    .size((state.canvas_width, state.canvas_height)),
+                            );
+                            let (world, mut camera) = scenes[id].render(width, height);
+                            if let Some(depth) = settings.recursion_limit {
+                                camera.set_recursion_limit(depth);
+                            }
+
+                            let metadata = Metadata::new(&scenes[id].name(), &world, &camera);
+
+                            if let Err(e) = format.save(&camera.render(&world), &path, &metadata) {
+                                eprintln!("Failed to save {}: {}", path.display(), e);
+                            }
+                        }
+                    }
 
-                        save_current_scene(
-                            &mut scenes[id],
-                            state.canvas_width,
-                            state.canvas_height,
-                            &path,
-                        );
+                    // Turntable export requested
+                    if let (true, Some(id)) = (state.timeline.export, current_scene) {
+                        if let Some(dir) = settings.file_dialog().pick_folder() {
+                            settings.output_dir = Some(dir.clone());
+
+                            let (width, height) = settings
+                                .resolution
+                                .size((state.canvas_width, state.canvas_height));
+
+                            let result =
+                                settings
+                                    .turntable
+                                    .export(scenes[id].as_mut(), width, height, &dir);
+
+                            if let Err(e) = result {
+                                eprintln!("Failed to export frames to {}: {}", dir.display(), e);
+                            }
+                        }
+                    }
+
+                    // Benchmark results export requested
+                    if state.benchmark.export {
+                        let path = settings
+                            .file_dialog()
+                            .add_filter("CSV", &["csv"])
+                            .set_file_name("benchmark.csv")
+                            .save_file();
+
+                        if let Some(path) = path {
+                            settings.output_dir = path.parent().map(Path::to_path_buf);
+
+                            if let Err(e) = std::fs::write(&path, settings.benchmark.to_csv()) {
+                                eprintln!("Failed to save {}: {}", path.display(), e);
+                            }
+                        }
                     }
 
                     // Finalize frame rendering
@@ -330,13 +762,76 @@ impl UiState {
         &mut self,
         ui: &im::Ui,
         scenes: &mut [Box<dyn Scene>],
+        current_scene: Option<usize>,
+        settings: &mut Settings,
         texture: Option<im::TextureId>,
+        reference: Option<im::TextureId>,
+    ) {
+        self.draw_main_menu(ui, settings);
+        self.draw_canvas(ui, settings, texture, reference);
+        self.draw_scene_picker(ui, scenes, &mut settings.export);
+        self.draw_render_settings(ui, settings);
+
+        if let Some(id) = current_scene {
+            self.draw_inspector(ui, scenes[id].as_ref());
+            self.draw_timeline(ui, &mut settings.turntable);
+        }
+
+        if settings.show_shortcuts {
+            let keymap = &mut settings.keymap;
+
+            ui.window("Shortcuts")
+                .opened(&mut settings.show_shortcuts)
+                .size([432., 256.], im::Condition::FirstUseEver)
+                .build(|| keymap.draw(ui));
+        }
+
+        if settings.show_benchmark {
+            let benchmark = &mut settings.benchmark;
+            let mut input = BenchmarkInput::default();
+
+            ui.window("Benchmark")
+                .opened(&mut settings.show_benchmark)
+                .size([480., 320.], im::Condition::FirstUseEver)
+                .build(|| input = benchmark.draw(ui));
+
+            self.benchmark = input;
+        }
+
+        let command = settings
+            .palette
+            .draw(ui, &settings.keymap)
+            .or_else(|| settings.keymap.triggered(ui));
+
+        if let Some(command) = command {
+            self.run_command(command, current_scene, settings);
+        }
+    }
+
+    fn run_command(
+        &mut self,
+        command: Command,
+        current_scene: Option<usize>,
+        settings: &mut Settings,
     ) {
-        self.draw_canvas(ui, texture);
-        self.draw_scene_picker(ui, scenes);
+        match command {
+            Command::Render => self.render_scene = current_scene,
+            Command::Save => self.save_scene = current_scene,
+            Command::Cancel => self.stop_rendering = true,
+            Command::Open => self.open_scene = true,
+            Command::ToggleCompare => settings.compare.enabled = !settings.compare.enabled,
+            Command::ToggleInspectPixels => settings.inspect_pixels = !settings.inspect_pixels,
+            Command::ShowPalette => settings.palette.open(),
+        }
     }
 
-    fn draw_canvas(&mut self, ui: &im::Ui, texture: Option<im::TextureId>) {
+    fn draw_canvas(
+        &mut self,
+        ui: &im::Ui,
+        settings: &mut Settings,
+        texture: Option<im::TextureId>,
+        reference: Option<im::TextureId>,
+    ) {
         ui.window("Canvas")
             .size(
                 [DEFAULT_WIDTH as f32, DEFAULT_HEIGHT as f32],
@@ -346,31 +841,208 @@ impl UiState {
             .position([48., 48.], im::Condition::FirstUseEver)
             .build(|| {
                 self.stop_rendering = ui.button("Stop rendering");
+
+                if let Some(progress) = self.progress {
+                    ui.same_line();
+                    self.draw_progress(ui, &progress);
+                }
+
+                ui.checkbox("Inspect pixels", &mut settings.inspect_pixels);
+
+                if reference.is_some() {
+                    ui.same_line();
+                    settings.compare.draw_controls(ui);
+                }
+
                 ui.separator();
 
                 // Track canvas size changes
-                let size = ui.content_region_avail();
-                self.canvas_width = size[0] as u32;
-                self.canvas_height = size[1] as u32;
+                let avail = ui.content_region_avail();
+                self.canvas_width = avail[0] as u32;
+                self.canvas_height = avail[1] as u32;
+
+                // Scale the render to fit the window, centering it along the other axis
+                let render_size = settings
+                    .resolution
+                    .size((self.canvas_width, self.canvas_height));
+                let size = resolution::letterbox(render_size, avail);
+
+                let cursor = ui.cursor_pos();
+                ui.set_cursor_pos([
+                    cursor[0] + (avail[0] - size[0]) / 2.0,
+                    cursor[1] + (avail[1] - size[1]) / 2.0,
+                ]);
 
                 if let Some(tid) = texture {
+                    let origin = ui.cursor_pos();
                     im::Image::new(tid, size).build(ui);
+                    ui.set_cursor_pos(origin);
+
+                    if let Some(rid) = reference.filter(|_| settings.compare.enabled) {
+                        settings.compare.draw_overlay(ui, rid, size);
+                        ui.set_cursor_pos(origin);
+                    }
+
+                    settings.bounds.draw(ui, size);
+                }
+
+                // Capture mouse input over the canvas for region selection, the divider or camera
+                // navigation, in this order
+                if size[0] > 0.0 && size[1] > 0.0 {
+                    ui.invisible_button_flags("##canvas", size, im::ButtonFlags::all());
+
+                    if region::is_selecting(ui) {
+                        self.render_region = region::select(ui);
+                    } else if reference.is_none() || !settings.compare.drag_divider(ui) {
+                        self.camera_input = CameraInput::capture(ui);
+                    }
+
+                    if settings.inspect_pixels && ui.is_item_hovered() {
+                        let min = ui.item_rect_min();
+                        let mouse = ui.io().mouse_pos;
+
+                        self.hovered_pixel =
+                            Some([(mouse[0] - min[0]) / size[0], (mouse[1] - min[1]) / size[1]]);
+                    }
+                }
+            });
+    }
+
+    fn draw_progress(&self, ui: &im::Ui, progress: &Progress) {
+        let remaining = match progress.remaining() {
+            Some(eta) => format!("{:.1}s left", eta.as_secs_f32()),
+            None => "estimating...".to_string(),
+        };
+
+        im::ProgressBar::new(progress.fraction())
+            .overlay_text(format!(
+                "{:.0}% - {:.1}s elapsed, {}",
+                progress.fraction() * 100.0,
+                progress.elapsed.as_secs_f32(),
+                remaining,
+            ))
+            .build(ui);
+    }
+
+    fn draw_main_menu(&mut self, ui: &im::Ui, settings: &mut Settings) {
+        if let Some(_menu_bar) = ui.begin_main_menu_bar() {
+            if let Some(_menu) = ui.begin_menu("File") {
+                self.open_scene = ui.menu_item("Open...");
+            }
+
+            if let Some(_menu) = ui.begin_menu("View") {
+                if ui.menu_item("Command palette") {
+                    settings.palette.open();
+                }
+
+                ui.menu_item_config("Shortcuts")
+                    .build_with_ref(&mut settings.show_shortcuts);
+                ui.menu_item_config("Benchmark")
+                    .build_with_ref(&mut settings.show_benchmark);
+            }
+        }
+    }
+
+    fn draw_render_settings(&mut self, ui: &im::Ui, settings: &mut Settings) {
+        ui.window("Render settings")
+            .size([432., 256.], im::Condition::FirstUseEver)
+            .position([48., 580.], im::Condition::FirstUseEver)
+            .build(|| {
+                self.settings_changed |= settings.resolution.draw(ui);
+
+                ui.separator();
+
+                let mut threads = settings.scheduling.threads as u32;
+                if ui.slider("Threads", 1, Scheduling::max_threads() as u32, &mut threads) {
+                    settings.scheduling.threads = threads as usize;
+                    self.settings_changed = true;
+                }
+
+                self.settings_changed |=
+                    ui.checkbox("Low priority", &mut settings.scheduling.low_priority);
+
+                ui.checkbox("Rasterized preview", &mut settings.preview);
+
+                let mut override_depth = settings.recursion_limit.is_some();
+                if ui.checkbox("Override recursion depth", &mut override_depth) {
+                    settings.recursion_limit = override_depth.then_some(DEFAULT_RECURSION_DEPTH);
+                    self.settings_changed = true;
+                }
+                if let Some(depth) = &mut settings.recursion_limit {
+                    self.settings_changed |= ui.slider("Recursion depth", 0, 16, depth);
+                }
+
+                ui.separator();
+
+                // The shaded scene comes first, followed by all the debug views
+                let mut view = settings
+                    .debug_view
+                    .and_then(|v| DebugView::ALL.iter().position(|&w| w == v))
+                    .map_or(0, |i| i + 1);
+
+                let labels = std::iter::once("Beauty")
+                    .chain(DebugView::ALL.iter().map(|v| v.name()))
+                    .collect::<Vec<_>>();
+
+                if ui.combo_simple_string("View", &mut view, &labels) {
+                    settings.debug_view = view.checked_sub(1).map(|i| DebugView::ALL[i]);
+                    self.settings_changed = true;
                 }
+
+                ui.checkbox("Bounding boxes", &mut settings.bounds.enabled);
+
+                ui.separator();
+                ui.text("Shift+drag on the canvas to re-render a region:");
+                settings.region.draw(ui);
+
+                ui.separator();
+                self.display_changed |= tonemap::draw(&mut settings.tone_mapping, ui);
             });
     }
 
-    fn draw_scene_picker(&mut self, ui: &im::Ui, scenes: &mut [Box<dyn Scene>]) {
+    fn draw_timeline(&mut self, ui: &im::Ui, turntable: &mut Turntable) {
+        ui.window("Timeline")
+            .size([432., 96.], im::Condition::FirstUseEver)
+            .position([580., 580.], im::Condition::FirstUseEver)
+            .build(|| {
+                self.timeline = turntable.draw(ui);
+            });
+    }
+
+    fn draw_inspector(&mut self, ui: &im::Ui, scene: &dyn Scene) {
+        ui.window("Inspector")
+            .size([432., 512.], im::Condition::FirstUseEver)
+            .position([800., 580.], im::Condition::FirstUseEver)
+            .build(|| {
+                ui.text(scene.name());
+                ui.separator();
+                self.edits = inspector::draw(ui, scene.world());
+            });
+    }
+
+    fn draw_scene_picker(
+        &mut self,
+        ui: &im::Ui,
+        scenes: &mut [Box<dyn Scene>],
+        export: &mut ExportSettings,
+    ) {
         ui.window("Scenarios")
             .size([432., 512.], im::Condition::FirstUseEver)
             .position([800., 48.], im::Condition::FirstUseEver)
             .build(|| {
                 for scene_id in 0..scenes.len() {
-                    self.draw_scene_entry(ui, scenes, scene_id);
+                    self.draw_scene_entry(ui, scenes, scene_id, export);
                 }
             });
     }
 
-    fn draw_scene_entry(&mut self, ui: &im::Ui, scenes: &mut [Box<dyn Scene>], scene_id: usize) {
+    fn draw_scene_entry(
+        &mut self,
+        ui: &im::Ui,
+        scenes: &mut [Box<dyn Scene>],
+        scene_id: usize,
+        export: &mut ExportSettings,
+    ) {
         let scene = scenes.get_mut(scene_id).unwrap();
         let name = scene.name();
 
@@ -381,24 +1053,82 @@ impl UiState {
             ui.separator();
             let force = ui.button(format!("Render it!##{name}"));
             ui.same_line();
-            let save = ui.button(format!("Save as PNG##{name}"));
+            if ui.button(format!("Save as...##{name}")) {
+                ui.open_popup(format!("Save as##{name}"));
+            }
 
             if redraw || force {
                 self.render_scene = Some(scene_id);
             }
-            if save {
+            if self.draw_save_popup(ui, &name, export) {
                 self.save_scene = Some(scene_id);
             }
         }
     }
+
+    /// Draws the output options for a scene, returning `true` if the user confirmed the save.
+    fn draw_save_popup(&self, ui: &im::Ui, name: &str, export: &mut ExportSettings) -> bool {
+        let mut save = false;
+
+        if let Some(_popup) = ui.begin_popup(format!("Save as##{name}")) {
+            ui.combo(
+                format!("Format##{name}"),
+                &mut export.format,
+                &ImageFormat::ALL,
+                |f| f.name().into(),
+            );
+
+            ui.checkbox(
+                format!("Override resolution##{name}"),
+                &mut export.override_size,
+            );
+
+            if export.override_size {
+                ui.input_scalar(format!("Width##{name}"), &mut export.width)
+                    .build();
+                ui.input_scalar(format!("Height##{name}"), &mut export.height)
+                    .build();
+            } else {
+                ui.text(format!(
+                    "Resolution: {}x{} (canvas)",
+                    self.canvas_width, self.canvas_height
+                ));
+            }
+
+            ui.separator();
+
+            if ui.button(format!("Save...##{name}")) {
+                ui.close_current_popup();
+                save = true;
+            }
+        }
+
+        save
+    }
 }
 
 impl GfxBackend {
-    fn render_to_texture(&mut self, width: u32, height: u32, canvas: &Canvas) {
+    fn render_to_texture(&mut self, canvas: &Canvas, tone_mapping: &ToneMapping) {
+        self.texture_id = Some(self.upload_canvas(canvas, tone_mapping, self.texture_id));
+    }
+
+    fn render_to_reference(&mut self, canvas: &Canvas, tone_mapping: &ToneMapping) {
+        self.reference_id = Some(self.upload_canvas(canvas, tone_mapping, self.reference_id));
+    }
+
+    /// Uploads `canvas` to a GPU texture through `tone_mapping`, replacing texture `id` if given.
+    fn upload_canvas(
+        &mut self,
+        canvas: &Canvas,
+        tone_mapping: &ToneMapping,
+        id: Option<im::TextureId>,
+    ) -> im::TextureId {
+        let (width, height) = (canvas.width(), canvas.height());
+
         let raw_data = canvas
             .iter()
-            .flat_map(|c| {
-                let (r, g, b) = c.to_rgb888();
+            .flat_map(|&c| {
+                let (r, g, b) = tone_mapping.map(c);
                 vec![b, g, r, 255]
             })
             .collect::<Vec<_>>();
@@ -416,30 +1146,12 @@ impl GfxBackend {
         let texture = Texture::new(&self.device, &self.renderer, texture_config);
         texture.write(&self.queue, &raw_data, width, height);
 
-        if let Some(id) = self.texture_id {
-            self.renderer.textures.replace(id, texture);
-        } else {
-            self.texture_id = Some(self.renderer.textures.insert(texture));
+        match id {
+            Some(id) => {
+                self.renderer.textures.replace(id, texture);
+                id
+            }
+            None => self.renderer.textures.insert(texture),
         }
     }
 }
-
-fn save_current_scene<P>(scene: &mut Box<dyn Scene>, width: u32, height: u32, path: P)
-where
-    P: AsRef<Path>,
-{
-    let canvas = scene.render(width, height).finalize();
-
-    let buf = canvas
-        .iter()
-        .flat_map(|p| {
-            let (r, g, b) = p.to_rgb888();
-            vec![r, g, b]
-        })
-        .collect::<Vec<u8>>();
-
-    ImageBuffer::<Rgb<u8>, _>::from_vec(width, height, buf)
-        .unwrap()
-        .save(path)
-        .unwrap();
-}
@@ -0,0 +1,483 @@
+//! Background rendering of scenes.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use rayon::{
+    iter::{IntoParallelIterator, ParallelIterator},
+    ThreadPool, ThreadPoolBuilder,
+};
+use serde::{Deserialize, Serialize};
+use tracy::{
+    query::World,
+    rendering::{Aov, Camera, Canvas, Color},
+};
+
+use crate::{debug::DebugView, region::Region};
+
+/// An update sent by the rendering thread.
+enum Update {
+    /// A block of contiguous scanlines in their final state, with the AOVs of their pixels.
+    Scanlines {
+        start: u32,
+        pixels: Vec<Color>,
+        aovs: Vec<Aov>,
+    },
+    /// A coarse approximation of the whole image, upscaled to its full size.
+    Preview(Canvas),
+}
+
+/// How the rendering work is scheduled on the user's machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scheduling {
+    /// Number of threads rendering in parallel.
+    pub threads: usize,
+    /// Whether the rendering threads should run at a lower priority than the rest of the system.
+    ///
+    /// Only supported on Linux, ignored elsewhere.
+    pub low_priority: bool,
+}
+
+impl Default for Scheduling {
+    fn default() -> Self {
+        Self {
+            threads: Self::max_threads(),
+            low_priority: false,
+        }
+    }
+}
+
+impl Scheduling {
+    /// Returns the number of threads that can run in parallel on this machine.
+    pub fn max_threads() -> usize {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    }
+
+    /// Builds a thread pool following these settings.
+    pub fn build_pool(&self) -> ThreadPool {
+        let low_priority = self.low_priority;
+
+        ThreadPoolBuilder::new()
+            .num_threads(self.threads.max(1))
+            .thread_name(|i| format!("tracy-render-{}", i))
+            .start_handler(move |_| {
+                if low_priority {
+                    lower_thread_priority();
+                }
+            })
+            .build()
+            .expect("failed to create rendering thread pool")
+    }
+}
+
+/// Lowers the scheduling priority of the calling thread.
+#[cfg(target_os = "linux")]
+fn lower_thread_priority() {
+    // On Linux, `nice` values are per-thread rather than per-process
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, 0, 10);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lower_thread_priority() {}
+
+/// Snapshot of the progress of a [`RenderWorker`].
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    /// Number of scanlines received so far.
+    pub completed: u32,
+    /// Total number of scanlines to be rendered.
+    pub total: u32,
+    /// Time elapsed since the render was started.
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Returns the completed fraction of the render, in the `[0, 1]` range.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.completed as f32 / self.total as f32
+        }
+    }
+
+    /// Estimates the time left before the render completes, assuming a constant rate.
+    ///
+    /// Returns `None` if no scanline has been completed yet.
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.completed == 0 {
+            None
+        } else {
+            let left = self.total - self.completed;
+            Some(self.elapsed.mul_f64(left as f64 / self.completed as f64))
+        }
+    }
+}
+
+/// A scene being rendered on a dedicated thread.
+///
+/// Completed scanlines are sent back over a channel and collected into a local canvas by
+/// [`RenderWorker::poll`], so that the UI thread never blocks on the renderer.
+pub struct RenderWorker {
+    canvas: Canvas,
+    aovs: Vec<Aov>,
+    completed: u32,
+    total: u32,
+    started: Instant,
+    updates: Receiver<Update>,
+    cancel: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RenderWorker {
+    /// Starts rendering `world` as seen by `camera` on a new thread, using a dedicated thread pool
+    /// configured according to `scheduling`.
+    ///
+    /// If a `view` is given, the scene is rendered in false colors rather than fully shaded.
+    pub fn spawn(
+        world: Arc<World>,
+        camera: Camera,
+        scheduling: Scheduling,
+        view: Option<DebugView>,
+    ) -> Self {
+        let canvas = Canvas::new(camera.horizontal_size(), camera.vertical_size());
+        let aovs = vec![Aov::default(); canvas.width() as usize * canvas.height() as usize];
+        let total = camera.vertical_size();
+
+        Self::start(
+            canvas,
+            aovs,
+            total,
+            scheduling,
+            move |tx, cancel| match view {
+                Some(view) => stream_debug_view(&world, &camera, view, tx, cancel),
+                None => stream_progressive(&world, &camera, tx, cancel),
+            },
+        )
+    }
+
+    /// Starts re-rendering `region` of `base` on a new thread, compositing the result over it.
+    ///
+    /// The AOVs of the pixels outside of the region are taken from `base_aovs`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `base_aovs` does not hold the AOVs of every pixel of `base`.
+    ///
+    /// The region is rendered according to its own quality settings, regardless of the ones of
+    /// `camera`.
+    pub fn spawn_region(
+        world: Arc<World>,
+        camera: Camera,
+        scheduling: Scheduling,
+        base: Canvas,
+        base_aovs: Vec<Aov>,
+        region: Region,
+    ) -> Self {
+        let rows = region.rows();
+        let total = rows.end - rows.start;
+        let camera = region.camera(&camera);
+        assert_eq!(
+            base_aovs.len(),
+            base.width() as usize * base.height() as usize,
+            "AOVs do not match the base render"
+        );
+
+        let canvas = base.clone();
+        let aovs = base_aovs.clone();
+
+        Self::start(canvas, aovs, total, scheduling, move |tx, cancel| {
+            stream_region(&world, &camera, &base, &base_aovs, &region, tx, cancel)
+        })
+    }
+
+    /// Shows `background` in place of the scanlines that have not been received yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `background` is not the same size as the render.
+    pub fn with_background(mut self, background: Canvas) -> Self {
+        assert_eq!(
+            (background.width(), background.height()),
+            (self.canvas.width(), self.canvas.height()),
+            "background size does not match the render"
+        );
+
+        self.canvas = background;
+        self
+    }
+
+    /// Runs `job` on a new thread, within a thread pool configured according to `scheduling`.
+    fn start<F>(canvas: Canvas, aovs: Vec<Aov>, total: u32, scheduling: Scheduling, job: F) -> Self
+    where
+        F: FnOnce(&Sender<Update>, &AtomicBool) + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let cancel = cancel.clone();
+            let pool = scheduling.build_pool();
+
+            thread::spawn(move || pool.install(|| job(&tx, &cancel)))
+        };
+
+        Self {
+            canvas,
+            aovs,
+            completed: 0,
+            total,
+            started: Instant::now(),
+            updates: rx,
+            cancel,
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns the canvas containing all the scanlines received so far.
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    /// Returns the AOVs of the pixels of [`RenderWorker::canvas`], row by row.
+    ///
+    /// The pixels of coarse previews have the default AOVs until their final scanline is received.
+    pub fn aovs(&self) -> &[Aov] {
+        &self.aovs
+    }
+
+    /// Returns the current progress of the render.
+    pub fn progress(&self) -> Progress {
+        Progress {
+            completed: self.completed,
+            total: self.total,
+            elapsed: self.started.elapsed(),
+        }
+    }
+
+    /// Returns whether the rendering thread has completed its job.
+    ///
+    /// Scanlines may still be pending in the channel, so [`RenderWorker::poll`] should be called
+    /// one last time after this method returns `true`.
+    pub fn is_finished(&self) -> bool {
+        match &self.thread {
+            Some(thread) => thread.is_finished(),
+            None => true,
+        }
+    }
+
+    /// Copies all the scanlines completed since the last call into the canvas, returning `true`
+    /// if the canvas was updated.
+    ///
+    /// Coarse previews of the image replace the whole canvas, but do not count towards the
+    /// progress of the render.
+    pub fn poll(&mut self) -> bool {
+        let width = self.canvas.width() as usize;
+        let mut updated = false;
+
+        for update in self.updates.try_iter() {
+            match update {
+                Update::Scanlines {
+                    start,
+                    pixels,
+                    aovs,
+                } => {
+                    let n = pixels.len() / width.max(1);
+                    let offset = start as usize * width;
+
+                    self.aovs[offset..offset + aovs.len()].copy_from_slice(&aovs);
+
+                    for (dst, src) in self
+                        .canvas
+                        .scanlines_mut(start as usize, n)
+                        .flat_map(|line| line.iter_mut())
+                        .zip(pixels)
+                    {
+                        *dst = src;
+                    }
+
+                    self.completed += n as u32;
+                }
+                Update::Preview(canvas) => self.canvas = canvas,
+            }
+
+            updated = true;
+        }
+
+        updated
+    }
+
+    /// Stops the rendering thread and waits for it to terminate.
+    pub fn stop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().expect("rendering thread panicked");
+        }
+    }
+}
+
+/// Renders `world` progressively, sending each coarse level of its preview pyramid over `tx`,
+/// followed by the completed scanlines of the full resolution image, until done or cancelled.
+fn stream_progressive(world: &World, camera: &Camera, tx: &Sender<Update>, cancel: &AtomicBool) {
+    let width = camera.horizontal_size() as usize;
+    let mut preview = camera.preview(world);
+    let mut done = 0;
+
+    while !cancel.load(Ordering::Relaxed) {
+        let stride = preview.stride();
+        let more = preview.advance();
+
+        let update = if stride == 1 {
+            let rows = preview.completed_rows();
+            let pixels = preview
+                .canvas()
+                .iter()
+                .skip(done as usize * width)
+                .take((rows - done) as usize * width)
+                .copied()
+                .collect();
+            let aovs = preview.aovs()[done as usize * width..rows as usize * width].to_vec();
+
+            let start = done;
+            done = rows;
+            Some(Update::Scanlines {
+                start,
+                pixels,
+                aovs,
+            })
+        } else if preview.stride() < stride {
+            preview
+                .level()
+                .map(|level| Update::Preview(upscale(&level, stride, camera)))
+        } else {
+            None
+        };
+
+        // The receiving end is gone, nobody is interested in the result anymore
+        if let Some(update) = update {
+            if tx.send(update).is_err() {
+                break;
+            }
+        }
+
+        if !more {
+            break;
+        }
+    }
+}
+
+/// Scales `level` up by `stride` to the full resolution of `camera`, repeating each pixel.
+fn upscale(level: &Canvas, stride: u32, camera: &Camera) -> Canvas {
+    let mut canvas = Canvas::new(camera.horizontal_size(), camera.vertical_size());
+
+    for (x, y, pixel) in canvas.enumerate_pixels_mut() {
+        if let Some(&c) = level.get(x / stride, y / stride) {
+            *pixel = c;
+        }
+    }
+
+    canvas
+}
+
+/// Renders `world` in false colors according to `view`, sending each scanline over `tx` until
+/// done or cancelled.
+fn stream_debug_view(
+    world: &World,
+    camera: &Camera,
+    view: DebugView,
+    tx: &Sender<Update>,
+    cancel: &AtomicBool,
+) {
+    for y in 0..camera.vertical_size() {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let (pixels, aovs) = (0..camera.horizontal_size())
+            .into_par_iter()
+            .map(|x| {
+                let ray = camera.ray_to(x, y);
+                let hit = world.interferences_with_ray(&ray).hit();
+
+                (
+                    view.color_of(world, hit.as_ref()),
+                    Aov::from_hit(hit.as_ref()),
+                )
+            })
+            .unzip();
+
+        let update = Update::Scanlines {
+            start: y,
+            pixels,
+            aovs,
+        };
+        if tx.send(update).is_err() {
+            break;
+        }
+    }
+}
+
+/// Renders `region` of `base` at a higher quality, sending each updated scanline over `tx` until
+/// done or cancelled.
+fn stream_region(
+    world: &World,
+    camera: &Camera,
+    base: &Canvas,
+    base_aovs: &[Aov],
+    region: &Region,
+    tx: &Sender<Update>,
+    cancel: &AtomicBool,
+) {
+    for y in region.rows() {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let mut pixels = base
+            .iter()
+            .skip((y * base.width()) as usize)
+            .take(base.width() as usize)
+            .copied()
+            .collect::<Vec<_>>();
+
+        let row = (y * base.width()) as usize;
+        let mut aovs = base_aovs[row..row + base.width() as usize].to_vec();
+
+        let columns = region.columns();
+        let (colors, region_aovs): (Vec<_>, Vec<_>) = columns
+            .clone()
+            .into_par_iter()
+            .map(|x| camera.render_pixel(world, x, y))
+            .unzip();
+
+        let span = columns.start as usize..columns.end as usize;
+        pixels[span.clone()].copy_from_slice(&colors);
+        aovs[span].copy_from_slice(&region_aovs);
+
+        let update = Update::Scanlines {
+            start: y,
+            pixels,
+            aovs,
+        };
+        if tx.send(update).is_err() {
+            break;
+        }
+    }
+}
+
+impl Drop for RenderWorker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -13,7 +13,7 @@ fn render_shaded_sphere(width: u32, height: u32) -> Canvas {
 
     world.add(Object::new_with_material(
         Sphere,
-        Matrix::identity(4),
+        Matrix::identity(),
         Material {
             pattern: Pattern::new(Color::new(1.0, 0.2, 1.0).into()),
             ..Default::default()
@@ -0,0 +1,83 @@
+//! Errors reported by the fallible APIs of the crate.
+
+use core::{error, fmt};
+
+/// Errors caused by invalid input to the library.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The number of elements provided to build a matrix does not match its order.
+    MatrixSize {
+        /// Order of the matrix being built.
+        order: usize,
+        /// Number of elements provided.
+        len: usize,
+    },
+    /// The size of a convolution kernel is not odd, or does not match the number of its weights.
+    KernelSize {
+        /// Width of the kernel being built.
+        width: usize,
+        /// Height of the kernel being built.
+        height: usize,
+        /// Number of weights provided.
+        len: usize,
+    },
+    /// A position does not lie within a canvas.
+    OutOfBounds {
+        /// Position being accessed.
+        position: (u32, u32),
+        /// Size of the canvas.
+        size: (u32, u32),
+    },
+    /// The size of an image does not match the number of its pixels.
+    ImageSize {
+        /// Width of the image being built.
+        width: u32,
+        /// Height of the image being built.
+        height: u32,
+        /// Number of pixels provided.
+        len: usize,
+    },
+    /// The transform of an object cannot be inverted.
+    NonInvertibleTransform {
+        /// Index of the object in its scene.
+        object: usize,
+    },
+    /// The parameters of a camera do not describe a valid projection.
+    InvalidCamera(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MatrixSize { order, len } => {
+                write!(
+                    f,
+                    "cannot build a {0}x{0} matrix from {1} elements",
+                    order, len
+                )
+            }
+            Error::KernelSize { width, height, len } => write!(
+                f,
+                "cannot build a {}x{} kernel from {} weights",
+                width, height, len
+            ),
+            Error::OutOfBounds { position, size } => write!(
+                f,
+                "position ({}, {}) is outside of a {}x{} canvas",
+                position.0, position.1, size.0, size.1
+            ),
+            Error::ImageSize { width, height, len } => write!(
+                f,
+                "cannot build a {}x{} image from {} pixels",
+                width, height, len
+            ),
+            Error::NonInvertibleTransform { object } => {
+                write!(f, "transform of object {} is not invertible", object)
+            }
+            Error::InvalidCamera(reason) => write!(f, "invalid camera: {}", reason),
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -0,0 +1,4 @@
+//! Import of models from files.
+
+pub mod obj;
+pub mod stl;
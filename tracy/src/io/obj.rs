@@ -0,0 +1,98 @@
+//! Loading of models in Wavefront OBJ format.
+//!
+//! Only the geometry of the model is read: vertices, and faces which are split into triangles
+//! around their first vertex. Texture coordinates, normals, groups and materials are ignored.
+
+use std::{fs, io, path::Path};
+
+use crate::{
+    math::{Matrix, Point3},
+    query::Object,
+    shape::{Mesh, Triangle},
+};
+
+/// Loads the OBJ file at `path` as an object with an identity transform.
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if the file is not a valid OBJ model.
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Object> {
+    Ok(Object::new(
+        parse(&fs::read_to_string(path)?)?,
+        Matrix::identity(),
+    ))
+}
+
+/// Parses the contents of an OBJ file into a mesh.
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if `data` is not a valid OBJ model.
+pub fn parse(data: &str) -> io::Result<Mesh> {
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+
+    for line in data.lines() {
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("v") => {
+                let mut coord = || -> io::Result<f32> {
+                    tokens
+                        .next()
+                        .and_then(|t| t.parse().ok())
+                        .ok_or_else(|| invalid_data("invalid vertex coordinates"))
+                };
+                vertices.push(Point3::new(coord()?, coord()?, coord()?));
+            }
+            Some("f") => {
+                let face = tokens
+                    .map(|t| vertex_index(t, vertices.len()).map(|i| vertices[i]))
+                    .collect::<io::Result<Vec<_>>>()?;
+
+                if face.len() < 3 {
+                    return Err(invalid_data("face with less than three vertices"));
+                }
+
+                triangles.extend(
+                    face.windows(2)
+                        .skip(1)
+                        .map(|w| Triangle::new(face[0], w[0], w[1])),
+                );
+            }
+            _ => (),
+        }
+    }
+
+    Ok(Mesh::new(triangles))
+}
+
+/// Returns the position in `0..count` of the vertex referenced by a vertex of a face, written as
+/// `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+///
+/// Indices start from 1, or count back from the last vertex when negative.
+fn vertex_index(token: &str, count: usize) -> io::Result<usize> {
+    let index: i64 = token
+        .split('/')
+        .next()
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| invalid_data("invalid face vertex"))?;
+
+    let index = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+
+    if (0..count as i64).contains(&index) {
+        Ok(index as usize)
+    } else {
+        Err(invalid_data(format!(
+            "face vertex {} is not defined",
+            token
+        )))
+    }
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,113 @@
+//! Loading of models in STL format.
+//!
+//! Both the binary and the ASCII variants of the format are supported. The normals stored in the
+//! file are ignored in favor of those computed from the vertices of each triangle, which are more
+//! reliable in practice.
+
+use std::{fs, io, path::Path, str};
+
+use crate::{
+    math::{Matrix, Point3},
+    query::Object,
+    shape::{Mesh, Triangle},
+};
+
+/// Size of the header of a binary STL file, including the number of triangles.
+const BINARY_HEADER_LEN: usize = 84;
+
+/// Size of a triangle in a binary STL file.
+const BINARY_TRIANGLE_LEN: usize = 50;
+
+/// Loads the STL file at `path` as an object with an identity transform.
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if the file is not a valid STL model.
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Object> {
+    Ok(Object::new(parse(&fs::read(path)?)?, Matrix::identity()))
+}
+
+/// Parses the contents of an STL file into a mesh.
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if `data` is not a valid STL model.
+pub fn parse(data: &[u8]) -> io::Result<Mesh> {
+    // ASCII files start with `solid`, but so do some binary ones: tell them apart by their size
+    let triangles = if is_binary(data) {
+        parse_binary(data)
+    } else if data.starts_with(b"solid") {
+        parse_ascii(data)?
+    } else {
+        return Err(invalid_data("not an STL file"));
+    };
+
+    Ok(Mesh::new(triangles))
+}
+
+/// Returns true if the size of `data` matches the number of triangles in its binary header.
+fn is_binary(data: &[u8]) -> bool {
+    data.get(80..BINARY_HEADER_LEN).is_some_and(|count| {
+        let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+        let len = count.checked_mul(BINARY_TRIANGLE_LEN);
+
+        len.and_then(|n| n.checked_add(BINARY_HEADER_LEN)) == Some(data.len())
+    })
+}
+
+fn parse_binary(data: &[u8]) -> Vec<Triangle> {
+    let f32_at = |b: &[u8], i: usize| f32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]);
+
+    data[BINARY_HEADER_LEN..]
+        .chunks_exact(BINARY_TRIANGLE_LEN)
+        .map(|t| {
+            // Each triangle starts with its normal, which is skipped
+            let vertex = |i: usize| {
+                let base = 12 + 12 * i;
+                Point3::new(f32_at(t, base), f32_at(t, base + 4), f32_at(t, base + 8))
+            };
+            Triangle::new(vertex(0), vertex(1), vertex(2))
+        })
+        .collect()
+}
+
+fn parse_ascii(data: &[u8]) -> io::Result<Vec<Triangle>> {
+    let text = str::from_utf8(data).map_err(|_| invalid_data("ASCII STL file is not UTF-8"))?;
+
+    let mut tokens = text.split_whitespace();
+    let mut vertices = Vec::new();
+    let mut facets = 0;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "facet" => facets += 1,
+            "vertex" => {
+                let mut coord = || -> io::Result<f32> {
+                    tokens
+                        .next()
+                        .and_then(|t| t.parse().ok())
+                        .ok_or_else(|| invalid_data("invalid vertex coordinates"))
+                };
+                vertices.push(Point3::new(coord()?, coord()?, coord()?));
+            }
+            _ => (),
+        }
+    }
+
+    if vertices.len() != 3 * facets {
+        return Err(invalid_data(format!(
+            "expected {} vertices for {} facets, found {}",
+            3 * facets,
+            facets,
+            vertices.len()
+        )));
+    }
+
+    Ok(vertices
+        .chunks_exact(3)
+        .map(|v| Triangle::new(v[0], v[1], v[2]))
+        .collect())
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
@@ -1,9 +1,32 @@
 //! A Rust implementation of The Ray Tracer Challenge book.
+//!
+//! With the default `std` feature disabled, the crate only depends on `core` and `alloc`. The
+//! [`math`] and [`shape`] modules, ray casting against individual objects and the shading
+//! primitives of [`rendering`] remain available, while worlds, cameras and image encoding are
+//! left out. Floating point functions are then provided by `libm`, through the `libm` feature.
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(missing_debug_implementations)]
 #![warn(missing_docs)]
 
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("either the `std` or the `libm` feature must be enabled");
+
+extern crate alloc;
+
+pub mod error;
+#[cfg(feature = "std")]
+pub mod io;
 pub mod math;
+#[cfg(feature = "std")]
+pub mod profile;
 pub mod query;
 pub mod rendering;
 pub mod shape;
+#[cfg(feature = "std")]
+pub mod testing;
+
+pub use error::Error;
+
+/// Version of the `tracy` crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -1,6 +1,11 @@
 //! Coordinate system.
 
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use core::{
+    fmt,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use super::{float, mul_add};
 
 /// A point in 3D space.
 #[cfg_attr(
@@ -77,21 +82,25 @@ impl Vec3 {
 
     /// Computes the magnitude of `self`.
     pub fn length(&self) -> f32 {
-        (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
+        float::sqrt(self.dot(self))
     }
 
     /// Returns `self` normalized to length 1.0.
     pub fn normalize(&self) -> Self {
-        Self {
-            x: self.x / self.length(),
-            y: self.y / self.length(),
-            z: self.z / self.length(),
+        let length = self.length();
+
+        // Multiplying by the reciprocal is faster, but rounds twice
+        if cfg!(all(feature = "fast-math", not(feature = "deterministic"))) {
+            let inv = 1.0 / length;
+            Self::new(self.x * inv, self.y * inv, self.z * inv)
+        } else {
+            Self::new(self.x / length, self.y / length, self.z / length)
         }
     }
 
     /// Computes the dot product of `self` and `rhs`.
     pub fn dot(&self, rhs: &Self) -> f32 {
-        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+        dot3([self.x, self.y, self.z], [rhs.x, rhs.y, rhs.z])
     }
 
     /// Computes the cross product of `self` and `rhs`.
@@ -313,6 +322,20 @@ impl From<Vec3> for Point3 {
     }
 }
 
+macro_rules! impl_display {
+    ($t:ty) => {
+        /// Formats the components as `(x, y, z)`, each with the precision of the formatter if any.
+        impl fmt::Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match f.precision() {
+                    Some(p) => write!(f, "({:.*}, {:.*}, {:.*})", p, self.x, p, self.y, p, self.z),
+                    None => write!(f, "({}, {}, {})", self.x, self.y, self.z),
+                }
+            }
+        }
+    };
+}
+
 impl_bin_op!(impl Add[add, +] for Point3 : Vec3 => Point3);
 impl_bin_op!(impl Sub[sub, -] for Point3 : Vec3 => Point3);
 impl_bin_op!(impl Sub[sub, -] for Point3 : Point3 => Vec3);
@@ -332,3 +355,12 @@ impl_assign_ops!(Point3 Vec3);
 
 impl_conversions!(Point3, 1.0);
 impl_conversions!(Vec3, 0.0);
+
+impl_display!(Point3);
+impl_display!(Vec3);
+
+/// Computes the dot product of two 3-component vectors.
+#[inline(always)]
+pub(crate) fn dot3(a: [f32; 3], b: [f32; 3]) -> f32 {
+    mul_add(a[2], b[2], mul_add(a[1], b[1], a[0] * b[0]))
+}
@@ -1,166 +1,122 @@
 //! Matrix representations and operations defined on them.
 
-use std::{
+use alloc::{format, string::String, vec::Vec};
+use core::{
+    fmt,
     ops::{Index, IndexMut, Mul},
     slice,
 };
 
-use super::{Point3, Vec3};
+use crate::Error;
+
+use super::{float, mul_add, Point3, Vec3};
 
 /// A NxN, column-major matrix.
-#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+///
+/// The order of the matrix is part of its type, and defaults to 4 as used by transforms. Matrices
+/// of order 2 and 3 only appear as submatrices when computing determinants and cofactors.
 #[derive(Debug, Clone, PartialEq)]
-pub struct Matrix {
-    data: [f32; 16],
-    order: usize,
+pub struct Matrix<const N: usize = 4> {
+    data: [[f32; N]; N],
 }
 
-impl Default for Matrix {
+impl<const N: usize> Default for Matrix<N> {
     fn default() -> Self {
-        Self::identity(4)
+        Self::identity()
     }
 }
 
-impl Matrix {
-    /// Creates a matrix of order `n` filled with zeros.
-    pub fn zeros(n: usize) -> Self {
+impl<const N: usize> Matrix<N> {
+    /// Creates a matrix filled with zeros.
+    pub fn zeros() -> Self {
         Self {
-            data: [0.0; 16],
-            order: n,
+            data: [[0.0; N]; N],
         }
     }
 
-    /// Creates the identity matrix of order `n`.
-    pub fn identity(n: usize) -> Self {
-        let mut data = [0.0; 16];
+    /// Creates the identity matrix.
+    pub fn identity() -> Self {
+        let mut out = Self::zeros();
 
-        for i in 0..n {
-            data[i * n + i] = 1.;
+        for i in 0..N {
+            out.data[i][i] = 1.;
         }
 
-        Self { data, order: n }
+        out
     }
 
-    /// Creates a matrix of order `n` with its elements filled with the components provided
-    /// by a slice in column-major order.
-    ///
-    /// # Panics
-    ///
-    /// Panics if `data.len() != n * n`.
-    pub fn from_column_slice<D: AsRef<[f32]>>(n: usize, data: D) -> Self {
-        let cols = data.as_ref();
-        assert_eq!(n * n, cols.len());
-
-        let mut data: [f32; 16] = Default::default();
-        data[..n * n].copy_from_slice(cols);
+    /// Creates a matrix from its rows.
+    pub fn from_rows(rows: [[f32; N]; N]) -> Self {
+        Self { data: rows }.transpose()
+    }
 
-        Self { data, order: n }
+    /// Creates a matrix from its columns.
+    pub fn from_columns(columns: [[f32; N]; N]) -> Self {
+        Self { data: columns }
     }
 
-    /// Creates a matrix of order `n` with its elements filled with the components provided
-    /// by a slice in row-major order.
+    /// Creates a matrix with its elements filled with the components provided by a slice in
+    /// column-major order.
     ///
     /// # Panics
     ///
-    /// Panics if `data.len() != n * n`.
-    pub fn from_row_slice<D: AsRef<[f32]>>(n: usize, data: D) -> Self {
-        Self::from_column_slice(n, data).transpose()
-    }
-
-    /// Creates a matrix that applies a translation of `(x,y,z)`.
-    pub fn from_translation(x: f32, y: f32, z: f32) -> Self {
-        let mut out = Self::identity(4);
-        out[(0, 3)] = x;
-        out[(1, 3)] = y;
-        out[(2, 3)] = z;
-        out
-    }
-
-    /// Creates a matrix that applies a non-uniform scaling of `(x,y,z)`.
-    pub fn from_scale(x: f32, y: f32, z: f32) -> Self {
-        let mut out = Self::identity(4);
-        out[(0, 0)] = x;
-        out[(1, 1)] = y;
-        out[(2, 2)] = z;
-        out
+    /// Panics if `data.len() != N * N`.
+    pub fn from_column_slice<D: AsRef<[f32]>>(data: D) -> Self {
+        Self::try_from_column_slice(data).expect("slice length does not match matrix order")
     }
 
-    /// Creates a matrix that applies a rotation of `rad` radians around the `x` axis.
-    pub fn from_rotation_x(rad: f32) -> Self {
-        let mut out = Self::identity(4);
-        out[(1, 1)] = rad.cos();
-        out[(1, 2)] = -rad.sin();
-        out[(2, 1)] = rad.sin();
-        out[(2, 2)] = rad.cos();
-        out
+    /// Creates a matrix with its elements filled with the components provided by a slice in
+    /// row-major order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data.len() != N * N`.
+    pub fn from_row_slice<D: AsRef<[f32]>>(data: D) -> Self {
+        Self::from_column_slice(data).transpose()
     }
 
-    /// Creates a matrix that applies a rotation of `rad` radians around the `y` axis.
-    pub fn from_rotation_y(rad: f32) -> Self {
-        let mut out = Self::identity(4);
-        out[(0, 0)] = rad.cos();
-        out[(0, 2)] = rad.sin();
-        out[(2, 0)] = -rad.sin();
-        out[(2, 2)] = rad.cos();
-        out
-    }
+    /// Creates a matrix with its elements filled with the components provided by a slice in
+    /// column-major order.
+    ///
+    /// Fails with [`Error::MatrixSize`] if `data.len() != N * N`.
+    pub fn try_from_column_slice<D: AsRef<[f32]>>(data: D) -> Result<Self, Error> {
+        let cols = data.as_ref();
+        if N * N != cols.len() {
+            return Err(Error::MatrixSize {
+                order: N,
+                len: cols.len(),
+            });
+        }
 
-    /// Creates a matrix that applies a rotation of `rad` radians around the `z` axis.
-    pub fn from_rotation_z(rad: f32) -> Self {
-        let mut out = Self::identity(4);
-        out[(0, 0)] = rad.cos();
-        out[(0, 1)] = -rad.sin();
-        out[(1, 0)] = rad.sin();
-        out[(1, 1)] = rad.cos();
-        out
-    }
+        let mut out = Self::zeros();
+        out.data.as_flattened_mut().copy_from_slice(cols);
 
-    /// Creates a matrix that applies the specified shear.
-    pub fn from_shear(xy: f32, xz: f32, yx: f32, yz: f32, zx: f32, zy: f32) -> Self {
-        let mut out = Self::identity(4);
-        out[(0, 1)] = xy;
-        out[(0, 2)] = xz;
-        out[(1, 0)] = yx;
-        out[(1, 2)] = yz;
-        out[(2, 0)] = zx;
-        out[(2, 1)] = zy;
-        out
+        Ok(out)
     }
 
-    /// Creates a view transform matrix looking at `center` from `eye`.
-    pub fn look_at(eye: Point3, center: Point3, up: Vec3) -> Matrix {
-        let fwd = (center - eye).normalize();
-        let up = up.normalize();
-        let left = fwd.cross(&up);
-        let up = left.cross(&fwd);
-
-        let orientation = Matrix::from_column_slice(
-            4,
-            [
-                left.x, up.x, -fwd.x, 0.0, left.y, up.y, -fwd.y, 0.0, left.z, up.z, -fwd.z, 0.0,
-                0.0, 0.0, 0.0, 1.0,
-            ],
-        );
-
-        orientation * Matrix::from_translation(-eye.x, -eye.y, -eye.z)
+    /// Creates a matrix with its elements filled with the components provided by a slice in
+    /// row-major order.
+    ///
+    /// Fails with [`Error::MatrixSize`] if `data.len() != N * N`.
+    pub fn try_from_row_slice<D: AsRef<[f32]>>(data: D) -> Result<Self, Error> {
+        Self::try_from_column_slice(data).map(|m| m.transpose())
     }
 
     /// Returns the order of this matrix, ie. the number of its rows/columns.
-    pub fn order(&self) -> usize {
-        self.order
+    pub const fn order(&self) -> usize {
+        N
     }
 
     /// Returns a reference to the element at position `(i,j)`, or `None` if the index is
     /// out-of-bounds.
     pub fn get(&self, (i, j): (usize, usize)) -> Option<&f32> {
-        self.data.get(self.liner_index(i, j))
+        self.data.get(j)?.get(i)
     }
 
     /// Returns a mutable reference to the element at position `(i,j)`, or `None` if the index is
     /// out-of-bounds.
     pub fn get_mut(&mut self, (i, j): (usize, usize)) -> Option<&mut f32> {
-        let idx = self.liner_index(i, j);
-        self.data.get_mut(idx)
+        self.data.get_mut(j)?.get_mut(i)
     }
 
     /// Returns a reference to the element at position `(i,j)` without bound-checking.
@@ -170,7 +126,7 @@ impl Matrix {
     /// Calling this method with an out-of-bounds index is undefined behavior even if the resulting
     /// reference is not used.
     pub unsafe fn get_unchecked(&self, (i, j): (usize, usize)) -> &f32 {
-        self.data.get_unchecked(self.liner_index(i, j))
+        self.data.get_unchecked(j).get_unchecked(i)
     }
 
     /// Returns a mutable reference to the element at position `(i,j)` without bound-checking.
@@ -180,137 +136,293 @@ impl Matrix {
     /// Calling this method with an out-of-bounds index is undefined behavior even if the resulting
     /// reference is not used.
     pub unsafe fn get_unchecked_mut(&mut self, (i, j): (usize, usize)) -> &mut f32 {
-        let idx = self.liner_index(i, j);
-        self.data.get_unchecked_mut(idx)
+        self.data.get_unchecked_mut(j).get_unchecked_mut(i)
     }
 
     /// Iterates through this matrix coordinates in column-major order.
     pub fn iter(&self) -> slice::Iter<f32> {
-        self.data.iter()
+        self.data.as_flattened().iter()
     }
 
     /// Mutably iterates through this matrix coordinates in column-major order.
     pub fn iter_mut(&mut self) -> slice::IterMut<f32> {
-        self.data.iter_mut()
+        self.data.as_flattened_mut().iter_mut()
     }
 
     /// Transposes `self`.
     pub fn transpose(&self) -> Self {
-        let mut out = Self::zeros(self.order);
+        let mut out = Self::zeros();
 
-        for i in 0..self.order {
-            for j in 0..self.order {
-                unsafe {
-                    *out.get_unchecked_mut((j, i)) = *self.get_unchecked((i, j));
-                }
+        for (j, col) in self.data.iter().enumerate() {
+            for (i, &x) in col.iter().enumerate() {
+                out.data[i][j] = x;
             }
         }
 
         out
     }
 
+    /// Returns true if the absolute difference of all corresponding elements between `self` and
+    /// `other` is less than or equal to `max_abs_diff`.
+    pub fn abs_diff_eq(&self, other: &Self, max_abs_diff: f32) -> bool {
+        self.iter()
+            .zip(other.iter())
+            .all(|(a, b)| (a - b).abs() <= max_abs_diff)
+    }
+}
+
+impl Matrix<2> {
+    /// Computes the determinant of the matrix.
+    pub fn det(&self) -> f32 {
+        self[(0, 0)] * self[(1, 1)] - self[(0, 1)] * self[(1, 0)]
+    }
+
     /// Returns the inverse of the matrix, or `None` if the matrix is not invertible.
-    pub fn inverse(&self) -> Option<Matrix> {
-        match self.order() {
-            0 => None,
-            4 => {
-                let mut out = Self::zeros(4);
-                if do_inverse4(self, &mut out) {
-                    Some(out)
-                } else {
-                    None
-                }
+    pub fn inverse(&self) -> Option<Self> {
+        self.inverse_by_cofactors()
+    }
+
+    /// Computes the cofactor of element `(i,j)`, ie. the possibly negated minor of `(i,j)`.
+    pub fn cofactor(&self, i: usize, j: usize) -> f32 {
+        let minor = self[(1 - i, 1 - j)];
+
+        if (i + j) % 2 == 0 {
+            minor
+        } else {
+            -minor
+        }
+    }
+}
+
+macro_rules! impl_cofactors {
+    ($($n:literal => $sub:literal),*) => {$(
+        impl Matrix<$n> {
+            /// Computes the determinant of the matrix.
+            pub fn det(&self) -> f32 {
+                (0..$n).fold(0., |det, i| det + self[(0, i)] * self.cofactor(0, i))
             }
-            n => {
-                let mut out = Self::zeros(n);
-                let det = self.det();
-                if det != 0. {
-                    for i in 0..n {
-                        for j in 0..n {
-                            let c = self.cofactor(i, j);
-                            out[(j, i)] = c / det;
-                        }
+
+            /// Returns the matrix obtained by removing `row` and `col` from `self`.
+            pub fn submatrix(&self, row: usize, col: usize) -> Matrix<$sub> {
+                let mut out = Matrix::zeros();
+                let mut iter = out.iter_mut();
+
+                for j in (0..$n).filter(|&j| j != col) {
+                    for i in (0..$n).filter(|&i| i != row) {
+                        *iter.next().unwrap() = self[(i, j)];
                     }
-                    Some(out)
+                }
+
+                out
+            }
+
+            /// Computes the minor of element `(i,j)`, ie. the determinant of the submatrix
+            /// `(i,j)`.
+            pub fn minor(&self, i: usize, j: usize) -> f32 {
+                self.submatrix(i, j).det()
+            }
+
+            /// Computes the cofactor of element `(i,j)`, ie. the possibly negated minor of
+            /// `(i,j)`.
+            pub fn cofactor(&self, i: usize, j: usize) -> f32 {
+                let minor = self.minor(i, j);
+
+                if (i + j) % 2 == 0 {
+                    minor
                 } else {
-                    None
+                    -minor
                 }
             }
         }
-    }
+    )*};
+}
 
-    /// Computes the determinant of the matrix.
-    pub fn det(&self) -> f32 {
-        if self.order() == 2 {
-            self[(0, 0)] * self[(1, 1)] - self[(0, 1)] * self[(1, 0)]
-        } else {
-            (0..self.order()).fold(0., |det, i| det + self[(0, i)] * self.cofactor(0, i))
-        }
+impl_cofactors!(3 => 2, 4 => 3);
+
+impl Matrix<3> {
+    /// Returns the inverse of the matrix, or `None` if the matrix is not invertible.
+    pub fn inverse(&self) -> Option<Self> {
+        self.inverse_by_cofactors()
     }
+}
 
-    /// Returns the matrix of order `n-1` obtain by removing `row` and `col` from `self`.
-    pub fn submatrix(&self, row: usize, col: usize) -> Matrix {
-        let mut out = Matrix::zeros(self.order() - 1);
-        let mut iter = out.iter_mut();
+macro_rules! impl_inverse_by_cofactors {
+    ($($n:literal),*) => {$(
+        impl Matrix<$n> {
+            /// Computes the inverse of the matrix as its adjugate divided by its determinant.
+            fn inverse_by_cofactors(&self) -> Option<Self> {
+                let det = self.det();
+                if det == 0. {
+                    return None;
+                }
 
-        for j in 0..self.order() {
-            for i in 0..self.order() {
-                if i != row && j != col {
-                    *iter.next().unwrap() = self[(i, j)];
+                let mut out = Self::zeros();
+                for i in 0..$n {
+                    for j in 0..$n {
+                        out[(j, i)] = self.cofactor(i, j) / det;
+                    }
                 }
+                Some(out)
             }
         }
+    )*};
+}
 
+impl_inverse_by_cofactors!(2, 3);
+
+impl Matrix {
+    /// Creates a matrix that applies a translation of `(x,y,z)`.
+    pub fn from_translation(x: f32, y: f32, z: f32) -> Self {
+        let mut out = Self::identity();
+        out[(0, 3)] = x;
+        out[(1, 3)] = y;
+        out[(2, 3)] = z;
         out
     }
 
-    /// Computes the minor of element `(i,j)`, ie. the determinant of the submatrix `(i,j)`.
-    pub fn minor(&self, i: usize, j: usize) -> f32 {
-        self.submatrix(i, j).det()
+    /// Creates a matrix that applies a non-uniform scaling of `(x,y,z)`.
+    pub fn from_scale(x: f32, y: f32, z: f32) -> Self {
+        let mut out = Self::identity();
+        out[(0, 0)] = x;
+        out[(1, 1)] = y;
+        out[(2, 2)] = z;
+        out
     }
 
-    /// Computes the cofactor of element `(i,j)`, ie. the possibly negated minor of `(i,j)`.
-    pub fn cofactor(&self, i: usize, j: usize) -> f32 {
-        let minor = self.minor(i, j);
+    /// Creates a matrix that applies a rotation of `rad` radians around the `x` axis.
+    pub fn from_rotation_x(rad: f32) -> Self {
+        let mut out = Self::identity();
+        out[(1, 1)] = float::cos(rad);
+        out[(1, 2)] = -float::sin(rad);
+        out[(2, 1)] = float::sin(rad);
+        out[(2, 2)] = float::cos(rad);
+        out
+    }
 
-        if (i + j) % 2 == 0 {
-            minor
-        } else {
-            -minor
-        }
+    /// Creates a matrix that applies a rotation of `rad` radians around the `y` axis.
+    pub fn from_rotation_y(rad: f32) -> Self {
+        let mut out = Self::identity();
+        out[(0, 0)] = float::cos(rad);
+        out[(0, 2)] = float::sin(rad);
+        out[(2, 0)] = -float::sin(rad);
+        out[(2, 2)] = float::cos(rad);
+        out
     }
 
-    /// Returns true if the two matrix have the same order and the absolute difference of all
-    /// corresponding elements between `self` and `other` is less than or equal to `max_abs_diff`.
-    pub fn abs_diff_eq(&self, other: &Self, max_abs_diff: f32) -> bool {
-        self.order == other.order
-            && self
-                .iter()
-                .zip(other.iter())
-                .all(|(a, b)| (a - b).abs() <= max_abs_diff)
+    /// Creates a matrix that applies a rotation of `rad` radians around the `z` axis.
+    pub fn from_rotation_z(rad: f32) -> Self {
+        let mut out = Self::identity();
+        out[(0, 0)] = float::cos(rad);
+        out[(0, 1)] = -float::sin(rad);
+        out[(1, 0)] = float::sin(rad);
+        out[(1, 1)] = float::cos(rad);
+        out
     }
 
-    /// Returns the linear index in the matrix storage corresponding to element `(irow,icol)`.
-    fn liner_index(&self, irow: usize, icol: usize) -> usize {
-        icol * self.order + irow
+    /// Creates a matrix that applies the specified shear.
+    pub fn from_shear(xy: f32, xz: f32, yx: f32, yz: f32, zx: f32, zy: f32) -> Self {
+        let mut out = Self::identity();
+        out[(0, 1)] = xy;
+        out[(0, 2)] = xz;
+        out[(1, 0)] = yx;
+        out[(1, 2)] = yz;
+        out[(2, 0)] = zx;
+        out[(2, 1)] = zy;
+        out
+    }
+
+    /// Creates a view transform matrix looking at `center` from `eye`.
+    pub fn look_at(eye: Point3, center: Point3, up: Vec3) -> Matrix {
+        let fwd = (center - eye).normalize();
+        let up = up.normalize();
+        let left = fwd.cross(&up);
+        let up = left.cross(&fwd);
+
+        let orientation = Matrix::from_columns([
+            [left.x, up.x, -fwd.x, 0.0],
+            [left.y, up.y, -fwd.y, 0.0],
+            [left.z, up.z, -fwd.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+
+        orientation * Matrix::from_translation(-eye.x, -eye.y, -eye.z)
+    }
+
+    /// Returns the inverse of the matrix, or `None` if the matrix is not invertible.
+    pub fn inverse(&self) -> Option<Matrix> {
+        let mut out = Self::zeros();
+        if do_inverse4(self, &mut out) {
+            Some(out)
+        } else {
+            None
+        }
     }
 }
 
-impl Index<(usize, usize)> for Matrix {
+impl<const N: usize> Index<(usize, usize)> for Matrix<N> {
     type Output = f32;
 
     fn index(&self, (i, j): (usize, usize)) -> &Self::Output {
-        self.data.index(self.liner_index(i, j))
+        &self.data[j][i]
     }
 }
 
-impl IndexMut<(usize, usize)> for Matrix {
+impl<const N: usize> IndexMut<(usize, usize)> for Matrix<N> {
     fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut Self::Output {
-        self.data.index_mut(self.liner_index(i, j))
+        &mut self.data[j][i]
+    }
+}
+
+/// Formats the matrix one row per line, with the elements of each column aligned to the right.
+///
+/// The elements are printed with the precision of the formatter, if any:
+///
+/// ```
+/// use tracy::math::Matrix;
+///
+/// let m = Matrix::from_rows([[1.0, -2.5], [30.0, 4.0]]);
+///
+/// assert_eq!(format!("{}", m), "[  1, -2.5 ]\n[ 30,    4 ]");
+/// assert_eq!(format!("{:.1}", m), "[  1.0, -2.5 ]\n[ 30.0,  4.0 ]");
+/// ```
+impl<const N: usize> fmt::Display for Matrix<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n = N;
+
+        let cells = (0..n * n)
+            .map(|k| {
+                let v = self[(k / n, k % n)];
+                match f.precision() {
+                    Some(p) => format!("{:.*}", p, v),
+                    None => format!("{}", v),
+                }
+            })
+            .collect::<Vec<String>>();
+
+        let widths = (0..n)
+            .map(|j| (0..n).map(|i| cells[i * n + j].len()).max().unwrap_or(0))
+            .collect::<Vec<_>>();
+
+        for i in 0..n {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "[ ")?;
+            for j in 0..n {
+                if j > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{:>width$}", cells[i * n + j], width = widths[j])?;
+            }
+            write!(f, " ]")?;
+        }
+
+        Ok(())
     }
 }
 
-impl Mul for Matrix {
+impl<const N: usize> Mul for Matrix<N> {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
@@ -318,7 +430,7 @@ impl Mul for Matrix {
     }
 }
 
-impl Mul<&Matrix> for Matrix {
+impl<const N: usize> Mul<&Matrix<N>> for Matrix<N> {
     type Output = Self;
 
     fn mul(self, rhs: &Self) -> Self::Output {
@@ -326,29 +438,24 @@ impl Mul<&Matrix> for Matrix {
     }
 }
 
-impl Mul<Matrix> for &Matrix {
-    type Output = Matrix;
+impl<const N: usize> Mul<Matrix<N>> for &Matrix<N> {
+    type Output = Matrix<N>;
 
-    fn mul(self, rhs: Matrix) -> Self::Output {
+    fn mul(self, rhs: Matrix<N>) -> Self::Output {
         self * &rhs
     }
 }
 
-impl<'a, 'b> Mul<&'b Matrix> for &'a Matrix {
-    type Output = Matrix;
-
-    fn mul(self, rhs: &'b Matrix) -> Self::Output {
-        assert_eq!(rhs.order, self.order);
+impl<'a, 'b, const N: usize> Mul<&'b Matrix<N>> for &'a Matrix<N> {
+    type Output = Matrix<N>;
 
-        let mut out = Self::Output::zeros(self.order);
+    fn mul(self, rhs: &'b Matrix<N>) -> Self::Output {
+        let mut out = Self::Output::zeros();
 
-        for i in 0..out.order {
-            for j in 0..out.order {
-                unsafe {
-                    *out.get_unchecked_mut((i, j)) = (0..out.order).fold(0., |sum, idx| {
-                        sum + self.get_unchecked((i, idx)) * rhs.get_unchecked((idx, j))
-                    });
-                }
+        for i in 0..N {
+            for j in 0..N {
+                out[(i, j)] =
+                    (0..N).fold(0., |sum, idx| mul_add(self[(i, idx)], rhs[(idx, j)], sum));
             }
         }
 
@@ -408,18 +515,15 @@ impl Mul<[f32; 4]> for &Matrix {
 
     #[allow(clippy::suspicious_arithmetic_impl)]
     fn mul(self, rhs: [f32; 4]) -> Self::Output {
-        [
-            (0..4).fold(0., |sum, i| sum + self[(0, i)] * rhs[i]),
-            (0..4).fold(0., |sum, i| sum + self[(1, i)] * rhs[i]),
-            (0..4).fold(0., |sum, i| sum + self[(2, i)] * rhs[i]),
-            (0..4).fold(0., |sum, i| sum + self[(3, i)] * rhs[i]),
-        ]
+        let row = |r| (0..4).fold(0., |sum, i| mul_add(self[(r, i)], rhs[i], sum));
+
+        [row(0), row(1), row(2), row(3)]
     }
 }
 
 // NOTE: this is an extremely efficient, loop-unrolled matrix inverse from MESA (MIT licensed).
 fn do_inverse4(m: &Matrix, out: &mut Matrix) -> bool {
-    let m = m.data;
+    let m = m.data.as_flattened();
 
     out[(0, 0)] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
         + m[9] * m[7] * m[14]
@@ -517,6 +621,37 @@ fn do_inverse4(m: &Matrix, out: &mut Matrix) -> bool {
     }
 }
 
+/// Serializes the elements of the matrix in column-major order, along with its order.
+#[cfg(feature = "serde-support")]
+impl<const N: usize> serde::Serialize for Matrix<N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{SerializeStruct, SerializeTuple};
+
+        struct Elements<'a>(&'a [f32]);
+
+        impl serde::Serialize for Elements<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                let mut tuple = serializer.serialize_tuple(self.0.len())?;
+                for x in self.0 {
+                    tuple.serialize_element(x)?;
+                }
+                tuple.end()
+            }
+        }
+
+        let mut s = serializer.serialize_struct("Matrix", 2)?;
+        s.serialize_field("data", &Elements(self.data.as_flattened()))?;
+        s.serialize_field("order", &N)?;
+        s.end()
+    }
+}
+
 #[cfg(feature = "serde-support")]
 impl<'de> serde::Deserialize<'de> for Matrix {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -643,7 +778,7 @@ impl<'de> serde::Deserialize<'de> for Matrix {
             where
                 V: SeqAccess<'de>,
             {
-                let mut m = Matrix::identity(4);
+                let mut m = Matrix::identity();
 
                 while let Some(isometry) = seq.next_element()? {
                     match isometry {
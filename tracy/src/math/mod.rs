@@ -5,9 +5,159 @@
 
 mod coords;
 mod matrix;
+mod noise;
+pub mod portable;
+mod rng;
 
 pub use coords::*;
 pub use matrix::*;
+pub use noise::*;
+pub use rng::*;
+
+/// Floating point functions used by the renderer.
+///
+/// They forward to the standard library, or to `libm` without the `std` feature. Transcendental
+/// functions are taken from [`portable`] instead when the `deterministic` feature is enabled.
+pub(crate) mod float {
+    #[cfg(feature = "deterministic")]
+    pub use super::portable::{atan2, cos, powf, sin};
+
+    // Only cameras need a tangent, and they are not available without `std`
+    #[cfg(all(feature = "deterministic", feature = "std"))]
+    pub use super::portable::tan;
+
+    #[cfg(all(not(feature = "deterministic"), feature = "std"))]
+    pub fn sin(x: f32) -> f32 {
+        x.sin()
+    }
+
+    #[cfg(all(not(feature = "deterministic"), feature = "std"))]
+    pub fn cos(x: f32) -> f32 {
+        x.cos()
+    }
+
+    #[cfg(all(not(feature = "deterministic"), feature = "std"))]
+    pub fn tan(x: f32) -> f32 {
+        x.tan()
+    }
+
+    #[cfg(all(not(feature = "deterministic"), feature = "std"))]
+    pub fn powf(x: f32, y: f32) -> f32 {
+        x.powf(y)
+    }
+
+    #[cfg(all(not(feature = "deterministic"), feature = "std"))]
+    pub fn atan2(y: f32, x: f32) -> f32 {
+        y.atan2(x)
+    }
+
+    #[cfg(all(not(feature = "deterministic"), not(feature = "std")))]
+    pub use libm::{atan2f as atan2, cosf as cos, powf, sinf as sin};
+
+    #[cfg(feature = "std")]
+    pub fn sqrt(x: f32) -> f32 {
+        x.sqrt()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn floor(x: f32) -> f32 {
+        x.floor()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn round(x: f32) -> f32 {
+        x.round()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn powi(x: f32, n: i32) -> f32 {
+        x.powi(n)
+    }
+
+    #[cfg(not(feature = "std"))]
+    pub use libm::{floorf as floor, roundf as round, sqrtf as sqrt};
+
+    /// Raises `x` to the integer power `n` by repeated squaring, like the standard library.
+    #[cfg(not(feature = "std"))]
+    pub fn powi(x: f32, n: i32) -> f32 {
+        let mut base = x;
+        let mut exp = n.unsigned_abs();
+        let mut out = 1.0;
+
+        loop {
+            if exp & 1 == 1 {
+                out *= base;
+            }
+            exp >>= 1;
+            if exp == 0 {
+                break;
+            }
+            base *= base;
+        }
+
+        if n < 0 {
+            1.0 / out
+        } else {
+            out
+        }
+    }
+}
+
+/// Computes `a * b + c`.
+///
+/// With the `fast-math` feature, on targets with hardware support for fused multiply-add (eg. when
+/// building with `-C target-cpu=native`), the product and the sum are computed by a single
+/// instruction, rounding only once. Results then differ in the last bits from strict IEEE 754
+/// evaluation, and from one machine to another, so the `deterministic` feature turns this off.
+#[inline(always)]
+pub(crate) fn mul_add(a: f32, b: f32, c: f32) -> f32 {
+    #[cfg(all(
+        feature = "fast-math",
+        not(feature = "deterministic"),
+        feature = "std",
+        any(target_feature = "fma", target_arch = "aarch64")
+    ))]
+    {
+        a.mul_add(b, c)
+    }
+
+    #[cfg(not(all(
+        feature = "fast-math",
+        not(feature = "deterministic"),
+        feature = "std",
+        any(target_feature = "fma", target_arch = "aarch64")
+    )))]
+    {
+        a * b + c
+    }
+}
 
 /// Arbitrarily small number for floating point comparison.
 pub const EPSILON: f32 = 1e-4;
+
+/// Tolerances compensating for the limited precision of floating point computations.
+///
+/// The defaults suit scenes whose objects are roughly unit sized. Scenes at very different scales
+/// may need to scale them accordingly, to avoid either shadow acne or visibly detached shadows.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerances {
+    /// Distance by which secondary rays are moved away from the surface they start from, so that
+    /// rounding errors do not make them hit it again. Defaults to [`EPSILON`].
+    pub shadow_bias: f32,
+    /// Smallest time of impact of an intersection for it to count as a hit. Defaults to `0`.
+    pub intersection: f32,
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Self {
+            shadow_bias: EPSILON,
+            intersection: 0.,
+        }
+    }
+}
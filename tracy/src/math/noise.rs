@@ -0,0 +1,164 @@
+use alloc::boxed::Box;
+use core::fmt;
+
+use super::{float, Point3, Rng, Vec3};
+
+/// Three-dimensional gradient noise, as in Perlin's improved noise (Perlin, 2002).
+///
+/// The noise is a smooth pseudo-random function of space, which is zero at all integer
+/// coordinates. Its lattice is shuffled from a seed, so the same seed always produces the same
+/// noise, and different seeds unrelated ones.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "u64", into = "u64")
+)]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Noise {
+    seed: u64,
+
+    // Derived parameters
+    permutation: Box<[u8; 256]>,
+}
+
+impl From<u64> for Noise {
+    fn from(seed: u64) -> Self {
+        Self::new(seed)
+    }
+}
+
+impl From<Noise> for u64 {
+    fn from(noise: Noise) -> Self {
+        noise.seed
+    }
+}
+
+impl fmt::Debug for Noise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Noise")
+            .field("seed", &self.seed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Noise {
+    /// Creates the noise function identified by `seed`.
+    pub fn new(seed: u64) -> Self {
+        let mut permutation = Box::new([0; 256]);
+        for (i, p) in permutation.iter_mut().enumerate() {
+            *p = i as u8;
+        }
+
+        // Fisher-Yates shuffle
+        let mut rng = Rng::new(seed);
+        for i in (1..permutation.len()).rev() {
+            let j = rng.next_u32() as usize % (i + 1);
+            permutation.swap(i, j);
+        }
+
+        Self { seed, permutation }
+    }
+
+    /// Returns the seed of this noise.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the value of the noise at `p`, roughly in the `[-1, 1]` range.
+    pub fn at(&self, p: &Point3) -> f32 {
+        let (x0, y0, z0) = (float::floor(p.x), float::floor(p.y), float::floor(p.z));
+        let (x, y, z) = (p.x - x0, p.y - y0, p.z - z0);
+
+        // The lattice repeats every 256 units
+        let cell = |c: f32| (c as i32 & 255) as usize;
+        let (xi, yi, zi) = (cell(x0), cell(y0), cell(z0));
+        let hash = |i: usize, j: usize, k: usize| {
+            let p = &self.permutation;
+            p[(p[(p[(xi + i) & 255] as usize + yi + j) & 255] as usize + zi + k) & 255]
+        };
+
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let lerp_x = |j, k| {
+            let (y, z) = (y - j as f32, z - k as f32);
+            lerp(
+                u,
+                gradient(hash(0, j, k), x, y, z),
+                gradient(hash(1, j, k), x - 1.0, y, z),
+            )
+        };
+
+        lerp(
+            w,
+            lerp(v, lerp_x(0, 0), lerp_x(1, 0)),
+            lerp(v, lerp_x(0, 1), lerp_x(1, 1)),
+        )
+    }
+
+    /// Returns the sum of `octaves` layers of noise at `p`, each with twice the frequency and
+    /// half the amplitude of the previous one, scaled to the range of [`Noise::at`].
+    ///
+    /// More octaves add finer details to the noise.
+    pub fn fractal_at(&self, p: &Point3, octaves: u32) -> f32 {
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut p = *p;
+
+        for _ in 0..octaves.max(1) {
+            sum += self.at(&p) * amplitude;
+            total += amplitude;
+            amplitude /= 2.0;
+            p = Point3::new(p.x * 2.0, p.y * 2.0, p.z * 2.0);
+        }
+
+        sum / total
+    }
+
+    /// Returns a vector whose components are three unrelated samples of the noise around `p`.
+    pub fn vector_at(&self, p: &Point3) -> Vec3 {
+        // Sample the noise far enough apart that the components look unrelated
+        Vec3::new(
+            self.at(p),
+            self.at(&(*p + Vec3::new(31.416, 47.853, 12.679))),
+            self.at(&(*p + Vec3::new(-23.431, 17.012, -71.337))),
+        )
+    }
+}
+
+/// Smoothstep of the improved noise, whose first and second derivatives are zero at 0 and 1.
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// Dot product of `(x, y, z)` with one of the twelve gradients pointing to the edges of a cube,
+/// chosen by `hash`.
+fn gradient(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(all(feature = "serde-support", test))]
+mod tests {
+    use serde_test::{assert_tokens, Token};
+
+    use super::*;
+
+    #[test]
+    fn serialize_as_seed() {
+        assert_tokens(&Noise::new(42), &[Token::U64(42)]);
+    }
+}
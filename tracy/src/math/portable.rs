@@ -0,0 +1,238 @@
+//! Portable implementations of transcendental functions.
+//!
+//! The standard library forwards `sin`, `cos`, `tan`, `atan2` and `powf` to the platform's math library,
+//! whose results may differ in the last bits between operating systems and architectures. The
+//! functions in this module only rely on basic IEEE 754 arithmetic, which is exactly rounded
+//! everywhere, so they produce bit-identical results on every machine.
+//!
+//! They are used by the renderer in place of their standard counterparts when the
+//! `deterministic` feature is enabled.
+
+use core::f64::consts::{FRAC_PI_2, FRAC_PI_4, LN_2, PI, SQRT_2};
+
+/// Low-order bits of π/2, for an extra-precise argument reduction.
+const FRAC_PI_2_LO: f64 = 6.123_233_995_736_766e-17;
+
+/// Computes the sine of `x` radians.
+pub fn sin(x: f32) -> f32 {
+    let (r, quadrant) = reduce(x as f64);
+
+    (match quadrant {
+        0 => sin_kernel(r),
+        1 => cos_kernel(r),
+        2 => -sin_kernel(r),
+        _ => -cos_kernel(r),
+    }) as f32
+}
+
+/// Computes the cosine of `x` radians.
+pub fn cos(x: f32) -> f32 {
+    let (r, quadrant) = reduce(x as f64);
+
+    (match quadrant {
+        0 => cos_kernel(r),
+        1 => -sin_kernel(r),
+        2 => -cos_kernel(r),
+        _ => sin_kernel(r),
+    }) as f32
+}
+
+/// Computes the tangent of `x` radians.
+pub fn tan(x: f32) -> f32 {
+    let (r, quadrant) = reduce(x as f64);
+
+    (if quadrant % 2 == 0 {
+        sin_kernel(r) / cos_kernel(r)
+    } else {
+        -cos_kernel(r) / sin_kernel(r)
+    }) as f32
+}
+
+/// Computes the four quadrant arctangent of `y` and `x`, in the `[-π, π]` range.
+///
+/// Unlike the standard library, the signs of zeros are not taken into account.
+pub fn atan2(y: f32, x: f32) -> f32 {
+    let (y, x) = (y as f64, x as f64);
+
+    (if x.is_nan() || y.is_nan() {
+        f64::NAN
+    } else if x == 0. {
+        match y {
+            y if y > 0. => FRAC_PI_2,
+            y if y < 0. => -FRAC_PI_2,
+            _ => 0.,
+        }
+    } else if x.is_infinite() && y.is_infinite() {
+        // The ratio below would be NaN, while the angle is that of a diagonal
+        let a = if x > 0. { FRAC_PI_4 } else { 3. * FRAC_PI_4 };
+        a.copysign(y)
+    } else {
+        let a = atan(y / x);
+        match x {
+            x if x > 0. => a,
+            _ if y >= 0. => a + PI,
+            _ => a - PI,
+        }
+    }) as f32
+}
+
+/// Raises `x` to the floating point power `y`.
+///
+/// Only non-negative bases are supported, returning NaN otherwise.
+pub fn powf(x: f32, y: f32) -> f32 {
+    if y == 0. || x == 1. {
+        return 1.;
+    }
+    if x.is_nan() || y.is_nan() || x < 0. {
+        return f32::NAN;
+    }
+    if x == 0. {
+        return if y > 0. { 0. } else { f32::INFINITY };
+    }
+
+    exp2(y as f64 * log2(x as f64)) as f32
+}
+
+/// Reduces `x` to the `[-π/4, π/4]` range, returning the reduced angle and its quadrant.
+fn reduce(x: f64) -> (f64, u32) {
+    let k = round(x / FRAC_PI_2);
+    let r = (x - k * FRAC_PI_2) - k * FRAC_PI_2_LO;
+
+    (r, (k as i64).rem_euclid(4) as u32)
+}
+
+/// Taylor series of `sin(x)` for `|x| <= π/4`.
+fn sin_kernel(x: f64) -> f64 {
+    let x2 = x * x;
+    let mut term = x;
+    let mut sum = x;
+
+    for n in 1..=8 {
+        term *= -x2 / ((2 * n) * (2 * n + 1)) as f64;
+        sum += term;
+    }
+
+    sum
+}
+
+/// Taylor series of `cos(x)` for `|x| <= π/4`.
+fn cos_kernel(x: f64) -> f64 {
+    let x2 = x * x;
+    let mut term = 1.;
+    let mut sum = 1.;
+
+    for n in 1..=8 {
+        term *= -x2 / ((2 * n - 1) * (2 * n)) as f64;
+        sum += term;
+    }
+
+    sum
+}
+
+/// Computes the arctangent of `x`.
+fn atan(x: f64) -> f64 {
+    if x.abs() > 1. {
+        return FRAC_PI_2.copysign(x) - atan(1. / x);
+    }
+
+    // Keep the argument of the series below tan(π/8), so that it converges quickly
+    if x.abs() > SQRT_2 - 1. {
+        let a = atan_kernel((x.abs() - 1.) / (x.abs() + 1.));
+        return (FRAC_PI_4 + a).copysign(x);
+    }
+
+    atan_kernel(x)
+}
+
+/// Taylor series of `atan(x)` for `|x| <= tan(π/8)`.
+fn atan_kernel(x: f64) -> f64 {
+    let x2 = x * x;
+    let mut term = x;
+    let mut sum = x;
+
+    for n in 1..=20 {
+        term *= -x2;
+        sum += term / (2 * n + 1) as f64;
+    }
+
+    sum
+}
+
+/// Computes the base-2 logarithm of a positive, finite `x`.
+fn log2(x: f64) -> f64 {
+    // Subnormal numbers have no implicit leading 1, normalize them first
+    if x < f64::MIN_POSITIVE {
+        return log2(x * (1u64 << 54) as f64) - 54.;
+    }
+
+    let bits = x.to_bits();
+    let mut exp = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let mut mantissa = f64::from_bits((bits & !(0x7ff_u64 << 52)) | (1023_u64 << 52));
+
+    // Keep the mantissa in [√½, √2) so that the series below converges quickly
+    if mantissa > SQRT_2 {
+        mantissa /= 2.;
+        exp += 1;
+    }
+
+    // ln(m) = 2·atanh(s) with s = (m - 1) / (m + 1), and |s| < 0.172
+    let s = (mantissa - 1.) / (mantissa + 1.);
+    let s2 = s * s;
+    let mut term = s;
+    let mut sum = s;
+
+    for n in 1..=10 {
+        term *= s2;
+        sum += term / (2 * n + 1) as f64;
+    }
+
+    exp as f64 + 2. * sum / LN_2
+}
+
+/// Computes `2^x`.
+fn exp2(x: f64) -> f64 {
+    if x > 1024. {
+        return f64::INFINITY;
+    }
+    if x < -1075. {
+        return 0.;
+    }
+
+    let n = round(x);
+    let r = (x - n) * LN_2;
+
+    // Taylor series of e^r, with |r| <= ln(2) / 2
+    let mut term = 1.;
+    let mut sum = 1.;
+
+    for k in 1..=14 {
+        term *= r / k as f64;
+        sum += term;
+    }
+
+    // Scale by 2^n in two steps, so that intermediate factors are always representable
+    let n = n as i64;
+    let half = n / 2;
+    sum * pow2(half) * pow2(n - half)
+}
+
+/// Returns `2^n` for `-1022 <= n <= 1023`, as an exact power of two.
+fn pow2(n: i64) -> f64 {
+    f64::from_bits(((n + 1023) as u64) << 52)
+}
+
+/// Rounds `x` to the nearest integer, away from zero on ties, like `f64::round`.
+fn round(x: f64) -> f64 {
+    // Such large numbers have no fractional part, infinities are let through as well
+    if x.is_nan() || x.abs() >= (1u64 << 52) as f64 {
+        return x;
+    }
+
+    let t = x as i64 as f64;
+    match x - t {
+        d if d >= 0.5 => t + 1.,
+        d if d <= -0.5 => t - 1.,
+        // Keep the sign of negative numbers rounding to zero
+        _ => t.copysign(x),
+    }
+}
@@ -0,0 +1,49 @@
+/// A small and fast pseudo-random number generator, implementing PCG32 (O'Neill, 2014).
+///
+/// Renders must not depend on how the work is split across threads, so generators are never
+/// shared: each one is seeded explicitly, eg. from the coordinates of the pixel being rendered,
+/// which makes the numbers it produces reproducible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+    increment: u64,
+}
+
+impl Rng {
+    /// Creates a new generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, 0)
+    }
+
+    /// Creates a new generator from a seed, drawing from one of many independent streams.
+    ///
+    /// Generators created with the same seed but different streams produce unrelated sequences.
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /// Returns the next number of this generator, uniformly distributed over all `u32` values.
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(self.increment);
+
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// Returns the next number of this generator, uniformly distributed in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        // Only keep as many bits as fit in the mantissa, so that the result never rounds up to 1
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+}
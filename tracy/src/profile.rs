@@ -0,0 +1,206 @@
+//! Instrumentation of the rendering pipeline.
+//!
+//! The renderer reports the time spent in each of its [`Phase`]s to the [`Profiler`] installed
+//! with [`set_profiler`]. The built-in [`Counters`] profiler accumulates the number of calls and
+//! the total time of each phase, while a custom profiler can forward the events to a tracing or
+//! profiling framework, eg. by entering a span in [`Profiler::enter`].
+//!
+//! Instrumentation points are only compiled in with the `profiling` feature, so that renders pay
+//! no cost for it by default. Without it, an installed profiler never receives any event.
+//!
+//! Phases may nest: a [`Phase::Shading`] event also covers the shadow rays cast while shading,
+//! and the reflected and refracted rays shaded recursively. Times are therefore inclusive, and
+//! the times of different phases should not be added together.
+//!
+//! ```
+//! use tracy::profile::{self, Counters, Phase};
+//!
+//! static COUNTERS: Counters = Counters::new();
+//!
+//! profile::set_profiler(&COUNTERS).unwrap();
+//!
+//! // ... render something ...
+//!
+//! println!("{} shadow rays", COUNTERS.calls(Phase::ShadowRays));
+//! ```
+
+use std::{
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
+
+#[cfg(feature = "profiling")]
+use std::time::Instant;
+
+/// A stage of the rendering pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Building the data structures used to cast rays through a world.
+    Build,
+    /// Tracing a batch of rays from the camera, including the shading of what they hit.
+    PrimaryRays,
+    /// Checking whether a point is lit by a light source.
+    ShadowRays,
+    /// Computing the color of a point hit by a ray.
+    Shading,
+    /// Transforming a rendered image before it is displayed or saved.
+    PostProcess,
+}
+
+impl Phase {
+    /// All the phases, in pipeline order.
+    pub const ALL: [Phase; 5] = [
+        Phase::Build,
+        Phase::PrimaryRays,
+        Phase::ShadowRays,
+        Phase::Shading,
+        Phase::PostProcess,
+    ];
+
+    /// Returns a human-readable name for this phase.
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Build => "build",
+            Phase::PrimaryRays => "primary rays",
+            Phase::ShadowRays => "shadow rays",
+            Phase::Shading => "shading",
+            Phase::PostProcess => "post-processing",
+        }
+    }
+}
+
+/// Receiver of the instrumentation events of the renderer.
+///
+/// Events are reported concurrently by all the rendering threads, and each [`Profiler::exit`]
+/// is reported on the same thread as the matching [`Profiler::enter`].
+pub trait Profiler: Send + Sync {
+    /// Called when the current thread enters `phase`.
+    fn enter(&self, phase: Phase) {
+        let _ = phase;
+    }
+
+    /// Called when the current thread leaves `phase`, after spending `elapsed` in it.
+    fn exit(&self, phase: Phase, elapsed: Duration);
+}
+
+static PROFILER: OnceLock<&'static dyn Profiler> = OnceLock::new();
+
+/// Installs the profiler receiving the events of the renderer.
+///
+/// A profiler can only be installed once, further calls returning an error.
+pub fn set_profiler(profiler: &'static dyn Profiler) -> Result<(), SetProfilerError> {
+    PROFILER.set(profiler).map_err(|_| SetProfilerError)
+}
+
+/// Error returned by [`set_profiler`] when a profiler is already installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetProfilerError;
+
+impl fmt::Display for SetProfilerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a profiler is already installed")
+    }
+}
+
+impl Error for SetProfilerError {}
+
+/// Reports the current thread as being in `phase` until the returned guard is dropped.
+///
+/// Applications can use this to report their own post-processing, or the rays they trace
+/// without going through a [`Camera`](crate::rendering::Camera).
+pub fn scope(phase: Phase) -> Scope {
+    #[cfg(feature = "profiling")]
+    {
+        let profiler = PROFILER.get().copied();
+        if let Some(profiler) = profiler {
+            profiler.enter(phase);
+        }
+
+        Scope {
+            phase,
+            profiler: profiler.map(|p| (p, Instant::now())),
+        }
+    }
+
+    #[cfg(not(feature = "profiling"))]
+    {
+        let _ = phase;
+        Scope {}
+    }
+}
+
+/// Guard returned by [`scope`].
+#[must_use = "the phase ends as soon as the guard is dropped"]
+pub struct Scope {
+    #[cfg(feature = "profiling")]
+    phase: Phase,
+    #[cfg(feature = "profiling")]
+    profiler: Option<(&'static dyn Profiler, Instant)>,
+}
+
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "profiling")]
+impl Drop for Scope {
+    fn drop(&mut self) {
+        if let Some((profiler, start)) = self.profiler {
+            profiler.exit(self.phase, start.elapsed());
+        }
+    }
+}
+
+/// A profiler counting the calls and the total time spent in each phase.
+#[derive(Debug)]
+pub struct Counters {
+    calls: [AtomicU64; Phase::ALL.len()],
+    nanos: [AtomicU64; Phase::ALL.len()],
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Counters {
+    /// Creates a new set of counters, all set to zero.
+    pub const fn new() -> Self {
+        Self {
+            calls: [const { AtomicU64::new(0) }; Phase::ALL.len()],
+            nanos: [const { AtomicU64::new(0) }; Phase::ALL.len()],
+        }
+    }
+
+    /// Returns the number of times `phase` was entered.
+    pub fn calls(&self, phase: Phase) -> u64 {
+        self.calls[phase as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns the total time spent in `phase`, summed over all threads.
+    pub fn total_time(&self, phase: Phase) -> Duration {
+        Duration::from_nanos(self.nanos[phase as usize].load(Ordering::Relaxed))
+    }
+
+    /// Sets all the counters back to zero.
+    pub fn reset(&self) {
+        for counter in self.calls.iter().chain(&self.nanos) {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Profiler for Counters {
+    fn exit(&self, phase: Phase, elapsed: Duration) {
+        self.calls[phase as usize].fetch_add(1, Ordering::Relaxed);
+        self.nanos[phase as usize].fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::math::{Matrix, Point3};
+
+use super::Ray;
+
+/// An axis-aligned bounding box.
+///
+/// Boxes may extend to infinity along some axes, as is the case for planes. A box whose minimum
+/// is greater than its maximum along any axis is empty, and contains no point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    /// The corner of the box with the smallest coordinates.
+    pub min: Point3,
+    /// The corner of the box with the largest coordinates.
+    pub max: Point3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl Aabb {
+    /// Creates the smallest box containing both `a` and `b`.
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Self {
+            min: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    /// Creates an empty box, which is the identity of [`Aabb::union`].
+    pub fn empty() -> Self {
+        Self {
+            min: Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    /// Creates a box containing the whole space.
+    pub fn infinite() -> Self {
+        Self {
+            min: Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            max: Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        }
+    }
+
+    /// Creates the smallest box containing all of `points`, which is empty if there are none.
+    pub fn from_points<I: IntoIterator<Item = Point3>>(points: I) -> Self {
+        points.into_iter().fold(Self::empty(), Self::with_point)
+    }
+
+    /// Returns true if `self` contains no point.
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    /// Returns true if `self` is neither empty nor extends to infinity along any axis.
+    pub fn is_finite(&self) -> bool {
+        !self.is_empty()
+            && [self.min, self.max]
+                .iter()
+                .all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite())
+    }
+
+    /// Returns true if `p` lies inside `self` or on its boundary.
+    pub fn contains(&self, p: &Point3) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    /// Returns the smallest box containing both `self` and `other`.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Self {
+            min: Point3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Point3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    /// Returns the smallest box containing both `self` and `p`.
+    pub fn with_point(self, p: Point3) -> Aabb {
+        self.union(&Self { min: p, max: p })
+    }
+
+    /// Returns the center of `self`.
+    pub fn center(&self) -> Point3 {
+        Point3::new(
+            (self.min.x + self.max.x) / 2.,
+            (self.min.y + self.max.y) / 2.,
+            (self.min.z + self.max.z) / 2.,
+        )
+    }
+
+    /// Returns the smallest box containing `self` once transformed by `m`.
+    ///
+    /// Unlike transforming the eight corners of the box, this also works for boxes extending to
+    /// infinity along some axes.
+    pub fn transform(&self, m: &Matrix) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let (min, max): ([f32; 3], [f32; 3]) = (self.min.into(), self.max.into());
+        let mut out = ([0.; 3], [0.; 3]);
+
+        // Each coordinate of the transformed box is a sum of terms, each of which is smallest
+        // and largest at either end of the corresponding axis of the original box (Arvo, 1990)
+        for i in 0..3 {
+            out.0[i] = m[(i, 3)];
+            out.1[i] = m[(i, 3)];
+
+            for j in 0..3 {
+                // Skipping null terms avoids multiplying them with infinite bounds
+                if m[(i, j)] != 0. {
+                    let (a, b) = (m[(i, j)] * min[j], m[(i, j)] * max[j]);
+                    out.0[i] += a.min(b);
+                    out.1[i] += a.max(b);
+                }
+            }
+        }
+
+        Self {
+            min: out.0.into(),
+            max: out.1.into(),
+        }
+    }
+
+    /// Returns the times of impact at which `ray` enters and leaves `self`, or `None` if it
+    /// misses it.
+    ///
+    /// The ray is treated as a line, so either time may be negative.
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let (origin, dir): ([f32; 3], [f32; 3]) = (ray.origin.into(), ray.dir.into());
+        let (min, max): ([f32; 3], [f32; 3]) = (self.min.into(), self.max.into());
+        let (mut t0, mut t1) = (f32::NEG_INFINITY, f32::INFINITY);
+
+        for i in 0..3 {
+            if dir[i] == 0. {
+                if origin[i] < min[i] || origin[i] > max[i] {
+                    return None;
+                }
+            } else {
+                let (a, b) = ((min[i] - origin[i]) / dir[i], (max[i] - origin[i]) / dir[i]);
+                t0 = t0.max(a.min(b));
+                t1 = t1.min(a.max(b));
+            }
+        }
+
+        if t0 <= t1 {
+            Some((t0, t1))
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the corners of `self` and `other` differ by at most `max_abs_diff` along
+    /// any axis, or are the same infinity.
+    pub fn abs_diff_eq(&self, other: &Self, max_abs_diff: f32) -> bool {
+        let close = |a: f32, b: f32| a == b || (a - b).abs() < max_abs_diff;
+
+        close(self.min.x, other.min.x)
+            && close(self.min.y, other.min.y)
+            && close(self.min.z, other.min.z)
+            && close(self.max.x, other.max.x)
+            && close(self.max.y, other.max.y)
+            && close(self.max.z, other.max.z)
+    }
+}
@@ -0,0 +1,76 @@
+//! Structure-of-arrays view of the objects of a world.
+//!
+//! Objects own their shape, material and transform together, which makes them large and
+//! heterogeneous. Casting a ray only needs a few of their properties, so these are copied into
+//! parallel arrays indexed by object handle, which the intersection loop walks linearly.
+
+use crate::{
+    math::Matrix,
+    profile::{self, Phase},
+};
+
+use super::Object;
+
+/// Per-object data read while casting rays, stored as parallel arrays.
+#[derive(Debug, Default)]
+pub(crate) struct Layout {
+    /// Inverse of each object's transform, mapping world space to object space.
+    pub(crate) inverses: Vec<Matrix>,
+    /// Transpose of each inverse, mapping object-space normals to world space.
+    pub(crate) normal_matrices: Vec<Matrix>,
+    /// Whether each object casts a shadow.
+    pub(crate) casts_shadow: Vec<bool>,
+    /// Refractive index of each object's material.
+    pub(crate) refractive_indices: Vec<f32>,
+}
+
+impl Layout {
+    /// Builds the arrays for `objects`.
+    ///
+    /// Missing objects, ie. the holes left by removed objects, get placeholder entries so that the
+    /// arrays stay indexed by handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transform of any object is not invertible.
+    pub(crate) fn new<'a, I>(objects: I) -> Self
+    where
+        I: ExactSizeIterator<Item = Option<&'a Object>>,
+    {
+        let _scope = profile::scope(Phase::Build);
+
+        let mut layout = Self {
+            inverses: Vec::with_capacity(objects.len()),
+            normal_matrices: Vec::with_capacity(objects.len()),
+            casts_shadow: Vec::with_capacity(objects.len()),
+            refractive_indices: Vec::with_capacity(objects.len()),
+        };
+
+        for obj in objects {
+            let obj = match obj {
+                Some(obj) => obj,
+                None => {
+                    layout.normal_matrices.push(Matrix::identity());
+                    layout.inverses.push(Matrix::identity());
+                    layout.casts_shadow.push(false);
+                    layout.refractive_indices.push(1.0);
+                    continue;
+                }
+            };
+
+            let (inv, normal_matrix) = obj
+                .inverse_transform()
+                .zip(obj.normal_matrix())
+                .expect("object transform is not invertible");
+
+            layout.normal_matrices.push(normal_matrix.clone());
+            layout.inverses.push(inv.clone());
+            layout.casts_shadow.push(obj.casts_shadow());
+            layout
+                .refractive_indices
+                .push(obj.material().refractive_index);
+        }
+
+        layout
+    }
+}
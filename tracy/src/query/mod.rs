@@ -1,13 +1,25 @@
 //! Geometric queries for ray tracing.
 
+mod bounds;
+#[cfg(feature = "std")]
+mod layout;
 mod object;
+#[cfg(feature = "std")]
+mod packet;
 mod ray;
+#[cfg(feature = "std")]
+mod scratch;
+#[cfg(feature = "std")]
 mod world;
 
-use std::any::Any;
+use core::any::Any;
 
+pub use bounds::*;
 pub use object::*;
+#[cfg(feature = "std")]
+pub use packet::*;
 pub use ray::*;
+#[cfg(feature = "std")]
 pub use world::*;
 
 /// A trait for converting a type into a `&dyn Any`.
@@ -1,26 +1,116 @@
-use crate::{math::Matrix, rendering::Material, shape::Shape};
+use alloc::{string::String, sync::Arc, vec::Vec};
 
-use super::{Ray, RayIntersections};
+use crate::{
+    math::{Matrix, Point3, Vec3},
+    rendering::Material,
+    shape::Shape,
+};
+
+use super::{Aabb, Ray, RayIntersections};
 
 /// An object that can be positioned in a scene.
+///
+/// Shapes are immutable and shared between the clones of an object, which are therefore cheap.
+///
+/// Objects with more than a shape and a transform are more easily created with
+/// [`Object::builder`]:
+///
+/// ```
+/// use tracy::{math::Matrix, query::Object, rendering::Material, shape::Sphere};
+///
+/// let ball = Object::builder(Sphere)
+///     .transform(Matrix::from_translation(0.0, 1.0, 0.0))
+///     .material(Material {
+///         reflective: 0.5,
+///         ..Default::default()
+///     })
+///     .name("ball")
+///     .tag("toys")
+///     .casts_shadow(false)
+///     .build();
+///
+/// assert_eq!(ball.name(), Some("ball"));
+/// assert!(ball.has_tag("toys"));
+/// ```
 #[cfg_attr(
     feature = "serde-support",
-    derive(serde::Serialize, serde::Deserialize)
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "ObjectDef")
 )]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Object {
-    shape: Box<dyn Shape>,
+    shape: Arc<dyn Shape>,
+    material: Material,
+    transform: Matrix,
+    casts_shadow: bool,
+    name: Option<String>,
+    tags: Vec<String>,
+
+    // Derived parameters
+    #[cfg_attr(feature = "serde-support", serde(skip_serializing))]
+    inverse: Option<Inverse>,
+}
+
+/// Inverse of the transform of an object, along with its transpose.
+#[derive(Debug, Clone)]
+struct Inverse {
+    matrix: Matrix,
+    transpose: Matrix,
+}
+
+impl Inverse {
+    fn of(transform: &Matrix) -> Option<Self> {
+        let matrix = transform.inverse()?;
+        Some(Self {
+            transpose: matrix.transpose(),
+            matrix,
+        })
+    }
+}
+
+/// Serialized form of an [`Object`], without its derived parameters.
+#[cfg(feature = "serde-support")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "Object")]
+struct ObjectDef {
+    shape: Arc<dyn Shape>,
     material: Material,
-    #[cfg_attr(feature = "serde-support", serde(default))]
+    #[serde(default)]
     transform: Matrix,
-    #[cfg_attr(
-        feature = "serde-support",
-        serde(default = "Object::default_casts_shadow")
-    )]
+    #[serde(default = "Object::default_casts_shadow")]
     casts_shadow: bool,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[cfg(feature = "serde-support")]
+impl From<ObjectDef> for Object {
+    fn from(def: ObjectDef) -> Self {
+        Self {
+            inverse: Inverse::of(&def.transform),
+            shape: def.shape,
+            material: def.material,
+            transform: def.transform,
+            casts_shadow: def.casts_shadow,
+            name: def.name,
+            tags: def.tags,
+        }
+    }
 }
 
 impl Object {
+    /// Returns a builder of an object with the given shape.
+    ///
+    /// Unless specified otherwise, the object has an identity transform and the default material,
+    /// casts shadows and has neither a name nor tags.
+    pub fn builder<S: Shape>(shape: S) -> ObjectBuilder {
+        ObjectBuilder {
+            object: Self::new(shape, Matrix::identity()),
+        }
+    }
+
     /// Creates a new object with the given shape and transformation.
     pub fn new<S: Shape>(shape: S, transform: Matrix) -> Self {
         Self::new_with_material(shape, transform, Default::default())
@@ -29,10 +119,13 @@ impl Object {
     /// Creates a new object with the given material.
     pub fn new_with_material<S: Shape>(shape: S, transform: Matrix, material: Material) -> Self {
         Self {
-            shape: Box::new(shape),
+            shape: Arc::new(shape),
             material,
+            inverse: Inverse::of(&transform),
             transform,
             casts_shadow: Self::default_casts_shadow(),
+            name: None,
+            tags: Vec::new(),
         }
     }
 
@@ -68,18 +161,154 @@ impl Object {
 
     /// Changes the transform of this object.
     pub fn set_transform(&mut self, transform: Matrix) {
+        self.inverse = Inverse::of(&transform);
         self.transform = transform;
     }
 
+    /// Returns the inverse of this object's transform, mapping world space to object space, or
+    /// `None` if the transform is not invertible.
+    ///
+    /// The inverse is computed once whenever the transform changes.
+    pub fn inverse_transform(&self) -> Option<&Matrix> {
+        self.inverse.as_ref().map(|inv| &inv.matrix)
+    }
+
+    /// Returns the transpose of the inverse of this object's transform, mapping object-space
+    /// normals to world space, or `None` if the transform is not invertible.
+    pub fn normal_matrix(&self) -> Option<&Matrix> {
+        self.inverse.as_ref().map(|inv| &inv.transpose)
+    }
+
     /// Returns whether this object will produce a shadow.
     pub fn casts_shadow(&self) -> bool {
         self.casts_shadow
     }
 
+    /// Returns the name of this object, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Changes the name of this object, or removes it if `name` is `None`.
+    pub fn set_name<N: Into<String>>(&mut self, name: Option<N>) {
+        self.name = name.map(Into::into);
+    }
+
+    /// Returns an iterator over the tags of this object, in the order they were added.
+    pub fn tags(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.tags.iter().map(String::as_str)
+    }
+
+    /// Returns true if this object is marked with `tag`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Marks this object with `tag`, unless it already is.
+    pub fn add_tag<T: Into<String>>(&mut self, tag: T) {
+        let tag = tag.into();
+        if !self.has_tag(&tag) {
+            self.tags.push(tag);
+        }
+    }
+
+    /// Removes `tag` from this object, returning true if it was marked with it.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let len = self.tags.len();
+        self.tags.retain(|t| t != tag);
+        self.tags.len() != len
+    }
+
+    /// Returns the bounding box of this object in world-space coordinates.
+    pub fn bounds(&self) -> Aabb {
+        self.shape.local_bounds().transform(&self.transform)
+    }
+
+    /// Computes the normal to the surface of this object at `point`, in world-space coordinates.
+    ///
+    /// The point is assumed to lie on the surface of the object, as with the points returned by
+    /// ray queries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transform of this object is not invertible.
+    pub fn normal_at(&self, point: &Point3) -> Vec3 {
+        let inv = self.expect_inverse();
+        let normal = self.shape.local_normal_at(&(&inv.matrix * *point));
+        (&inv.transpose * normal).normalize()
+    }
+
     /// Computes the intersections between this object and a ray.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transform of this object is not invertible.
     pub fn interferences_with_ray(&self, ray: &Ray) -> RayIntersections {
+        let inv = self.expect_inverse();
         self.shape()
-            .intersections_in_world_space(self.transform(), ray)
+            .intersections_with_inverse(&inv.matrix, &inv.transpose, ray)
+    }
+
+    fn expect_inverse(&self) -> &Inverse {
+        self.inverse
+            .as_ref()
+            .expect("object transform is not invertible")
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        self.shape.same_shape(other.shape())
+            && self.material == other.material
+            && self.transform == other.transform
+            && self.casts_shadow == other.casts_shadow
+            && self.name == other.name
+            && self.tags == other.tags
+    }
+}
+
+/// Builder of [`Object`]s, as returned by [`Object::builder`].
+#[derive(Debug, Clone)]
+pub struct ObjectBuilder {
+    object: Object,
+}
+
+impl ObjectBuilder {
+    /// Sets the transform applied to the object's shape.
+    pub fn transform(mut self, transform: Matrix) -> Self {
+        self.object.set_transform(transform);
+        self
+    }
+
+    /// Sets the material of the object.
+    pub fn material(mut self, material: Material) -> Self {
+        self.object.material = material;
+        self
+    }
+
+    /// Sets the name of the object.
+    pub fn name<N: Into<String>>(mut self, name: N) -> Self {
+        self.object.name = Some(name.into());
+        self
+    }
+
+    /// Marks the object with a tag.
+    ///
+    /// Objects can have any number of tags, and the same tag can be shared by many objects.
+    pub fn tag<T: Into<String>>(mut self, tag: T) -> Self {
+        self.object.add_tag(tag);
+        self
+    }
+
+    /// Sets whether the object produces a shadow.
+    pub fn casts_shadow(mut self, casts_shadow: bool) -> Self {
+        self.object.casts_shadow = casts_shadow;
+        self
+    }
+
+    /// Builds the object.
+    pub fn build(self) -> Object {
+        self.object
     }
 }
 
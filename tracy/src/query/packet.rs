@@ -0,0 +1,219 @@
+//! Intersection of packets of rays with simple shapes.
+//!
+//! Testing several rays against the same primitive at once lays out the intersection math over
+//! arrays of lanes, which the compiler turns into SIMD instructions. Packets are most effective for
+//! coherent rays, such as the rays cast through the same pixel when supersampling.
+//!
+//! The kernels perform exactly the same operations as their scalar counterparts, so the
+//! intersections they find are identical to the ones found one ray at a time.
+
+use crate::{
+    math::{dot3, mul_add, Matrix, Point3, Vec3},
+    shape::{self, Cube, Shape, Sphere},
+};
+
+use super::{Ray, RayIntersection};
+
+/// A packet of `N` rays, stored as a structure of arrays.
+///
+/// Packets of 4 and 8 rays map to the width of SSE and AVX registers respectively.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayPacket<const N: usize> {
+    /// Coordinates of the starting points of the rays, one array per axis.
+    pub origin: [[f32; N]; 3],
+    /// Coordinates of the directions of the rays, one array per axis.
+    pub dir: [[f32; N]; 3],
+}
+
+impl<const N: usize> RayPacket<N> {
+    /// Creates a packet from `N` rays.
+    pub fn new(rays: &[Ray; N]) -> Self {
+        let mut packet = Self {
+            origin: [[0.0; N]; 3],
+            dir: [[0.0; N]; 3],
+        };
+
+        for (lane, ray) in rays.iter().enumerate() {
+            packet.origin[0][lane] = ray.origin.x;
+            packet.origin[1][lane] = ray.origin.y;
+            packet.origin[2][lane] = ray.origin.z;
+            packet.dir[0][lane] = ray.dir.x;
+            packet.dir[1][lane] = ray.dir.y;
+            packet.dir[2][lane] = ray.dir.z;
+        }
+
+        packet
+    }
+
+    /// Returns the ray in lane `lane` of this packet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lane` is not less than `N`.
+    pub fn ray(&self, lane: usize) -> Ray {
+        Ray::new(
+            Point3::new(
+                self.origin[0][lane],
+                self.origin[1][lane],
+                self.origin[2][lane],
+            ),
+            Vec3::new(self.dir[0][lane], self.dir[1][lane], self.dir[2][lane]),
+        )
+    }
+
+    /// Creates a new packet by applying a transformation to all the rays of `self`.
+    pub fn transform_by(&self, m: &Matrix) -> Self {
+        Self {
+            origin: transform_lanes(m, &self.origin, 1.0),
+            dir: transform_lanes(m, &self.dir, 0.0),
+        }
+    }
+}
+
+/// Applies `m` to each lane of `v`, with `w` as the homogeneous coordinate.
+fn transform_lanes<const N: usize>(m: &Matrix, v: &[[f32; N]; 3], w: f32) -> [[f32; N]; 3] {
+    let mut out = [[0.0; N]; 3];
+
+    for (row, out) in out.iter_mut().enumerate() {
+        for lane in 0..N {
+            let coords = [v[0][lane], v[1][lane], v[2][lane], w];
+            out[lane] = (0..4).fold(0.0, |sum, i| mul_add(m[(row, i)], coords[i], sum));
+        }
+    }
+
+    out
+}
+
+/// Entry and exit times of each ray of a packet through a convex shape.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacketHits<const N: usize> {
+    /// Whether each ray intersects the shape at all.
+    pub mask: [bool; N],
+    /// Time of impact of each ray entering the shape.
+    pub near: [f32; N],
+    /// Time of impact of each ray exiting the shape.
+    pub far: [f32; N],
+}
+
+/// Intersects each ray of `packet` with the unit sphere centered at the origin.
+pub fn intersect_unit_sphere<const N: usize>(packet: &RayPacket<N>) -> PacketHits<N> {
+    let [ox, oy, oz] = &packet.origin;
+    let [dx, dy, dz] = &packet.dir;
+
+    let mut hits = PacketHits {
+        mask: [false; N],
+        near: [0.0; N],
+        far: [0.0; N],
+    };
+
+    for lane in 0..N {
+        let dir = [dx[lane], dy[lane], dz[lane]];
+        let origin = [ox[lane], oy[lane], oz[lane]];
+
+        let a = dot3(dir, dir);
+        let b = 2. * dot3(dir, origin);
+        let c = dot3(origin, origin) - 1.;
+
+        let discriminant = b * b - 4. * a * c;
+        let sqrt = discriminant.sqrt();
+
+        hits.mask[lane] = discriminant >= 0.;
+        hits.near[lane] = (-b - sqrt) / (2. * a);
+        hits.far[lane] = (-b + sqrt) / (2. * a);
+    }
+
+    hits
+}
+
+/// Intersects each ray of `packet` with the axis-aligned box spanning from `min` to `max`.
+pub fn intersect_aabb<const N: usize>(
+    packet: &RayPacket<N>,
+    min: &Point3,
+    max: &Point3,
+) -> PacketHits<N> {
+    let min = [min.x, min.y, min.z];
+    let max = [max.x, max.y, max.z];
+
+    let mut hits = PacketHits {
+        mask: [true; N],
+        near: [f32::NEG_INFINITY; N],
+        far: [f32::INFINITY; N],
+    };
+
+    for axis in 0..3 {
+        for lane in 0..N {
+            let origin = packet.origin[axis][lane];
+            let dir = packet.dir[axis][lane];
+
+            let t0 = (min[axis] - origin) / dir;
+            let t1 = (max[axis] - origin) / dir;
+            let (tmin, tmax) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            hits.mask[lane] &= tmin < tmax;
+            hits.near[lane] = hits.near[lane].max(tmin);
+            hits.far[lane] = hits.far[lane].min(tmax);
+        }
+    }
+
+    for lane in 0..N {
+        hits.mask[lane] &= hits.near[lane] <= hits.far[lane];
+    }
+
+    hits
+}
+
+/// Computes the intersections of each ray of `packet` with `shape` using a packet kernel, passing
+/// them to `f` along with the lane of the ray.
+///
+/// `inv` is the inverse of the transform of the shape, and `normal_matrix` its transpose.
+///
+/// Returns `false` without calling `f` if there is no kernel for `shape`.
+pub(crate) fn intersections_with_packet<const N: usize, F>(
+    shape: &dyn Shape,
+    inv: &Matrix,
+    normal_matrix: &Matrix,
+    packet: &RayPacket<N>,
+    mut f: F,
+) -> bool
+where
+    F: FnMut(usize, RayIntersection),
+{
+    let shape = shape.as_any();
+    let is_sphere = shape.is::<Sphere>();
+
+    if !is_sphere && !shape.is::<Cube>() {
+        return false;
+    }
+
+    let local = packet.transform_by(inv);
+
+    let hits = if is_sphere {
+        intersect_unit_sphere(&local)
+    } else {
+        intersect_aabb(
+            &local,
+            &Point3::new(-1.0, -1.0, -1.0),
+            &Point3::new(1.0, 1.0, 1.0),
+        )
+    };
+
+    for lane in (0..N).filter(|&lane| hits.mask[lane]) {
+        let ray = local.ray(lane);
+
+        for &toi in &[hits.near[lane], hits.far[lane]] {
+            let point = ray.point_at(toi);
+            let normal = if is_sphere {
+                point.into()
+            } else {
+                shape::cube_normal_at(&point)
+            };
+
+            f(
+                lane,
+                RayIntersection::new(toi, (normal_matrix * normal).normalize()),
+            );
+        }
+    }
+
+    true
+}
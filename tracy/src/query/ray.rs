@@ -1,9 +1,18 @@
 //! Basic elements of ray tracing computations.
 
-use std::{cmp::Ordering, vec::IntoIter};
+use alloc::vec::IntoIter;
+use core::{iter::FromIterator, ops::RangeBounds};
+
+use smallvec::SmallVec;
 
 use crate::math::{Matrix, Point3, Vec3};
 
+/// Number of intersections stored inline by [`RayIntersections`], enough for all built-in shapes.
+const INLINE_INTERSECTIONS: usize = 4;
+
+/// List of intersections that only allocates past [`INLINE_INTERSECTIONS`] elements.
+pub(crate) type IntersectionList = SmallVec<[RayIntersection; INLINE_INTERSECTIONS]>;
+
 /// Trait of objects which can be tested for intersection with a ray.
 pub trait RayCast {
     /// Computes all the intersection points between `self` and `ray` in local-space coordinates.
@@ -14,17 +23,28 @@ pub trait RayCast {
     /// The ray is given in world-space coordinates.
     fn intersections_in_world_space(&self, m: &Matrix, ray: &Ray) -> RayIntersections {
         let inv = m.inverse().unwrap();
-        let local_ray = ray.transform_by(&inv);
-
-        RayIntersections::from(
-            self.intersections_in_local_space(&local_ray)
-                .map(|x| RayIntersection {
-                    normal: (inv.transpose() * x.normal).normalize(),
-                    ..x
-                })
-                .collect::<Vec<_>>()
-                .into_iter(),
-        )
+        self.intersections_with_inverse(&inv, &inv.transpose(), ray)
+    }
+
+    /// Computes all the intersection points between `self` and `ray`, given the inverse `inv` of
+    /// the transform of `self` and its transpose `normal_matrix`.
+    ///
+    /// This avoids inverting the transform again when casting many rays against the same shape.
+    fn intersections_with_inverse(
+        &self,
+        inv: &Matrix,
+        normal_matrix: &Matrix,
+        ray: &Ray,
+    ) -> RayIntersections {
+        let local_ray = ray.transform_by(inv);
+
+        // Transform the normals in place rather than collecting them into a new list
+        let mut xs = self.intersections_in_local_space(&local_ray);
+        for x in xs.intersections.as_mut_slice() {
+            x.normal = (normal_matrix * x.normal).normalize();
+        }
+
+        xs
     }
 }
 
@@ -78,14 +98,33 @@ impl RayIntersection {
 }
 
 /// Iterator over all the intersections between a [`Ray`] and a [`Shape`].
+///
+/// Intersections are stored inline up to a small number, so that most shapes can report their
+/// hits without allocating.
 #[derive(Debug, Clone)]
 pub struct RayIntersections {
-    pub(crate) intersections: IntoIter<RayIntersection>,
+    pub(crate) intersections: smallvec::IntoIter<[RayIntersection; INLINE_INTERSECTIONS]>,
+}
+
+impl Default for RayIntersections {
+    fn default() -> Self {
+        Self {
+            intersections: IntersectionList::new().into_iter(),
+        }
+    }
 }
 
 impl From<IntoIter<RayIntersection>> for RayIntersections {
     fn from(intersections: IntoIter<RayIntersection>) -> Self {
-        Self { intersections }
+        intersections.collect()
+    }
+}
+
+impl FromIterator<RayIntersection> for RayIntersections {
+    fn from_iter<I: IntoIterator<Item = RayIntersection>>(iter: I) -> Self {
+        Self {
+            intersections: iter.into_iter().collect::<IntersectionList>().into_iter(),
+        }
     }
 }
 
@@ -100,7 +139,23 @@ impl Iterator for RayIntersections {
 impl RayIntersections {
     /// Returns the first intersection to have hit the target.
     pub fn hit(self) -> Option<RayIntersection> {
-        self.filter(|r| r.toi >= 0.)
-            .min_by(|a, b| a.toi.partial_cmp(&b.toi).unwrap_or(Ordering::Greater))
+        self.hit_within(0.0..)
+    }
+
+    /// Returns the first intersection along the ray whose time of impact lies within `range`.
+    ///
+    /// Intersections with a NaN time of impact are never returned.
+    pub fn hit_within<R: RangeBounds<f32>>(self, range: R) -> Option<RayIntersection> {
+        self.filter(|i| range.contains(&i.toi))
+            .min_by(|a, b| a.toi.total_cmp(&b.toi))
+    }
+
+    /// Returns the intersection closest to the origin of the ray whose time of impact lies within
+    /// `range`, whether it is in front of or behind the origin.
+    ///
+    /// Intersections with a NaN time of impact are never returned.
+    pub fn closest_in<R: RangeBounds<f32>>(self, range: R) -> Option<RayIntersection> {
+        self.filter(|i| range.contains(&i.toi))
+            .min_by(|a, b| a.toi.abs().total_cmp(&b.toi.abs()))
     }
 }
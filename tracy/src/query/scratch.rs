@@ -0,0 +1,50 @@
+//! Per-thread scratch buffers reused across ray queries.
+//!
+//! Every ray cast against a world needs a couple of short-lived lists, and a single pixel of a
+//! reflective or refractive scene casts dozens of rays. Instead of allocating these lists for each
+//! ray, each thread keeps a small pool of cleared buffers that are handed out and given back.
+
+use std::cell::RefCell;
+
+use super::{ObjectHandle, RayIntersection};
+
+/// Maximum number of free buffers kept by each pool.
+///
+/// Buffers are only held while a ray is being queried, so even deep recursions need few of them.
+const MAX_FREE_BUFFERS: usize = 16;
+
+/// A pool of reusable vectors.
+pub(crate) struct Pool<T> {
+    free: RefCell<Vec<Vec<T>>>,
+}
+
+impl<T> Pool<T> {
+    const fn new() -> Self {
+        Self {
+            free: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Takes an empty buffer from the pool, allocating a new one if none is free.
+    pub(crate) fn take(&self) -> Vec<T> {
+        self.free.borrow_mut().pop().unwrap_or_default()
+    }
+
+    /// Gives `buf` back to the pool, clearing it.
+    pub(crate) fn give(&self, mut buf: Vec<T>) {
+        let mut free = self.free.borrow_mut();
+
+        if free.len() < MAX_FREE_BUFFERS && buf.capacity() > 0 {
+            buf.clear();
+            free.push(buf);
+        }
+    }
+}
+
+thread_local! {
+    /// Intersections of a ray with the objects of a world.
+    pub(crate) static INTERSECTIONS: Pool<(ObjectHandle, RayIntersection)> = const { Pool::new() };
+
+    /// Objects containing the current point of a ray, to compute refractions.
+    pub(crate) static CONTAINERS: Pool<ObjectHandle> = const { Pool::new() };
+}
@@ -1,49 +1,93 @@
 use std::{
-    slice::{Iter, IterMut},
-    vec::IntoIter,
+    array,
+    cmp::Ordering,
+    f32::consts::PI,
+    mem,
+    ops::RangeBounds,
+    sync::{Arc, OnceLock},
 };
 
-use itertools::Itertools;
-
 use crate::{
-    math::{Matrix, Point3, Vec3, EPSILON},
-    rendering::{self, Color, Material, Pattern, PointLight},
+    math::{float, Matrix, Point3, Rng, Tolerances, Vec3},
+    profile::{self, Phase},
+    rendering::{
+        self, AreaLight, Color, Environment, Fresnel, LightSource, Material, Pattern, PointLight,
+        RenderSettings, ShadingModel,
+    },
     shape::Sphere,
 };
 
-use super::{Object, Ray, RayIntersection};
+use super::{
+    layout::Layout,
+    packet::{self, RayPacket},
+    scratch::{CONTAINERS, INTERSECTIONS},
+    Object, Ray, RayIntersection,
+};
 
 /// A handle to an object in a world.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct ObjectHandle(u32);
+///
+/// Handles identify objects rather than compare them: two objects with the same shape, transform
+/// and material are equal, but are still told apart by their handles.
+///
+/// Handles remain valid when other objects are removed from the world. Once its object is
+/// removed, a handle is stale and no longer identifies any object, even if the slot of the removed
+/// object is reused by a new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectHandle {
+    index: u32,
+    generation: u32,
+}
+
+/// A handle to a light in a world.
+///
+/// Handles remain valid when other lights are removed from the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightHandle(u32);
+
+/// A handle to an area light in a world.
+///
+/// Handles remain valid when other area lights are removed from the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AreaLightHandle(u32);
 
 /// A container of collidable objects.
-#[derive(Debug)]
+///
+/// Objects and lights are shared between a world and its [snapshots](World::snapshot), and only
+/// copied when either of them is modified.
+///
+/// Cloning a world is the same as taking a snapshot of it. The clone behaves as a deep copy: its
+/// objects keep their handles, and it can be modified independently of the original.
+///
+/// The default world is the test world of the book while the deprecated `legacy-default-world`
+/// feature is enabled, as it is by default, and empty otherwise. Use [`World::new`] or
+/// [`World::book_default`] to get either regardless of the feature, which will be removed in the
+/// next release.
+#[derive(Debug, Clone)]
 pub struct World {
-    objects: Vec<Object>,
-    lights: Vec<PointLight>,
+    /// Objects indexed by handle, with removed objects left as holes until their slot is reused.
+    objects: Arc<Vec<Slot>>,
+    /// Indices of the holes left in `objects`.
+    free_slots: Vec<u32>,
+    /// Lights indexed by handle, with removed lights left as holes to keep handles stable.
+    lights: Arc<Vec<Option<PointLight>>>,
+    /// Area lights indexed by handle, with removed lights left as holes like `lights`.
+    area_lights: Arc<Vec<Option<AreaLight>>>,
+    /// Data of the objects read while casting rays, built on first use and discarded whenever
+    /// the objects may have changed.
+    layout: Arc<OnceLock<Layout>>,
+    tolerances: Tolerances,
+    recursion_limit: Option<u32>,
+    render_settings: RenderSettings,
+    /// Shared like the objects, since it may hold large images.
+    environment: Arc<Environment>,
 }
 
 impl Default for World {
     fn default() -> Self {
-        let mat = Material {
-            pattern: Pattern::new(Color::new(0.8, 1.0, 0.6).into()),
-            diffuse: 0.7,
-            specular: 0.2,
-            ..Default::default()
-        };
-
-        Self {
-            objects: vec![
-                Object::new_with_material(Sphere, Matrix::identity(4), mat),
-                Object::new(Sphere, Matrix::from_scale(0.5, 0.5, 0.5)),
-            ],
-            lights: vec![PointLight {
-                position: (-10., 10., -10.).into(),
-                color: Color::WHITE,
-                intensity: 1.,
-                casts_shadows: true,
-            }],
+        if cfg!(feature = "legacy-default-world") {
+            Self::book_default()
+        } else {
+            Self::new()
         }
     }
 }
@@ -52,80 +96,361 @@ impl World {
     /// Creates an empty world.
     pub fn new() -> Self {
         Self {
-            objects: Vec::new(),
-            lights: Vec::new(),
+            objects: Default::default(),
+            free_slots: Vec::new(),
+            lights: Default::default(),
+            area_lights: Default::default(),
+            layout: Default::default(),
+            tolerances: Default::default(),
+            recursion_limit: None,
+            render_settings: Default::default(),
+            environment: Default::default(),
         }
     }
 
+    /// Creates the default world of the book, made of two concentric spheres lit by a single
+    /// light.
+    ///
+    /// This is also returned by [`World::default`] while the deprecated `legacy-default-world`
+    /// feature is enabled.
+    pub fn book_default() -> Self {
+        let mat = Material {
+            pattern: Pattern::new(Color::new(0.8, 1.0, 0.6).into()),
+            diffuse: 0.7,
+            specular: 0.2,
+            ..Default::default()
+        };
+
+        let mut world = Self::new();
+        world.add(Object::new_with_material(Sphere, Matrix::identity(), mat));
+        world.add(Object::new(Sphere, Matrix::from_scale(0.5, 0.5, 0.5)));
+        world.add_light(PointLight {
+            position: (-10., 10., -10.).into(),
+            color: Color::WHITE,
+            intensity: 1.,
+            casts_shadows: true,
+            attenuation: Default::default(),
+        });
+        world
+    }
+
+    /// Returns a snapshot of the current state of this world.
+    ///
+    /// Taking a snapshot is cheap, since the objects and lights are shared rather than copied.
+    /// Later changes to either world do not affect the other: the first change to an object or
+    /// light after a snapshot is taken copies the list it belongs to, as well as the object
+    /// itself, leaving the other objects shared.
+    ///
+    /// This is useful to keep editing a world while rendering it on another thread.
+    pub fn snapshot(&self) -> World {
+        self.clone()
+    }
+
+    /// Returns the tolerances used when casting rays in this world.
+    pub fn tolerances(&self) -> &Tolerances {
+        &self.tolerances
+    }
+
+    /// Sets the tolerances used when casting rays in this world.
+    ///
+    /// The defaults suit scenes whose objects are roughly unit sized, and may need tuning for
+    /// much larger or smaller scenes.
+    pub fn set_tolerances(&mut self, tolerances: Tolerances) {
+        self.tolerances = tolerances;
+    }
+
+    /// Returns the recursion limit of this world, or `None` if it was never set.
+    pub fn recursion_limit(&self) -> Option<u32> {
+        self.recursion_limit
+    }
+
+    /// Sets the recursion limit of this world, ie. how many times a ray is allowed to be
+    /// reflected/refracted by an object.
+    ///
+    /// The limit is used by cameras which do not have their own, and allows scenes to ask for
+    /// more or less bounces than the default.
+    pub fn set_recursion_limit(&mut self, limit: u32) {
+        self.recursion_limit = Some(limit);
+    }
+
+    /// Returns the optional rendering effects enabled in this world.
+    pub fn render_settings(&self) -> &RenderSettings {
+        &self.render_settings
+    }
+
+    /// Sets the optional rendering effects enabled in this world.
+    pub fn set_render_settings(&mut self, settings: RenderSettings) {
+        self.render_settings = settings;
+    }
+
+    /// Returns the surroundings of this world, seen by the rays which do not hit any object.
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Sets the surroundings of this world, seen by the rays which do not hit any object.
+    ///
+    /// Reflective and transparent objects pick up the colors of the environment, as do diffuse
+    /// objects when path tracing.
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.environment = Arc::new(environment);
+    }
+
     /// Adds an object to this world.
+    ///
+    /// The object may take the place of a previously removed one, in which case it comes at the
+    /// same position in [`World::objects`].
     pub fn add(&mut self, object: Object) -> ObjectHandle {
-        self.objects.push(object);
-        ObjectHandle(self.objects.len() as u32 - 1)
+        self.invalidate_layout();
+        let objects = Arc::make_mut(&mut self.objects);
+
+        if let Some(index) = self.free_slots.pop() {
+            let slot = &mut objects[index as usize];
+            slot.object = Some(Arc::new(object));
+            ObjectHandle {
+                index,
+                generation: slot.generation,
+            }
+        } else {
+            objects.push(Slot {
+                generation: 0,
+                object: Some(Arc::new(object)),
+            });
+            ObjectHandle {
+                index: objects.len() as u32 - 1,
+                generation: 0,
+            }
+        }
+    }
+
+    /// Removes the object identified by this handle from this world, returning it.
+    ///
+    /// The handle, as well as any copy of it, becomes stale and no longer identifies any object.
+    pub fn remove(&mut self, handle: ObjectHandle) -> Option<Object> {
+        self.get(handle)?;
+        self.invalidate_layout();
+
+        let slot = &mut Arc::make_mut(&mut self.objects)[handle.index as usize];
+        let object = slot.object.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.index);
+
+        Some(Arc::try_unwrap(object).unwrap_or_else(|object| (*object).clone()))
+    }
+
+    /// Replaces the object identified by this handle with `object`, returning the previous one.
+    ///
+    /// The handle keeps identifying the new object. If the handle is stale, the world is left
+    /// untouched and `None` is returned.
+    pub fn replace(&mut self, handle: ObjectHandle, object: Object) -> Option<Object> {
+        Some(mem::replace(self.get_mut(handle)?, object))
     }
 
     /// Returns a reference to the object identified by this handle.
     pub fn get(&self, handle: ObjectHandle) -> Option<&Object> {
-        self.objects.get(handle.0 as usize)
+        let slot = self.objects.get(handle.index as usize)?;
+        if slot.generation == handle.generation {
+            slot.object.as_deref()
+        } else {
+            None
+        }
     }
 
     /// Returns a mutable reference to the object identified by this handle.
     pub fn get_mut(&mut self, handle: ObjectHandle) -> Option<&mut Object> {
-        self.objects.get_mut(handle.0 as usize)
+        self.get(handle)?;
+        self.invalidate_layout();
+        Arc::make_mut(&mut self.objects)[handle.index as usize]
+            .object
+            .as_mut()
+            .map(Arc::make_mut)
     }
 
     /// Returns an iterator over this world's objects.
-    pub fn objects(&self) -> Iter<Object> {
-        self.objects.iter()
+    pub fn objects(&self) -> impl Iterator<Item = &Object> {
+        self.objects
+            .iter()
+            .filter_map(|slot| slot.object.as_deref())
     }
 
     /// Returns a mutable iterator over this world's objects.
-    pub fn objects_mut(&mut self) -> IterMut<Object> {
-        self.objects.iter_mut()
+    pub fn objects_mut(&mut self) -> impl Iterator<Item = &mut Object> {
+        self.invalidate_layout();
+        Arc::make_mut(&mut self.objects)
+            .iter_mut()
+            .filter_map(|slot| slot.object.as_mut().map(Arc::make_mut))
+    }
+
+    /// Returns an iterator over the handles of this world's objects, in the same order as
+    /// [`World::objects`].
+    pub fn object_handles(&self) -> impl Iterator<Item = ObjectHandle> + '_ {
+        self.entries().map(|(hnd, _)| hnd)
+    }
+
+    /// Returns the handle of the first object with the given name, if any.
+    pub fn find_by_name(&self, name: &str) -> Option<ObjectHandle> {
+        self.entries()
+            .find(|(_, obj)| obj.name() == Some(name))
+            .map(|(hnd, _)| hnd)
+    }
+
+    /// Returns an iterator over the handles of the objects marked with `tag`, in the same order
+    /// as [`World::objects`].
+    pub fn objects_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = ObjectHandle> + 'a {
+        self.entries()
+            .filter(move |(_, obj)| obj.has_tag(tag))
+            .map(|(hnd, _)| hnd)
     }
 
     /// Adds a new light source to this world.
-    pub fn add_light(&mut self, light: PointLight) {
-        self.lights.push(light);
+    pub fn add_light(&mut self, light: PointLight) -> LightHandle {
+        Arc::make_mut(&mut self.lights).push(Some(light));
+        LightHandle(self.lights.len() as u32 - 1)
     }
 
-    /// Removes the first occurrence of `light` from this world.
-    pub fn remove_light(&mut self, light: &PointLight) {
-        if let Some((pos, _)) = self.lights.iter_mut().find_position(|l| l == &light) {
-            self.lights.remove(pos);
-        }
+    /// Removes the light identified by this handle from this world, returning it.
+    pub fn remove_light(&mut self, handle: LightHandle) -> Option<PointLight> {
+        self.lights.get(handle.0 as usize)?.as_ref()?;
+        Arc::make_mut(&mut self.lights)[handle.0 as usize].take()
+    }
+
+    /// Returns a reference to the light identified by this handle.
+    pub fn get_light(&self, handle: LightHandle) -> Option<&PointLight> {
+        self.lights.get(handle.0 as usize)?.as_ref()
+    }
+
+    /// Returns a mutable reference to the light identified by this handle.
+    pub fn get_light_mut(&mut self, handle: LightHandle) -> Option<&mut PointLight> {
+        self.lights.get(handle.0 as usize)?.as_ref()?;
+        Arc::make_mut(&mut self.lights)[handle.0 as usize].as_mut()
     }
 
     /// Returns an iterator over this world's lights.
-    pub fn lights(&self) -> Iter<PointLight> {
-        self.lights.iter()
+    pub fn lights(&self) -> impl Iterator<Item = &PointLight> {
+        self.lights.iter().flatten()
     }
 
     /// Returns a mutable iterator over this world's lights.
-    pub fn lights_mut(&mut self) -> IterMut<PointLight> {
-        self.lights.iter_mut()
+    pub fn lights_mut(&mut self) -> impl Iterator<Item = &mut PointLight> {
+        Arc::make_mut(&mut self.lights).iter_mut().flatten()
+    }
+
+    /// Returns an iterator over the handles of this world's lights, in the same order as
+    /// [`World::lights`].
+    pub fn light_handles(&self) -> impl Iterator<Item = LightHandle> + '_ {
+        self.lights
+            .iter()
+            .enumerate()
+            .filter(|(_, light)| light.is_some())
+            .map(|(i, _)| LightHandle(i as u32))
+    }
+
+    /// Adds a new area light to this world.
+    pub fn add_area_light(&mut self, light: AreaLight) -> AreaLightHandle {
+        Arc::make_mut(&mut self.area_lights).push(Some(light));
+        AreaLightHandle(self.area_lights.len() as u32 - 1)
+    }
+
+    /// Removes the area light identified by this handle from this world, returning it.
+    pub fn remove_area_light(&mut self, handle: AreaLightHandle) -> Option<AreaLight> {
+        self.area_lights.get(handle.0 as usize)?.as_ref()?;
+        Arc::make_mut(&mut self.area_lights)[handle.0 as usize].take()
+    }
+
+    /// Returns a reference to the area light identified by this handle.
+    pub fn get_area_light(&self, handle: AreaLightHandle) -> Option<&AreaLight> {
+        self.area_lights.get(handle.0 as usize)?.as_ref()
+    }
+
+    /// Returns a mutable reference to the area light identified by this handle.
+    pub fn get_area_light_mut(&mut self, handle: AreaLightHandle) -> Option<&mut AreaLight> {
+        self.area_lights.get(handle.0 as usize)?.as_ref()?;
+        Arc::make_mut(&mut self.area_lights)[handle.0 as usize].as_mut()
+    }
+
+    /// Returns an iterator over this world's area lights.
+    pub fn area_lights(&self) -> impl Iterator<Item = &AreaLight> {
+        self.area_lights.iter().flatten()
+    }
+
+    /// Returns a mutable iterator over this world's area lights.
+    pub fn area_lights_mut(&mut self) -> impl Iterator<Item = &mut AreaLight> {
+        Arc::make_mut(&mut self.area_lights).iter_mut().flatten()
+    }
+
+    /// Returns an iterator over the handles of this world's area lights, in the same order as
+    /// [`World::area_lights`].
+    pub fn area_light_handles(&self) -> impl Iterator<Item = AreaLightHandle> + '_ {
+        self.area_lights
+            .iter()
+            .enumerate()
+            .filter(|(_, light)| light.is_some())
+            .map(|(i, _)| AreaLightHandle(i as u32))
     }
 
     /// Computes the intersections between all the object in this world and a ray.
     ///
     /// The intersections returned by this method are sorted by time of impact in ascending order.
     pub fn interferences_with_ray<'a>(&'a self, ray: &'a Ray) -> InterferencesWithRay {
-        InterferencesWithRay {
-            ray,
-            world: self,
-            inner: self
-                .handles()
-                .map(move |hnd| {
-                    let obj = self.get(hnd).unwrap();
-                    (
-                        hnd,
-                        obj.shape()
-                            .intersections_in_world_space(obj.transform(), ray),
+        let layout = self.layout();
+        let mut intersections = INTERSECTIONS.with(|pool| pool.take());
+
+        for (hnd, obj) in self.entries() {
+            let i = hnd.index as usize;
+            intersections.extend(
+                obj.shape()
+                    .intersections_with_inverse(
+                        &layout.inverses[i],
+                        &layout.normal_matrices[i],
+                        ray,
                     )
-                })
-                .flat_map(|(obj, intersections)| intersections.map(move |i| (obj, i)))
-                .sorted_unstable_by(|(_, x1), (_, x2)| x1.toi.partial_cmp(&x2.toi).unwrap()),
-            containers: Vec::with_capacity(8),
+                    .map(|i| (hnd, i)),
+            );
+        }
+
+        InterferencesWithRay::new(ray, self, intersections)
+    }
+
+    /// Computes the intersections between all the objects in this world and a packet of rays.
+    ///
+    /// This is equivalent to calling [`World::interferences_with_ray`] for each ray, but spheres
+    /// and cubes are tested against all the rays at once.
+    pub fn interferences_with_packet<'a, const N: usize>(
+        &'a self,
+        rays: &'a [Ray; N],
+    ) -> [InterferencesWithRay<'a, 'a>; N] {
+        let layout = self.layout();
+        let packet = RayPacket::new(rays);
+        let mut intersections: [_; N] = array::from_fn(|_| INTERSECTIONS.with(|pool| pool.take()));
+
+        for (hnd, obj) in self.entries() {
+            let i = hnd.index as usize;
+            let inv = &layout.inverses[i];
+            let normal_matrix = &layout.normal_matrices[i];
+
+            let vectorized = packet::intersections_with_packet(
+                obj.shape(),
+                inv,
+                normal_matrix,
+                &packet,
+                |lane, i| intersections[lane].push((hnd, i)),
+            );
+
+            if !vectorized {
+                for (ray, intersections) in rays.iter().zip(intersections.iter_mut()) {
+                    intersections.extend(
+                        obj.shape()
+                            .intersections_with_inverse(inv, normal_matrix, ray)
+                            .map(|i| (hnd, i)),
+                    );
+                }
+            }
         }
+
+        let mut intersections = IntoIterator::into_iter(intersections);
+        array::from_fn(|lane| {
+            InterferencesWithRay::new(&rays[lane], self, intersections.next().unwrap())
+        })
     }
 
     /// Recursively computes the color at the specified interference point.
@@ -133,36 +458,36 @@ impl World {
     /// The recursion will be at most `remaining` deep. Returns `None` if the recursion limit is
     /// reached.
     pub fn shade_hit(&self, interference: &Interference, remaining: u32) -> Color {
+        let _scope = profile::scope(Phase::Shading);
+
         let obj = self
             .get(interference.handle)
             .expect("invalid object handle in interference");
 
-        let surface = self.lights().fold(Color::BLACK, |surface, light| {
-            surface
-                + rendering::phong_lighting(
-                    obj,
-                    light,
-                    &interference.over_point,
-                    &interference.eye,
-                    &interference.normal,
-                    light.casts_shadows && self.is_in_shadow(&interference.over_point, light),
-                )
-        });
+        let visibility = self.ambient_visibility(&interference.over_point, &interference.normal);
+
+        let surface = self
+            .lights()
+            .map(|light| self.direct_lighting(obj, light, interference, visibility))
+            .chain(
+                self.area_lights()
+                    .map(|light| self.direct_lighting(obj, light, interference, visibility)),
+            )
+            .fold(Color::BLACK, |surface, color| surface + color);
 
         let reflected = self.reflected_color(interference, remaining);
         let refracted = self.refracted_color(interference, remaining);
 
-        let m = obj.material();
-        if m.reflective > 0.0 && m.transparency > 0.0 {
-            let reflectance = interference.schlick();
-            surface + reflected * reflectance + refracted * (1.0 - reflectance)
-        } else {
-            surface + reflected + refracted
-        }
+        let (reflectance, transmittance) = fresnel_weights(obj, interference);
+        surface + reflected * reflectance + refracted * transmittance
     }
 
     /// Recursively computes the reflected color at the specified interference point.
     ///
+    /// Rough reflections average as many rays as set in the
+    /// [render settings](World::render_settings), each of them recursing on its own: their cost
+    /// grows quickly when rough surfaces reflect each other.
+    ///
     /// The recursion will be at most `remaining` deep. Returns `None` if the recursion limit is
     /// reached.
     pub fn reflected_color(&self, interference: &Interference, remaining: u32) -> Color {
@@ -170,19 +495,28 @@ impl World {
             .get(interference.handle)
             .expect("invalid object handle in interference");
 
-        let reflective = obj.material().reflective;
+        let m = obj.material();
 
-        if remaining == 0 || reflective == 0.0 {
+        if remaining == 0 || m.reflective == 0.0 {
             Color::BLACK
         } else {
-            let r = Ray::new(interference.over_point, interference.reflect);
-            let c = self.color_at(&r, remaining - 1);
-            c * reflective
+            let c = self.blurred_color(
+                &interference.over_point,
+                &interference.reflect,
+                &interference.normal,
+                m.reflection_blur(),
+                self.render_settings.glossy_samples,
+                remaining - 1,
+            );
+            c * m.reflective
         }
     }
 
     /// Recursively computes the refracted color at the specified interference point.
     ///
+    /// Rough refractions average several rays like rough reflections, see
+    /// [`World::reflected_color`].
+    ///
     /// The recursion will be at most `remaining` deep. Returns `None` if the recursion limit is
     /// reached.
     pub fn refracted_color(&self, interference: &Interference, remaining: u32) -> Color {
@@ -190,44 +524,286 @@ impl World {
             .get(interference.handle)
             .expect("invalid object handle in interference");
 
-        let transparency = obj.material().transparency;
+        let m = obj.material();
 
-        if remaining == 0 || transparency == 0.0 {
+        if remaining == 0 || m.transparency == 0.0 {
             Color::BLACK
+        } else if let Some(direction) = interference.refracted_direction() {
+            let c = self.blurred_color(
+                &interference.under_point,
+                &direction,
+                &-interference.normal,
+                m.refraction_roughness,
+                self.render_settings.translucency_samples,
+                remaining - 1,
+            );
+            c * m.transparency
         } else {
-            let n_ratio = interference.n1 / interference.n2;
-            let cos_i = interference.eye.dot(&interference.normal);
-            let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
-
-            if sin2_t > 1.0 {
-                Color::BLACK
-            } else {
-                let cos_t = (1.0 - sin2_t).sqrt();
-                let direction =
-                    interference.normal * (n_ratio * cos_i - cos_t) - interference.eye * n_ratio;
+            Color::BLACK
+        }
+    }
 
-                let r = Ray::new(interference.under_point, direction);
-                let c = self.color_at(&r, remaining - 1);
-                c * transparency
-            }
+    /// Computes the color seen from `origin` in direction `dir`, blurred when `roughness` is
+    /// positive by averaging `samples` rays spread in a cone around it.
+    ///
+    /// The rays are kept on the side of the surface that `side` points to.
+    fn blurred_color(
+        &self,
+        origin: &Point3,
+        dir: &Vec3,
+        side: &Vec3,
+        roughness: f32,
+        samples: u32,
+        remaining: u32,
+    ) -> Color {
+        if roughness <= 0.0 || samples <= 1 {
+            return self.color_at(&Ray::new(*origin, *dir), remaining);
         }
+
+        // Spread the rays like for ambient occlusion, to keep rendering deterministic
+        let sum = (0..samples)
+            .map(|i| {
+                let (u, v) = hammersley(i, samples);
+                let dir = cone_direction(dir, side, roughness, u, v);
+                self.color_at(&Ray::new(*origin, dir), remaining)
+            })
+            .fold(Color::BLACK, |sum, c| sum + c);
+
+        sum / samples as f32
     }
 
     /// Recursively computes the color at the intersection between an object and a ray.
     ///
-    /// The recursion will be at most `remaining` deep. Returns `None` if the recursion limit is
-    /// reached.
+    /// The recursion will be at most `remaining` deep. Returns the color of the
+    /// [environment](World::environment) if the ray does not hit any object.
     pub fn color_at(&self, ray: &Ray, remaining: u32) -> Color {
         if let Some(hit) = self.interferences_with_ray(ray).hit() {
             self.shade_hit(&hit, remaining)
         } else {
-            Color::BLACK
+            self.environment.color_at(&ray.dir)
+        }
+    }
+
+    /// Estimates the color seen along `ray` with Monte Carlo path tracing.
+    ///
+    /// Unlike [`World::color_at`], which only follows mirror reflections and refractions, paths
+    /// also bounce off diffuse surfaces in random directions, so that objects are lit by the light
+    /// reflected by the others. At each hit, the path either bounces diffusely, is reflected or is
+    /// refracted, with probabilities following the material, and the lights are sampled directly.
+    /// After a few bounces, paths carrying little light are ended at random (Russian roulette).
+    ///
+    /// Ambient light is ignored, since the light bouncing between objects replaces it. Paths
+    /// bounce at most `depth` times.
+    ///
+    /// Each call returns a single, noisy sample, drawing random numbers from `rng`. Averaging many
+    /// samples converges to the final color, see [`Camera::set_samples_per_pixel`].
+    ///
+    /// [`Camera::set_samples_per_pixel`]: crate::rendering::Camera::set_samples_per_pixel
+    pub fn trace_path(&self, ray: &Ray, depth: u32, rng: &mut Rng) -> Color {
+        let mut color = Color::BLACK;
+        let mut throughput = Color::WHITE;
+        let mut ray = *ray;
+
+        for bounce in 0..=depth {
+            let hit = match self.interferences_with_ray(&ray).hit() {
+                Some(hit) => hit,
+                None => {
+                    color += throughput * self.environment.color_at(&ray.dir);
+                    break;
+                }
+            };
+
+            let obj = self
+                .get(hit.handle)
+                .expect("invalid object handle in interference");
+            let m = obj.material();
+
+            let direct = self
+                .lights()
+                .map(|light| self.direct_lighting(obj, light, &hit, 0.0))
+                .chain(
+                    self.area_lights()
+                        .map(|light| self.direct_lighting(obj, light, &hit, 0.0)),
+                )
+                .fold(Color::BLACK, |direct, c| direct + c);
+            color += throughput * direct;
+
+            if bounce == depth {
+                break;
+            }
+
+            if bounce >= ROULETTE_DEPTH {
+                let survival = throughput.r.max(throughput.g).max(throughput.b).min(1.0);
+                if rng.next_f32() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+
+            // Split the light leaving the surface like `shade_hit` does, choosing reflections
+            // by their average reflectance and tinting them afterwards
+            let (reflectance, transmittance) = fresnel_weights(obj, &hit);
+            let average = (reflectance.r + reflectance.g + reflectance.b) / 3.0;
+            let reflective = m.reflective * average;
+            let transparency = m.transparency * transmittance;
+
+            // Follow one of the parts at random, weighting it by the inverse of its probability
+            let diffuse = match m.shading {
+                ShadingModel::Phong => m.diffuse,
+                ShadingModel::Pbr(_) => 1.0 - m.metalness(),
+            };
+            let total = diffuse + reflective + transparency;
+            let pick = rng.next_f32() * total;
+
+            ray = if pick < reflective {
+                throughput = throughput * reflectance / average;
+
+                let roughness = m.reflection_blur();
+                let dir = if roughness > 0.0 {
+                    let (u, v) = (rng.next_f32(), rng.next_f32());
+                    cone_direction(&hit.reflect, &hit.normal, roughness, u, v)
+                } else {
+                    hit.reflect
+                };
+                Ray::new(hit.over_point, dir)
+            } else if pick < reflective + transparency {
+                let dir = match hit.refracted_direction() {
+                    Some(dir) => dir,
+                    None => break,
+                };
+
+                let dir = if m.refraction_roughness > 0.0 {
+                    let (u, v) = (rng.next_f32(), rng.next_f32());
+                    cone_direction(&dir, &-hit.normal, m.refraction_roughness, u, v)
+                } else {
+                    dir
+                };
+                Ray::new(hit.under_point, dir)
+            } else {
+                let local_point = obj
+                    .inverse_transform()
+                    .expect("object transform is not invertible")
+                    * hit.over_point;
+                throughput = throughput * m.color_on(obj.shape(), &local_point);
+
+                let dir = cosine_weighted_direction(&hit.normal, rng.next_f32(), rng.next_f32());
+                Ray::new(hit.over_point, dir)
+            };
+
+            throughput *= total;
+        }
+
+        color
+    }
+
+    /// Computes the color seen by each ray of a packet, like [`World::color_at`].
+    ///
+    /// Only the rays of the packet are tested together, while the reflected, refracted and shadow
+    /// rays cast to shade each hit are traced one by one.
+    pub fn color_at_packet<const N: usize>(&self, rays: &[Ray; N], remaining: u32) -> [Color; N] {
+        let mut colors = [Color::BLACK; N];
+
+        for ((color, interferences), ray) in colors
+            .iter_mut()
+            .zip(self.interferences_with_packet(rays))
+            .zip(rays)
+        {
+            *color = match interferences.hit() {
+                Some(hit) => self.shade_hit(&hit, remaining),
+                None => self.environment.color_at(&ray.dir),
+            };
         }
+
+        colors
     }
 
     /// Checks whether the given point lies in shadow of the specified light source.
     pub fn is_in_shadow(&self, point: &Point3, light: &PointLight) -> bool {
-        let v = light.position - point;
+        self.is_occluded(point, &light.position)
+    }
+
+    /// Computes the fraction of the specified light source which reaches the given point, ie.
+    /// the fraction of the points sampled on the light which do not leave it in shadow.
+    ///
+    /// Points lying in the penumbra of an area light get values between `0.0` and `1.0`.
+    pub fn light_intensity_at<L: LightSource>(&self, point: &Point3, light: &L) -> f32 {
+        let samples = light.sample_count();
+        let lit = (0..samples)
+            .filter(|&i| !self.is_occluded(point, &light.sample(i)))
+            .count();
+
+        lit as f32 / samples as f32
+    }
+
+    /// Computes the fraction of the ambient light reaching a point with the given normal, ie. not
+    /// blocked by objects closer than the ambient occlusion radius.
+    ///
+    /// Rays are cast from the point over the hemisphere around the normal, more of them towards
+    /// the normal than at grazing angles, following the cosine of their angle with the normal.
+    /// Only objects casting shadows occlude the ambient light. Returns `1.0` if ambient occlusion
+    /// is disabled in the [render settings](World::render_settings).
+    pub fn ambient_visibility(&self, point: &Point3, normal: &Vec3) -> f32 {
+        let RenderSettings {
+            ao_samples,
+            ao_radius,
+            ..
+        } = self.render_settings;
+
+        if ao_samples == 0 {
+            return 1.0;
+        }
+
+        // Spread the rays evenly over the hemisphere using the Hammersley point set, rather than
+        // random numbers, to keep rendering deterministic
+        let occluded = (0..ao_samples)
+            .filter(|&i| {
+                let (u, v) = hammersley(i, ao_samples);
+                let r = Ray::new(*point, cosine_weighted_direction(normal, u, v));
+                self.interferences_with_ray(&r)
+                    .hit_with_shadow()
+                    .is_some_and(|hit| hit.toi < ao_radius)
+            })
+            .count();
+
+        1.0 - occluded as f32 / ao_samples as f32
+    }
+
+    /// Computes the contribution of a light source to the color of an interference point, with
+    /// its ambient contribution scaled by `ambient_visibility`.
+    fn direct_lighting<L: LightSource>(
+        &self,
+        obj: &Object,
+        light: &L,
+        interference: &Interference,
+        ambient_visibility: f32,
+    ) -> Color {
+        let intensity = if light.casts_shadows() {
+            self.light_intensity_at(&interference.over_point, light)
+        } else {
+            1.0
+        };
+
+        let lighting = match obj.material().shading {
+            ShadingModel::Phong => rendering::occluded_phong_lighting,
+            ShadingModel::Pbr(_) => rendering::occluded_pbr_lighting,
+        };
+
+        lighting(
+            obj,
+            light,
+            &interference.over_point,
+            &interference.eye,
+            &interference.normal,
+            intensity,
+            ambient_visibility,
+        )
+    }
+
+    /// Checks whether any object casting shadows lies between the given point and `target`.
+    fn is_occluded(&self, point: &Point3, target: &Point3) -> bool {
+        let _scope = profile::scope(Phase::ShadowRays);
+
+        let v = *target - point;
         let distance = v.length();
         let direction = v.normalize();
 
@@ -239,11 +815,162 @@ impl World {
         }
     }
 
-    fn handles(&self) -> impl Iterator<Item = ObjectHandle> {
-        (0..self.objects.len()).map(|i| ObjectHandle(i as u32))
+    /// Returns the arrays of object data used while casting rays, building them if needed.
+    fn layout(&self) -> &Layout {
+        self.layout
+            .get_or_init(|| Layout::new(self.objects.iter().map(|slot| slot.object.as_deref())))
+    }
+
+    /// Discards the arrays of object data, which are rebuilt on next use.
+    fn invalidate_layout(&mut self) {
+        self.layout = Default::default();
+    }
+
+    /// Returns an iterator over this world's objects along with their handles.
+    fn entries(&self) -> impl Iterator<Item = (ObjectHandle, &Object)> {
+        self.objects.iter().enumerate().filter_map(|(i, slot)| {
+            let hnd = ObjectHandle {
+                index: i as u32,
+                generation: slot.generation,
+            };
+            Some((hnd, slot.object.as_deref()?))
+        })
+    }
+}
+
+/// Returns how much of the reflected and of the refracted light leave the surface of `obj` at
+/// `interference`, following the Fresnel model of its material.
+fn fresnel_weights(obj: &Object, interference: &Interference) -> (Color, f32) {
+    let m = obj.material();
+    let metalness = m.metalness();
+
+    let tint = if metalness > 0.0 {
+        let local_point = obj
+            .inverse_transform()
+            .expect("object transform is not invertible")
+            * interference.over_point;
+        let color = m.color_on(obj.shape(), &local_point);
+        Color::WHITE + (color - Color::WHITE) * metalness
+    } else {
+        Color::WHITE
+    };
+
+    let weighted = match m.fresnel {
+        Fresnel::Transparent => m.reflective > 0.0 && m.transparency > 0.0,
+        Fresnel::Schlick => true,
+        Fresnel::None => false,
+    };
+
+    if !weighted {
+        return (tint, 1.0);
+    }
+
+    let reflectance = interference.schlick();
+    if metalness > 0.0 {
+        // Schlick's approximation, with the tint as the reflectance at normal incidence, blended
+        // with the reflectance of dielectrics for partially metallic materials
+        let cos = interference.eye.dot(&interference.normal).max(0.0);
+        let grazing = (1.0 - cos).powi(5);
+        let metal = tint + (Color::WHITE - tint) * grazing;
+        (
+            Color::WHITE * (reflectance * (1.0 - metalness)) + metal * metalness,
+            1.0 - reflectance,
+        )
+    } else {
+        (Color::WHITE * reflectance, 1.0 - reflectance)
+    }
+}
+
+/// Returns the `i`-th of the `n` points of the Hammersley set, which are evenly spread over
+/// `[0, 1)²`.
+fn hammersley(i: u32, n: u32) -> (f32, f32) {
+    (
+        (i as f32 + 0.5) / n as f32,
+        i.reverse_bits() as f32 / 4_294_967_296.0,
+    )
+}
+
+/// Maps `(u, v)` in `[0, 1)²` to a direction in the hemisphere around `normal`.
+///
+/// Uniformly distributed `(u, v)` give directions whose density is proportional to the cosine of
+/// their angle with the normal.
+fn cosine_weighted_direction(normal: &Vec3, u: f32, v: f32) -> Vec3 {
+    let r = u.sqrt();
+    let phi = 2.0 * PI * v;
+
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    tangent * (r * float::cos(phi)) + bitangent * (r * float::sin(phi)) + *normal * (1.0 - u).sqrt()
+}
+
+/// Maps `(u, v)` in `[0, 1)²` to a direction in the cone around `axis` whose half-angle grows
+/// from zero to 90 degrees with `roughness`.
+///
+/// Uniformly distributed `(u, v)` give directions uniformly spread over the cone. Directions
+/// pointing below the surface with the given `normal` are mirrored above it.
+fn cone_direction(axis: &Vec3, normal: &Vec3, roughness: f32, u: f32, v: f32) -> Vec3 {
+    let cos_max = float::cos(roughness.min(1.0) * PI / 2.0);
+    let cos_theta = 1.0 - u * (1.0 - cos_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+
+    let axis = axis.normalize();
+    let (tangent, bitangent) = orthonormal_basis(&axis);
+    let dir = tangent * (sin_theta * float::cos(phi))
+        + bitangent * (sin_theta * float::sin(phi))
+        + axis * cos_theta;
+
+    let below = dir.dot(normal);
+    if below < 0.0 {
+        dir - *normal * (2.0 * below)
+    } else {
+        dir
+    }
+}
+
+/// Returns two vectors forming an orthonormal basis with the unit vector `n` (Duff et al., 2017).
+fn orthonormal_basis(n: &Vec3) -> (Vec3, Vec3) {
+    let sign = 1.0f32.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+
+    (
+        Vec3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+        Vec3::new(b, sign + n.y * n.y * a, -n.y),
+    )
+}
+
+/// A place for an object in a world, which is reused once the object is removed.
+#[derive(Debug, Clone)]
+struct Slot {
+    /// Number of times the object in this slot was removed, used to detect stale handles.
+    generation: u32,
+    object: Option<Arc<Object>>,
+}
+
+/// Below this many elements, intersections are sorted by insertion rather than with a general
+/// purpose sort.
+const INSERTION_SORT_THRESHOLD: usize = 16;
+
+/// Sorts `xs` by time of impact in ascending order, using a total ordering so that NaN times of
+/// impact cannot break the sort.
+fn sort_by_toi(xs: &mut [(ObjectHandle, RayIntersection)]) {
+    if xs.len() > INSERTION_SORT_THRESHOLD {
+        xs.sort_unstable_by(|(_, x1), (_, x2)| x1.toi.total_cmp(&x2.toi));
+        return;
+    }
+
+    for i in 1..xs.len() {
+        let mut j = i;
+        while j > 0 && xs[j - 1].1.toi.total_cmp(&xs[j].1.toi) == Ordering::Greater {
+            xs.swap(j - 1, j);
+            j -= 1;
+        }
     }
 }
 
+/// Number of bounces after which [`World::trace_path`] may end paths early.
+const ROULETTE_DEPTH: u32 = 3;
+
 /// An intersection between a world object and a ray.
 #[derive(Debug, Clone)]
 pub struct Interference {
@@ -272,6 +999,21 @@ pub struct Interference {
 }
 
 impl Interference {
+    /// Computes the direction of the ray refracted at this intersection, or `None` in case of
+    /// total internal reflection.
+    pub fn refracted_direction(&self) -> Option<Vec3> {
+        let n_ratio = self.n1 / self.n2;
+        let cos_i = self.eye.dot(&self.normal);
+        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+
+        if sin2_t > 1.0 {
+            None
+        } else {
+            let cos_t = (1.0 - sin2_t).sqrt();
+            Some(self.normal * (n_ratio * cos_i - cos_t) - self.eye * n_ratio)
+        }
+    }
+
     /// Computes the reflectance at this intersection.
     pub fn schlick(&self) -> f32 {
         let mut cos = self.eye.dot(&self.normal);
@@ -293,32 +1035,90 @@ impl Interference {
 }
 
 /// Iterator over all the objects in the world that intersect a specific ray.
+///
+/// The buffers backing this iterator are borrowed from a per-thread pool and given back when it
+/// is dropped, so that casting a ray does not allocate in the common case.
 #[derive(Debug, Clone)]
 pub struct InterferencesWithRay<'a, 'b> {
     ray: &'a Ray,
     world: &'b World,
-    inner: IntoIter<(ObjectHandle, RayIntersection)>,
+    intersections: Vec<(ObjectHandle, RayIntersection)>,
+    next: usize,
     containers: Vec<ObjectHandle>,
 }
 
-impl<'a> InterferencesWithRay<'a, '_> {
+impl Drop for InterferencesWithRay<'_, '_> {
+    fn drop(&mut self) {
+        let intersections = std::mem::take(&mut self.intersections);
+        let containers = std::mem::take(&mut self.containers);
+
+        // The pools may already be gone if the thread is exiting
+        let _ = INTERSECTIONS.try_with(|pool| pool.give(intersections));
+        let _ = CONTAINERS.try_with(|pool| pool.give(containers));
+    }
+}
+
+impl<'a, 'b> InterferencesWithRay<'a, 'b> {
+    /// Creates an iterator over `intersections` of `ray` with the objects of `world`.
+    fn new(
+        ray: &'a Ray,
+        world: &'b World,
+        mut intersections: Vec<(ObjectHandle, RayIntersection)>,
+    ) -> Self {
+        sort_by_toi(&mut intersections);
+
+        Self {
+            ray,
+            world,
+            intersections,
+            next: 0,
+            containers: CONTAINERS.with(|pool| pool.take()),
+        }
+    }
+
     /// Returns the first intersection to have hit an object in the world.
-    pub fn hit(mut self) -> Option<Interference> {
-        self.find(|i| i.toi >= 0.)
+    ///
+    /// Intersections closer than the [intersection tolerance](Tolerances::intersection) of the
+    /// world are ignored.
+    pub fn hit(self) -> Option<Interference> {
+        let min_toi = self.world.tolerances.intersection;
+
+        self.hit_within(min_toi..)
+    }
+
+    /// Returns the first intersection along the ray whose time of impact lies within `range`.
+    ///
+    /// Intersections with a NaN time of impact are never returned.
+    pub fn hit_within<R: RangeBounds<f32>>(mut self, range: R) -> Option<Interference> {
+        self.find(|i| range.contains(&i.toi))
+    }
+
+    /// Returns the intersection closest to the origin of the ray whose time of impact lies within
+    /// `range`, whether it is in front of or behind the origin.
+    ///
+    /// Intersections with a NaN time of impact are never returned.
+    pub fn closest_in<R: RangeBounds<f32>>(self, range: R) -> Option<Interference> {
+        self.filter(|i| range.contains(&i.toi))
+            .min_by(|a, b| a.toi.abs().total_cmp(&b.toi.abs()))
     }
 
     /// Returns the first intersection to have hit an object in the world which casts a shadow.
     pub fn hit_with_shadow(mut self) -> Option<Interference> {
-        let world = self.world;
+        let layout = self.world.layout();
+        let min_toi = self.world.tolerances.intersection;
 
-        self.find(|i| i.toi >= 0. && world.get(i.handle).unwrap().casts_shadow())
+        self.find(|i| i.toi >= min_toi && layout.casts_shadow[i.handle.index as usize])
     }
 
     /// Returns the refractive index of the last entered object, or `None` if no objects have been
     /// entered by this iterator yet.
     fn get_current_refractive_index(&self) -> Option<f32> {
         let hnd = self.containers.last()?;
-        Some(self.world.get(*hnd)?.material().refractive_index)
+        self.world
+            .layout()
+            .refractive_indices
+            .get(hnd.index as usize)
+            .copied()
     }
 }
 
@@ -326,36 +1126,38 @@ impl Iterator for InterferencesWithRay<'_, '_> {
     type Item = Interference;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|(handle, i)| {
-            let eye = -self.ray.dir;
-            let inside = i.normal.dot(&eye) < 0.;
-            let normal = if inside { -i.normal } else { i.normal };
-            let reflect = self.ray.dir.reflect(&normal);
-            let point = self.ray.point_at(i.toi);
+        let (handle, i) = self.intersections.get(self.next).cloned()?;
+        self.next += 1;
 
-            let n1 = self.get_current_refractive_index().unwrap_or(1.0);
+        let eye = -self.ray.dir;
+        let inside = i.normal.dot(&eye) < 0.;
+        let normal = if inside { -i.normal } else { i.normal };
+        let reflect = self.ray.dir.reflect(&normal);
+        let point = self.ray.point_at(i.toi);
 
-            if self.containers.contains(&handle) {
-                self.containers.retain(|elem| elem != &handle);
-            } else {
-                self.containers.push(handle);
-            }
+        let n1 = self.get_current_refractive_index().unwrap_or(1.0);
 
-            let n2 = self.get_current_refractive_index().unwrap_or(1.0);
-
-            Interference {
-                handle,
-                toi: i.toi,
-                point,
-                over_point: point + normal * EPSILON,
-                under_point: point - normal * EPSILON,
-                eye,
-                normal,
-                reflect,
-                inside,
-                n1,
-                n2,
-            }
+        if self.containers.contains(&handle) {
+            self.containers.retain(|elem| elem != &handle);
+        } else {
+            self.containers.push(handle);
+        }
+
+        let n2 = self.get_current_refractive_index().unwrap_or(1.0);
+        let bias = self.world.tolerances.shadow_bias;
+
+        Some(Interference {
+            handle,
+            toi: i.toi,
+            point,
+            over_point: point + normal * bias,
+            under_point: point - normal * bias,
+            eye,
+            normal,
+            reflect,
+            inside,
+            n1,
+            n2,
         })
     }
 }
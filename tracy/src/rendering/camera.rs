@@ -1,26 +1,85 @@
-use rayon::iter::{ParallelBridge, ParallelIterator};
+use core::f32::consts::PI;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 use crate::{
-    math::{Matrix, Point3, Vec3},
-    query::{Ray, World},
-    rendering::Canvas,
+    math::{float, Matrix, Point3, Rng, Vec3, EPSILON},
+    profile::{self, Phase},
+    query::{Interference, ObjectHandle, Ray, World},
+    rendering::{Canvas, Color},
+    Error,
 };
 
 /// Default recursion depth when computing reflections.
 pub const DEFAULT_RECURSION_DEPTH: u32 = 5;
 
+/// Side of the square tiles in which the canvas is split for rendering, in pixels.
+const TILE_SIZE: usize = 16;
+
+/// Number of rays traced together when supersampling a pixel.
+const PACKET_SIZE: usize = 4;
+
+/// Algorithm used by a [`Camera`] to compute the color seen by its rays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Integrator {
+    /// Whitted-style ray tracing with [`World::color_at`], as in the book.
+    #[default]
+    Whitted,
+    /// Monte Carlo path tracing with [`World::trace_path`], which also renders the light bouncing
+    /// off diffuse surfaces but needs many samples per pixel to converge.
+    PathTracing,
+}
+
+/// Arbitrary output variables (AOVs) of a pixel, describing the geometry seen through it.
+///
+/// They are recorded from the first ray cast through the pixel while it is rendered, which is the
+/// ray through its center with a single sample, or else the first of its random samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aov {
+    /// Handle to the first object hit, or `None` if the ray did not hit anything.
+    pub object: Option<ObjectHandle>,
+    /// Distance from the camera to the first hit, infinite if the ray did not hit anything.
+    pub depth: f32,
+}
+
+impl Default for Aov {
+    fn default() -> Self {
+        Self {
+            object: None,
+            depth: f32::INFINITY,
+        }
+    }
+}
+
+impl Aov {
+    /// Records the AOVs of a ray whose first hit is `hit`.
+    pub fn from_hit(hit: Option<&Interference>) -> Self {
+        match hit {
+            Some(hit) => Self {
+                object: Some(hit.handle),
+                depth: hit.toi,
+            },
+            None => Self::default(),
+        }
+    }
+}
+
 /// A perspective 3D camera.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
     size: (u32, u32),
     fov: f32,
     transform: Matrix,
-    recursion_limit: u32,
+    recursion_limit: Option<u32>,
+    integrator: Integrator,
+    samples_per_pixel: u32,
 
     // Derived parameters
     pixel_size: f32,
     half_width: f32,
     half_height: f32,
+    inverse: Matrix,
+    origin: Point3,
 }
 
 /// Prefab for a [`Camera`].
@@ -55,22 +114,59 @@ impl Camera {
     ///
     /// The view transformation will be multiplicative identity.
     pub fn new(hsize: u32, vsize: u32, fov: f32) -> Self {
-        Self::new_with_transform(hsize, vsize, fov, Matrix::identity(4))
+        Self::new_with_transform(hsize, vsize, fov, Matrix::identity())
     }
 
     /// Creates a new perspective camera with a view transform matrix.
+    ///
+    /// See [`Camera::try_new`] for a version validating the parameters first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `transform` is not invertible.
     pub fn new_with_transform(hsize: u32, vsize: u32, fov: f32, transform: Matrix) -> Self {
+        let inverse = transform
+            .inverse()
+            .expect("camera view transform is not invertible");
+
+        Self::with_inverse(hsize, vsize, fov, transform, inverse)
+    }
+
+    /// Creates a new perspective camera with a view transform matrix, checking that it describes
+    /// a valid projection.
+    ///
+    /// Fails with [`Error::InvalidCamera`] if the canvas is empty, the field of view is not
+    /// within `(0, π)` radians, or `transform` is not invertible.
+    pub fn try_new(hsize: u32, vsize: u32, fov: f32, transform: Matrix) -> Result<Self, Error> {
+        if hsize == 0 || vsize == 0 {
+            return Err(Error::InvalidCamera("canvas size is zero"));
+        }
+        if !(fov > 0.0 && fov < PI) {
+            return Err(Error::InvalidCamera(
+                "field of view is not between 0 and 180 degrees",
+            ));
+        }
+
+        let inverse = invert_view_transform(&transform)?;
+        Ok(Self::with_inverse(hsize, vsize, fov, transform, inverse))
+    }
+
+    fn with_inverse(hsize: u32, vsize: u32, fov: f32, transform: Matrix, inverse: Matrix) -> Self {
         let mut camera = Camera {
             size: (hsize, vsize),
             fov,
             transform,
-            recursion_limit: DEFAULT_RECURSION_DEPTH,
+            recursion_limit: None,
+            integrator: Integrator::default(),
+            samples_per_pixel: 1,
             pixel_size: 0.0,
             half_width: 0.0,
             half_height: 0.0,
+            inverse,
+            origin: Point3::new(0.0, 0.0, 0.0),
         };
 
-        camera.update();
+        camera.update_projection();
         camera
     }
 
@@ -107,40 +203,150 @@ impl Camera {
     }
 
     /// Sets this camera's view transform.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `transform` is not invertible, see [`Camera::try_set_view_transform`] to detect
+    /// it instead.
     pub fn set_view_transform(&mut self, transform: Matrix) {
         self.transform = transform;
         self.update();
     }
 
+    /// Sets this camera's view transform.
+    ///
+    /// Fails with [`Error::InvalidCamera`] if `transform` is not invertible, leaving the camera
+    /// unchanged.
+    pub fn try_set_view_transform(&mut self, transform: Matrix) -> Result<(), Error> {
+        self.inverse = invert_view_transform(&transform)?;
+        self.transform = transform;
+        self.update_projection();
+        Ok(())
+    }
+
     /// Returns the size in world-space units of a pixel on the canvas.
     pub fn pixel_size(&self) -> f32 {
         self.pixel_size
     }
 
+    /// Returns this camera's recursion limit, ie. how many times a ray is allowed to be
+    /// reflected/refracted by an object, or `None` if it was never set.
+    pub fn recursion_limit(&self) -> Option<u32> {
+        self.recursion_limit
+    }
+
     /// Updates this camera's recursion limit, ie. how many times a ray is allowed to be
     /// reflected/refracted by an object.
+    ///
+    /// The limit of the camera takes precedence over the one of the world being rendered.
     pub fn set_recursion_limit(&mut self, limit: u32) {
-        self.recursion_limit = limit;
+        self.recursion_limit = Some(limit);
+    }
+
+    /// Returns the recursion limit used when rendering `world` through this camera.
+    ///
+    /// This is the limit of the camera if set, or else the limit of the world if set, or else
+    /// [`DEFAULT_RECURSION_DEPTH`].
+    pub fn recursion_limit_for(&self, world: &World) -> u32 {
+        self.recursion_limit
+            .or_else(|| world.recursion_limit())
+            .unwrap_or(DEFAULT_RECURSION_DEPTH)
+    }
+
+    /// Returns the integrator used by this camera. Defaults to [`Integrator::Whitted`].
+    pub fn integrator(&self) -> Integrator {
+        self.integrator
+    }
+
+    /// Updates the integrator used by this camera.
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
+    /// Returns how many rays are cast through each pixel. Defaults to `1`.
+    pub fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    /// Updates how many rays are cast through each pixel.
+    ///
+    /// With more than one sample, or when path tracing, the rays pass through random points of
+    /// the pixel and their colors are averaged, which also smooths jagged edges. The random
+    /// numbers are seeded from the pixel's coordinates, so renders are still reproducible.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` is zero.
+    pub fn set_samples_per_pixel(&mut self, samples: u32) {
+        assert!(samples > 0, "at least one sample per pixel is needed");
+        self.samples_per_pixel = samples;
     }
 
     /// Constructs a ray originating at the camera position and directed towards point `(x,y)`
     /// in the canvas.
     pub fn ray_to(&self, x: u32, y: u32) -> Ray {
-        // offset from the edge of the canvas to the pixel's center
-        let xoffset = (x as f32 + 0.5) * self.pixel_size;
-        let yoffset = (y as f32 + 0.5) * self.pixel_size;
+        // aim at the pixel's center
+        self.ray_through(x as f32 + 0.5, y as f32 + 0.5)
+    }
+
+    /// Constructs a ray originating at the camera position and passing through the canvas at
+    /// `(x,y)`, where integer coordinates lie on the edges between pixels.
+    ///
+    /// This can be used to cast multiple rays through different points of the same pixel.
+    pub fn ray_through(&self, x: f32, y: f32) -> Ray {
+        // offset from the edge of the canvas to the point
+        let xoffset = x * self.pixel_size;
+        let yoffset = y * self.pixel_size;
 
         // untransformed coordinates of the pixel in world space
         let world_x = self.half_width - xoffset;
         let world_y = self.half_height - yoffset;
 
-        // transform the canvas point and the origin, then compute the ray's direction vector
-        let t_inv = self.transform.inverse().unwrap();
-        let pixel = &t_inv * Point3::new(world_x, world_y, -1.0);
-        let origin = &t_inv * Point3::new(0.0, 0.0, 0.0);
-        let direction = (pixel - origin).normalize();
+        // transform the canvas point, then compute the ray's direction vector
+        let pixel = &self.inverse * Point3::new(world_x, world_y, -1.0);
+        let direction = (pixel - self.origin).normalize();
+
+        Ray::new(self.origin, direction)
+    }
+
+    /// Constructs the rays directed towards the center of each pixel of scanline `y`, from left
+    /// to right.
+    ///
+    /// This is equivalent to calling [`Camera::ray_to`] for each pixel, but only transforms the
+    /// start of the scanline to world space.
+    pub fn rays_for_scanline(&self, y: u32) -> impl Iterator<Item = Ray> + '_ {
+        self.rays_for_span(y, 0..self.horizontal_size())
+    }
+
+    /// Constructs the rays directed towards the center of pixels `xs` of scanline `y`.
+    fn rays_for_span<I>(&self, y: u32, xs: I) -> impl Iterator<Item = Ray> + '_
+    where
+        I: Iterator<Item = u32> + 'static,
+    {
+        let world_y = self.half_height - (y as f32 + 0.5) * self.pixel_size;
+
+        // the scanline starts at the left edge of the canvas and runs along -x
+        let start = &self.inverse * Point3::new(self.half_width, world_y, -1.0);
+        let step = &self.inverse * Vec3::new(-self.pixel_size, 0.0, 0.0);
+
+        xs.map(move |x| {
+            let pixel = start + step * (x as f32 + 0.5);
+            Ray::new(self.origin, (pixel - self.origin).normalize())
+        })
+    }
+
+    /// Computes the color and the AOVs of pixel `(x,y)` as seen through this camera, with the
+    /// same samples as [`Camera::render`].
+    pub fn render_pixel(&self, world: &World, x: u32, y: u32) -> (Color, Aov) {
+        let _scope = profile::scope(Phase::PrimaryRays);
 
-        Ray::new(origin, direction)
+        self.shade_pixel(
+            world,
+            x,
+            y,
+            &self.ray_to(x, y),
+            self.recursion_limit_for(world),
+        )
     }
 
     /// Renders `world` to a canvas through this camera.
@@ -153,8 +359,139 @@ impl Camera {
         Stream::new(self, world)
     }
 
+    /// Renders `world` through this camera as a pyramid of images of increasing resolution.
+    pub fn preview<'a, 'b>(&'a self, world: &'b World) -> Preview<'a, 'b> {
+        Preview::new(self, world)
+    }
+
+    /// Renders `world` one band of scanlines at a time, passing each scanline to `f` from top to
+    /// bottom as soon as it is complete.
+    ///
+    /// Only a few scanlines are held in memory at any time, so that images too large for a
+    /// [`Canvas`] can be written out while they are rendered, eg. with a
+    /// [`PpmWriter`](super::PpmWriter). Rendering stops at the first error returned by `f`.
+    pub fn render_rows<F, E>(&self, world: &World, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&[Color]) -> Result<(), E>,
+    {
+        let width = self.horizontal_size() as usize;
+        let mut band = vec![Color::BLACK; width * TILE_SIZE];
+
+        for start in (0..self.vertical_size()).step_by(TILE_SIZE) {
+            let count = (self.vertical_size() - start).min(TILE_SIZE as u32) as usize;
+            let band = &mut band[..width * count];
+
+            self.render_scanlines(world, start, band.chunks_exact_mut(width));
+
+            for row in band.chunks_exact(width) {
+                f(row)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renders `lines`, the consecutive scanlines starting from `start`.
+    ///
+    /// The scanlines are split in tiles, which rayon's work-stealing scheduler hands out to the
+    /// threads of the current pool. Even when some part of the frame is much more expensive to
+    /// render than the rest, the work is spread evenly among the threads.
+    fn render_scanlines<'a, I>(&self, world: &World, start: u32, lines: I)
+    where
+        I: Iterator<Item = &'a mut [Color]>,
+    {
+        let columns = (self.horizontal_size() as usize).div_ceil(TILE_SIZE);
+
+        // Each tile is made of the spans of pixels it covers on each of its scanlines
+        let mut tiles = Vec::<Vec<(u32, u32, &mut [Color])>>::new();
+
+        for (i, line) in lines.enumerate() {
+            let first_tile = i / TILE_SIZE * columns;
+            if tiles.len() <= first_tile {
+                tiles.resize_with(first_tile + columns, Vec::new);
+            }
+
+            for (column, span) in line.chunks_mut(TILE_SIZE).enumerate() {
+                let x = (column * TILE_SIZE) as u32;
+                tiles[first_tile + column].push((x, start + i as u32, span));
+            }
+        }
+
+        tiles.into_par_iter().for_each(|tile| {
+            let _scope = profile::scope(Phase::PrimaryRays);
+            let depth = self.recursion_limit_for(world);
+
+            for (x, y, span) in tile {
+                let rays = self.rays_for_span(y, x..x + span.len() as u32);
+
+                for ((x, pixel), ray) in (x..).zip(span.iter_mut()).zip(rays) {
+                    *pixel = self.shade_pixel(world, x, y, &ray, depth).0;
+                }
+            }
+        });
+    }
+
+    /// Computes the color and the AOVs of pixel `(x,y)`, given the ray through its center.
+    fn shade_pixel(&self, world: &World, x: u32, y: u32, ray: &Ray, depth: u32) -> (Color, Aov) {
+        if self.integrator == Integrator::Whitted && self.samples_per_pixel == 1 {
+            return trace_primary(world, ray, depth);
+        }
+
+        let mut rng = Rng::new((u64::from(y) << 32) | u64::from(x));
+        let sample = |rng: &mut Rng| {
+            let (dx, dy) = (rng.next_f32(), rng.next_f32());
+            self.ray_through(x as f32 + dx, y as f32 + dy)
+        };
+
+        let first = sample(&mut rng);
+        let (mut color, aov) = match self.integrator {
+            Integrator::Whitted => trace_primary(world, &first, depth),
+            Integrator::PathTracing => (
+                world.trace_path(&first, depth, &mut rng),
+                Aov::from_hit(world.interferences_with_ray(&first).hit().as_ref()),
+            ),
+        };
+
+        match self.integrator {
+            Integrator::Whitted => {
+                // The rays through a pixel are coherent, so trace them in packets when possible
+                let mut remaining = self.samples_per_pixel as usize - 1;
+
+                while remaining >= PACKET_SIZE {
+                    let rays = [(); PACKET_SIZE].map(|_| sample(&mut rng));
+                    for c in world.color_at_packet(&rays, depth) {
+                        color += c;
+                    }
+                    remaining -= PACKET_SIZE;
+                }
+
+                for _ in 0..remaining {
+                    color += world.color_at(&sample(&mut rng), depth);
+                }
+            }
+            Integrator::PathTracing => {
+                for _ in 1..self.samples_per_pixel {
+                    let ray = sample(&mut rng);
+                    color += world.trace_path(&ray, depth, &mut rng);
+                }
+            }
+        }
+
+        (color / self.samples_per_pixel as f32, aov)
+    }
+
     fn update(&mut self) {
-        let half_view = (self.fov / 2.0).tan();
+        self.inverse = self
+            .transform
+            .inverse()
+            .expect("camera view transform is not invertible");
+        self.update_projection();
+    }
+
+    /// Updates the derived parameters of the camera from its size, field of view and inverse
+    /// view transform.
+    fn update_projection(&mut self) {
+        let half_view = float::tan(self.fov / 2.0);
         let aspect_ratio = self.horizontal_size() as f32 / self.vertical_size() as f32;
 
         if aspect_ratio >= 1.0 {
@@ -166,9 +503,25 @@ impl Camera {
         };
 
         self.pixel_size = self.half_width * 2.0 / self.horizontal_size() as f32;
+        self.origin = &self.inverse * Point3::new(0.0, 0.0, 0.0);
+    }
+}
+
+/// Computes the color seen along a primary ray like [`World::color_at`], along with its AOVs.
+fn trace_primary(world: &World, ray: &Ray, depth: u32) -> (Color, Aov) {
+    match world.interferences_with_ray(ray).hit() {
+        Some(hit) => (world.shade_hit(&hit, depth), Aov::from_hit(Some(&hit))),
+        None => (world.environment().color_at(&ray.dir), Aov::default()),
     }
 }
 
+/// Inverts the view transform of a camera.
+fn invert_view_transform(transform: &Matrix) -> Result<Matrix, Error> {
+    transform
+        .inverse()
+        .ok_or(Error::InvalidCamera("view transform is not invertible"))
+}
+
 /// Streaming iterator over the scanlines produced by [`Camera::render`].
 #[derive(Debug)]
 pub struct Stream<'a, 'b> {
@@ -181,12 +534,15 @@ pub struct Stream<'a, 'b> {
 
 impl<'a, 'b> Stream<'a, 'b> {
     /// Creates a new stream that will render `world` as seen by `camera`.
+    ///
+    /// Scanlines are rendered in batches as large as the current rayon thread pool, so a custom
+    /// pool can be [installed](rayon::ThreadPool::install) to limit the number of threads used.
     pub fn new(camera: &'a Camera, world: &'b World) -> Self {
         Self {
             camera,
             world,
             canvas: Canvas::new(camera.horizontal_size(), camera.vertical_size()),
-            threads: num_cpus::get(),
+            threads: rayon::current_num_threads(),
             current_line: 0,
         }
     }
@@ -196,42 +552,239 @@ impl<'a, 'b> Stream<'a, 'b> {
         &self.canvas
     }
 
+    /// Returns the number of scanlines that have been fully rendered so far.
+    pub fn completed_lines(&self) -> u32 {
+        self.current_line.min(self.total_lines())
+    }
+
+    /// Returns the total number of scanlines to be rendered by this stream.
+    pub fn total_lines(&self) -> u32 {
+        self.camera.vertical_size()
+    }
+
     /// Computes and return the next scanline, returning `true` if more processing is needed.
     pub fn advance(&mut self) -> bool {
         if self.current_line >= self.camera.vertical_size() {
             return false;
         }
 
-        let Stream { camera, world, .. } = self;
+        self.render_lines(self.current_line, self.threads as u32);
+        self.current_line += self.threads as u32;
+        true
+    }
 
-        let y = self.current_line;
+    /// Finish rendering this stream and return the final canvas.
+    ///
+    /// All the remaining scanlines are rendered at once, so that no thread is left waiting for
+    /// the others until the whole frame is complete.
+    pub fn finalize(mut self) -> Canvas {
+        let remaining = self.total_lines() - self.completed_lines();
 
+        self.render_lines(self.current_line, remaining);
+        self.current_line += remaining;
         self.canvas
-            .scanlines_mut(self.current_line as usize, self.threads)
+    }
+
+    /// Renders `count` scanlines starting from `start`.
+    fn render_lines(&mut self, start: u32, count: u32) {
+        self.camera.render_scanlines(
+            self.world,
+            start,
+            self.canvas.scanlines_mut(start as usize, count as usize),
+        );
+    }
+}
+
+/// Largest side of the first level of a [`Preview`], in pixels.
+pub const PREVIEW_SIZE: u32 = 64;
+
+/// Progressive renderer producing a pyramid of images, as returned by [`Camera::preview`].
+///
+/// The first level of the pyramid is at most [`PREVIEW_SIZE`] pixels wide and tall, and each
+/// following level doubles its resolution up to the camera's. Every level only renders the pixels
+/// missing from the previous one, so the whole pyramid costs as much as a single render, and the
+/// last level is identical to the output of [`Camera::render`].
+///
+/// The [AOVs](Aov) of the pixels are recorded along with their colors.
+///
+/// Iterating over a preview renders and returns each level in turn.
+#[derive(Debug)]
+pub struct Preview<'a, 'b> {
+    camera: &'a Camera,
+    world: &'b World,
+    canvas: Canvas,
+    aovs: Vec<Aov>,
+    threads: usize,
+    stride: u32,
+    current_row: u32,
+    completed_stride: Option<u32>,
+}
+
+impl<'a, 'b> Preview<'a, 'b> {
+    /// Creates a new preview of `world` as seen by `camera`.
+    ///
+    /// Rows are rendered in batches as large as the current rayon thread pool, like [`Stream`].
+    pub fn new(camera: &'a Camera, world: &'b World) -> Self {
+        let (width, height) = camera.size;
+        let stride = width.max(height).div_ceil(PREVIEW_SIZE).next_power_of_two();
+
+        Self {
+            camera,
+            world,
+            canvas: Canvas::new(width, height),
+            aovs: vec![Aov::default(); width as usize * height as usize],
+            threads: rayon::current_num_threads(),
+            stride,
+            current_row: 0,
+            completed_stride: None,
+        }
+    }
+
+    /// Returns the distance between the pixels sampled by the level being rendered, which is
+    /// `1` for the full resolution image.
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// Returns the number of rows of the level being rendered that are complete.
+    ///
+    /// At full resolution, these are the scanlines of [`Preview::canvas`] that are final.
+    pub fn completed_rows(&self) -> u32 {
+        self.current_row
+    }
+
+    /// Returns the number of rows of the level being rendered.
+    pub fn total_rows(&self) -> u32 {
+        self.camera.vertical_size().div_ceil(self.stride)
+    }
+
+    /// Returns the full resolution canvas holding all the pixels rendered so far.
+    ///
+    /// The pixels that have not been sampled by any level yet are black.
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    /// Returns the AOVs of the pixels of [`Preview::canvas`], row by row.
+    ///
+    /// The pixels that have not been sampled by any level yet have the default AOVs.
+    pub fn aovs(&self) -> &[Aov] {
+        &self.aovs
+    }
+
+    /// Returns true once the full resolution image is complete.
+    pub fn is_finished(&self) -> bool {
+        self.completed_stride == Some(1)
+    }
+
+    /// Returns the last level of the pyramid that was completed, if any.
+    pub fn level(&self) -> Option<Canvas> {
+        let stride = self.completed_stride?;
+        let (width, height) = self.camera.size;
+
+        let mut level = Canvas::new(width.div_ceil(stride), height.div_ceil(stride));
+        for (y, row) in self.canvas.scanlines().step_by(stride as usize).enumerate() {
+            for (x, &c) in row.iter().step_by(stride as usize).enumerate() {
+                level.put(x as u32, y as u32, c);
+            }
+        }
+
+        Some(level)
+    }
+
+    /// Renders the next rows of the current level, returning `true` if more processing is needed.
+    pub fn advance(&mut self) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+
+        let count = (self.threads as u32).min(self.total_rows() - self.current_row);
+        self.render_rows(self.current_row, count);
+        self.current_row += count;
+
+        if self.current_row == self.total_rows() {
+            self.completed_stride = Some(self.stride);
+            if self.stride > 1 {
+                self.stride /= 2;
+                self.current_row = 0;
+            }
+        }
+
+        !self.is_finished()
+    }
+
+    /// Renders `count` rows of the current level, starting from `start`.
+    fn render_rows(&mut self, start: u32, count: u32) {
+        let Preview {
+            camera,
+            world,
+            canvas,
+            aovs,
+            stride,
+            completed_stride,
+            ..
+        } = self;
+
+        let stride = *stride;
+        let width = camera.horizontal_size();
+
+        // The pixels lying on the grid of the previous level have already been rendered
+        let previous = *completed_stride;
+        let known = move |x: u32, y: u32| match previous {
+            Some(s) => x.is_multiple_of(s) && y.is_multiple_of(s),
+            None => false,
+        };
+
+        let rows = canvas
+            .scanlines_mut((start * stride) as usize, (count * stride) as usize)
+            .step_by(stride as usize)
+            .zip(
+                aovs.chunks_mut(width as usize)
+                    .skip((start * stride) as usize)
+                    .step_by(stride as usize),
+            )
             .enumerate()
-            .par_bridge()
-            .for_each(|(i, line)| {
-                for x in 0..camera.horizontal_size() {
-                    let ray = camera.ray_to(x, y + i as u32);
-                    let color = world.color_at(&ray, camera.recursion_limit);
+            .map(|(i, (row, aovs))| ((start + i as u32) * stride, row, aovs))
+            .collect::<Vec<_>>();
 
-                    line[x as usize] = color;
-                }
-            });
+        rows.into_par_iter().for_each(|(y, row, aovs)| {
+            let _scope = profile::scope(Phase::PrimaryRays);
 
-        self.current_line += self.threads as u32;
-        true
+            let xs = (0..width).step_by(stride as usize);
+            let rays = camera.rays_for_span(y, xs.clone());
+            let depth = camera.recursion_limit_for(world);
+
+            for (x, ray) in xs.zip(rays) {
+                if !known(x, y) {
+                    let (color, aov) = camera.shade_pixel(world, x, y, &ray, depth);
+                    row[x as usize] = color;
+                    aovs[x as usize] = aov;
+                }
+            }
+        });
     }
+}
 
-    /// Finish rendering this stream and return the final canvas.
-    pub fn finalize(mut self) -> Canvas {
-        while self.advance() {}
-        self.canvas
+impl Iterator for Preview<'_, '_> {
+    type Item = Canvas;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let completed = self.completed_stride;
+
+        while self.completed_stride == completed && self.advance() {}
+
+        if self.completed_stride == completed {
+            None
+        } else {
+            self.level()
+        }
     }
 }
 
 impl CameraPrefab {
     /// Builds a `Camera` from this prefab.
+    ///
+    /// See [`CameraPrefab::try_build`] for a version validating the prefab first.
     pub fn build(self) -> Camera {
         Camera::new_with_transform(
             self.width,
@@ -240,6 +793,25 @@ impl CameraPrefab {
             Matrix::look_at(self.from, self.to, self.up),
         )
     }
+
+    /// Builds a `Camera` from this prefab, checking that it describes a valid projection.
+    ///
+    /// Fails with [`Error::InvalidCamera`] if the canvas is empty, the field of view is not
+    /// within `(0, 180)` degrees, or the view direction is zero or parallel to the up vector.
+    pub fn try_build(self) -> Result<Camera, Error> {
+        if (self.to - self.from).cross(&self.up).length() < EPSILON {
+            return Err(Error::InvalidCamera(
+                "view direction is zero or parallel to the up vector",
+            ));
+        }
+
+        Camera::try_new(
+            self.width,
+            self.height,
+            self.fov.to_radians(),
+            Matrix::look_at(self.from, self.to, self.up),
+        )
+    }
 }
 
 #[cfg(all(feature = "serde-support", test))]
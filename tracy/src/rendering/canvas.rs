@@ -1,8 +1,15 @@
 //! Virtual canvas to which the final image will be rendered.
 
-use std::slice;
+use alloc::{vec, vec::Vec};
+use core::slice;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
 
-use super::Color;
+use crate::Error;
+
+use super::{Color, Kernel};
+#[cfg(feature = "std")]
+use super::{PpmFormat, PpmWriter};
 
 /// A canvas is a rectangular grid of pixels, each with its own [`Color`].
 #[derive(Debug, Default, Clone)]
@@ -46,64 +53,248 @@ impl Canvas {
         self.grid.iter_mut()
     }
 
+    /// Returns an iterator over the pixels of this canvas along with their `(x, y)` position.
+    ///
+    /// The canvas is traversed top-to-bottom, left-to-right.
+    pub fn enumerate_pixels(&self) -> impl Iterator<Item = (u32, u32, &Color)> {
+        self.rows()
+            .zip(0..)
+            .flat_map(|(row, y)| row.iter().zip(0..).map(move |(c, x)| (x, y, c)))
+    }
+
+    /// Returns a mutable iterator over the pixels of this canvas along with their `(x, y)`
+    /// position.
+    ///
+    /// The canvas is traversed top-to-bottom, left-to-right.
+    pub fn enumerate_pixels_mut(&mut self) -> impl Iterator<Item = (u32, u32, &mut Color)> {
+        self.rows_mut()
+            .zip(0..)
+            .flat_map(|(row, y)| row.iter_mut().zip(0..).map(move |(c, x)| (x, y, c)))
+    }
+
+    /// Returns an iterator over the rows of this canvas, from top to bottom.
+    pub fn rows(&self) -> slice::ChunksExact<'_, Color> {
+        // An empty canvas has no rows, whatever its width
+        self.grid.chunks_exact(self.width.max(1) as usize)
+    }
+
+    /// Returns a mutable iterator over the rows of this canvas, from top to bottom.
+    pub fn rows_mut(&mut self) -> slice::ChunksExactMut<'_, Color> {
+        self.grid.chunks_exact_mut(self.width.max(1) as usize)
+    }
+
+    /// Returns an iterator over the scanlines of this canvas, from top to bottom.
+    ///
+    /// This is the same as [`Canvas::rows`].
+    pub fn scanlines(&self) -> impl Iterator<Item = &[Color]> {
+        self.rows()
+    }
+
     /// Returns an iterator over at most `n` contiguous scanlines of `self`, starting at `start`.
     pub fn scanlines_mut(&mut self, start: usize, n: usize) -> impl Iterator<Item = &mut [Color]> {
-        self.grid
-            .chunks_exact_mut(self.width as usize)
-            .skip(start)
-            .take(n)
+        self.rows_mut().skip(start).take(n)
     }
 
     /// Sets the pixel at position `(x,y)` to the specified color.
     ///
-    /// # Panics
-    ///
-    /// Panics if the specified position does not lie within the canvas.
+    /// Positions that do not lie within the canvas are ignored, see [`Canvas::try_put`] to detect
+    /// them instead.
     pub fn put(&mut self, x: u32, y: u32, c: Color) {
+        let _ = self.try_put(x, y, c);
+    }
+
+    /// Sets the pixel at position `(x,y)` to the specified color.
+    ///
+    /// Fails with [`Error::OutOfBounds`] if the specified position does not lie within the
+    /// canvas.
+    pub fn try_put(&mut self, x: u32, y: u32, c: Color) -> Result<(), Error> {
         if x < self.width() && y < self.height() {
             self.grid[(y * self.width + x) as usize] = c;
+            Ok(())
+        } else {
+            Err(Error::OutOfBounds {
+                position: (x, y),
+                size: (self.width, self.height),
+            })
         }
     }
 
     /// Returns the color of the pixel at position `(x,y)`, or `None` is the position is not within
     /// the canvas.
     pub fn get(&self, x: u32, y: u32) -> Option<&Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
         self.grid.get((y * self.width + x) as usize)
     }
 
-    /// Converts the canvas' contents to PPM format.
-    pub fn convert_to_ppm(&self) -> String {
-        let mut ppm = format!("P3\n{} {}\n{}\n", self.width(), self.height(), 255);
-        ppm.reserve((self.width() * self.height() * 12) as usize);
-
-        for y in 0..self.height() {
-            let mut line_len = 0;
-
-            for x in 0..self.width() {
-                let (r, g, b) = self.get(x, y).unwrap().to_rgb888();
-
-                // Lines should not be longer than 70 characters in PPM files.
-                // Iterate over each color component in order to split lines as close as possible
-                // to the 70 character mark.
-                for val in &[r, g, b] {
-                    let s = format!("{} ", val);
-
-                    // Swap out the last space for a newline and reset the length counter
-                    if line_len + s.len() > 70 {
-                        ppm.pop();
-                        ppm.push('\n');
-                        line_len = 0;
-                    }
-
-                    ppm += &s;
-                    line_len += s.len();
+    /// Returns a copy of `self` convolved with `kernel`.
+    ///
+    /// Pixels beyond the edges of the canvas are taken to be the same as the closest edge pixel.
+    pub fn convolve(&self, kernel: &Kernel) -> Canvas {
+        let (rx, ry) = ((kernel.width() / 2) as i64, (kernel.height() / 2) as i64);
+        let (w, h) = (self.width as i64, self.height as i64);
+
+        let mut out = Canvas::new(self.width, self.height);
+        for (x, y, pixel) in out.enumerate_pixels_mut() {
+            let mut acc = Color::BLACK;
+
+            for (i, &weight) in kernel.weights().iter().enumerate() {
+                let dx = (i % kernel.width()) as i64 - rx;
+                let dy = (i / kernel.width()) as i64 - ry;
+                let sx = (x as i64 + dx).clamp(0, w - 1);
+                let sy = (y as i64 + dy).clamp(0, h - 1);
+
+                acc += self.grid[(sy * w + sx) as usize] * weight;
+            }
+
+            *pixel = acc;
+        }
+        out
+    }
+
+    /// Creates a canvas from the contents of a binary (P6) PPM file with 8-bit channels, or
+    /// returns `None` if `data` is not a valid PPM image.
+    pub fn from_binary_ppm(data: &[u8]) -> Option<Self> {
+        let mut fields = Vec::with_capacity(4);
+        let mut pos = 0;
+
+        // The header is made of four whitespace-separated fields, possibly interleaved with
+        // comments, followed by a single whitespace character.
+        while fields.len() < 4 {
+            match data.get(pos)? {
+                b'#' => pos += data[pos..].iter().position(|&c| c == b'\n')?,
+                c if c.is_ascii_whitespace() => pos += 1,
+                _ => {
+                    let len = data[pos..]
+                        .iter()
+                        .position(|c| c.is_ascii_whitespace())
+                        .unwrap_or(data.len() - pos);
+
+                    fields.push(core::str::from_utf8(&data[pos..pos + len]).ok()?);
+                    pos += len;
                 }
             }
+        }
 
-            ppm.pop();
-            ppm.push('\n');
+        let (width, height) = (fields[1].parse().ok()?, fields[2].parse::<u32>().ok()?);
+        if fields[0] != "P6" || fields[3] != "255" {
+            return None;
         }
 
-        ppm
+        let size = (width as usize)
+            .checked_mul(height as usize)?
+            .checked_mul(3)?;
+
+        let pixels = data.get(pos + 1..)?;
+        if pixels.len() != size {
+            return None;
+        }
+
+        Some(Self {
+            grid: pixels
+                .chunks_exact(3)
+                .map(|c| Color::from_rgb888(c[0], c[1], c[2]))
+                .collect(),
+            width,
+            height,
+        })
+    }
+
+    /// Compares the pixels of `self` and `other` once quantized to 8 bits per channel, or returns
+    /// `None` if the two canvases have different sizes.
+    #[cfg(feature = "std")]
+    pub fn diff(&self, other: &Canvas) -> Option<CanvasDiff> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+
+        let mut diff = CanvasDiff::default();
+        let mut sum = 0.;
+        let mut sum_sq = 0.;
+
+        for (a, b) in self.iter().zip(other.iter()) {
+            let (a, b) = (a.to_rgb888(), b.to_rgb888());
+            let errors = [
+                (a.0 as i32 - b.0 as i32).abs(),
+                (a.1 as i32 - b.1 as i32).abs(),
+                (a.2 as i32 - b.2 as i32).abs(),
+            ];
+
+            for &e in &errors {
+                diff.max_error = diff.max_error.max(e as u8);
+                sum += e as f64;
+                sum_sq += (e * e) as f64;
+            }
+
+            if errors.iter().any(|&e| e > 0) {
+                diff.differing_pixels += 1;
+            }
+        }
+
+        let samples = (self.grid.len() * 3).max(1) as f64;
+        diff.mean_error = sum / samples;
+        diff.rmse = (sum_sq / samples).sqrt();
+
+        Some(diff)
+    }
+
+    /// Converts the canvas' contents to PPM format.
+    #[cfg(feature = "std")]
+    pub fn convert_to_ppm(&self) -> String {
+        let ppm = self
+            .write_ppm(Vec::new(), PpmFormat::Ascii)
+            .expect("writing to a vector cannot fail");
+
+        String::from_utf8(ppm).expect("plain PPM files are ASCII")
+    }
+
+    /// Converts the canvas' contents to binary (P6) PPM format.
+    #[cfg(feature = "std")]
+    pub fn convert_to_binary_ppm(&self) -> Vec<u8> {
+        let ppm = Vec::with_capacity((self.width() * self.height() * 3) as usize + 32);
+
+        self.write_ppm(ppm, PpmFormat::Binary)
+            .expect("writing to a vector cannot fail")
+    }
+
+    /// Writes the canvas' contents to `w` in PPM format, returning `w`.
+    #[cfg(feature = "std")]
+    pub fn write_ppm<W: Write>(&self, w: W, format: PpmFormat) -> io::Result<W> {
+        let mut writer = PpmWriter::new(w, self.width(), self.height(), format)?;
+        for row in self.scanlines() {
+            writer.write_row(row)?;
+        }
+        writer.finish()
+    }
+}
+
+/// Metrics of the difference between two canvases, as returned by [`Canvas::diff`].
+///
+/// All errors are measured on 8-bit channel values, ie. in the `[0, 255]` range.
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CanvasDiff {
+    /// Largest difference of any channel of any pixel.
+    pub max_error: u8,
+    /// Average difference over all channels of all pixels.
+    pub mean_error: f64,
+    /// Root mean square of the differences over all channels of all pixels.
+    pub rmse: f64,
+    /// Number of pixels with at least one differing channel.
+    pub differing_pixels: usize,
+}
+
+#[cfg(feature = "std")]
+impl CanvasDiff {
+    /// Returns the peak signal-to-noise ratio in decibels, which is infinite for identical
+    /// canvases.
+    pub fn psnr(&self) -> f64 {
+        20. * (255. / self.rmse).log10()
+    }
+
+    /// Returns true if the two canvases are identical.
+    pub fn is_identical(&self) -> bool {
+        self.differing_pixels == 0
     }
 }
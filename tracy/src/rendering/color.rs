@@ -1,10 +1,20 @@
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
+#[cfg(feature = "serde-support")]
+use alloc::string::String;
+#[cfg(feature = "serde-support")]
+use core::convert::TryFrom;
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
+
+use crate::math::float;
 
 /// A color in RGB format.
+///
+/// When deserialized, a color can be written either as a list of its components, or as a string
+/// holding a hex code or a CSS color name, as accepted by [`Color::from_hex`] and
+/// [`Color::from_css_name`].
 #[cfg_attr(
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize),
-    serde(from = "[f32; 3]")
+    serde(try_from = "ColorRepr")
 )]
 #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Color {
@@ -28,6 +38,29 @@ impl From<[f32; 3]> for Color {
     }
 }
 
+/// Representations of a color in scene files.
+#[cfg(feature = "serde-support")]
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum ColorRepr {
+    Components([f32; 3]),
+    Code(String),
+}
+
+#[cfg(feature = "serde-support")]
+impl TryFrom<ColorRepr> for Color {
+    type Error = String;
+
+    fn try_from(repr: ColorRepr) -> Result<Self, Self::Error> {
+        match repr {
+            ColorRepr::Components(c) => Ok(c.into()),
+            ColorRepr::Code(code) => Color::from_hex(&code)
+                .or_else(|| Color::from_css_name(&code))
+                .ok_or_else(|| alloc::format!("invalid color: {}", code)),
+        }
+    }
+}
+
 impl Color {
     /// The black color.
     pub const BLACK: Color = Color::new(0.0, 0.0, 0.0);
@@ -35,6 +68,48 @@ impl Color {
     /// The white color.
     pub const WHITE: Color = Color::new(1.0, 1.0, 1.0);
 
+    /// The CSS `silver` color (`#c0c0c0`).
+    pub const SILVER: Color = Color::new(0.752_941_2, 0.752_941_2, 0.752_941_2);
+
+    /// The CSS `gray` color (`#808080`).
+    pub const GRAY: Color = Color::new(0.501_960_8, 0.501_960_8, 0.501_960_8);
+
+    /// The CSS `maroon` color (`#800000`).
+    pub const MAROON: Color = Color::new(0.501_960_8, 0.0, 0.0);
+
+    /// The CSS `red` color (`#ff0000`).
+    pub const RED: Color = Color::new(1.0, 0.0, 0.0);
+
+    /// The CSS `purple` color (`#800080`).
+    pub const PURPLE: Color = Color::new(0.501_960_8, 0.0, 0.501_960_8);
+
+    /// The CSS `fuchsia` color (`#ff00ff`).
+    pub const FUCHSIA: Color = Color::new(1.0, 0.0, 1.0);
+
+    /// The CSS `green` color (`#008000`). See [`Color::LIME`] for pure green.
+    pub const GREEN: Color = Color::new(0.0, 0.501_960_8, 0.0);
+
+    /// The CSS `lime` color (`#00ff00`).
+    pub const LIME: Color = Color::new(0.0, 1.0, 0.0);
+
+    /// The CSS `olive` color (`#808000`).
+    pub const OLIVE: Color = Color::new(0.501_960_8, 0.501_960_8, 0.0);
+
+    /// The CSS `yellow` color (`#ffff00`).
+    pub const YELLOW: Color = Color::new(1.0, 1.0, 0.0);
+
+    /// The CSS `navy` color (`#000080`).
+    pub const NAVY: Color = Color::new(0.0, 0.0, 0.501_960_8);
+
+    /// The CSS `blue` color (`#0000ff`).
+    pub const BLUE: Color = Color::new(0.0, 0.0, 1.0);
+
+    /// The CSS `teal` color (`#008080`).
+    pub const TEAL: Color = Color::new(0.0, 0.501_960_8, 0.501_960_8);
+
+    /// The CSS `aqua` color (`#00ffff`).
+    pub const AQUA: Color = Color::new(0.0, 1.0, 1.0);
+
     /// Creates a new color from its components.
     pub const fn new(r: f32, g: f32, b: f32) -> Self {
         Self { r, g, b }
@@ -48,12 +123,151 @@ impl Color {
             && (self.b - other.b).abs() < max_abs_diff
     }
 
+    /// Creates a new color from its RGB888 representation.
+    pub fn from_rgb888(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r as f32 / 255., g as f32 / 255., b as f32 / 255.)
+    }
+
+    /// Creates a new color from a hex code such as `#ffcc00`, or `None` if `code` is not valid.
+    ///
+    /// The leading `#` is optional, and the three-digit shorthand `#fc0` is accepted as well.
+    /// Channels are mapped to `[0, 1]` the same way as [`Color::from_rgb888`].
+    pub fn from_hex(code: &str) -> Option<Self> {
+        let code = code.strip_prefix('#').unwrap_or(code);
+        if !code.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let channel =
+            |i: usize, len: usize| u8::from_str_radix(&code[i * len..(i + 1) * len], 16).ok();
+
+        match code.len() {
+            6 => Some(Self::from_rgb888(
+                channel(0, 2)?,
+                channel(1, 2)?,
+                channel(2, 2)?,
+            )),
+            3 => Some(Self::from_rgb888(
+                channel(0, 1)? * 0x11,
+                channel(1, 1)? * 0x11,
+                channel(2, 1)? * 0x11,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns the color with the given CSS name, or `None` if `name` is not one of the sixteen
+    /// basic CSS colors. Names are case-insensitive.
+    pub fn from_css_name(name: &str) -> Option<Self> {
+        const NAMES: [(&str, Color); 16] = [
+            ("black", Color::BLACK),
+            ("silver", Color::SILVER),
+            ("gray", Color::GRAY),
+            ("white", Color::WHITE),
+            ("maroon", Color::MAROON),
+            ("red", Color::RED),
+            ("purple", Color::PURPLE),
+            ("fuchsia", Color::FUCHSIA),
+            ("green", Color::GREEN),
+            ("lime", Color::LIME),
+            ("olive", Color::OLIVE),
+            ("yellow", Color::YELLOW),
+            ("navy", Color::NAVY),
+            ("blue", Color::BLUE),
+            ("teal", Color::TEAL),
+            ("aqua", Color::AQUA),
+        ];
+
+        NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, c)| c)
+    }
+
+    /// Creates a new color from its hue in degrees, saturation and value, the last two in
+    /// `[0, 1]`.
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let c = v * s;
+        Self::from_hue(h, c, v - c)
+    }
+
+    /// Creates a new color from its hue in degrees, saturation and lightness, the last two in
+    /// `[0, 1]`.
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Self {
+        let c = (1. - (2. * l - 1.).abs()) * s;
+        Self::from_hue(h, c, l - c / 2.)
+    }
+
+    /// Returns the hue in degrees, saturation and value of `self`.
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (max, min) = self.extrema();
+        let s = if max > 0. { (max - min) / max } else { 0. };
+
+        (self.hue(), s, max)
+    }
+
+    /// Returns the hue in degrees, saturation and lightness of `self`.
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (max, min) = self.extrema();
+        let l = (max + min) / 2.;
+        let s = if max > min {
+            (max - min) / (1. - (2. * l - 1.).abs())
+        } else {
+            0.
+        };
+
+        (self.hue(), s, l)
+    }
+
     /// Returns the RGB888 representation of `self`.
     pub fn to_rgb888(self) -> (u8, u8, u8) {
         (
-            (self.r * 255.).clamp(0., 255.).round() as u8,
-            (self.g * 255.).clamp(0., 255.).round() as u8,
-            (self.b * 255.).clamp(0., 255.).round() as u8,
+            float::round((self.r * 255.).clamp(0., 255.)) as u8,
+            float::round((self.g * 255.).clamp(0., 255.)) as u8,
+            float::round((self.b * 255.).clamp(0., 255.)) as u8,
+        )
+    }
+
+    /// Creates a color from its hue in degrees, chroma and the offset added to all channels.
+    fn from_hue(h: f32, c: f32, m: f32) -> Self {
+        let h = (h % 360. + 360.) % 360. / 60.;
+        let x = c * (1. - (h % 2. - 1.).abs());
+
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.),
+            1 => (x, c, 0.),
+            2 => (0., c, x),
+            3 => (0., x, c),
+            4 => (x, 0., c),
+            _ => (c, 0., x),
+        };
+
+        Self::new(r + m, g + m, b + m)
+    }
+
+    /// Returns the hue of `self` in degrees, or zero for shades of gray.
+    fn hue(self) -> f32 {
+        let (max, min) = self.extrema();
+        let d = max - min;
+
+        let h = if d == 0. {
+            0.
+        } else if max == self.r {
+            (self.g - self.b) / d
+        } else if max == self.g {
+            (self.b - self.r) / d + 2.
+        } else {
+            (self.r - self.g) / d + 4.
+        };
+
+        (h * 60. + 360.) % 360.
+    }
+
+    /// Returns the largest and smallest channels of `self`.
+    fn extrema(self) -> (f32, f32) {
+        (
+            self.r.max(self.g).max(self.b),
+            self.r.min(self.g).min(self.b),
         )
     }
 }
@@ -213,7 +427,7 @@ impl DivAssign<f32> for Color {
 
 #[cfg(all(feature = "serde-support", test))]
 mod tests {
-    use serde_test::{assert_de_tokens, Token};
+    use serde_test::{assert_de_tokens, assert_de_tokens_error, Token};
 
     use super::*;
 
@@ -232,4 +446,11 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn deserialize_from_code() {
+        assert_de_tokens(&Color::from_rgb888(255, 204, 0), &[Token::Str("#ffcc00")]);
+        assert_de_tokens(&Color::TEAL, &[Token::Str("Teal")]);
+        assert_de_tokens_error::<Color>(&[Token::Str("#ffcc0")], "invalid color: #ffcc0");
+    }
 }
@@ -0,0 +1,67 @@
+use crate::{
+    math::{Point3, Vec3},
+    shape::{Cube, Sphere},
+};
+
+use super::{Color, Pattern};
+
+/// The surroundings of a world, seen by the rays which do not hit any object.
+///
+/// The default environment is black, as in the book.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Environment {
+    /// A single color in every direction.
+    Solid(Color),
+    /// Vertical gradient between two colors, eg. for a simple sky.
+    Gradient {
+        /// The color seen when looking straight down.
+        bottom: Color,
+        /// The color seen when looking straight up.
+        top: Color,
+    },
+    /// A pattern wrapped around a sphere surrounding the world, using the spherical texture
+    /// mapping.
+    ///
+    /// Image patterns should use [`TextureMapping::Shape`](super::TextureMapping::Shape), so
+    /// that an equirectangular image covers the whole sphere.
+    Spherical(Pattern),
+    /// A pattern wrapped around a cube surrounding the world, using the cube texture mapping.
+    ///
+    /// Image patterns should use [`TextureMapping::Shape`](super::TextureMapping::Shape), with
+    /// the six faces laid out as in the texture mapping of [`Cube`].
+    Cube(Pattern),
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::Solid(Color::BLACK)
+    }
+}
+
+impl Environment {
+    /// Returns the color of the environment seen in direction `dir`.
+    pub fn color_at(&self, dir: &Vec3) -> Color {
+        let dir = dir.normalize();
+
+        match self {
+            &Environment::Solid(c) => c,
+            Environment::Gradient { bottom, top } => {
+                bottom + (top - bottom) * ((dir.y + 1.0) / 2.0)
+            }
+            Environment::Spherical(pattern) => {
+                pattern.color_on(&Sphere, &Point3::new(dir.x, dir.y, dir.z))
+            }
+            Environment::Cube(pattern) => {
+                // Project the direction on the surface of the cube
+                let max = dir.x.abs().max(dir.y.abs()).max(dir.z.abs());
+                let p = Point3::new(dir.x / max, dir.y / max, dir.z / max);
+                pattern.color_on(&Cube, &p)
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Convolution kernels for post-processing rendered images.
+
+use alloc::{vec, vec::Vec};
+use core::f32::consts::E;
+
+use crate::{math::float, Error};
+
+/// A rectangular grid of weights to be convolved with a [`Canvas`](super::Canvas).
+///
+/// Kernels have odd sizes, so that they are centered on the pixel being filtered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kernel {
+    weights: Vec<f32>,
+    width: usize,
+    height: usize,
+}
+
+impl Kernel {
+    /// Creates a new kernel from its weights, in row-major order.
+    ///
+    /// Fails with [`Error::KernelSize`] if either side of the kernel is not odd, or if the number
+    /// of weights does not match its size.
+    pub fn new(width: usize, height: usize, weights: Vec<f32>) -> Result<Self, Error> {
+        if width.is_multiple_of(2) || height.is_multiple_of(2) || weights.len() != width * height {
+            return Err(Error::KernelSize {
+                width,
+                height,
+                len: weights.len(),
+            });
+        }
+
+        Ok(Self {
+            weights,
+            width,
+            height,
+        })
+    }
+
+    /// Creates a square kernel of side `2 * radius + 1` averaging all the pixels it covers.
+    pub fn box_blur(radius: usize) -> Self {
+        let side = 2 * radius + 1;
+        let n = side * side;
+
+        Self {
+            weights: vec![1. / n as f32; n],
+            width: side,
+            height: side,
+        }
+    }
+
+    /// Creates a square kernel of side `2 * radius + 1` sampling a gaussian with standard
+    /// deviation `sigma`, normalized so that its weights add up to one.
+    ///
+    /// A gaussian narrower than a pixel leaves images unchanged, so the kernel is the identity if
+    /// `sigma` is not positive.
+    pub fn gaussian(radius: usize, sigma: f32) -> Self {
+        let side = 2 * radius + 1;
+        let r = radius as f32;
+        let s = 2. * sigma * sigma;
+
+        // Deviations so small that their square is zero would divide zero by zero as well
+        if sigma.is_nan() || sigma <= 0. || s == 0. {
+            let mut weights = vec![0.; side * side];
+            weights[side * side / 2] = 1.;
+
+            return Self {
+                weights,
+                width: side,
+                height: side,
+            };
+        }
+
+        let weights = (0..side * side)
+            .map(|i| {
+                let (x, y) = ((i % side) as f32 - r, (i / side) as f32 - r);
+                float::powf(E, -(x * x + y * y) / s)
+            })
+            .collect();
+
+        Self {
+            weights,
+            width: side,
+            height: side,
+        }
+        .normalized()
+    }
+
+    /// Creates a 3x3 kernel enhancing the difference of each pixel with its neighbors.
+    pub fn sharpen() -> Self {
+        Self {
+            weights: vec![0., -1., 0., -1., 5., -1., 0., -1., 0.],
+            width: 3,
+            height: 3,
+        }
+    }
+
+    /// Creates a 3x3 laplacian kernel, which is zero wherever the image is uniform.
+    pub fn edge_detect() -> Self {
+        Self {
+            weights: vec![-1., -1., -1., -1., 8., -1., -1., -1., -1.],
+            width: 3,
+            height: 3,
+        }
+    }
+
+    /// Returns the width of the kernel.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the kernel.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the weights of the kernel, in row-major order.
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    /// Returns the weight applied to the pixel at offset `(dx, dy)` from the filtered one, or
+    /// `None` if the offset lies outside of the kernel.
+    pub fn get(&self, dx: isize, dy: isize) -> Option<f32> {
+        let x = dx + (self.width / 2) as isize;
+        let y = dy + (self.height / 2) as isize;
+
+        if (0..self.width as isize).contains(&x) && (0..self.height as isize).contains(&y) {
+            Some(self.weights[y as usize * self.width + x as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Scales the weights of the kernel so that they add up to one, preserving the brightness of
+    /// filtered images. Kernels whose weights add up to zero are returned unchanged.
+    pub fn normalized(mut self) -> Self {
+        let sum = self.weights.iter().sum::<f32>();
+        if sum != 0. {
+            for w in &mut self.weights {
+                *w /= sum;
+            }
+        }
+        self
+    }
+}
@@ -1,9 +1,11 @@
 //! Light sources.
 
+use alloc::{vec, vec::Vec};
+
 use crate::{
-    math::{Point3, Vec3},
+    math::{float, Point3, Vec3},
     query::Object,
-    rendering::Color,
+    rendering::{Color, Material},
 };
 
 /// A point light source.
@@ -22,6 +24,8 @@ pub struct PointLight {
     pub intensity: f32,
     /// Whether or not this light should cast shadows.
     pub casts_shadows: bool,
+    /// Falloff of the brightness of the light with distance.
+    pub attenuation: Attenuation,
 }
 
 impl Default for PointLight {
@@ -31,40 +35,333 @@ impl Default for PointLight {
             color: Color::WHITE,
             intensity: 1.0,
             casts_shadows: true,
+            attenuation: Attenuation::default(),
+        }
+    }
+}
+
+/// Falloff of the brightness of a light with the distance `d` from it.
+///
+/// The light reaching a point is divided by `constant + linear * d + quadratic * d²`. The
+/// default only has a constant coefficient of one, which leaves the brightness unchanged at any
+/// distance, as in the book. Physically accurate lights only have a quadratic coefficient.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    /// Coefficient independent of the distance.
+    pub constant: f32,
+    /// Coefficient of the distance.
+    pub linear: f32,
+    /// Coefficient of the square of the distance.
+    pub quadratic: f32,
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+impl Attenuation {
+    /// No falloff at all.
+    pub const NONE: Attenuation = Attenuation {
+        constant: 1.0,
+        linear: 0.0,
+        quadratic: 0.0,
+    };
+
+    /// Returns the fraction of the brightness of a light remaining at distance `d` from it.
+    ///
+    /// The fraction is never greater than one, so that coefficients summing to less than one do
+    /// not amplify lights close to them.
+    pub fn factor(&self, d: f32) -> f32 {
+        let denom = self.constant + self.linear * d + self.quadratic * d * d;
+
+        if denom > 1.0 {
+            1.0 / denom
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A rectangular light source, which casts soft shadows.
+///
+/// The light spans the parallelogram with a corner in `corner` and edges `uvec` and `vvec`. The
+/// parallelogram is split into `usteps` by `vsteps` cells, and each cell is sampled once when
+/// computing the illumination of a point, so that points which only see some of the cells lie in
+/// the penumbra.
+///
+/// By default, each cell is sampled in its center, which still leaves visible bands in the
+/// penumbrae. Assigning a [`Sequence`] to `jitter` offsets the sampled points within their cells.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaLight {
+    /// Position of one of the corners of the light in the world.
+    pub corner: Point3,
+    /// First edge of the light, starting from `corner`.
+    pub uvec: Vec3,
+    /// Number of cells along the first edge.
+    pub usteps: u32,
+    /// Second edge of the light, starting from `corner`.
+    pub vvec: Vec3,
+    /// Number of cells along the second edge.
+    pub vsteps: u32,
+    /// Color of the light source.
+    pub color: Color,
+    /// Brightness of the light source.
+    pub intensity: f32,
+    /// Whether or not this light should cast shadows.
+    pub casts_shadows: bool,
+    /// Offsets of the sampled points within their cells.
+    pub jitter: Sequence,
+    /// Falloff of the brightness of the light with distance, from each sampled point.
+    pub attenuation: Attenuation,
+}
+
+impl Default for AreaLight {
+    fn default() -> Self {
+        Self {
+            corner: (0.0, 0.0, 0.0).into(),
+            uvec: Vec3::unit_x(),
+            usteps: 1,
+            vvec: Vec3::unit_z(),
+            vsteps: 1,
+            color: Color::WHITE,
+            intensity: 1.0,
+            casts_shadows: true,
+            jitter: Sequence::default(),
+            attenuation: Attenuation::default(),
+        }
+    }
+}
+
+impl AreaLight {
+    /// Returns the center of this light.
+    pub fn position(&self) -> Point3 {
+        self.corner + (self.uvec + self.vvec) * 0.5
+    }
+
+    /// Returns the point sampled in the cell of this light at column `u` and row `v`.
+    pub fn point_on(&self, u: u32, v: u32) -> Point3 {
+        let (usteps, vsteps) = self.steps();
+        let cell = (v * usteps + u) as usize;
+
+        self.corner
+            + self.uvec * ((u as f32 + self.jitter.get(2 * cell)) / usteps as f32)
+            + self.vvec * ((v as f32 + self.jitter.get(2 * cell + 1)) / vsteps as f32)
+    }
+
+    /// Returns the number of cells along each edge, treating zero as one.
+    fn steps(&self) -> (u32, u32) {
+        (self.usteps.max(1), self.vsteps.max(1))
+    }
+}
+
+/// A cyclic sequence of numbers, used to jitter the points sampled on an [`AreaLight`].
+///
+/// Rendering is deterministic, so the sequence stands in for a random number generator: the
+/// sample of each cell of a light always uses the same numbers, taken in turn from the sequence
+/// and wrapping around at its end. The numbers should lie in `[0, 1)`.
+///
+/// The default sequence only contains `0.5`, so that lights are sampled in the center of each
+/// cell.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Vec<f32>", into = "Vec<f32>")
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequence {
+    values: Vec<f32>,
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Self { values: vec![0.5] }
+    }
+}
+
+impl From<Vec<f32>> for Sequence {
+    fn from(values: Vec<f32>) -> Self {
+        Self::new(values)
+    }
+}
+
+impl From<Sequence> for Vec<f32> {
+    fn from(seq: Sequence) -> Self {
+        seq.values
+    }
+}
+
+impl Sequence {
+    /// Creates a sequence repeating `values`, or the default sequence if `values` is empty.
+    pub fn new(values: Vec<f32>) -> Self {
+        if values.is_empty() {
+            Self::default()
+        } else {
+            Self { values }
         }
     }
+
+    /// Returns the `i`-th number of this sequence.
+    pub fn get(&self, i: usize) -> f32 {
+        self.values[i % self.values.len()]
+    }
+}
+
+/// Trait of light sources that can illuminate a world.
+///
+/// Lights are made of one or more points, which are sampled in turn to compute the illumination
+/// of a surface.
+pub trait LightSource {
+    /// Returns the color of this light, scaled by its brightness.
+    fn radiance(&self) -> Color;
+
+    /// Returns whether or not this light casts shadows.
+    fn casts_shadows(&self) -> bool;
+
+    /// Returns the falloff of the brightness of this light with distance.
+    fn attenuation(&self) -> &Attenuation;
+
+    /// Returns the number of points sampled on this light.
+    fn sample_count(&self) -> usize;
+
+    /// Returns the `i`-th point sampled on this light, with `i` less than
+    /// [`sample_count`](LightSource::sample_count).
+    fn sample(&self, i: usize) -> Point3;
+}
+
+impl LightSource for PointLight {
+    fn radiance(&self) -> Color {
+        self.color * self.intensity
+    }
+
+    fn casts_shadows(&self) -> bool {
+        self.casts_shadows
+    }
+
+    fn attenuation(&self) -> &Attenuation {
+        &self.attenuation
+    }
+
+    fn sample_count(&self) -> usize {
+        1
+    }
+
+    fn sample(&self, _: usize) -> Point3 {
+        self.position
+    }
+}
+
+impl LightSource for AreaLight {
+    fn radiance(&self) -> Color {
+        self.color * self.intensity
+    }
+
+    fn casts_shadows(&self) -> bool {
+        self.casts_shadows
+    }
+
+    fn attenuation(&self) -> &Attenuation {
+        &self.attenuation
+    }
+
+    fn sample_count(&self) -> usize {
+        let (usteps, vsteps) = self.steps();
+        (usteps * vsteps) as usize
+    }
+
+    fn sample(&self, i: usize) -> Point3 {
+        let (usteps, _) = self.steps();
+        self.point_on(i as u32 % usteps, i as u32 / usteps)
+    }
 }
 
 /// Computes the illumination of a surface point according to the Phong reflection model.
 ///
-/// The `point` is given in world-space coordinates.
-pub fn phong_lighting(
+/// The `point` is given in world-space coordinates. The diffuse and specular contributions are
+/// attenuated with the distance from each point sampled on the light and averaged over them, then
+/// scaled by `light_intensity`, which is the fraction of the light reaching the point: `0.0` when
+/// it is in shadow, `1.0` when it is fully lit. The ambient contribution is not attenuated.
+pub fn phong_lighting<L: LightSource>(
     object: &Object,
-    light: &PointLight,
+    light: &L,
     point: &Point3,
     eye: &Vec3,
     normal: &Vec3,
-    in_shadow: bool,
+    light_intensity: f32,
+) -> Color {
+    occluded_phong_lighting(object, light, point, eye, normal, light_intensity, 1.0)
+}
+
+/// Computes the illumination of a surface point like [`phong_lighting`], with the ambient
+/// contribution scaled by `ambient_visibility`, ie. the fraction of it not occluded by nearby
+/// objects.
+pub(crate) fn occluded_phong_lighting<L: LightSource>(
+    object: &Object,
+    light: &L,
+    point: &Point3,
+    eye: &Vec3,
+    normal: &Vec3,
+    light_intensity: f32,
+    ambient_visibility: f32,
 ) -> Color {
     let material = object.material();
 
     // convert point to local-space coordinates
-    let local_point = object.transform().inverse().unwrap() * point;
+    let local_point = object
+        .inverse_transform()
+        .expect("object transform is not invertible")
+        * point;
 
     // combine the surface color with the light's color/intensity
-    let effective_color = material.color_at(&local_point) * light.color * light.intensity;
-
-    // find the direction to the light source
-    let lightv = (light.position - point).normalize();
+    let effective_color = material.color_on(object.shape(), &local_point) * light.radiance();
 
     // compute the ambient contribution
-    let ambient = effective_color * material.ambient;
+    let ambient = effective_color * (material.ambient * ambient_visibility);
 
     // early exit if the point is in shadow
-    if in_shadow {
+    if light_intensity <= 0.0 {
         return ambient;
     }
 
+    let samples = light.sample_count();
+    let lit = (0..samples).fold(Color::BLACK, |sum, i| {
+        let lightv = light.sample(i) - point;
+        let falloff = light.attenuation().factor(lightv.length());
+
+        sum + diffuse_and_specular(
+            material,
+            effective_color,
+            light.radiance(),
+            &lightv.normalize(),
+            eye,
+            normal,
+        ) * falloff
+    });
+
+    // add the three contributions together to get the final shading
+    ambient + lit * (light_intensity / samples as f32)
+}
+
+/// Computes the diffuse and specular contributions of a light coming from direction `lightv`.
+fn diffuse_and_specular(
+    material: &Material,
+    effective_color: Color,
+    radiance: Color,
+    lightv: &Vec3,
+    eye: &Vec3,
+    normal: &Vec3,
+) -> Color {
     let diffuse;
     let specular;
 
@@ -81,17 +378,17 @@ pub fn phong_lighting(
 
         // reflect_dot_eye is the cosine of the angle between the reflection and eye vectors.
         // A negative number means the light reflects away from the eye.
-        let reflectv = (-lightv).reflect(normal);
+        let reflectv = (-*lightv).reflect(normal);
         let reflect_dot_eye = reflectv.dot(eye);
 
         if reflect_dot_eye <= 0. {
             specular = Color::BLACK;
         } else {
             // compute the specular contribution
-            let factor = reflect_dot_eye.powf(material.shininess);
-            specular = light.color * light.intensity * material.specular * factor;
+            let factor = float::powf(reflect_dot_eye, material.shininess);
+            specular = radiance * material.specular * factor;
         }
     }
-    // add the three contributions together to get the final shading
-    ambient + diffuse + specular
+
+    diffuse + specular
 }
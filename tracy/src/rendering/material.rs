@@ -1,8 +1,8 @@
 //! Materials that can be applied to objects for rendering.
 
-use crate::{math::Point3, rendering::Color};
+use crate::{math::Point3, rendering::Color, shape::Shape};
 
-use super::Pattern;
+use super::{Pattern, PbrMaterial};
 
 /// A material with standard properties.
 #[cfg_attr(
@@ -24,10 +24,76 @@ pub struct Material {
     pub shininess: f32,
     /// 0 for completely opaque materials, 1 for a perfect mirror.
     pub reflective: f32,
+    /// 0 for sharp reflections, up to 1 for reflections blurred over the whole hemisphere.
+    ///
+    /// Rough reflections average several rays spread in a cone around the mirror direction, see
+    /// [`RenderSettings::glossy_samples`](super::RenderSettings::glossy_samples). Ignored with
+    /// the physically-based model, see [`Material::reflection_blur`].
+    pub reflection_roughness: f32,
     /// Larger for materials that let more light through.
     pub transparency: f32,
+    /// 0 for clear materials, up to 1 for refractions blurred over the whole hemisphere, as
+    /// through frosted glass.
+    ///
+    /// Rough refractions average several rays spread in a cone around the refracted direction,
+    /// see [`RenderSettings::translucency_samples`](super::RenderSettings::translucency_samples).
+    pub refraction_roughness: f32,
     /// Degree to which light will bend when entering or exiting the material.
     pub refractive_index: f32,
+    /// How reflected and refracted light are weighted with the viewing angle.
+    pub fresnel: Fresnel,
+    /// Whether reflections are tinted with the color of the surface, as with metals. Ignored with
+    /// the physically-based model, see [`Material::metalness`].
+    pub metallic: bool,
+    /// How the surface is lit by light sources.
+    pub shading: ShadingModel,
+}
+
+/// Models of how surfaces are lit by light sources.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShadingModel {
+    /// The Phong reflection model of the book, using the `diffuse`, `specular` and `shininess`
+    /// of the material.
+    #[default]
+    Phong,
+    /// A physically-based model with the given parameters, see
+    /// [`pbr_lighting`](super::pbr_lighting).
+    ///
+    /// The `diffuse`, `specular` and `shininess` of the material are ignored. Reflections and
+    /// refractions work as with the Phong model, except that their tint and blur follow the
+    /// `metallic` and `roughness` of the model rather than those of the material.
+    Pbr(PbrMaterial),
+}
+
+/// Models of the Fresnel effect, ie. how much light a surface reflects depending on the angle it
+/// is seen from.
+///
+/// When applied, the effect is computed with the Schlick approximation, from the refractive
+/// indices on both sides of the surface.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fresnel {
+    /// Only applied to materials which are both reflective and transparent, as in the book.
+    #[default]
+    Transparent,
+    /// Applied to all materials, so that opaque dielectrics such as plastic or varnished wood
+    /// reflect more at grazing angles.
+    ///
+    /// For metallic materials, the color of the surface is taken as their reflectance when seen
+    /// head-on, which fades to white at grazing angles.
+    Schlick,
+    /// Never applied: reflected and refracted light only depend on the material's `reflective`
+    /// and `transparency`.
+    None,
 }
 
 impl Default for Material {
@@ -39,8 +105,13 @@ impl Default for Material {
             specular: 0.9,
             shininess: 200.0,
             reflective: 0.0,
+            reflection_roughness: 0.0,
             transparency: 0.0,
+            refraction_roughness: 0.0,
             refractive_index: 1.0,
+            fresnel: Fresnel::default(),
+            metallic: false,
+            shading: ShadingModel::default(),
         }
     }
 }
@@ -50,6 +121,38 @@ impl Material {
     pub fn color_at(&self, p: &Point3) -> Color {
         self.pattern.color_at(p)
     }
+
+    /// Returns the color of `self` at local-space coordinates `p`, on the surface of `shape`.
+    ///
+    /// See [`Pattern::color_on`].
+    pub fn color_on(&self, shape: &dyn Shape, p: &Point3) -> Color {
+        self.pattern.color_on(shape, p)
+    }
+
+    /// Returns how much the reflections of `self` are tinted with the color of its surface, from
+    /// `0` to `1`.
+    ///
+    /// This is the `metallic` parameter of the physically-based model, or `1` for Phong materials
+    /// flagged as [`metallic`](Material::metallic).
+    pub fn metalness(&self) -> f32 {
+        match self.shading {
+            ShadingModel::Phong if self.metallic => 1.0,
+            ShadingModel::Phong => 0.0,
+            ShadingModel::Pbr(pbr) => pbr.metallic.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Returns how much the reflections of `self` are blurred, from `0` for sharp reflections to
+    /// `1`.
+    ///
+    /// This is the `roughness` parameter of the physically-based model, or the
+    /// [`reflection_roughness`](Material::reflection_roughness) of Phong materials.
+    pub fn reflection_blur(&self) -> f32 {
+        match self.shading {
+            ShadingModel::Phong => self.reflection_roughness,
+            ShadingModel::Pbr(pbr) => pbr.roughness.clamp(0.0, 1.0),
+        }
+    }
 }
 
 #[cfg(all(feature = "serde-support", test))]
@@ -1,22 +1,46 @@
 //! Rendering primitives and utilities.
 
+#[cfg(feature = "std")]
 mod camera;
 mod canvas;
 mod color;
+mod environment;
+mod filter;
 mod light;
 mod material;
 mod pattern;
+mod pbr;
+#[cfg(feature = "std")]
+mod ppm;
+mod settings;
+#[cfg(feature = "std")]
+mod tonemap;
 
+#[cfg(feature = "std")]
 pub use camera::*;
 pub use canvas::*;
 pub use color::*;
+pub use environment::*;
+pub use filter::*;
 pub use light::*;
 pub use material::*;
 pub use pattern::*;
+pub use pbr::*;
+#[cfg(feature = "std")]
+pub use ppm::*;
+pub use settings::*;
+#[cfg(feature = "std")]
+pub use tonemap::*;
 
-use crate::query::{Object, World};
+#[cfg(feature = "std")]
+use crate::{
+    math::Tolerances,
+    query::{Object, World},
+    Error,
+};
 
 /// Prefab containing all the elements required to build a renderable scene.
+#[cfg(feature = "std")]
 #[cfg_attr(
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
@@ -27,23 +51,67 @@ pub struct ScenePrefab {
     pub camera: CameraPrefab,
     /// The lights in the scene.
     pub lights: Vec<PointLight>,
+    /// The area lights in the scene.
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub area_lights: Vec<AreaLight>,
     /// The list of objects in the scene.
     pub objects: Vec<Object>,
+    /// The tolerances used when rendering the scene.
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub tolerances: Tolerances,
+    /// The recursion limit of the scene, or `None` to use the default one.
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub recursion_limit: Option<u32>,
+    /// The optional rendering effects of the scene.
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub render_settings: RenderSettings,
+    /// The surroundings of the scene, seen by rays missing all the objects.
+    #[cfg_attr(feature = "serde-support", serde(default))]
+    pub environment: Environment,
 }
 
+#[cfg(feature = "std")]
 impl ScenePrefab {
     /// Consumes this prefab and builds the corresponding scene, ie. a world and a camera.
     pub fn build(self) -> (World, Camera) {
         let mut world = World::new();
+        world.set_tolerances(self.tolerances);
+        world.set_render_settings(self.render_settings);
+        world.set_environment(self.environment);
+        if let Some(limit) = self.recursion_limit {
+            world.set_recursion_limit(limit);
+        }
 
         for light in self.lights {
             world.add_light(light);
         }
 
+        for light in self.area_lights {
+            world.add_area_light(light);
+        }
+
         for obj in self.objects {
             world.add(obj);
         }
 
         (world, self.camera.build())
     }
+
+    /// Consumes this prefab and builds the corresponding scene, checking its validity first.
+    ///
+    /// Fails with [`Error::InvalidCamera`] if the camera prefab is not valid, or with
+    /// [`Error::NonInvertibleTransform`] if the transform of any object cannot be inverted.
+    pub fn try_build(self) -> Result<(World, Camera), Error> {
+        self.camera.try_build()?;
+
+        if let Some(object) = self
+            .objects
+            .iter()
+            .position(|obj| obj.inverse_transform().is_none())
+        {
+            return Err(Error::NonInvertibleTransform { object });
+        }
+
+        Ok(self.build())
+    }
 }
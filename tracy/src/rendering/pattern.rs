@@ -1,17 +1,69 @@
-use crate::math::{Matrix, Point3};
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "serde-support")]
+use core::convert::TryFrom;
 
-use super::Color;
+use crate::{
+    math::{float, Matrix, Noise, Point3},
+    shape::{Cylinder, Plane, Shape, Sphere},
+    Error,
+};
+
+use super::{Canvas, Color};
 
 /// A nestable, colored pattern.
 #[cfg_attr(
     feature = "serde-support",
-    derive(serde::Serialize, serde::Deserialize)
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "PatternDef")
 )]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Pattern {
     kind: PatternKind,
-    #[cfg_attr(feature = "serde-support", serde(default))]
     transform: Matrix,
+
+    // Derived parameters
+    #[cfg_attr(feature = "serde-support", serde(skip_serializing))]
+    inverse: Matrix,
+}
+
+/// Serialized form of a [`Pattern`], without its derived parameters.
+#[cfg(feature = "serde-support")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "Pattern")]
+struct PatternDef {
+    kind: PatternKind,
+    #[serde(default)]
+    transform: Matrix,
+}
+
+#[cfg(feature = "serde-support")]
+impl TryFrom<PatternDef> for Pattern {
+    type Error = &'static str;
+
+    fn try_from(def: PatternDef) -> Result<Self, Self::Error> {
+        if let PatternKind::Image {
+            pixels,
+            width,
+            height,
+            ..
+        } = &def.kind
+        {
+            if !image_size_matches(pixels, *width, *height) {
+                return Err("image pattern size does not match its pixels");
+            }
+        }
+
+        let inverse = def
+            .transform
+            .inverse()
+            .ok_or("pattern transform is not invertible")?;
+
+        Ok(Self {
+            kind: def.kind,
+            transform: def.transform,
+            inverse,
+        })
+    }
 }
 
 /// Different kinds of patterns.
@@ -48,25 +100,181 @@ pub enum PatternKind {
     /// The pattern is constant in the `y` coordinate, with gradient stops at each integer
     /// concentric ring on the `xz` plane.
     RadialGradient(Color, Color),
+    /// Another pattern, looked up at points displaced by noise.
+    ///
+    /// Points are moved by up to about `scale` units along each axis, which makes eg. stripes
+    /// and rings look like marble or wood.
+    Perturbed {
+        /// The pattern being perturbed.
+        pattern: Box<Pattern>,
+        /// The noise displacing the points.
+        noise: Noise,
+        /// How far the points are displaced.
+        scale: f32,
+    },
+    /// Gradient between two colors following the value of a fractal noise.
+    Noise {
+        /// The color where the noise is lowest.
+        low: Color,
+        /// The color where the noise is highest.
+        high: Color,
+        /// The noise driving the gradient.
+        noise: Noise,
+        /// How many layers of increasingly fine noise are summed, at least one.
+        #[cfg_attr(feature = "serde-support", serde(default))]
+        octaves: u32,
+    },
+    /// An image applied to the surface as a texture.
+    ///
+    /// Points are mapped to the `(u, v)` coordinates of the image by `mapping`, with `(0, 0)` at
+    /// its bottom left corner, and colors are looked up with `filter`.
+    ///
+    /// Images should be created with [`PatternKind::image`] or [`PatternKind::try_image`], which
+    /// check that the size of the image matches its pixels. Images whose size does not are black.
+    Image {
+        /// The colors of the image, row by row from the top.
+        pixels: Vec<Color>,
+        /// The width of the image, in pixels.
+        width: u32,
+        /// The height of the image, in pixels.
+        height: u32,
+        /// How points are mapped to texture coordinates.
+        #[cfg_attr(feature = "serde-support", serde(default))]
+        mapping: TextureMapping,
+        /// How colors are looked up between pixels.
+        #[cfg_attr(feature = "serde-support", serde(default))]
+        filter: TextureFilter,
+    },
     /// Test pattern that returns a color with the same coordinate of the point hit.
     Test,
 }
 
+/// Mappings of points to the `(u, v)` coordinates of an [`PatternKind::Image`].
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureMapping {
+    /// The mapping of the shape the pattern is applied to, see [`Shape::uv_at`].
+    ///
+    /// When the shape is not known, as with [`Pattern::color_at`], this is the planar mapping.
+    #[default]
+    Shape,
+    /// Repeats the texture over each unit square of the `xz` plane.
+    Planar,
+    /// Wraps the texture around the unit sphere.
+    Spherical,
+    /// Wraps the texture around the Y axis, repeating it every unit along it.
+    Cylindrical,
+}
+
+/// Ways of looking up the color of an [`PatternKind::Image`] between its pixels.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureFilter {
+    /// The color of the closest pixel.
+    #[default]
+    Nearest,
+    /// Linear interpolation of the colors of the four closest pixels.
+    Bilinear,
+}
+
 impl From<Color> for PatternKind {
     fn from(c: Color) -> Self {
         Self::Solid(c)
     }
 }
 
+impl PatternKind {
+    /// Creates an image pattern with the pixels of `canvas`.
+    pub fn image(canvas: &Canvas, mapping: TextureMapping, filter: TextureFilter) -> Self {
+        Self::Image {
+            pixels: canvas.iter().copied().collect(),
+            width: canvas.width(),
+            height: canvas.height(),
+            mapping,
+            filter,
+        }
+    }
+
+    /// Creates an image pattern of `width`×`height` pixels, given row by row from the top.
+    ///
+    /// Fails with [`Error::ImageSize`] if the number of pixels does not match the size.
+    pub fn try_image(
+        pixels: Vec<Color>,
+        width: u32,
+        height: u32,
+        mapping: TextureMapping,
+        filter: TextureFilter,
+    ) -> Result<Self, Error> {
+        if !image_size_matches(&pixels, width, height) {
+            return Err(Error::ImageSize {
+                width,
+                height,
+                len: pixels.len(),
+            });
+        }
+
+        Ok(Self::Image {
+            pixels,
+            width,
+            height,
+            mapping,
+            filter,
+        })
+    }
+
+    /// Creates an image pattern from a decoded image.
+    ///
+    /// Channel values are scaled to `[0, 1]` as they are, without any gamma decoding.
+    #[cfg(feature = "image")]
+    pub fn from_image(
+        image: &image::DynamicImage,
+        mapping: TextureMapping,
+        filter: TextureFilter,
+    ) -> Self {
+        let image = image.to_rgb32f();
+
+        Self::Image {
+            pixels: image
+                .pixels()
+                .map(|&image::Rgb([r, g, b])| Color::new(r, g, b))
+                .collect(),
+            width: image.width(),
+            height: image.height(),
+            mapping,
+            filter,
+        }
+    }
+}
+
 impl Pattern {
     /// Create a new pattern with an identity trasformation applied.
     pub fn new(kind: PatternKind) -> Self {
-        Self::new_with_transform(kind, Matrix::identity(4))
+        Self::new_with_transform(kind, Matrix::identity())
     }
 
     /// Creates a new pattern with an applied transformation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `transform` is not invertible.
     pub fn new_with_transform(kind: PatternKind, transform: Matrix) -> Self {
-        Self { kind, transform }
+        let inverse = transform
+            .inverse()
+            .expect("pattern transform is not invertible");
+
+        Self {
+            kind,
+            transform,
+            inverse,
+        }
     }
 
     /// Returns the pattern kind of `self`.
@@ -81,42 +289,133 @@ impl Pattern {
 
     /// Returns the color of `self` at object-space coordinates `p`.
     pub fn color_at(&self, p: &Point3) -> Color {
-        let p = self.transform.inverse().unwrap() * p;
+        self.sample(p, None)
+    }
+
+    /// Returns the color of `self` at object-space coordinates `p`, on the surface of `shape`.
+    ///
+    /// Unlike [`Pattern::color_at`], images are mapped with the texture coordinates of `shape`.
+    pub fn color_on(&self, shape: &dyn Shape, p: &Point3) -> Color {
+        self.sample(p, Some(shape))
+    }
+
+    fn sample(&self, p: &Point3, shape: Option<&dyn Shape>) -> Color {
+        let p = &self.inverse * p;
 
         match &self.kind {
             &PatternKind::Solid(c) => c,
             PatternKind::Stripes(a, b) => {
-                if (p.x.floor() as i32) % 2 == 0 {
-                    a.color_at(&p)
+                if (float::floor(p.x) as i32) % 2 == 0 {
+                    a.sample(&p, shape)
                 } else {
-                    b.color_at(&p)
+                    b.sample(&p, shape)
                 }
             }
             PatternKind::Rings(a, b) => {
-                if (p.x.powi(2) + p.z.powi(2)).sqrt().floor() as i32 % 2 == 0 {
-                    a.color_at(&p)
+                if float::floor(float::sqrt(float::powi(p.x, 2) + float::powi(p.z, 2))) as i32 % 2
+                    == 0
+                {
+                    a.sample(&p, shape)
                 } else {
-                    b.color_at(&p)
+                    b.sample(&p, shape)
                 }
             }
             PatternKind::Checkers(a, b) => {
-                if (p.x.floor() + p.y.floor() + p.z.floor()) as i32 % 2 == 0 {
-                    a.color_at(&p)
+                if (float::floor(p.x) + float::floor(p.y) + float::floor(p.z)) as i32 % 2 == 0 {
+                    a.sample(&p, shape)
                 } else {
-                    b.color_at(&p)
+                    b.sample(&p, shape)
                 }
             }
-            PatternKind::Blended(a, b) => (a.color_at(&p) + b.color_at(&p)) / 2.0,
-            PatternKind::LinearGradient(a, b) => a + (b - a) * (p.x - p.x.floor()),
+            PatternKind::Blended(a, b) => (a.sample(&p, shape) + b.sample(&p, shape)) / 2.0,
+            PatternKind::LinearGradient(a, b) => a + (b - a) * (p.x - float::floor(p.x)),
             PatternKind::RadialGradient(a, b) => {
-                let dist = (p.x.powi(2) + p.z.powi(2)).sqrt();
-                a + (b - a) * (dist - dist.floor())
+                let dist = float::sqrt(float::powi(p.x, 2) + float::powi(p.z, 2));
+                a + (b - a) * (dist - float::floor(dist))
+            }
+            PatternKind::Perturbed {
+                pattern,
+                noise,
+                scale,
+            } => pattern.sample(&(p + noise.vector_at(&p) * *scale), shape),
+            PatternKind::Noise {
+                low,
+                high,
+                noise,
+                octaves,
+            } => {
+                let t = (noise.fractal_at(&p, *octaves) + 1.0) / 2.0;
+                low + (high - low) * t.clamp(0.0, 1.0)
+            }
+            PatternKind::Image {
+                pixels,
+                width,
+                height,
+                mapping,
+                filter,
+            } => {
+                let (u, v) = match (mapping, shape) {
+                    (TextureMapping::Shape, Some(shape)) => shape.uv_at(&p),
+                    (TextureMapping::Shape, None) | (TextureMapping::Planar, _) => Plane.uv_at(&p),
+                    (TextureMapping::Spherical, _) => Sphere.uv_at(&p),
+                    (TextureMapping::Cylindrical, _) => Cylinder::default().uv_at(&p),
+                };
+
+                sample_image(pixels, *width, *height, *filter, u, v)
             }
             PatternKind::Test => Color::new(p.x, p.y, p.z),
         }
     }
 }
 
+/// Returns whether an image of `width`×`height` pixels is made of exactly `pixels`.
+fn image_size_matches(pixels: &[Color], width: u32, height: u32) -> bool {
+    (width as usize).checked_mul(height as usize) == Some(pixels.len())
+}
+
+/// Looks up the color of an image at texture coordinates `(u, v)`.
+///
+/// Coordinates outside of the image are clamped to its edges. Empty images, or images with fewer
+/// pixels than their size, are black.
+fn sample_image(
+    pixels: &[Color],
+    width: u32,
+    height: u32,
+    filter: TextureFilter,
+    u: f32,
+    v: f32,
+) -> Color {
+    let size = (width as usize).saturating_mul(height as usize);
+    if width == 0 || height == 0 || pixels.len() < size {
+        return Color::BLACK;
+    }
+
+    // Position in pixels from the top left corner of the image
+    let x = u * width as f32;
+    let y = (1.0 - v) * height as f32;
+
+    // Negative coordinates saturate to zero when cast
+    let pixel = |x: f32, y: f32| {
+        let x = (x as u32).min(width - 1);
+        let y = (y as u32).min(height - 1);
+        pixels[y as usize * width as usize + x as usize]
+    };
+
+    match filter {
+        TextureFilter::Nearest => pixel(x, y),
+        TextureFilter::Bilinear => {
+            // Interpolate between the centers of the four closest pixels
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (x0, y0) = (float::floor(x), float::floor(y));
+            let (tx, ty) = (x - x0, y - y0);
+
+            let top = pixel(x0, y0) * (1.0 - tx) + pixel(x0 + 1.0, y0) * tx;
+            let bottom = pixel(x0, y0 + 1.0) * (1.0 - tx) + pixel(x0 + 1.0, y0 + 1.0) * tx;
+            top * (1.0 - ty) + bottom * ty
+        }
+    }
+}
+
 #[cfg(all(feature = "serde-support", test))]
 mod tests {
     use serde_test::{assert_de_tokens, Token};
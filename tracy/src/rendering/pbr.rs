@@ -0,0 +1,144 @@
+//! Physically-based shading.
+
+use core::f32::consts::PI;
+
+use crate::{
+    math::{float, Point3, Vec3},
+    query::Object,
+    rendering::{Color, LightSource, ShadingModel},
+};
+
+/// Lowest roughness used for shading, since perfectly smooth surfaces would have infinitely
+/// small and bright highlights.
+const MIN_ROUGHNESS: f32 = 0.03;
+
+/// Reflectance at normal incidence of non-metallic materials.
+const DIELECTRIC_REFLECTANCE: f32 = 0.04;
+
+/// Parameters of the physically-based shading model, see [`ShadingModel::Pbr`].
+///
+/// The albedo of the surface, ie. its base color, is given by the pattern of the material.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PbrMaterial {
+    /// From `0` for dielectrics such as plastic, to `1` for metals. Metals have no diffuse
+    /// reflection, and tint their highlights and reflections with their albedo. Defaults to `0`.
+    pub metallic: f32,
+    /// From `0` for smooth surfaces with sharp highlights and reflections, to `1` for rough
+    /// surfaces with broad and dim highlights and blurred reflections. Defaults to `0.5`.
+    pub roughness: f32,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            metallic: 0.0,
+            roughness: 0.5,
+        }
+    }
+}
+
+/// Computes the illumination of a surface point with the Cook-Torrance BRDF and the GGX
+/// distribution of microfacets.
+///
+/// The parameters are the same as for [`phong_lighting`](super::phong_lighting), and the light
+/// is attenuated and averaged over its samples in the same way. Lights are scaled so that a
+/// white, rough dielectric lit head-on is about as bright as a Phong material with a diffuse of
+/// `1`, which allows scenes to mix both models. The ambient contribution is the albedo scaled by
+/// the ambient of the material.
+///
+/// Objects whose material uses the Phong model are shaded with the default [`PbrMaterial`].
+pub fn pbr_lighting<L: LightSource>(
+    object: &Object,
+    light: &L,
+    point: &Point3,
+    eye: &Vec3,
+    normal: &Vec3,
+    light_intensity: f32,
+) -> Color {
+    occluded_pbr_lighting(object, light, point, eye, normal, light_intensity, 1.0)
+}
+
+/// Computes the illumination of a surface point like [`pbr_lighting`], with the ambient
+/// contribution scaled by `ambient_visibility`.
+pub(crate) fn occluded_pbr_lighting<L: LightSource>(
+    object: &Object,
+    light: &L,
+    point: &Point3,
+    eye: &Vec3,
+    normal: &Vec3,
+    light_intensity: f32,
+    ambient_visibility: f32,
+) -> Color {
+    let material = object.material();
+    let pbr = match material.shading {
+        ShadingModel::Pbr(pbr) => pbr,
+        ShadingModel::Phong => PbrMaterial::default(),
+    };
+
+    let local_point = object
+        .inverse_transform()
+        .expect("object transform is not invertible")
+        * point;
+
+    let albedo = material.color_on(object.shape(), &local_point);
+    let ambient = albedo * light.radiance() * (material.ambient * ambient_visibility);
+
+    if light_intensity <= 0.0 {
+        return ambient;
+    }
+
+    let samples = light.sample_count();
+    let lit = (0..samples).fold(Color::BLACK, |sum, i| {
+        let lightv = light.sample(i) - point;
+        let falloff = light.attenuation().factor(lightv.length());
+
+        sum + cook_torrance(albedo, &pbr, &lightv.normalize(), eye, normal) * falloff
+    });
+
+    ambient + lit * light.radiance() * (light_intensity / samples as f32)
+}
+
+/// Computes the light reflected towards `eye` by a unit of light coming from direction `lightv`.
+fn cook_torrance(
+    albedo: Color,
+    pbr: &PbrMaterial,
+    lightv: &Vec3,
+    eye: &Vec3,
+    normal: &Vec3,
+) -> Color {
+    let n_dot_l = normal.dot(lightv);
+    let n_dot_v = normal.dot(eye);
+
+    if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+        return Color::BLACK;
+    }
+
+    let halfway = (*lightv + *eye).normalize();
+    let n_dot_h = normal.dot(&halfway).max(0.0);
+    let v_dot_h = eye.dot(&halfway).max(0.0);
+
+    let roughness = pbr.roughness.clamp(MIN_ROUGHNESS, 1.0);
+    let metallic = pbr.metallic.clamp(0.0, 1.0);
+
+    // Fraction of microfacets oriented along the halfway vector
+    let alpha2 = float::powi(roughness * roughness, 2);
+    let distribution = alpha2 / (PI * float::powi(n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0, 2));
+
+    // Fraction of microfacets neither shadowed nor masked by the others
+    let k = float::powi(roughness + 1.0, 2) / 8.0;
+    let geometry = n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+
+    // Fraction of light reflected rather than refracted into the surface
+    let f0 = Color::WHITE * (DIELECTRIC_REFLECTANCE * (1.0 - metallic)) + albedo * metallic;
+    let fresnel = f0 + (Color::WHITE - f0) * float::powi(1.0 - v_dot_h, 5);
+
+    let specular = fresnel * (distribution * geometry / (4.0 * n_dot_v * n_dot_l));
+    let diffuse = (Color::WHITE - fresnel) * albedo * ((1.0 - metallic) / PI);
+
+    (diffuse + specular) * (PI * n_dot_l)
+}
@@ -0,0 +1,135 @@
+//! Incremental encoding of images in PPM format.
+
+use std::io::{self, Write};
+
+use super::Color;
+
+/// Encoding of the pixels of a PPM image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PpmFormat {
+    /// Plain (P3) format, with pixels written as decimal numbers.
+    Ascii,
+    /// Raw (P6) format, with pixels written as bytes.
+    Binary,
+}
+
+/// Writer of PPM images one scanline at a time.
+///
+/// Scanlines are encoded as soon as they are written, so the whole image never needs to be held in
+/// memory. This is useful to save renders too large to fit in a [`Canvas`](super::Canvas).
+#[derive(Debug)]
+pub struct PpmWriter<W: Write> {
+    inner: W,
+    format: PpmFormat,
+    width: u32,
+    height: u32,
+    rows_written: u32,
+    line: Vec<u8>,
+}
+
+impl<W: Write> PpmWriter<W> {
+    /// Writes the header of a `width`×`height` image to `inner`, returning a writer for its
+    /// scanlines.
+    pub fn new(mut inner: W, width: u32, height: u32, format: PpmFormat) -> io::Result<Self> {
+        let magic = match format {
+            PpmFormat::Ascii => "P3",
+            PpmFormat::Binary => "P6",
+        };
+        write!(inner, "{}\n{} {}\n{}\n", magic, width, height, 255)?;
+
+        Ok(Self {
+            inner,
+            format,
+            width,
+            height,
+            rows_written: 0,
+            line: Vec::new(),
+        })
+    }
+
+    /// Returns the number of scanlines written so far.
+    pub fn rows_written(&self) -> u32 {
+        self.rows_written
+    }
+
+    /// Writes the next scanline of the image, from top to bottom.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if `row` is not as wide as the image, or if all
+    /// the scanlines have already been written.
+    pub fn write_row(&mut self, row: &[Color]) -> io::Result<()> {
+        if row.len() != self.width as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("expected a row of {} pixels, got {}", self.width, row.len()),
+            ));
+        }
+        if self.rows_written >= self.height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "all the rows of the image have already been written",
+            ));
+        }
+
+        self.line.clear();
+        match self.format {
+            PpmFormat::Ascii => encode_ascii_row(row, &mut self.line),
+            PpmFormat::Binary => {
+                for c in row {
+                    let (r, g, b) = c.to_rgb888();
+                    self.line.extend_from_slice(&[r, g, b]);
+                }
+            }
+        }
+
+        self.inner.write_all(&self.line)?;
+        self.rows_written += 1;
+        Ok(())
+    }
+
+    /// Flushes the image and returns the underlying writer.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if some scanlines are yet to be written.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.rows_written != self.height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "only {} of the {} rows of the image were written",
+                    self.rows_written, self.height
+                ),
+            ));
+        }
+
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Appends the plain PPM encoding of a scanline to `out`, ending with a newline.
+fn encode_ascii_row(row: &[Color], out: &mut Vec<u8>) {
+    let mut line_len = 0;
+
+    for c in row {
+        let (r, g, b) = c.to_rgb888();
+
+        // Lines should not be longer than 70 characters in PPM files.
+        // Iterate over each color component in order to split lines as close as possible
+        // to the 70 character mark.
+        for val in &[r, g, b] {
+            let s = format!("{} ", val);
+
+            // Swap out the last space for a newline and reset the length counter
+            if line_len + s.len() > 70 {
+                out.pop();
+                out.push(b'\n');
+                line_len = 0;
+            }
+
+            out.extend_from_slice(s.as_bytes());
+            line_len += s.len();
+        }
+    }
+
+    out.pop();
+    out.push(b'\n');
+}
@@ -0,0 +1,35 @@
+/// Optional rendering effects of a world, which trade speed for quality.
+///
+/// The effects only apply to scenes using them, which otherwise render as in the book.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    /// Number of rays cast around each shaded point to estimate how much of its ambient light is
+    /// blocked by nearby objects. Ambient occlusion is disabled when zero, which is the default.
+    pub ao_samples: u32,
+    /// Distance within which objects occlude the ambient light of a point. Defaults to `1`.
+    pub ao_radius: f32,
+    /// Number of rays averaged to render the reflections of rough materials, see
+    /// [`Material::reflection_roughness`](super::Material::reflection_roughness). Reflections are
+    /// sharp when zero or one. Defaults to `8`, and can be lowered to speed up previews.
+    pub glossy_samples: u32,
+    /// Number of rays averaged to render the refractions of rough materials, see
+    /// [`Material::refraction_roughness`](super::Material::refraction_roughness). Refractions are
+    /// sharp when zero or one. Defaults to `8`.
+    pub translucency_samples: u32,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            ao_samples: 0,
+            ao_radius: 1.0,
+            glossy_samples: 8,
+            translucency_samples: 8,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Mapping of rendered colors to the display.
+
+use crate::{
+    math::float,
+    profile::{self, Phase},
+};
+
+use super::{Canvas, Color};
+
+/// Curve compressing linear colors into the displayable range.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    /// Colors brighter than white are clipped, as in the book.
+    Clamp,
+    /// Reinhard's operator, `x / (1 + x)`.
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+impl ToneMapOperator {
+    /// All the operators, in the order they are presented to the user.
+    pub const ALL: [ToneMapOperator; 3] = [
+        ToneMapOperator::Clamp,
+        ToneMapOperator::Reinhard,
+        ToneMapOperator::Aces,
+    ];
+
+    /// Returns a human-readable name for this operator.
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapOperator::Clamp => "Clamp",
+            ToneMapOperator::Reinhard => "Reinhard",
+            ToneMapOperator::Aces => "ACES filmic",
+        }
+    }
+
+    /// Applies the curve to a single color channel.
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            ToneMapOperator::Clamp => x,
+            ToneMapOperator::Reinhard => x / (1.0 + x),
+            ToneMapOperator::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+        }
+    }
+}
+
+/// Display transform applied to a render before it is shown or saved.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapping {
+    /// Curve mapping linear colors to the displayable range.
+    pub operator: ToneMapOperator,
+    /// Exposure adjustment, in stops.
+    pub exposure: f32,
+    /// Gamma of the display encoding, `1.0` leaving colors linear.
+    pub gamma: f32,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            operator: ToneMapOperator::Clamp,
+            exposure: 0.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl ToneMapping {
+    /// Returns true if this transform leaves colors unchanged, as in the book.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Maps a linear color to its display-encoded counterpart, in the `[0, 1]` range.
+    pub fn map_color(&self, color: Color) -> Color {
+        let scale = float::powf(2.0, self.exposure);
+        let encode = |x: f32| {
+            float::powf(
+                self.operator.apply(x * scale).clamp(0.0, 1.0),
+                1.0 / self.gamma.max(f32::EPSILON),
+            )
+        };
+
+        Color::new(encode(color.r), encode(color.g), encode(color.b))
+    }
+
+    /// Maps a linear color to its RGB888 on-screen representation.
+    pub fn map(&self, color: Color) -> (u8, u8, u8) {
+        self.map_color(color).to_rgb888()
+    }
+
+    /// Returns a copy of `canvas` with all of its pixels mapped to the display.
+    pub fn apply(&self, canvas: &Canvas) -> Canvas {
+        let _scope = profile::scope(Phase::PostProcess);
+
+        let mut out = canvas.clone();
+        for c in out.iter_mut() {
+            *c = self.map_color(*c);
+        }
+        out
+    }
+}
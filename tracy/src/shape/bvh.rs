@@ -0,0 +1,182 @@
+//! Bounding volume hierarchy over the triangles of a mesh.
+
+use alloc::vec::Vec;
+use core::{cell::Cell, ops::Range};
+
+use crate::{
+    math::{float, Point3},
+    query::{Aabb, Ray},
+};
+
+use super::{Shape, Triangle};
+
+/// Largest number of triangles in a leaf of the hierarchy.
+const LEAF_SIZE: usize = 4;
+
+/// Size of the traversal stack, which is enough for any hierarchy built by median splits.
+const STACK_SIZE: usize = 64;
+
+/// A node of a [`Bvh`].
+///
+/// The first child of an inner node is stored right after it, so only the second one is recorded.
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    bounds: Aabb,
+    /// For leaves, the position of their first triangle in the index list. For inner nodes, the
+    /// position of their second child.
+    offset: u32,
+    /// Number of triangles of a leaf, or zero for inner nodes.
+    count: u32,
+}
+
+/// A binary tree of bounding boxes around groups of triangles.
+///
+/// Queries only visit the triangles lying in the boxes they may reach, which makes them roughly
+/// logarithmic in the number of triangles rather than linear.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Bvh {
+    nodes: Vec<Node>,
+    /// Indices of the triangles, sorted so that each leaf covers a contiguous range of them.
+    indices: Vec<u32>,
+}
+
+impl Bvh {
+    /// Builds the hierarchy of `triangles`, splitting each group at the median of the centers of
+    /// its triangles along the axis where they are the most spread out.
+    pub(crate) fn new(triangles: &[Triangle]) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::new(),
+            indices: (0..triangles.len() as u32).collect(),
+        };
+
+        if !triangles.is_empty() {
+            let centers = triangles
+                .iter()
+                .map(|t| t.local_bounds().center())
+                .collect::<Vec<_>>();
+
+            bvh.build(triangles, &centers, 0..triangles.len());
+        }
+
+        bvh
+    }
+
+    /// Returns the bounding box of all the triangles in the hierarchy.
+    pub(crate) fn bounds(&self) -> Aabb {
+        self.nodes
+            .first()
+            .map_or_else(Aabb::empty, |node| node.bounds)
+    }
+
+    /// Appends the subtree covering `range` of the index list.
+    fn build(&mut self, triangles: &[Triangle], centers: &[Point3], range: Range<usize>) {
+        let indices = &mut self.indices[range.clone()];
+
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            bounds: indices.iter().fold(Aabb::empty(), |b, &i| {
+                b.union(&triangles[i as usize].local_bounds())
+            }),
+            offset: range.start as u32,
+            count: range.len() as u32,
+        });
+
+        if range.len() <= LEAF_SIZE {
+            return;
+        }
+
+        let spread = Aabb::from_points(indices.iter().map(|&i| centers[i as usize]));
+        let extent = spread.max - spread.min;
+        let key: fn(&Point3) -> f32 = if extent.x >= extent.y && extent.x >= extent.z {
+            |p| p.x
+        } else if extent.y >= extent.z {
+            |p| p.y
+        } else {
+            |p| p.z
+        };
+
+        let mid = range.len() / 2;
+        indices.select_nth_unstable_by(mid, |&a, &b| {
+            key(&centers[a as usize]).total_cmp(&key(&centers[b as usize]))
+        });
+
+        let mid = range.start + mid;
+        self.build(triangles, centers, range.start..mid);
+        self.nodes[node].offset = self.nodes.len() as u32;
+        self.nodes[node].count = 0;
+        self.build(triangles, centers, mid..range.end);
+    }
+
+    /// Calls `f` with the index of each triangle lying in a leaf whose box is crossed by the line
+    /// of `ray`.
+    pub(crate) fn for_each_along<F: FnMut(usize)>(&self, ray: &Ray, f: F) {
+        self.traverse(|bounds| bounds.intersect_ray(ray).is_some(), f);
+    }
+
+    /// Returns the index of the triangle closest to `p` among `triangles`, which must be the
+    /// triangles this hierarchy was built from.
+    pub(crate) fn closest_to(&self, triangles: &[Triangle], p: &Point3) -> Option<usize> {
+        let mut closest = None;
+        let min_distance = Cell::new(f32::INFINITY);
+
+        self.traverse(
+            // Skip the boxes farther away than the closest triangle found so far
+            |bounds| distance_to_box(bounds, p) <= min_distance.get(),
+            |i| {
+                let distance = triangles[i].distance_to(p);
+                if closest.is_none() || distance < min_distance.get() {
+                    closest = Some(i);
+                    min_distance.set(distance);
+                }
+            },
+        );
+
+        closest
+    }
+
+    /// Walks the nodes whose box is accepted by `visit`, passing the triangles of their leaves to
+    /// `f`.
+    fn traverse<V, F>(&self, mut visit: V, mut f: F)
+    where
+        V: FnMut(&Aabb) -> bool,
+        F: FnMut(usize),
+    {
+        let mut stack = [0u32; STACK_SIZE];
+        let mut len = usize::from(!self.nodes.is_empty());
+
+        while len > 0 {
+            len -= 1;
+            let index = stack[len] as usize;
+            let node = &self.nodes[index];
+
+            if !visit(&node.bounds) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack[len] = node.offset;
+                stack[len + 1] = index as u32 + 1;
+                len += 2;
+                continue;
+            }
+
+            let start = node.offset as usize;
+            for &i in &self.indices[start..start + node.count as usize] {
+                f(i as usize);
+            }
+        }
+    }
+}
+
+/// Returns the distance between `p` and the closest point of `bounds`.
+fn distance_to_box(bounds: &Aabb, p: &Point3) -> f32 {
+    let gap = |min: f32, max: f32, v: f32| (min - v).max(v - max).max(0.0);
+
+    let (dx, dy, dz) = (
+        gap(bounds.min.x, bounds.max.x, p.x),
+        gap(bounds.min.y, bounds.max.y, p.y),
+        gap(bounds.min.z, bounds.max.z, p.z),
+    );
+
+    float::sqrt(dx * dx + dy * dy + dz * dz)
+}
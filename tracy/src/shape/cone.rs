@@ -0,0 +1,191 @@
+//! The double-napped cone shape.
+
+use crate::{
+    math::{float, Point3, Vec3, EPSILON},
+    query::{Aabb, IntersectionList, Ray, RayCast, RayIntersection, RayIntersections},
+};
+
+use super::{azimuth, fract, Shape};
+
+/// A double-napped cone with its apex in the origin, extending along the Y axis.
+///
+/// The radius of the cone at any height is the absolute value of its Y coordinate. In scene
+/// files, its ends can be given either as `bottom` and `top`, or as `minimum` and `maximum` like
+/// in the book.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cone {
+    #[cfg_attr(feature = "serde-support", serde(alias = "maximum"))]
+    top: f32,
+    #[cfg_attr(feature = "serde-support", serde(alias = "minimum"))]
+    bottom: f32,
+    closed: bool,
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self {
+            top: f32::INFINITY,
+            bottom: f32::NEG_INFINITY,
+            closed: false,
+        }
+    }
+}
+
+impl Cone {
+    /// Returns the upper Y coordinate of this cone.
+    pub fn top(&self) -> f32 {
+        self.top
+    }
+
+    /// Returns the lower Y coordinate of this cone.
+    pub fn bottom(&self) -> f32 {
+        self.bottom
+    }
+
+    /// Changes the upper Y coordinate of `self` to `y`.
+    ///
+    /// If `y` is lower than the current lower coordinate, the two are swapped.
+    pub fn set_top(&mut self, y: f32) {
+        if y < self.bottom() {
+            self.top = self.bottom;
+            self.bottom = y;
+        } else {
+            self.top = y;
+        }
+    }
+
+    /// Changes the lower Y coordinate of `self` to `y`.
+    ///
+    /// If `y` is higher than the current upper coordinate, the two are swapped.
+    pub fn set_bottom(&mut self, y: f32) {
+        if y > self.top() {
+            self.bottom = self.top;
+            self.top = y;
+        } else {
+            self.bottom = y;
+        }
+    }
+
+    /// Returns whether this cone is capped at its ends.
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Marks this cone's ends as capped if `closed` is true, or uncapped otherwise.
+    pub fn set_closed(&mut self, closed: bool) {
+        self.closed = closed;
+    }
+
+    /// Computes the normal at the given point
+    fn normal_at(&self, point: &Point3) -> Vec3 {
+        let dist = float::powi(point.x, 2) + float::powi(point.z, 2);
+
+        if dist < float::powi(self.top, 2) && point.y >= self.top - EPSILON {
+            Vec3::unit_y()
+        } else if dist < float::powi(self.bottom, 2) && point.y <= self.bottom + EPSILON {
+            -Vec3::unit_y()
+        } else if dist < EPSILON * EPSILON {
+            // The wall normal vanishes at the apex, so use the axis pointing away from the nappe
+            // the point lies on instead, ie. the average of the normals around it
+            if point.y > 0.0 {
+                -Vec3::unit_y()
+            } else {
+                Vec3::unit_y()
+            }
+        } else {
+            let y = float::sqrt(dist);
+            Vec3::new(point.x, if point.y > 0.0 { -y } else { y }, point.z)
+        }
+    }
+
+    /// Appends `t` to the list of intersections if it hits the wall between the ends of the cone.
+    fn push_wall_intersection(&self, ray: &Ray, t: f32, xs: &mut IntersectionList) {
+        let y = ray.origin.y + t * ray.dir.y;
+
+        if self.bottom() < y && y < self.top() {
+            xs.push(RayIntersection {
+                toi: t,
+                normal: self.normal_at(&ray.point_at(t)),
+            });
+        }
+    }
+
+    /// Appends to the list of intersections any hits with this cone's caps, if capped.
+    fn intersections_at_caps(&self, ray: &Ray, xs: &mut IntersectionList) {
+        if self.closed() && ray.dir.y.abs() > EPSILON {
+            for &y in &[self.bottom, self.top] {
+                let t = (y - ray.origin.y) / ray.dir.y;
+                if check_cap(ray, t, y.abs()) {
+                    xs.push(RayIntersection {
+                        toi: t,
+                        normal: self.normal_at(&ray.point_at(t)),
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde-support", typetag::serde)]
+impl Shape for Cone {
+    fn local_bounds(&self) -> Aabb {
+        let r = self.bottom.abs().max(self.top.abs());
+        Aabb::new(
+            Point3::new(-r, self.bottom, -r),
+            Point3::new(r, self.top, r),
+        )
+    }
+
+    fn local_normal_at(&self, p: &Point3) -> Vec3 {
+        self.normal_at(p)
+    }
+
+    /// Wraps the texture around the cone, repeating it along the Y axis every unit.
+    fn uv_at(&self, p: &Point3) -> (f32, f32) {
+        (azimuth(p), fract(p.y))
+    }
+}
+
+impl RayCast for Cone {
+    fn intersections_in_local_space(&self, ray: &Ray) -> RayIntersections {
+        let mut xs = IntersectionList::new();
+
+        let a = float::powi(ray.dir.x, 2) - float::powi(ray.dir.y, 2) + float::powi(ray.dir.z, 2);
+        let b = 2.0 * ray.origin.x * ray.dir.x - 2.0 * ray.origin.y * ray.dir.y
+            + 2.0 * ray.origin.z * ray.dir.z;
+        let c = float::powi(ray.origin.x, 2) - float::powi(ray.origin.y, 2)
+            + float::powi(ray.origin.z, 2);
+
+        if a.abs() > EPSILON {
+            let disc = float::powi(b, 2) - 4.0 * a * c;
+
+            // Rays grazing the cone may end up with a slightly negative discriminant
+            if disc > -EPSILON {
+                let root = float::sqrt(disc.max(0.0));
+                let t0 = (-b - root) / (2.0 * a);
+                let t1 = (-b + root) / (2.0 * a);
+
+                self.push_wall_intersection(ray, t0.min(t1), &mut xs);
+                self.push_wall_intersection(ray, t0.max(t1), &mut xs);
+            }
+        } else if b.abs() > EPSILON {
+            // The ray is parallel to one of the nappes, so it only hits the other one
+            self.push_wall_intersection(ray, -c / (2.0 * b), &mut xs);
+        }
+
+        self.intersections_at_caps(ray, &mut xs);
+        xs.into_iter().collect()
+    }
+}
+
+fn check_cap(ray: &Ray, t: f32, radius: f32) -> bool {
+    let x = ray.origin.x + t * ray.dir.x;
+    let z = ray.origin.z + t * ray.dir.z;
+
+    (x * x + z * z) <= radius * radius
+}
@@ -2,7 +2,7 @@
 
 use crate::{
     math::{Point3, Vec3},
-    query::{Ray, RayCast, RayIntersection, RayIntersections},
+    query::{Aabb, Ray, RayCast, RayIntersection, RayIntersections},
 };
 
 use super::Shape;
@@ -12,11 +12,55 @@ use super::Shape;
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Cube;
 
 #[cfg_attr(feature = "serde-support", typetag::serde)]
-impl Shape for Cube {}
+impl Shape for Cube {
+    fn local_bounds(&self) -> Aabb {
+        Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0))
+    }
+
+    fn local_normal_at(&self, p: &Point3) -> Vec3 {
+        normal_at(p)
+    }
+
+    /// Maps the faces of the cube to a texture laid out as an unfolded cross, four faces wide
+    /// and three faces high:
+    ///
+    /// ```text
+    ///         +-------+
+    ///         |  up   |
+    /// +-------+-------+-------+-------+
+    /// | left  | front | right | back  |
+    /// +-------+-------+-------+-------+
+    ///         | down  |
+    ///         +-------+
+    /// ```
+    ///
+    /// The front face is the one facing the positive Z axis.
+    fn uv_at(&self, p: &Point3) -> (f32, f32) {
+        let (x, y, z) = (p.x, p.y, p.z);
+        let max = x.abs().max(y.abs()).max(z.abs());
+
+        // Coordinates within the face, and position of the face in the cross
+        let ((u, v), (column, row)) = if max == x {
+            ((1.0 - z, y + 1.0), (2.0, 1.0))
+        } else if max == -x {
+            ((z + 1.0, y + 1.0), (0.0, 1.0))
+        } else if max == y {
+            ((x + 1.0, 1.0 - z), (1.0, 2.0))
+        } else if max == -y {
+            ((x + 1.0, z + 1.0), (1.0, 0.0))
+        } else if max == z {
+            ((x + 1.0, y + 1.0), (1.0, 1.0))
+        } else {
+            ((1.0 - x, y + 1.0), (3.0, 1.0))
+        };
+
+        ((column + u / 2.0) / 4.0, (row + v / 2.0) / 3.0)
+    }
+}
 
 impl RayCast for Cube {
     fn intersections_in_local_space(&self, ray: &Ray) -> RayIntersections {
@@ -32,28 +76,22 @@ impl RayCast for Cube {
                     let tmin = xtmin.max(ytmin).max(ztmin);
                     let tmax = xtmax.min(ytmax).min(ztmax);
 
-                    return RayIntersections::from(
-                        if tmin > tmax {
-                            vec![]
-                        } else {
-                            vec![
-                                RayIntersection {
-                                    toi: tmin,
-                                    normal: normal_at(&ray.point_at(tmin)),
-                                },
-                                RayIntersection {
-                                    toi: tmax,
-                                    normal: normal_at(&ray.point_at(tmax)),
-                                },
-                            ]
-                        }
-                        .into_iter(),
-                    );
+                    if tmin > tmax {
+                        return RayIntersections::default();
+                    }
+
+                    return [tmin, tmax]
+                        .iter()
+                        .map(|&toi| RayIntersection {
+                            toi,
+                            normal: normal_at(&ray.point_at(toi)),
+                        })
+                        .collect();
                 }
             }
         }
 
-        RayIntersections::from(vec![].into_iter())
+        RayIntersections::default()
     }
 }
 
@@ -69,7 +107,7 @@ fn check_axis(origin: f32, dir: f32) -> (f32, f32) {
 }
 
 #[allow(clippy::float_cmp)]
-fn normal_at(point: &Point3) -> Vec3 {
+pub(crate) fn normal_at(point: &Point3) -> Vec3 {
     let maxc = point.x.abs().max(point.y.abs()).max(point.z.abs());
 
     if maxc == point.x.abs() {
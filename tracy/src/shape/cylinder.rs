@@ -1,21 +1,26 @@
 //! The unit cylinder shape.
 
 use crate::{
-    math::{Point3, Vec3, EPSILON},
-    query::{Ray, RayCast, RayIntersection, RayIntersections},
+    math::{float, Point3, Vec3, EPSILON},
+    query::{Aabb, IntersectionList, Ray, RayCast, RayIntersection, RayIntersections},
 };
 
-use super::Shape;
+use super::{azimuth, fract, Shape};
 
 /// A cylinder with unit radius centered around the origin and extending along the Y axis.
+///
+/// In scene files, its ends can be given either as `bottom` and `top`, or as `minimum` and
+/// `maximum` like in the book.
 #[cfg_attr(
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize),
     serde(default)
 )]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Cylinder {
+    #[cfg_attr(feature = "serde-support", serde(alias = "maximum"))]
     top: f32,
+    #[cfg_attr(feature = "serde-support", serde(alias = "minimum"))]
     bottom: f32,
     closed: bool,
 }
@@ -77,7 +82,7 @@ impl Cylinder {
 
     /// Computes the normal at the given point
     fn normal_at(&self, point: &Point3) -> Vec3 {
-        let dist = point.x.powi(2) + point.z.powi(2);
+        let dist = float::powi(point.x, 2) + float::powi(point.z, 2);
 
         if dist < 1.0 && point.y >= self.top - EPSILON {
             Vec3::unit_y()
@@ -89,7 +94,7 @@ impl Cylinder {
     }
 
     /// Appends to the list of intersections any hits with this cylinder's caps, if capped.
-    fn intersections_at_caps(&self, ray: &Ray, xs: &mut Vec<RayIntersection>) {
+    fn intersections_at_caps(&self, ray: &Ray, xs: &mut IntersectionList) {
         if self.closed() && ray.dir.y.abs() > EPSILON {
             for &y in &[self.bottom, self.top] {
                 let t = (y - ray.origin.y) / ray.dir.y;
@@ -105,23 +110,41 @@ impl Cylinder {
 }
 
 #[cfg_attr(feature = "serde-support", typetag::serde)]
-impl Shape for Cylinder {}
+impl Shape for Cylinder {
+    fn local_bounds(&self) -> Aabb {
+        Aabb::new(
+            Point3::new(-1.0, self.bottom, -1.0),
+            Point3::new(1.0, self.top, 1.0),
+        )
+    }
+
+    fn local_normal_at(&self, p: &Point3) -> Vec3 {
+        self.normal_at(p)
+    }
+
+    /// Wraps the texture around the cylinder, repeating it along the Y axis every unit.
+    ///
+    /// Points on the caps are mapped like those on the wall right below or above them.
+    fn uv_at(&self, p: &Point3) -> (f32, f32) {
+        (azimuth(p), fract(p.y))
+    }
+}
 
 impl RayCast for Cylinder {
     fn intersections_in_local_space(&self, ray: &Ray) -> RayIntersections {
-        let mut xs = Vec::with_capacity(2);
+        let mut xs = IntersectionList::new();
 
-        let a = ray.dir.x.powi(2) + ray.dir.z.powi(2);
+        let a = float::powi(ray.dir.x, 2) + float::powi(ray.dir.z, 2);
 
         if a > EPSILON {
             let b = 2.0 * ray.origin.x * ray.dir.x + 2.0 * ray.origin.z * ray.dir.z;
-            let c = ray.origin.x.powi(2) + ray.origin.z.powi(2) - 1.0;
+            let c = float::powi(ray.origin.x, 2) + float::powi(ray.origin.z, 2) - 1.0;
 
-            let disc = b.powi(2) - 4.0 * a * c;
+            let disc = float::powi(b, 2) - 4.0 * a * c;
 
             if disc >= 0.0 {
-                let t0 = (-b - disc.sqrt()) / (2.0 * a);
-                let t1 = (-b + disc.sqrt()) / (2.0 * a);
+                let t0 = (-b - float::sqrt(disc)) / (2.0 * a);
+                let t1 = (-b + float::sqrt(disc)) / (2.0 * a);
 
                 let y0 = ray.origin.y + t0 * ray.dir.y;
                 let y1 = ray.origin.y + t1 * ray.dir.y;
@@ -143,7 +166,7 @@ impl RayCast for Cylinder {
         }
 
         self.intersections_at_caps(ray, &mut xs);
-        RayIntersections::from(xs.into_iter())
+        xs.into_iter().collect()
     }
 }
 
@@ -153,3 +176,32 @@ fn check_cap(ray: &Ray, t: f32) -> bool {
 
     (x * x + z * z) <= 1.0
 }
+
+#[cfg(all(feature = "serde-support", test))]
+mod tests {
+    use serde_test::{assert_de_tokens, Token};
+
+    use super::*;
+
+    #[test]
+    fn deserialize_with_book_names() {
+        let mut cyl = Cylinder::default();
+        cyl.set_bottom(1.0);
+        cyl.set_top(2.0);
+
+        assert_de_tokens(
+            &cyl,
+            &[
+                Token::Struct {
+                    name: "Cylinder",
+                    len: 2,
+                },
+                Token::Str("minimum"),
+                Token::F32(1.0),
+                Token::Str("maximum"),
+                Token::F32(2.0),
+                Token::StructEnd,
+            ],
+        );
+    }
+}
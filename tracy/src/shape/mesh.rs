@@ -0,0 +1,83 @@
+//! The triangle mesh shape.
+
+use alloc::vec::Vec;
+
+use crate::{
+    math::{Point3, Vec3},
+    query::{Aabb, IntersectionList, Ray, RayCast, RayIntersection, RayIntersections},
+};
+
+use super::{bvh::Bvh, Shape, Triangle};
+
+/// A group of triangles sharing the same transform, such as a model loaded from a file.
+///
+/// The triangles are kept in a bounding volume hierarchy, so that rays are only tested against the
+/// triangles whose boxes they cross. Each intersection carries the normal of the triangle that was
+/// hit. In scene files, a mesh is written as the list of its triangles.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Vec<Triangle>", into = "Vec<Triangle>")
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    triangles: Vec<Triangle>,
+    bvh: Bvh,
+}
+
+impl From<Vec<Triangle>> for Mesh {
+    fn from(triangles: Vec<Triangle>) -> Self {
+        Self::new(triangles)
+    }
+}
+
+impl From<Mesh> for Vec<Triangle> {
+    fn from(mesh: Mesh) -> Self {
+        mesh.triangles
+    }
+}
+
+impl Mesh {
+    /// Creates a new mesh from its triangles, leaving out the degenerate ones.
+    pub fn new(mut triangles: Vec<Triangle>) -> Self {
+        triangles.retain(|t| !t.is_degenerate());
+
+        let bvh = Bvh::new(&triangles);
+
+        Self { triangles, bvh }
+    }
+
+    /// Returns the triangles of this mesh.
+    pub fn triangles(&self) -> &[Triangle] {
+        &self.triangles
+    }
+}
+
+#[cfg_attr(feature = "serde-support", typetag::serde)]
+impl Shape for Mesh {
+    fn local_bounds(&self) -> Aabb {
+        self.bvh.bounds()
+    }
+
+    /// Returns the normal of the triangle closest to `p`.
+    fn local_normal_at(&self, p: &Point3) -> Vec3 {
+        self.bvh
+            .closest_to(&self.triangles, p)
+            .map_or_else(Vec3::zero, |i| self.triangles[i].normal())
+    }
+}
+
+impl RayCast for Mesh {
+    fn intersections_in_local_space(&self, ray: &Ray) -> RayIntersections {
+        let mut xs = IntersectionList::new();
+
+        self.bvh.for_each_along(ray, |i| {
+            let t = &self.triangles[i];
+            if let Some(toi) = t.toi(ray) {
+                xs.push(RayIntersection::new(toi, t.normal()));
+            }
+        });
+
+        xs.into_iter().collect()
+    }
+}
@@ -1,19 +1,87 @@
 //! Collision shapes supported by the ray tracer.
 
-use std::fmt::Debug;
+use core::{f32::consts::PI, fmt::Debug};
 
+pub use cone::*;
 pub use cube::*;
 pub use cylinder::*;
+pub use mesh::*;
 pub use plane::*;
+pub use quad::*;
 pub use sphere::*;
+pub use triangle::*;
 
-use crate::query::{AsAny, RayCast};
+#[cfg(feature = "std")]
+pub(crate) use cube::normal_at as cube_normal_at;
 
+use crate::{
+    math::{float, Point3, Vec3},
+    query::{Aabb, AsAny, RayCast},
+};
+
+mod bvh;
+mod cone;
 mod cube;
 mod cylinder;
+mod mesh;
 mod plane;
+mod quad;
 mod sphere;
+mod triangle;
 
 /// Traits common to all shapes.
 #[cfg_attr(feature = "serde-support", typetag::serde)]
-pub trait Shape: 'static + Debug + Send + Sync + RayCast + AsAny {}
+pub trait Shape: 'static + Debug + Send + Sync + RayCast + AsAny + ShapeEq {
+    /// Returns the bounding box of this shape in local-space coordinates.
+    ///
+    /// The default implementation returns an infinite box, which is always correct but useless
+    /// for culling.
+    fn local_bounds(&self) -> Aabb {
+        Aabb::infinite()
+    }
+
+    /// Computes the normal to the surface of this shape at `p`, in local-space coordinates.
+    ///
+    /// The point is assumed to lie on the surface of the shape, and the normal need not be
+    /// normalized.
+    fn local_normal_at(&self, p: &Point3) -> Vec3;
+
+    /// Maps a point on the surface of this shape, in local-space coordinates, to the `(u, v)`
+    /// coordinates of a texture, both in the `[0, 1]` range.
+    ///
+    /// The default implementation is a planar mapping, which repeats the texture over each unit
+    /// square of the `xz` plane.
+    fn uv_at(&self, p: &Point3) -> (f32, f32) {
+        (fract(p.x), fract(p.z))
+    }
+}
+
+/// Returns the fractional part of `x`, which is always positive.
+fn fract(x: f32) -> f32 {
+    x - float::floor(x)
+}
+
+/// Maps the angle of `p` around the Y axis to `[0, 1]`, counterclockwise when seen from above.
+fn azimuth(p: &Point3) -> f32 {
+    1.0 - (float::atan2(p.x, p.z) / (2.0 * PI) + 0.5)
+}
+
+/// A trait for comparing shapes whose concrete type is not known.
+///
+/// It is implemented for all the shapes that implement [`PartialEq`].
+pub trait ShapeEq {
+    /// Returns true if `other` is a shape of the same type as `self`, and equal to it.
+    fn same_shape(&self, other: &dyn Shape) -> bool;
+}
+
+impl<T: Shape + PartialEq> ShapeEq for T {
+    fn same_shape(&self, other: &dyn Shape) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+}
+
+impl PartialEq for dyn Shape {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_shape(other)
+    }
+}
@@ -1,6 +1,8 @@
+use core::iter;
+
 use crate::{
-    math::{Vec3, EPSILON},
-    query::{Ray, RayCast, RayIntersection, RayIntersections},
+    math::{Point3, Vec3, EPSILON},
+    query::{Aabb, Ray, RayCast, RayIntersection, RayIntersections},
 };
 
 use super::Shape;
@@ -10,24 +12,33 @@ use super::Shape;
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Plane;
 
 #[cfg_attr(feature = "serde-support", typetag::serde)]
-impl Shape for Plane {}
+impl Shape for Plane {
+    fn local_bounds(&self) -> Aabb {
+        Aabb::new(
+            Point3::new(f32::NEG_INFINITY, 0.0, f32::NEG_INFINITY),
+            Point3::new(f32::INFINITY, 0.0, f32::INFINITY),
+        )
+    }
+
+    fn local_normal_at(&self, _: &Point3) -> Vec3 {
+        Vec3::unit_y()
+    }
+}
 
 impl RayCast for Plane {
     fn intersections_in_local_space(&self, ray: &Ray) -> RayIntersections {
         if ray.dir.y.abs() < EPSILON {
-            return RayIntersections::from(Vec::new().into_iter());
+            return RayIntersections::default();
         }
 
-        RayIntersections::from(
-            vec![RayIntersection {
-                toi: -ray.origin.y / ray.dir.y,
-                normal: Vec3::unit_y(),
-            }]
-            .into_iter(),
-        )
+        iter::once(RayIntersection {
+            toi: -ray.origin.y / ray.dir.y,
+            normal: Vec3::unit_y(),
+        })
+        .collect()
     }
 }
@@ -0,0 +1,103 @@
+//! The bounded rectangle shape.
+
+use core::iter;
+
+use crate::{
+    math::{Point3, Vec3, EPSILON},
+    query::{Aabb, Ray, RayCast, RayIntersection, RayIntersections},
+};
+
+use super::Shape;
+
+/// A rectangle lying on `xz` and centered around the origin.
+///
+/// Unlike a [`Plane`](super::Plane), a quad only extends for its width along the X axis and for
+/// its depth along the Z axis, which makes it suitable for finite walls and floors. By default, it
+/// matches a face of the unit cube, with a width and depth of 2.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quad {
+    width: f32,
+    depth: f32,
+}
+
+impl Default for Quad {
+    fn default() -> Self {
+        Self {
+            width: 2.0,
+            depth: 2.0,
+        }
+    }
+}
+
+impl Quad {
+    /// Creates a new quad with the given size along the X and Z axes.
+    pub fn new(width: f32, depth: f32) -> Self {
+        Self {
+            width: width.abs(),
+            depth: depth.abs(),
+        }
+    }
+
+    /// Returns the size of this quad along the X axis.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Returns the size of this quad along the Z axis.
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    /// Changes the size of `self` along the X axis to `width`.
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width.abs();
+    }
+
+    /// Changes the size of `self` along the Z axis to `depth`.
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth.abs();
+    }
+}
+
+#[cfg_attr(feature = "serde-support", typetag::serde)]
+impl Shape for Quad {
+    fn local_bounds(&self) -> Aabb {
+        let (x, z) = (self.width / 2.0, self.depth / 2.0);
+        Aabb::new(Point3::new(-x, 0.0, -z), Point3::new(x, 0.0, z))
+    }
+
+    fn local_normal_at(&self, _: &Point3) -> Vec3 {
+        Vec3::unit_y()
+    }
+
+    /// Stretches the texture over the whole quad.
+    fn uv_at(&self, p: &Point3) -> (f32, f32) {
+        (p.x / self.width + 0.5, p.z / self.depth + 0.5)
+    }
+}
+
+impl RayCast for Quad {
+    fn intersections_in_local_space(&self, ray: &Ray) -> RayIntersections {
+        if ray.dir.y.abs() < EPSILON {
+            return RayIntersections::default();
+        }
+
+        let toi = -ray.origin.y / ray.dir.y;
+        let p = ray.point_at(toi);
+
+        if p.x.abs() > self.width / 2.0 || p.z.abs() > self.depth / 2.0 {
+            return RayIntersections::default();
+        }
+
+        iter::once(RayIntersection {
+            toi,
+            normal: Vec3::unit_y(),
+        })
+        .collect()
+    }
+}
@@ -1,22 +1,38 @@
 //! The unit sphere shape.
 
+use core::f32::consts::PI;
+
 use crate::{
-    math::Point3,
-    query::{Ray, RayCast, RayIntersection, RayIntersections},
+    math::{float, Point3, Vec3},
+    query::{Aabb, Ray, RayCast, RayIntersection, RayIntersections},
 };
 
-use super::Shape;
+use super::{azimuth, Shape};
 
 /// The unit sphere.
 #[cfg_attr(
     feature = "serde-support",
     derive(serde::Serialize, serde::Deserialize)
 )]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Sphere;
 
 #[cfg_attr(feature = "serde-support", typetag::serde)]
-impl Shape for Sphere {}
+impl Shape for Sphere {
+    fn local_bounds(&self) -> Aabb {
+        Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0))
+    }
+
+    fn local_normal_at(&self, p: &Point3) -> Vec3 {
+        (*p).into()
+    }
+
+    /// Maps the longitude of `p` to `u` and its latitude to `v`, as in a world map.
+    fn uv_at(&self, p: &Point3) -> (f32, f32) {
+        let polar = float::atan2(float::sqrt(p.x * p.x + p.z * p.z), p.y);
+        (azimuth(p), 1.0 - polar / PI)
+    }
+}
 
 impl RayCast for Sphere {
     fn intersections_in_local_space(&self, ray: &Ray) -> RayIntersections {
@@ -29,18 +45,15 @@ impl RayCast for Sphere {
         let discriminant = b * b - 4. * a * c;
 
         if discriminant < 0. {
-            return RayIntersections::from(Vec::new().into_iter());
+            return RayIntersections::default();
         }
 
-        RayIntersections::from(
-            [
-                (-b - discriminant.sqrt()) / (2. * a),
-                (-b + discriminant.sqrt()) / (2. * a),
-            ]
-            .iter()
-            .map(|&toi| RayIntersection::new(toi, (ray.origin + ray.dir * toi).into()))
-            .collect::<Vec<_>>()
-            .into_iter(),
-        )
+        [
+            (-b - float::sqrt(discriminant)) / (2. * a),
+            (-b + float::sqrt(discriminant)) / (2. * a),
+        ]
+        .iter()
+        .map(|&toi| RayIntersection::new(toi, (ray.origin + ray.dir * toi).into()))
+        .collect()
     }
 }
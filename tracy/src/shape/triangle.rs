@@ -0,0 +1,148 @@
+//! The triangle shape.
+
+use crate::{
+    math::{Point3, Vec3, EPSILON},
+    query::{Aabb, Ray, RayCast, RayIntersection, RayIntersections},
+};
+
+use super::Shape;
+
+/// A flat triangle, given by its three vertices.
+///
+/// In scene files, a triangle is written as the list of its vertices.
+#[cfg_attr(
+    feature = "serde-support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "[Point3; 3]", into = "[Point3; 3]")
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Triangle {
+    vertices: [Point3; 3],
+    e1: Vec3,
+    e2: Vec3,
+    normal: Vec3,
+}
+
+impl From<[Point3; 3]> for Triangle {
+    fn from([p1, p2, p3]: [Point3; 3]) -> Self {
+        Self::new(p1, p2, p3)
+    }
+}
+
+impl From<Triangle> for [Point3; 3] {
+    fn from(t: Triangle) -> Self {
+        t.vertices
+    }
+}
+
+impl Triangle {
+    /// Creates a new triangle from its vertices.
+    ///
+    /// The normal of the triangle faces the side from which the vertices appear in clockwise
+    /// order, as in the book.
+    pub fn new(p1: Point3, p2: Point3, p3: Point3) -> Self {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+
+        Self {
+            vertices: [p1, p2, p3],
+            e1,
+            e2,
+            normal: e2.cross(&e1).normalize(),
+        }
+    }
+
+    /// Returns the vertices of this triangle.
+    pub fn vertices(&self) -> &[Point3; 3] {
+        &self.vertices
+    }
+
+    /// Returns the normal of this triangle.
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    /// Returns true if the vertices of this triangle are aligned, so that it has no area.
+    pub fn is_degenerate(&self) -> bool {
+        self.e2.cross(&self.e1).length() < EPSILON * EPSILON
+    }
+
+    /// Returns the distance between `p` and the closest point of this triangle.
+    pub(crate) fn distance_to(&self, p: &Point3) -> f32 {
+        let [a, b, c] = self.vertices;
+        let ap = *p - a;
+
+        // Express the projection of `p` on the plane of the triangle in barycentric coordinates
+        let height = self.normal.dot(&ap);
+        let q = ap - self.normal * height;
+
+        let (d11, d12, d22) = (
+            self.e1.dot(&self.e1),
+            self.e1.dot(&self.e2),
+            self.e2.dot(&self.e2),
+        );
+        let (dq1, dq2) = (q.dot(&self.e1), q.dot(&self.e2));
+        let denom = d11 * d22 - d12 * d12;
+        let v = (d22 * dq1 - d12 * dq2) / denom;
+        let w = (d11 * dq2 - d12 * dq1) / denom;
+
+        if v >= 0.0 && w >= 0.0 && v + w <= 1.0 {
+            return height.abs();
+        }
+
+        // Otherwise, the closest point lies on one of the edges
+        [(a, b), (b, c), (c, a)]
+            .iter()
+            .map(|(start, end)| {
+                let edge = *end - *start;
+                let t = ((*p - *start).dot(&edge) / edge.dot(&edge)).clamp(0.0, 1.0);
+                (*p - (*start + edge * t)).length()
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Returns the time of impact of `ray` with this triangle, using the Möller-Trumbore
+    /// algorithm.
+    pub(crate) fn toi(&self, ray: &Ray) -> Option<f32> {
+        let dir_cross_e2 = ray.dir.cross(&self.e2);
+        let det = self.e1.dot(&dir_cross_e2);
+        if det.abs() < EPSILON {
+            return None;
+        }
+
+        let f = 1.0 / det;
+        let p1_to_origin = ray.origin - self.vertices[0];
+        let u = f * p1_to_origin.dot(&dir_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let origin_cross_e1 = p1_to_origin.cross(&self.e1);
+        let v = f * ray.dir.dot(&origin_cross_e1);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        Some(f * self.e2.dot(&origin_cross_e1))
+    }
+}
+
+#[cfg_attr(feature = "serde-support", typetag::serde)]
+impl Shape for Triangle {
+    fn local_bounds(&self) -> Aabb {
+        Aabb::from_points(self.vertices.iter().copied())
+    }
+
+    fn local_normal_at(&self, _: &Point3) -> Vec3 {
+        self.normal
+    }
+}
+
+impl RayCast for Triangle {
+    fn intersections_in_local_space(&self, ray: &Ray) -> RayIntersections {
+        self.toi(ray)
+            .map(|toi| RayIntersection::new(toi, self.normal))
+            .into_iter()
+            .collect()
+    }
+}
@@ -0,0 +1,239 @@
+//! Golden-image regression testing.
+//!
+//! A [`GoldenImage`] compares a rendered [`Canvas`] against a reference image stored on disk in
+//! binary PPM format. When the two differ by more than the allowed [`Tolerance`], the rendered
+//! image and a visual diff are written next to the reference to help track down the regression.
+//!
+//! References are created or updated by running the checks with the `TRACY_UPDATE_GOLDEN`
+//! environment variable set, in which case the rendered image always replaces the reference.
+//!
+//! Rendering involves no time-dependent behavior, the random numbers used by path tracing and
+//! multisampling are seeded from the pixel coordinates, and each pixel is computed independently
+//! of how the work is split across threads, so the same scene always produces the same image on a
+//! given machine. With the `deterministic` feature, the output is also identical
+//! across machines, and references can be checked with [`Tolerance::EXACT`]. The feature takes
+//! precedence over `fast-math`, whose optimizations are then left out.
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    query::World,
+    rendering::{Camera, Canvas, CanvasDiff, Color},
+};
+
+/// Environment variable that, when set, makes checks overwrite their reference image.
+pub const UPDATE_ENV_VAR: &str = "TRACY_UPDATE_GOLDEN";
+
+/// Maximum allowed difference between a rendered image and its reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Maximum root mean square error, over 8-bit channel values.
+    pub max_rmse: f64,
+    /// Maximum difference of any channel of any pixel.
+    pub max_error: u8,
+}
+
+impl Tolerance {
+    /// A tolerance requiring the rendered image to be identical to its reference.
+    pub const EXACT: Tolerance = Tolerance {
+        max_rmse: 0.,
+        max_error: 0,
+    };
+
+    /// Returns true if `diff` is within this tolerance.
+    pub fn accepts(&self, diff: &CanvasDiff) -> bool {
+        diff.rmse <= self.max_rmse && diff.max_error <= self.max_error
+    }
+}
+
+impl Default for Tolerance {
+    /// Allows for small rounding differences between platforms, while catching any visible
+    /// shading change.
+    fn default() -> Self {
+        Self {
+            max_rmse: 0.5,
+            max_error: 8,
+        }
+    }
+}
+
+/// Reasons for a golden-image check to fail.
+#[derive(Debug)]
+pub enum GoldenError {
+    /// The reference image could not be read or the rendered image could not be saved.
+    Io(PathBuf, io::Error),
+    /// The reference image is not a valid binary PPM file.
+    InvalidReference(PathBuf),
+    /// The rendered image and its reference have different sizes.
+    SizeMismatch {
+        /// Size of the reference image.
+        expected: (u32, u32),
+        /// Size of the rendered image.
+        actual: (u32, u32),
+    },
+    /// The rendered image differs from its reference by more than the allowed tolerance.
+    Mismatch {
+        /// Metrics of the difference between the two images.
+        diff: CanvasDiff,
+        /// Path where the rendered image was saved.
+        actual: PathBuf,
+        /// Path where the visual diff was saved.
+        diff_image: PathBuf,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            GoldenError::InvalidReference(path) => {
+                write!(f, "{}: not a valid binary PPM image", path.display())
+            }
+            GoldenError::SizeMismatch { expected, actual } => write!(
+                f,
+                "size mismatch: expected {}x{}, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            GoldenError::Mismatch {
+                diff,
+                actual,
+                diff_image,
+            } => write!(
+                f,
+                "{} pixels differ (max error {}, RMSE {:.3}), see {} and {}",
+                diff.differing_pixels,
+                diff.max_error,
+                diff.rmse,
+                actual.display(),
+                diff_image.display()
+            ),
+        }
+    }
+}
+
+impl Error for GoldenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GoldenError::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A reference image against which renders are checked.
+#[derive(Debug, Clone)]
+pub struct GoldenImage {
+    path: PathBuf,
+    tolerance: Tolerance,
+}
+
+impl GoldenImage {
+    /// Creates a check against the reference image at `path`, with the default tolerance.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            tolerance: Tolerance::default(),
+        }
+    }
+
+    /// Sets the maximum allowed difference from the reference image.
+    pub fn with_tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Returns the path of the reference image.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Renders `world` through `camera` and checks the result against the reference image.
+    pub fn check_scene(&self, world: &World, camera: &Camera) -> Result<CanvasDiff, GoldenError> {
+        self.check(&camera.render(world))
+    }
+
+    /// Checks `canvas` against the reference image, returning the metrics of their difference.
+    ///
+    /// If [`UPDATE_ENV_VAR`] is set, `canvas` is saved as the new reference instead.
+    pub fn check(&self, canvas: &Canvas) -> Result<CanvasDiff, GoldenError> {
+        if std::env::var_os(UPDATE_ENV_VAR).is_some() {
+            write_ppm(&self.path, canvas)?;
+            return Ok(CanvasDiff::default());
+        }
+
+        let data = fs::read(&self.path).map_err(|e| GoldenError::Io(self.path.clone(), e))?;
+        let reference = Canvas::from_binary_ppm(&data)
+            .ok_or_else(|| GoldenError::InvalidReference(self.path.clone()))?;
+
+        let diff = canvas
+            .diff(&reference)
+            .ok_or_else(|| GoldenError::SizeMismatch {
+                expected: (reference.width(), reference.height()),
+                actual: (canvas.width(), canvas.height()),
+            })?;
+
+        if self.tolerance.accepts(&diff) {
+            return Ok(diff);
+        }
+
+        let actual = self.artifact_path("actual");
+        let diff_image = self.artifact_path("diff");
+
+        write_ppm(&actual, canvas)?;
+        write_ppm(&diff_image, &visual_diff(&reference, canvas))?;
+
+        Err(GoldenError::Mismatch {
+            diff,
+            actual,
+            diff_image,
+        })
+    }
+
+    /// Checks `canvas` against the reference image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the check fails.
+    pub fn assert(&self, canvas: &Canvas) {
+        if let Err(e) = self.check(canvas) {
+            panic!("golden image {} check failed: {}", self.path.display(), e);
+        }
+    }
+
+    /// Returns the path of an artifact stored next to the reference, eg. `name.diff.ppm`.
+    fn artifact_path(&self, kind: &str) -> PathBuf {
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        self.path.with_file_name(format!("{}.{}.ppm", stem, kind))
+    }
+}
+
+/// Builds an image highlighting in red the pixels of `actual` that differ from `reference`,
+/// over a dimmed grayscale copy of the reference.
+pub fn visual_diff(reference: &Canvas, actual: &Canvas) -> Canvas {
+    let mut out = Canvas::new(reference.width(), reference.height());
+
+    for (o, (r, a)) in out.iter_mut().zip(reference.iter().zip(actual.iter())) {
+        let err = (r.r - a.r)
+            .abs()
+            .max((r.g - a.g).abs())
+            .max((r.b - a.b).abs());
+
+        *o = if r.to_rgb888() == a.to_rgb888() {
+            let luma = 0.2126 * r.r + 0.7152 * r.g + 0.0722 * r.b;
+            Color::WHITE * (luma * 0.3)
+        } else {
+            // Make even the smallest differences stand out
+            Color::new(0.5 + err.min(1.) * 0.5, 0., 0.)
+        };
+    }
+
+    out
+}
+
+fn write_ppm(path: &Path, canvas: &Canvas) -> Result<(), GoldenError> {
+    fs::write(path, canvas.convert_to_binary_ppm()).map_err(|e| GoldenError::Io(path.into(), e))
+}
@@ -139,3 +139,53 @@ fn cross_product_of_two_vectors() {
     assert_abs_diff!(a.cross(&b), Vec3::new(-1., 2., -1.));
     assert_abs_diff!(b.cross(&a), Vec3::new(1., -2., 1.));
 }
+
+#[test]
+fn normalizing_a_vector_is_accurate() {
+    let mut max_error = 0f64;
+
+    for i in 1..1000 {
+        let v = Vec3::new(i as f32 * 0.37, -(i as f32).sqrt(), 1000. / i as f32);
+        let n = v.normalize();
+
+        let exact = {
+            let (x, y, z) = (v.x as f64, v.y as f64, v.z as f64);
+            let length = (x * x + y * y + z * z).sqrt();
+            [x / length, y / length, z / length]
+        };
+
+        for (&actual, exact) in [n.x, n.y, n.z].iter().zip(&exact) {
+            max_error = max_error.max((actual as f64 - exact).abs());
+        }
+    }
+
+    // The reciprocal used by the `fast-math` feature adds a rounding to the strict division
+    assert!(
+        max_error <= 2. * f32::EPSILON as f64,
+        "absolute error of {:e}",
+        max_error
+    );
+}
+
+#[test]
+#[cfg(feature = "deterministic")]
+fn normalizing_vectors_deterministically() {
+    // Even when `fast-math` is enabled too, each component is divided by the length
+    for &v in &[Vec3::new(1.0, 2.0, 3.0), Vec3::new(-0.1, 7.3, 1e-3)] {
+        let length = v.length();
+        assert_eq!(
+            v.normalize(),
+            Vec3::new(v.x / length, v.y / length, v.z / length)
+        );
+    }
+}
+
+#[test]
+fn displaying_points_and_vectors() {
+    assert_eq!(Point3::new(4.0, -4.25, 3.0).to_string(), "(4, -4.25, 3)");
+    assert_eq!(Vec3::new(1.0, 0.5, -2.0).to_string(), "(1, 0.5, -2)");
+    assert_eq!(
+        format!("{:.2}", Vec3::new(1.0, 0.5, -2.0)),
+        "(1.00, 0.50, -2.00)"
+    );
+}
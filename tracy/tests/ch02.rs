@@ -1,4 +1,9 @@
-use tracy::rendering::{Canvas, Color};
+use std::io;
+
+use tracy::{
+    rendering::{Canvas, Color, Kernel, PpmFormat, PpmWriter, ToneMapOperator, ToneMapping},
+    Error,
+};
 pub use utils::*;
 
 mod utils;
@@ -38,6 +43,57 @@ fn multiplying_colors() {
     assert_abs_diff!(c1 * c2, Color::new(0.9, 0.2, 0.04));
 }
 
+#[test]
+fn creating_colors_from_hex_codes() {
+    assert_abs_diff!(
+        Color::from_hex("#ffcc00").unwrap(),
+        Color::new(1.0, 0.8, 0.0)
+    );
+    assert_abs_diff!(Color::from_hex("008080").unwrap(), Color::TEAL);
+    assert_abs_diff!(Color::from_hex("#fc0").unwrap(), Color::new(1.0, 0.8, 0.0));
+    assert!(Color::from_hex("#ffcc0").is_none());
+    assert!(Color::from_hex("#ffcc0g").is_none());
+    assert!(Color::from_hex("#+f+f+f").is_none());
+}
+
+#[test]
+fn creating_colors_from_css_names() {
+    assert_abs_diff!(Color::from_css_name("fuchsia").unwrap(), Color::FUCHSIA);
+    assert_abs_diff!(
+        Color::from_css_name("Green").unwrap(),
+        Color::from_rgb888(0, 128, 0)
+    );
+    assert!(Color::from_css_name("rebeccapurple").is_none());
+}
+
+#[test]
+fn converting_colors_from_hsv_and_hsl() {
+    assert_abs_diff!(Color::from_hsv(0., 1., 1.), Color::RED);
+    assert_abs_diff!(Color::from_hsv(120., 1., 0.5), Color::new(0.0, 0.5, 0.0));
+    assert_abs_diff!(Color::from_hsv(-60., 1., 1.), Color::FUCHSIA);
+    assert_abs_diff!(Color::from_hsl(240., 1., 0.5), Color::BLUE);
+    assert_abs_diff!(Color::from_hsl(60., 1., 0.25), Color::new(0.5, 0.5, 0.0));
+    assert_abs_diff!(Color::from_hsl(0., 0., 0.5), Color::new(0.5, 0.5, 0.5));
+}
+
+#[test]
+fn converting_colors_to_hsv_and_hsl() {
+    let c = Color::from_hex("#ffcc00").unwrap();
+
+    let (h, s, v) = c.to_hsv();
+    assert_f32!(h, 48.);
+    assert_f32!(s, 1.);
+    assert_f32!(v, 1.);
+
+    let (h, s, l) = c.to_hsl();
+    assert_f32!(h, 48.);
+    assert_f32!(s, 1.);
+    assert_f32!(l, 0.5);
+
+    assert_abs_diff!(Color::from_hsl(h, s, l), c);
+    assert_eq!(Color::GRAY.to_hsl().1, 0.);
+}
+
 #[test]
 fn creating_a_canvas() {
     let c = Canvas::new(10, 20);
@@ -54,6 +110,134 @@ fn writing_pixels_to_a_canvas() {
     assert_abs_diff!(c.get(2, 3).unwrap(), red);
 }
 
+#[test]
+fn iterating_over_the_pixels_of_a_canvas() {
+    let mut c = Canvas::new(3, 2);
+    for (x, y, pixel) in c.enumerate_pixels_mut() {
+        *pixel = Color::new(x as f32, y as f32, 0.);
+    }
+
+    assert_eq!(
+        c.enumerate_pixels()
+            .map(|(x, y, _)| (x, y))
+            .collect::<Vec<_>>(),
+        vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]
+    );
+    assert!(c
+        .enumerate_pixels()
+        .all(|(x, y, p)| *p == Color::new(x as f32, y as f32, 0.)));
+}
+
+#[test]
+fn iterating_over_the_rows_of_a_canvas() {
+    let mut c = Canvas::new(3, 2);
+    for (y, row) in c.rows_mut().enumerate() {
+        row.fill(Color::new(y as f32, 0., 0.));
+    }
+
+    assert_eq!(c.rows().len(), 2);
+    assert!(c.rows().nth(1).unwrap().iter().all(|p| p.r == 1.));
+    assert_eq!(Canvas::default().rows().count(), 0);
+    assert_eq!(Canvas::new(0, 5).rows().count(), 0);
+}
+
+#[test]
+fn building_convolution_kernels() {
+    let k = Kernel::new(3, 1, vec![1., 2., 3.]).unwrap();
+    assert_eq!(k.get(-1, 0), Some(1.));
+    assert_eq!(k.get(1, 0), Some(3.));
+    assert_eq!(k.get(0, 1), None);
+    assert_eq!(k.normalized().weights(), &[1. / 6., 2. / 6., 3. / 6.]);
+
+    assert_eq!(
+        Kernel::new(2, 3, vec![0.; 6]),
+        Err(Error::KernelSize {
+            width: 2,
+            height: 3,
+            len: 6
+        })
+    );
+    assert!(Kernel::new(3, 3, vec![0.; 8]).is_err());
+
+    let g = Kernel::gaussian(2, 1.);
+    assert_eq!((g.width(), g.height()), (5, 5));
+    assert_f32!(g.weights().iter().sum::<f32>(), 1.);
+    assert_f32!(g.get(-1, 2).unwrap(), g.get(2, 1).unwrap());
+    assert!(g.get(0, 0).unwrap() > g.get(1, 0).unwrap());
+}
+
+#[test]
+fn convolving_a_canvas() {
+    let mut c = Canvas::new(5, 5);
+    c.put(2, 2, Color::new(9., 9., 9.));
+
+    let blurred = c.convolve(&Kernel::box_blur(1));
+    assert_abs_diff!(blurred.get(1, 1).unwrap(), Color::WHITE);
+    assert_abs_diff!(blurred.get(2, 2).unwrap(), Color::WHITE);
+    assert_abs_diff!(blurred.get(0, 0).unwrap(), Color::BLACK);
+
+    let edges = c.convolve(&Kernel::edge_detect());
+    assert_abs_diff!(edges.get(2, 2).unwrap(), Color::new(72., 72., 72.));
+    assert_abs_diff!(edges.get(2, 1).unwrap(), Color::new(-9., -9., -9.));
+}
+
+#[test]
+fn a_gaussian_kernel_without_deviation_is_the_identity() {
+    let mut c = Canvas::new(3, 3);
+    c.put(1, 1, Color::new(9., 9., 9.));
+
+    for &sigma in &[0., -1., f32::NAN, 1e-30] {
+        let g = Kernel::gaussian(1, sigma);
+        assert_eq!((g.width(), g.height()), (3, 3));
+        assert_eq!(g.get(0, 0), Some(1.));
+        assert_eq!(g.weights().iter().sum::<f32>(), 1.);
+
+        assert!(c.convolve(&g).iter().eq(c.iter()));
+    }
+}
+
+#[test]
+fn convolving_extends_the_edges_of_a_canvas() {
+    let mut c = Canvas::new(2, 1);
+    c.put(0, 0, Color::WHITE);
+
+    let sharpened = c.convolve(&Kernel::sharpen());
+    assert_abs_diff!(sharpened.get(0, 0).unwrap(), Color::new(2., 2., 2.));
+    assert_abs_diff!(sharpened.get(1, 0).unwrap(), Color::new(-1., -1., -1.));
+
+    let uniform = Canvas::new(4, 3).convolve(&Kernel::gaussian(3, 2.));
+    assert!(uniform.iter().all(|p| p.abs_diff_eq(&Color::BLACK, 1e-6)));
+}
+
+#[test]
+fn writing_pixels_outside_of_a_canvas() {
+    let mut c = Canvas::new(10, 20);
+    let red = Color::new(1., 0., 0.);
+
+    assert_eq!(
+        c.try_put(10, 3, red),
+        Err(Error::OutOfBounds {
+            position: (10, 3),
+            size: (10, 20)
+        })
+    );
+    assert!(c.iter().all(|&p| p == Color::BLACK));
+
+    assert_eq!(c.try_put(9, 19, red), Ok(()));
+    assert_abs_diff!(c.get(9, 19).unwrap(), red);
+}
+
+#[test]
+fn reading_pixels_outside_of_a_canvas() {
+    let mut c = Canvas::new(10, 20);
+    c.put(0, 1, Color::new(1., 0., 0.));
+
+    assert!(c.get(10, 0).is_none());
+    assert!(c.get(0, 20).is_none());
+    assert!(c.get(u32::MAX, u32::MAX).is_none());
+    assert_eq!(c.get(0, 1), Some(&Color::new(1., 0., 0.)));
+}
+
 #[test]
 fn constructing_the_ppm_header() {
     let c = Canvas::new(5, 3);
@@ -104,3 +288,125 @@ fn ppm_files_are_terminated_by_a_newline_character() {
     let ppm = c.convert_to_ppm();
     assert_eq!(ppm.chars().last(), Some('\n'));
 }
+
+#[test]
+fn writing_a_ppm_file_one_row_at_a_time() {
+    let mut c = Canvas::new(10, 2);
+    c.put(3, 1, Color::new(0.5, 0.25, 1.));
+
+    for &format in &[PpmFormat::Ascii, PpmFormat::Binary] {
+        let mut w = PpmWriter::new(Vec::new(), 10, 2, format).unwrap();
+        for row in c.scanlines() {
+            w.write_row(row).unwrap();
+        }
+
+        assert_eq!(w.rows_written(), 2);
+        assert_eq!(
+            w.finish().unwrap(),
+            c.write_ppm(Vec::new(), format).unwrap()
+        );
+    }
+}
+
+#[test]
+fn writing_invalid_rows_to_a_ppm_file() {
+    let mut w = PpmWriter::new(Vec::new(), 2, 1, PpmFormat::Binary).unwrap();
+
+    let err = w.write_row(&[Color::BLACK]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    w.write_row(&[Color::BLACK, Color::WHITE]).unwrap();
+
+    let err = w.write_row(&[Color::BLACK, Color::WHITE]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn finishing_an_incomplete_ppm_file() {
+    let mut w = PpmWriter::new(Vec::new(), 1, 2, PpmFormat::Ascii).unwrap();
+    w.write_row(&[Color::WHITE]).unwrap();
+
+    assert_eq!(w.finish().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn constructing_the_binary_ppm_pixel_data() {
+    let mut c = Canvas::new(2, 2);
+    c.put(0, 0, Color::new(1.5, 0., 0.));
+    c.put(1, 1, Color::new(0., 0.5, 1.));
+
+    let ppm = c.convert_to_binary_ppm();
+    assert_eq!(&ppm[..11], b"P6\n2 2\n255\n");
+    assert_eq!(&ppm[11..], &[255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 255][..]);
+}
+
+#[test]
+fn reading_a_binary_ppm_file() {
+    let mut c = Canvas::new(2, 2);
+    c.put(0, 0, Color::new(1.5, 0., 0.));
+    c.put(1, 1, Color::new(0., 0.4, 1.));
+
+    let ppm = c.convert_to_binary_ppm();
+    let read = Canvas::from_binary_ppm(&ppm).unwrap();
+
+    assert_eq!(read.width(), 2);
+    assert_eq!(read.height(), 2);
+    assert_abs_diff!(*read.get(0, 0).unwrap(), Color::new(1., 0., 0.));
+    assert_abs_diff!(*read.get(1, 1).unwrap(), Color::new(0., 0.4, 1.));
+    assert_eq!(read.convert_to_binary_ppm(), ppm);
+}
+
+#[test]
+fn reading_an_invalid_ppm_file() {
+    assert!(Canvas::from_binary_ppm(b"P3\n1 1\n255\n0 0 0\n").is_none());
+    assert!(Canvas::from_binary_ppm(b"P6\n2 2\n255\n\x00\x00\x00").is_none());
+    assert!(Canvas::from_binary_ppm(b"P6\n# comment\n1 1\n255\n\x00\x00\x00").is_some());
+}
+
+#[test]
+fn reading_a_ppm_file_with_an_oversized_header() {
+    assert!(Canvas::from_binary_ppm(b"P6\n65536 65536\n255\n").is_none());
+    assert!(Canvas::from_binary_ppm(b"P6\n4294967295 4294967295\n255\n").is_none());
+}
+
+#[test]
+fn comparing_canvases() {
+    let a = Canvas::new(2, 2);
+    let mut b = Canvas::new(2, 2);
+
+    assert!(a.diff(&b).unwrap().is_identical());
+    assert!(a.diff(&Canvas::new(2, 3)).is_none());
+
+    b.put(1, 0, Color::new(1., 0., 0.));
+    let diff = a.diff(&b).unwrap();
+
+    assert_eq!(diff.differing_pixels, 1);
+    assert_eq!(diff.max_error, 255);
+    assert_f32!(diff.mean_error as f32, 255. / 12.);
+    assert_f32!(diff.rmse as f32, (255. * 255. / 12f32).sqrt());
+}
+
+#[test]
+fn the_default_tone_mapping_clamps_colors() {
+    let tm = ToneMapping::default();
+
+    assert!(tm.is_identity());
+    assert_eq!(tm.map(Color::new(0.5, 1.5, -0.5)), (128, 255, 0));
+}
+
+#[test]
+fn tone_mapping_with_exposure_and_gamma() {
+    let tm = ToneMapping {
+        operator: ToneMapOperator::Reinhard,
+        exposure: 1.0,
+        gamma: 2.0,
+    };
+
+    let mut c = Canvas::new(1, 1);
+    c.put(0, 0, Color::new(0.5, 0., 1.5));
+
+    assert_abs_diff!(
+        *tm.apply(&c).get(0, 0).unwrap(),
+        Color::new(0.5f32.sqrt(), 0., 0.75f32.sqrt())
+    );
+}
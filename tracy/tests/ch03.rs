@@ -1,16 +1,43 @@
-use tracy::math::{Matrix, Point3};
+use tracy::{
+    math::{Matrix, Point3},
+    Error,
+};
 pub use utils::*;
 
 mod utils;
 
 #[test]
-fn constructing_and_inspecting_a_4x4_matrix() {
-    let m = Matrix::from_row_slice(
-        4,
-        [
-            1., 2., 3., 4., 5.5, 6.5, 7.5, 8.5, 9., 10., 11., 12., 13.5, 14.5, 15.5, 16.5,
-        ],
+fn constructing_a_matrix_from_a_slice_of_the_wrong_size() {
+    assert_eq!(
+        Matrix::<3>::try_from_row_slice([1., 2., 3., 4.]),
+        Err(Error::MatrixSize { order: 3, len: 4 })
+    );
+    assert_eq!(
+        Matrix::<4>::try_from_column_slice([0.; 25]),
+        Err(Error::MatrixSize { order: 4, len: 25 })
     );
+    assert_eq!(
+        Matrix::<2>::try_from_row_slice([-3., 5., 1., -2.]),
+        Ok(Matrix::<2>::from_row_slice([-3., 5., 1., -2.]))
+    );
+}
+
+#[test]
+fn constructing_a_matrix_from_rows_and_columns() {
+    let rows = Matrix::from_rows([[1., 2., 3.], [4., 5., 6.], [7., 8., 9.]]);
+    let columns = Matrix::from_columns([[1., 4., 7.], [2., 5., 8.], [3., 6., 9.]]);
+
+    assert_eq!(rows, columns);
+    assert_eq!(rows.order(), 3);
+    assert_f32!(rows[(0, 2)], 3.);
+    assert_f32!(rows[(2, 0)], 7.);
+}
+
+#[test]
+fn constructing_and_inspecting_a_4x4_matrix() {
+    let m = Matrix::<4>::from_row_slice([
+        1., 2., 3., 4., 5.5, 6.5, 7.5, 8.5, 9., 10., 11., 12., 13.5, 14.5, 15.5, 16.5,
+    ]);
 
     for (pos, val) in vec![
         ((0, 0), 1.),
@@ -29,7 +56,7 @@ fn constructing_and_inspecting_a_4x4_matrix() {
 
 #[test]
 fn a_2x2_matrix_ought_to_be_representable() {
-    let m = Matrix::from_row_slice(2, [-3., 5., 1., -2.]);
+    let m = Matrix::<2>::from_row_slice([-3., 5., 1., -2.]);
 
     for (pos, val) in vec![((0, 0), -3.), ((0, 1), 5.), ((1, 0), 1.), ((1, 1), -2.)].into_iter() {
         assert_f32!(m[pos], val);
@@ -38,7 +65,7 @@ fn a_2x2_matrix_ought_to_be_representable() {
 
 #[test]
 fn a_3x3_matrix_ought_to_be_representable() {
-    let m = Matrix::from_row_slice(3, [-3., 5., 0., 1., -2., -7., 0., 1., 1.]);
+    let m = Matrix::<3>::from_row_slice([-3., 5., 0., 1., -2., -7., 0., 1., 1.]);
 
     for (pos, val) in vec![((0, 0), -3.), ((1, 1), -2.), ((2, 2), 1.)].into_iter() {
         assert_f32!(m[pos], val);
@@ -47,76 +74,52 @@ fn a_3x3_matrix_ought_to_be_representable() {
 
 #[test]
 fn matrix_equality_with_identical_matrices() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            1., 2., 3., 4., 5., 6., 7., 8., 9., 8., 7., 6., 5., 4., 3., 2.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        1., 2., 3., 4., 5., 6., 7., 8., 9., 8., 7., 6., 5., 4., 3., 2.,
+    ]);
 
-    let b = Matrix::from_row_slice(
-        4,
-        [
-            1., 2., 3., 4., 5., 6., 7., 8., 9., 8., 7., 6., 5., 4., 3., 2.,
-        ],
-    );
+    let b = Matrix::<4>::from_row_slice([
+        1., 2., 3., 4., 5., 6., 7., 8., 9., 8., 7., 6., 5., 4., 3., 2.,
+    ]);
 
     assert_abs_diff!(a, &b);
 }
 
 #[test]
 fn matrix_equality_with_different_matrices() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            1., 2., 3., 4., 5., 6., 7., 8., 9., 8., 7., 6., 5., 4., 3., 2.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        1., 2., 3., 4., 5., 6., 7., 8., 9., 8., 7., 6., 5., 4., 3., 2.,
+    ]);
 
-    let b = Matrix::from_row_slice(
-        4,
-        [
-            2., 3., 4., 5., 6., 7., 8., 9., 8., 7., 6., 5., 4., 3., 2., 1.,
-        ],
-    );
+    let b = Matrix::<4>::from_row_slice([
+        2., 3., 4., 5., 6., 7., 8., 9., 8., 7., 6., 5., 4., 3., 2., 1.,
+    ]);
 
     assert_not_abs_diff!(a, b);
 }
 
 #[test]
 fn multiplying_two_matrices() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            1., 2., 3., 4., 5., 6., 7., 8., 9., 8., 7., 6., 5., 4., 3., 2.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        1., 2., 3., 4., 5., 6., 7., 8., 9., 8., 7., 6., 5., 4., 3., 2.,
+    ]);
 
-    let b = Matrix::from_row_slice(
-        4,
-        [
-            -2., 1., 2., 3., 3., 2., 1., -1., 4., 3., 6., 5., 1., 2., 7., 8.,
-        ],
-    );
+    let b = Matrix::<4>::from_row_slice([
+        -2., 1., 2., 3., 3., 2., 1., -1., 4., 3., 6., 5., 1., 2., 7., 8.,
+    ]);
 
-    let prod = Matrix::from_row_slice(
-        4,
-        [
-            20., 22., 50., 48., 44., 54., 114., 108., 40., 58., 110., 102., 16., 26., 46., 42.,
-        ],
-    );
+    let prod = Matrix::<4>::from_row_slice([
+        20., 22., 50., 48., 44., 54., 114., 108., 40., 58., 110., 102., 16., 26., 46., 42.,
+    ]);
 
     assert_abs_diff!(a * b, prod);
 }
 
 #[test]
 fn a_matrix_multiplied_by_a_tuple() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            1., 2., 3., 4., 2., 4., 4., 2., 8., 6., 4., 1., 0., 0., 0., 1.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        1., 2., 3., 4., 2., 4., 4., 2., 8., 6., 4., 1., 0., 0., 0., 1.,
+    ]);
 
     let b = Point3::new(1., 2., 3.);
 
@@ -125,79 +128,67 @@ fn a_matrix_multiplied_by_a_tuple() {
 
 #[test]
 fn multiplying_a_matrix_by_the_identity_matrix() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            0., 1., 2., 4., 1., 2., 4., 8., 2., 4., 8., 16., 4., 8., 16., 32.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        0., 1., 2., 4., 1., 2., 4., 8., 2., 4., 8., 16., 4., 8., 16., 32.,
+    ]);
 
-    assert_abs_diff!(&a * Matrix::identity(4), a);
+    assert_abs_diff!(&a * Matrix::identity(), a);
 }
 
 #[test]
 fn multiplying_the_identity_matrix_by_a_tuple() {
     let a = Point3::new(1., 2., 3.);
-    assert_abs_diff!(Matrix::identity(4) * a, a);
+    assert_abs_diff!(Matrix::identity() * a, a);
 }
 
 #[test]
 fn transposing_a_matrix() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            0., 9., 3., 0., 9., 8., 0., 8., 1., 8., 5., 3., 0., 0., 5., 8.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        0., 9., 3., 0., 9., 8., 0., 8., 1., 8., 5., 3., 0., 0., 5., 8.,
+    ]);
 
-    let transpose = Matrix::from_row_slice(
-        4,
-        [
-            0., 9., 1., 0., 9., 8., 8., 0., 3., 0., 5., 5., 0., 8., 3., 8.,
-        ],
-    );
+    let transpose = Matrix::<4>::from_row_slice([
+        0., 9., 1., 0., 9., 8., 8., 0., 3., 0., 5., 5., 0., 8., 3., 8.,
+    ]);
 
     assert_abs_diff!(a.transpose(), transpose);
 }
 
 #[test]
 fn transposing_the_identity_matrix() {
-    assert_abs_diff!(Matrix::identity(4).transpose(), Matrix::identity(4));
+    assert_abs_diff!(Matrix::<4>::identity().transpose(), Matrix::identity());
 }
 
 #[test]
 fn calculating_the_determinant_of_a_2x2_matrix() {
-    let a = Matrix::from_row_slice(2, [1., 5., -3., 2.]);
+    let a = Matrix::<2>::from_row_slice([1., 5., -3., 2.]);
     assert_f32!(a.det(), 17.);
 }
 
 #[test]
 fn a_submatrix_of_a_3x3_matrix_is_a_2x2_matrix() {
-    let a = Matrix::from_row_slice(3, [1., 5., 0., -3., 2., 7., 0., 6., -3.]);
+    let a = Matrix::<3>::from_row_slice([1., 5., 0., -3., 2., 7., 0., 6., -3.]);
 
     assert_abs_diff!(
         a.submatrix(0, 2),
-        Matrix::from_row_slice(2, [-3., 2., 0., 6.])
+        Matrix::<2>::from_row_slice([-3., 2., 0., 6.])
     );
 }
 
 #[test]
 fn a_submatrix_of_a_4x4_matrix_is_a_3x3_matrix() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            -6., 1., 1., 6., -8., 5., 8., 6., -1., 0., 8., 2., -7., 1., -1., 1.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        -6., 1., 1., 6., -8., 5., 8., 6., -1., 0., 8., 2., -7., 1., -1., 1.,
+    ]);
 
-    let exp = Matrix::from_row_slice(3, [-6., 1., 6., -8., 8., 6., -7., -1., 1.]);
+    let exp = Matrix::<3>::from_row_slice([-6., 1., 6., -8., 8., 6., -7., -1., 1.]);
 
     assert_abs_diff!(a.submatrix(2, 1), exp);
 }
 
 #[test]
 fn calculating_a_minor_of_a_3x3_matrix() {
-    let a = Matrix::from_row_slice(3, [3., 5., 0., 2., -1., -7., 6., -1., 5.]);
+    let a = Matrix::<3>::from_row_slice([3., 5., 0., 2., -1., -7., 6., -1., 5.]);
     let b = a.submatrix(1, 0);
 
     assert_f32!(b.det(), 25.);
@@ -206,7 +197,7 @@ fn calculating_a_minor_of_a_3x3_matrix() {
 
 #[test]
 fn calculating_a_cofactor_of_a_3x3_matrix() {
-    let a = Matrix::from_row_slice(3, [3., 5., 0., 2., -1., -7., 6., -1., 5.]);
+    let a = Matrix::<3>::from_row_slice([3., 5., 0., 2., -1., -7., 6., -1., 5.]);
 
     assert_f32!(a.minor(0, 0), -12.);
     assert_f32!(a.cofactor(0, 0), -12.);
@@ -216,7 +207,7 @@ fn calculating_a_cofactor_of_a_3x3_matrix() {
 
 #[test]
 fn calculating_the_determinant_of_a_3x3_matrix() {
-    let a = Matrix::from_row_slice(3, [1., 2., 6., -5., 8., -4., 2., 6., 4.]);
+    let a = Matrix::<3>::from_row_slice([1., 2., 6., -5., 8., -4., 2., 6., 4.]);
 
     assert_f32!(a.cofactor(0, 0), 56.);
     assert_f32!(a.cofactor(0, 1), 12.);
@@ -226,12 +217,9 @@ fn calculating_the_determinant_of_a_3x3_matrix() {
 
 #[test]
 fn calculating_the_determinant_of_a_4x4_matrix() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            -2., -8., 3., 5., -3., 1., 7., 3., 1., 2., -9., 6., -6., 7., 7., -9.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        -2., -8., 3., 5., -3., 1., 7., 3., 1., 2., -9., 6., -6., 7., 7., -9.,
+    ]);
 
     assert_f32!(a.cofactor(0, 0), 690.);
     assert_f32!(a.cofactor(0, 1), 447.);
@@ -242,12 +230,9 @@ fn calculating_the_determinant_of_a_4x4_matrix() {
 
 #[test]
 fn testing_an_invertible_matrix_for_invertibility() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            6., 4., 4., 4., 5., 5., 7., 6., 4., -9., 3., -7., 9., 1., 7., -6.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        6., 4., 4., 4., 5., 5., 7., 6., 4., -9., 3., -7., 9., 1., 7., -6.,
+    ]);
 
     assert_f32!(a.det(), -2120.);
     assert!(a.inverse().is_some());
@@ -255,12 +240,9 @@ fn testing_an_invertible_matrix_for_invertibility() {
 
 #[test]
 fn testing_a_noninvertible_matrix_for_invertibility() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            -4., 2., -2., -3., 9., 6., 2., 6., 0., -5., 1., -5., 0., 0., 0., 0.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        -4., 2., -2., -3., 9., 6., 2., 6., 0., -5., 1., -5., 0., 0., 0., 0.,
+    ]);
 
     assert_f32!(a.det(), 0.);
     assert!(a.inverse().is_none());
@@ -268,12 +250,9 @@ fn testing_a_noninvertible_matrix_for_invertibility() {
 
 #[test]
 fn calculating_the_inverse_of_a_matrix() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            -5., 2., 6., -8., 1., -5., 1., 8., 7., 7., -6., -7., 1., -3., 7., 4.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        -5., 2., 6., -8., 1., -5., 1., 8., 7., 7., -6., -7., 1., -3., 7., 4.,
+    ]);
 
     let b = a.inverse().unwrap();
 
@@ -283,73 +262,124 @@ fn calculating_the_inverse_of_a_matrix() {
     assert_f32!(a.cofactor(3, 2), 105.);
     assert_f32!(b[(2, 3)], 105. / 532.);
 
-    let exp = Matrix::from_row_slice(
-        4,
-        [
-            0.21805, 0.45113, 0.24060, -0.04511, -0.80827, -1.45677, -0.44361, 0.52068, -0.07895,
-            -0.22368, -0.05263, 0.19737, -0.52256, -0.81391, -0.30075, 0.30639,
-        ],
-    );
+    let exp = Matrix::<4>::from_row_slice([
+        0.21805, 0.45113, 0.24060, -0.04511, -0.80827, -1.45677, -0.44361, 0.52068, -0.07895,
+        -0.22368, -0.05263, 0.19737, -0.52256, -0.81391, -0.30075, 0.30639,
+    ]);
 
     assert_abs_diff!(b, exp);
 }
 
 #[test]
 fn calculating_the_inverse_of_another_matrix() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            8., -5., 9., 2., 7., 5., 6., 1., -6., 0., 9., 6., -3., 0., -9., -4.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        8., -5., 9., 2., 7., 5., 6., 1., -6., 0., 9., 6., -3., 0., -9., -4.,
+    ]);
 
-    let inv = Matrix::from_row_slice(
-        4,
-        [
-            -0.15385, -0.15385, -0.28205, -0.53846, -0.07692, 0.12308, 0.02564, 0.03077, 0.35897,
-            0.35897, 0.43590, 0.92308, -0.69231, -0.69231, -0.76923, -1.92308,
-        ],
-    );
+    let inv = Matrix::<4>::from_row_slice([
+        -0.15385, -0.15385, -0.28205, -0.53846, -0.07692, 0.12308, 0.02564, 0.03077, 0.35897,
+        0.35897, 0.43590, 0.92308, -0.69231, -0.69231, -0.76923, -1.92308,
+    ]);
 
     assert_abs_diff!(a.inverse().unwrap(), inv);
 }
 
 #[test]
 fn calculating_the_inverse_of_a_third_matrix() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            9., 3., 0., 9., -5., -2., -6., -3., -4., 9., 6., 4., -7., 6., 6., 2.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        9., 3., 0., 9., -5., -2., -6., -3., -4., 9., 6., 4., -7., 6., 6., 2.,
+    ]);
 
-    let inv = Matrix::from_row_slice(
-        4,
-        [
-            -0.04074, -0.07778, 0.14444, -0.22222, -0.07778, 0.03333, 0.36667, -0.33333, -0.02901,
-            -0.14630, -0.10926, 0.12963, 0.17778, 0.06667, -0.26667, 0.33333,
-        ],
-    );
+    let inv = Matrix::<4>::from_row_slice([
+        -0.04074, -0.07778, 0.14444, -0.22222, -0.07778, 0.03333, 0.36667, -0.33333, -0.02901,
+        -0.14630, -0.10926, 0.12963, 0.17778, 0.06667, -0.26667, 0.33333,
+    ]);
 
     assert_abs_diff!(a.inverse().unwrap(), inv);
 }
 
 #[test]
 fn multiplying_a_product_by_its_inverse() {
-    let a = Matrix::from_row_slice(
-        4,
-        [
-            3., -9., 7., 3., 3., -8., 2., -9., -4., 4., 4., 1., -6., 5., -1., 1.,
-        ],
-    );
+    let a = Matrix::<4>::from_row_slice([
+        3., -9., 7., 3., 3., -8., 2., -9., -4., 4., 4., 1., -6., 5., -1., 1.,
+    ]);
 
-    let b = Matrix::from_row_slice(
-        4,
-        [
-            8., 2., 2., 2., 3., -1., 7., 0., 7., 0., 5., 4., 6., -2., 0., 5.,
-        ],
-    );
+    let b = Matrix::<4>::from_row_slice([
+        8., 2., 2., 2., 3., -1., 7., 0., 7., 0., 5., 4., 6., -2., 0., 5.,
+    ]);
 
     let c = &a * &b;
     assert_abs_diff!(c * b.inverse().unwrap(), a);
 }
+
+#[test]
+fn inverting_smaller_matrices() {
+    let a = Matrix::from_rows([[3., 5., 0.], [2., -1., -7.], [6., -1., 5.]]);
+    assert_abs_diff!(&a * a.inverse().unwrap(), Matrix::identity());
+
+    let b = Matrix::from_rows([[-3., 5.], [1., -2.]]);
+    assert_abs_diff!(
+        b.inverse().unwrap(),
+        Matrix::from_rows([[-2., -5.], [-1., -3.]])
+    );
+
+    assert!(Matrix::from_rows([[1., 2.], [2., 4.]]).inverse().is_none());
+}
+
+/// Returns an endless sequence of pseudo-random values in `[-10, 10)`, always the same.
+fn samples() -> impl Iterator<Item = f32> {
+    let mut state = 0x2545_f491_u32;
+
+    std::iter::repeat_with(move || {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (state >> 8) as f32 / (1 << 24) as f32 * 20. - 10.
+    })
+}
+
+#[test]
+fn multiplying_a_matrix_by_a_point_is_accurate() {
+    let mut xs = samples();
+    let mut max_error = 0f64;
+
+    for _ in 0..1000 {
+        let mut data = [0.; 16];
+        data.iter_mut().for_each(|x| *x = xs.next().unwrap());
+
+        let m = Matrix::<4>::from_row_slice(data);
+        let p = [xs.next().unwrap(), xs.next().unwrap(), xs.next().unwrap()];
+        let q = &m * Point3::new(p[0], p[1], p[2]);
+
+        for (row, &actual) in [q.x, q.y, q.z].iter().enumerate() {
+            let terms = [
+                m[(row, 0)] as f64 * p[0] as f64,
+                m[(row, 1)] as f64 * p[1] as f64,
+                m[(row, 2)] as f64 * p[2] as f64,
+                m[(row, 3)] as f64,
+            ];
+            let exact = terms.iter().sum::<f64>();
+            let magnitude = terms.iter().map(|t| t.abs()).sum::<f64>();
+
+            max_error = max_error.max((actual as f64 - exact).abs() / magnitude);
+        }
+    }
+
+    // Four products and four sums are rounded at most, fewer with the `fast-math` feature
+    assert!(
+        max_error <= 4. * f32::EPSILON as f64,
+        "relative error of {:e}",
+        max_error
+    );
+}
+
+#[test]
+fn displaying_a_matrix() {
+    let m = Matrix::from_translation(2.0, -3.5, 10.0);
+
+    assert_eq!(
+        m.to_string(),
+        "[ 1, 0, 0,    2 ]\n\
+         [ 0, 1, 0, -3.5 ]\n\
+         [ 0, 0, 1,   10 ]\n\
+         [ 0, 0, 0,    1 ]"
+    );
+}
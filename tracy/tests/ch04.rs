@@ -1,6 +1,6 @@
 use std::f32::consts::{FRAC_1_SQRT_2, PI};
 
-use tracy::math::{Matrix, Point3, Vec3};
+use tracy::math::{portable, Matrix, Point3, Vec3};
 pub use utils::*;
 
 mod utils;
@@ -165,3 +165,32 @@ fn chained_transformations_must_be_applied_in_reverse_order() {
 
     assert_abs_diff!(transform * p, Point3::new(15., 0., 7.));
 }
+
+#[test]
+fn portable_trigonometric_functions_match_the_standard_ones() {
+    for i in -1000..=1000 {
+        let x = i as f32 * 0.01;
+
+        assert!((portable::sin(x) - x.sin()).abs() <= 1e-6);
+        assert!((portable::cos(x) - x.cos()).abs() <= 1e-6);
+
+        if x.cos().abs() > 1e-2 {
+            assert!((portable::tan(x) - x.tan()).abs() <= 1e-6 * x.tan().abs().max(1.));
+        }
+    }
+}
+
+#[test]
+fn portable_arctangent_matches_the_standard_one() {
+    for i in -50..=50 {
+        for j in -50..=50 {
+            let (y, x) = (i as f32 * 0.37, j as f32 * 0.23);
+            assert!((portable::atan2(y, x) - y.atan2(x)).abs() <= 1e-6);
+        }
+    }
+
+    assert!((portable::atan2(1e30, 1e-30) - PI / 2.).abs() <= 1e-6);
+    assert!((portable::atan2(-1., -1e30) + PI).abs() <= 1e-6);
+    assert!((portable::atan2(f32::INFINITY, -f32::INFINITY) - 3. * PI / 4.).abs() <= 1e-6);
+    assert!(portable::atan2(f32::NAN, 1.).is_nan());
+}
@@ -1,7 +1,10 @@
 use itertools::Itertools;
 use tracy::{
     math::{Matrix, Point3, Vec3},
-    query::{Ray, RayIntersection, RayIntersections},
+    query::{
+        intersect_unit_sphere, Object, Ray, RayIntersection, RayIntersections, RayPacket, World,
+    },
+    shape::Sphere,
 };
 pub use utils::*;
 
@@ -102,7 +105,7 @@ fn a_sphere_is_behind_a_ray() {
 
 #[test]
 fn a_sphere_default_transformation() {
-    assert_abs_diff!(sphere().transform(), Matrix::identity(4));
+    assert_abs_diff!(sphere().transform(), Matrix::identity());
 }
 
 #[test]
@@ -240,9 +243,89 @@ fn the_hit_is_always_the_lowest_nonnegative_intersection() {
     assert_f32!(xs.hit().unwrap().toi, 2.);
 }
 
+#[test]
+fn the_hit_within_a_range_of_times() {
+    let xs = || {
+        [5., 7., -3., 2., f32::NAN]
+            .iter()
+            .map(|&toi| RayIntersection {
+                toi,
+                normal: Vec3::default(),
+            })
+            .collect::<RayIntersections>()
+    };
+
+    assert_f32!(xs().hit_within(3.0..).unwrap().toi, 5.);
+    assert_f32!(xs().hit_within(..).unwrap().toi, -3.);
+    assert_f32!(xs().hit_within(6.0..=7.0).unwrap().toi, 7.);
+    assert!(xs().hit_within(2.5..5.0).is_none());
+}
+
+#[test]
+fn the_closest_intersection_within_a_range_of_times() {
+    let xs = || {
+        [5., -1.5, 7., -3., 2., f32::NAN]
+            .iter()
+            .map(|&toi| RayIntersection {
+                toi,
+                normal: Vec3::default(),
+            })
+            .collect::<RayIntersections>()
+    };
+
+    assert_f32!(xs().closest_in(..).unwrap().toi, -1.5);
+    assert_f32!(xs().closest_in(..-2.0).unwrap().toi, -3.);
+    assert!(xs().closest_in(8.0..).is_none());
+}
+
 fn tois_with_default_sphere(ray: &Ray) -> Vec<f32> {
     sphere()
         .interferences_with_ray(ray)
         .map(|x| x.toi)
         .collect()
 }
+
+#[test]
+fn a_packet_of_rays_intersects_a_sphere() {
+    let rays = [
+        Ray::new(Point3::new(0., 0., -5.), Vec3::unit_z()),
+        Ray::new(Point3::new(0., 1., -5.), Vec3::unit_z()),
+        Ray::new(Point3::new(0., 2., -5.), Vec3::unit_z()),
+        Ray::new(Point3::new(0., 0., 0.), Vec3::unit_z()),
+    ];
+
+    let hits = intersect_unit_sphere(&RayPacket::new(&rays));
+
+    assert_eq!(hits.mask, [true, true, false, true]);
+    for &(lane, near, far) in &[(0, 4., 6.), (1, 5., 5.), (3, -1., 1.)] {
+        assert_f32!(hits.near[lane], near);
+        assert_f32!(hits.far[lane], far);
+    }
+}
+
+#[test]
+fn intersecting_a_transformed_sphere_with_a_packet_of_rays() {
+    let mut w = World::new();
+    w.add(Object::new(Sphere, Matrix::from_scale(2., 2., 2.)));
+
+    let rays = [
+        Ray::new(Point3::new(0., 0., -5.), Vec3::unit_z()),
+        Ray::new(
+            Point3::new(0.3, 1.2, -5.),
+            Vec3::new(0., 0.1, 1.).normalize(),
+        ),
+        Ray::new(Point3::new(5., 5., -5.), Vec3::unit_z()),
+        Ray::new(Point3::new(-1., 0.5, 0.), Vec3::new(1., 1., 1.).normalize()),
+    ];
+
+    for (xs, r) in w.interferences_with_packet(&rays).iter_mut().zip(&rays) {
+        let expected = w.interferences_with_ray(r).collect::<Vec<_>>();
+        let actual = xs.collect::<Vec<_>>();
+
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(&expected) {
+            assert_eq!(a.toi, e.toi);
+            assert_eq!(a.normal, e.normal);
+        }
+    }
+}
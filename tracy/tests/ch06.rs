@@ -2,9 +2,13 @@ use std::f32::consts::FRAC_1_SQRT_2;
 
 use rendering::Pattern;
 use tracy::{
-    math::{Matrix, Point3, Vec3, EPSILON},
-    query::Ray,
-    rendering::{self, Color, Material, PointLight},
+    math::{portable, Matrix, Point3, Vec3, EPSILON},
+    query::{Object, Ray, World},
+    rendering::{
+        self, Attenuation, Color, Material, PbrMaterial, PointLight, ShadingModel,
+        DEFAULT_RECURSION_DEPTH,
+    },
+    shape::Sphere,
 };
 pub use utils::*;
 
@@ -207,9 +211,212 @@ fn lighting_in_several_configurations() {
             &Point3::new(0., 0., 0.),
             &eyev,
             &normalv,
-            false,
+            1.0,
         );
 
         assert_abs_diff!(res, exp);
     }
 }
+
+#[test]
+fn lights_do_not_attenuate_by_default() {
+    assert_eq!(PointLight::default().attenuation, Attenuation::NONE);
+
+    for &d in &[0.0, 1.0, 100.0] {
+        assert_f32!(Attenuation::NONE.factor(d), 1.0);
+    }
+}
+
+#[test]
+fn attenuating_light_with_distance() {
+    let falloff = Attenuation {
+        constant: 1.0,
+        linear: 0.5,
+        quadratic: 0.25,
+    };
+    assert_f32!(falloff.factor(0.0), 1.0);
+    assert_f32!(falloff.factor(2.0), 1.0 / 3.0);
+    assert_f32!(falloff.factor(4.0), 1.0 / 7.0);
+
+    // Lights are never amplified, even close to the light source
+    let inverse_square = Attenuation {
+        constant: 0.0,
+        linear: 0.0,
+        quadratic: 1.0,
+    };
+    assert_f32!(inverse_square.factor(0.5), 1.0);
+    assert_f32!(inverse_square.factor(2.0), 0.25);
+}
+
+#[test]
+fn lighting_with_an_attenuated_light() {
+    let eye = Vec3::new(0.0, 0.0, -1.0);
+    let normal = Vec3::new(0.0, 0.0, -1.0);
+    let light = PointLight {
+        position: Point3::new(0.0, 0.0, -10.0),
+        attenuation: Attenuation {
+            quadratic: 0.01,
+            ..Attenuation::NONE
+        },
+        ..Default::default()
+    };
+
+    let res = rendering::phong_lighting(
+        &sphere(),
+        &light,
+        &Point3::new(0., 0., 0.),
+        &eye,
+        &normal,
+        1.0,
+    );
+
+    // The ambient term is left as is, while the diffuse and specular ones are halved
+    assert_abs_diff!(
+        res,
+        Color::new(0.1, 0.1, 0.1) + Color::new(1.8, 1.8, 1.8) * 0.5
+    );
+}
+
+/// Creates a sphere with a physically-based material.
+fn pbr_sphere(color: Color, metallic: f32, roughness: f32) -> Object {
+    Object::new_with_material(
+        Sphere,
+        Matrix::identity(),
+        Material {
+            pattern: Pattern::new(color.into()),
+            shading: ShadingModel::Pbr(PbrMaterial {
+                metallic,
+                roughness,
+            }),
+            ..Default::default()
+        },
+    )
+}
+
+/// Lights a point of `object` head-on, like `phong_lighting`.
+fn pbr_lighting_head_on(object: &Object) -> Color {
+    let v = Vec3::new(0.0, 0.0, -1.0);
+    let light = PointLight {
+        position: Point3::new(0.0, 0.0, -10.0),
+        ..Default::default()
+    };
+
+    rendering::pbr_lighting(object, &light, &Point3::new(0., 0., -1.), &v, &v, 1.0)
+}
+
+#[test]
+fn the_default_shading_model() {
+    assert_eq!(Material::default().shading, ShadingModel::Phong);
+    assert_eq!(
+        PbrMaterial::default(),
+        PbrMaterial {
+            metallic: 0.0,
+            roughness: 0.5
+        }
+    );
+}
+
+#[test]
+fn pbr_lighting_of_a_rough_dielectric() {
+    // The diffuse and specular terms reflect 96% and 1% of the light, plus the ambient term
+    assert_abs_diff!(
+        pbr_lighting_head_on(&pbr_sphere(Color::WHITE, 0.0, 1.0)),
+        Color::new(1.07, 1.07, 1.07)
+    );
+}
+
+#[test]
+fn pbr_lighting_of_a_rough_metal() {
+    // Metals only have a specular term, tinted by their albedo
+    assert_abs_diff!(
+        pbr_lighting_head_on(&pbr_sphere(Color::new(1.0, 0.8, 0.2), 1.0, 1.0)),
+        Color::new(0.35, 0.28, 0.07)
+    );
+}
+
+#[test]
+fn pbr_lighting_with_the_light_behind_the_surface() {
+    let eye = Vec3::new(0.0, 0.0, -1.0);
+    let normal = Vec3::new(0.0, 0.0, -1.0);
+    let light = PointLight {
+        position: Point3::new(0.0, 0.0, 10.0),
+        ..Default::default()
+    };
+
+    let res = rendering::pbr_lighting(
+        &pbr_sphere(Color::WHITE, 0.0, 0.5),
+        &light,
+        &Point3::new(0., 0., 0.),
+        &eye,
+        &normal,
+        1.0,
+    );
+
+    assert_abs_diff!(res, Color::new(0.1, 0.1, 0.1));
+}
+
+#[test]
+fn smoother_surfaces_have_brighter_highlights() {
+    let smooth = pbr_lighting_head_on(&pbr_sphere(Color::WHITE, 1.0, 0.2));
+    let rough = pbr_lighting_head_on(&pbr_sphere(Color::WHITE, 1.0, 0.8));
+
+    assert!(smooth.r > rough.r);
+}
+
+#[test]
+fn mixing_shading_models_in_a_world() {
+    let mut w = World::new();
+    let light = PointLight {
+        position: Point3::new(-10.0, 10.0, -10.0),
+        ..Default::default()
+    };
+    w.add_light(light.clone());
+
+    let phong = w.add(Object::new(
+        Sphere,
+        Matrix::from_translation(-1.5, 0.0, 0.0),
+    ));
+    let pbr = w.add(Object::new_with_material(
+        Sphere,
+        Matrix::from_translation(1.5, 0.0, 0.0),
+        pbr_sphere(Color::WHITE, 0.5, 0.3).material().clone(),
+    ));
+
+    for &(x, handle) in &[(-1.5, phong), (1.5, pbr)] {
+        let r = Ray::new(Point3::new(x, 0.0, -5.0), Vec3::unit_z());
+        let hit = w.interferences_with_ray(&r).hit().unwrap();
+        let obj = w.get(handle).unwrap();
+
+        let lighting = if handle == phong {
+            rendering::phong_lighting
+        } else {
+            rendering::pbr_lighting
+        };
+
+        assert_eq!(hit.handle, handle);
+        assert_abs_diff!(
+            w.color_at(&r, DEFAULT_RECURSION_DEPTH),
+            lighting(obj, &light, &hit.over_point, &hit.eye, &hit.normal, 1.0)
+        );
+    }
+}
+
+#[test]
+fn portable_powf_matches_the_standard_one() {
+    for &base in &[0.001f32, 0.1, 0.5, 0.9, 0.999, 1.0, 2.0, 10.0] {
+        for &exp in &[0.5f32, 1.0, 10.0, 200.0, 300.0] {
+            let expected = base.powf(exp);
+            let actual = portable::powf(base, exp);
+
+            if expected.is_infinite() {
+                assert_eq!(actual, expected);
+            } else {
+                assert!((actual - expected).abs() <= 1e-6 * expected.max(1e-30));
+            }
+        }
+    }
+
+    assert_eq!(portable::powf(0., 10.), 0.);
+    assert_eq!(portable::powf(0.5, 0.), 1.);
+    assert!(portable::powf(-1., 0.5).is_nan());
+}
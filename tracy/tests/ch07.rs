@@ -1,11 +1,19 @@
-use std::f32::consts::{FRAC_1_SQRT_2, PI};
+use std::{
+    collections::HashSet,
+    f32::consts::{FRAC_1_SQRT_2, PI},
+};
 
 use tracy::{
-    math::{Matrix, Point3, Vec3, EPSILON},
-    query::{Ray, World},
+    math::{Matrix, Point3, Rng, Vec3, EPSILON},
+    profile::{self, Counters, Phase},
+    query::{Object, Ray, World},
     rendering::{
-        Camera, Color, Material, Pattern, PatternKind, PointLight, DEFAULT_RECURSION_DEPTH,
+        Aov, Camera, CameraPrefab, Color, Integrator, Material, Pattern, PatternKind, PointLight,
+        ScenePrefab, DEFAULT_RECURSION_DEPTH, PREVIEW_SIZE,
     },
+    shape::{Plane, Sphere},
+    testing::GoldenImage,
+    Error,
 };
 pub use utils::*;
 
@@ -20,7 +28,33 @@ fn creating_a_world() {
 }
 
 #[test]
-fn the_default_world() {
+#[cfg(not(feature = "legacy-default-world"))]
+fn the_default_world_is_empty() {
+    let w = World::default();
+
+    assert_eq!(w.objects().count(), 0);
+    assert_eq!(w.lights().count(), 0);
+}
+
+#[test]
+#[cfg(feature = "legacy-default-world")]
+fn the_legacy_default_world_is_the_world_of_the_book() {
+    let w = World::default();
+    let book = World::book_default();
+
+    assert_eq!(
+        w.lights().collect::<Vec<_>>(),
+        book.lights().collect::<Vec<_>>()
+    );
+    for (a, b) in w.objects().zip(book.objects()) {
+        assert_eq!(a.material(), b.material());
+        assert_eq!(a.transform(), b.transform());
+    }
+    assert_eq!(w.objects().count(), 2);
+}
+
+#[test]
+fn the_default_world_of_the_book() {
     let light = PointLight {
         position: Point3::new(-10., 10., -10.),
         ..Default::default()
@@ -37,7 +71,7 @@ fn the_default_world() {
     let mut s2 = sphere();
     s2.set_transform(Matrix::from_scale(0.5, 0.5, 0.5));
 
-    let w = World::default();
+    let w = World::book_default();
     let mut objs = w.objects();
 
     assert_eq!(w.lights().next(), Some(&light));
@@ -53,7 +87,7 @@ fn the_default_world() {
 
 #[test]
 fn intersect_a_world_with_a_ray() {
-    let w = World::default();
+    let w = World::book_default();
 
     let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
 
@@ -66,6 +100,199 @@ fn intersect_a_world_with_a_ray() {
     assert_f32!(xs[3].toi, 6.0);
 }
 
+#[test]
+fn intersect_a_world_with_many_objects() {
+    let mut w = World::new();
+
+    // Added back to front, so that the intersections are found in reverse order
+    for i in (0..12).rev() {
+        w.add(Object::new(
+            Sphere,
+            Matrix::from_translation(0.0, 0.0, i as f32 * 3.0),
+        ));
+    }
+
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
+
+    let xs = w.interferences_with_ray(&r).collect::<Vec<_>>();
+
+    assert_eq!(xs.len(), 24);
+    for (i, pair) in xs.chunks(2).enumerate() {
+        assert_f32!(pair[0].toi, 4.0 + i as f32 * 3.0);
+        assert_f32!(pair[1].toi, 6.0 + i as f32 * 3.0);
+    }
+}
+
+#[test]
+fn intersect_a_world_after_moving_an_object() {
+    let mut w = World::new();
+    let s = w.add(sphere());
+
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
+    assert_f32!(w.interferences_with_ray(&r).next().unwrap().toi, 4.0);
+
+    w.get_mut(s)
+        .unwrap()
+        .set_transform(Matrix::from_translation(0.0, 0.0, 2.0));
+
+    assert_f32!(w.interferences_with_ray(&r).next().unwrap().toi, 6.0);
+}
+
+#[test]
+fn accessing_lights_by_handle() {
+    let mut w = World::new();
+    let l1 = w.add_light(PointLight::default());
+    let l2 = w.add_light(PointLight::default());
+    let l3 = w.add_light(PointLight::default());
+
+    w.get_light_mut(l2).unwrap().intensity = 0.5;
+    assert_eq!(w.get_light(l1).unwrap().intensity, 1.0);
+    assert_eq!(w.get_light(l2).unwrap().intensity, 0.5);
+
+    assert_eq!(w.remove_light(l1), Some(PointLight::default()));
+    assert_eq!(w.remove_light(l1), None);
+    assert!(w.get_light(l1).is_none());
+
+    assert_eq!(w.get_light(l2).unwrap().intensity, 0.5);
+    assert_eq!(w.light_handles().collect::<Vec<_>>(), [l2, l3]);
+    assert_eq!(w.lights().count(), 2);
+}
+
+#[test]
+fn editing_a_world_does_not_affect_its_snapshots() {
+    let mut w = World::new();
+    let s = w.add(sphere());
+    w.add_light(PointLight::default());
+
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
+    let snapshot = w.snapshot();
+
+    w.get_mut(s)
+        .unwrap()
+        .set_transform(Matrix::from_translation(0.0, 0.0, 2.0));
+    w.add(sphere());
+    w.lights_mut().next().unwrap().intensity = 0.5;
+
+    assert_eq!(snapshot.objects().count(), 1);
+    assert_eq!(snapshot.lights().next().unwrap().intensity, 1.0);
+    assert_f32!(snapshot.interferences_with_ray(&r).next().unwrap().toi, 4.0);
+
+    assert_eq!(w.objects().count(), 2);
+    assert_f32!(w.interferences_with_ray(&r).next().unwrap().toi, 4.0);
+    assert_f32!(
+        w.interferences_with_ray(&r)
+            .find(|i| i.handle == s)
+            .unwrap()
+            .toi,
+        6.0
+    );
+}
+
+#[test]
+fn cloning_a_world_preserves_handles() {
+    let mut w = World::new();
+    let a = w.add(sphere());
+    let b = w.add(glass_sphere());
+    w.add_light(PointLight::default());
+
+    let mut clone = w.clone();
+    assert_eq!(clone.get(a), w.get(a));
+    assert_eq!(clone.get(b), w.get(b));
+    assert!(clone.lights().eq(w.lights()));
+
+    clone
+        .get_mut(b)
+        .unwrap()
+        .set_transform(Matrix::from_translation(0.0, 0.0, 2.0));
+    let c = clone.add(sphere());
+
+    assert_eq!(clone.get(a), w.get(a));
+    assert_ne!(clone.get(b), w.get(b));
+    assert_eq!(clone.get(c), Some(&sphere()));
+    assert_eq!(w.get(c), None);
+}
+
+#[test]
+fn removing_objects() {
+    let mut w = World::new();
+    let a = w.add(sphere());
+    let b = w.add(glass_sphere());
+    let c = w.add(plane());
+
+    assert_eq!(w.remove(b), Some(glass_sphere()));
+    assert_eq!(w.remove(b), None);
+    assert_eq!(w.get(b), None);
+    assert_eq!(w.get(a), Some(&sphere()));
+    assert_eq!(w.get(c), Some(&plane()));
+    assert_eq!(w.object_handles().collect::<Vec<_>>(), [a, c]);
+    assert_eq!(w.objects().count(), 2);
+
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
+    assert!(w.interferences_with_ray(&r).all(|i| i.handle == a));
+}
+
+#[test]
+fn stale_handles_do_not_identify_reused_slots() {
+    let mut w = World::new();
+    let a = w.add(sphere());
+    w.remove(a);
+
+    let b = w.add(glass_sphere());
+    assert_ne!(a, b);
+    assert_eq!(w.get(a), None);
+    assert!(w.get_mut(a).is_none());
+    assert_eq!(w.remove(a), None);
+    assert_eq!(w.replace(a, plane()), None);
+    assert_eq!(w.get(b), Some(&glass_sphere()));
+}
+
+#[test]
+fn replacing_objects() {
+    let mut w = World::new();
+    let a = w.add(sphere());
+
+    assert_eq!(w.replace(a, glass_sphere()), Some(sphere()));
+    assert_eq!(w.get(a), Some(&glass_sphere()));
+
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
+    let hit = w.interferences_with_ray(&r).next().unwrap();
+    assert_eq!(hit.handle, a);
+    assert_f32!(hit.n2, 1.5);
+}
+
+#[test]
+fn removing_objects_does_not_affect_snapshots() {
+    let mut w = World::new();
+    let a = w.add(sphere());
+    let snapshot = w.snapshot();
+
+    w.remove(a);
+    assert_eq!(snapshot.get(a), Some(&sphere()));
+    assert_eq!(w.get(a), None);
+}
+
+#[test]
+fn telling_identical_objects_apart_by_handle() {
+    let mut w = World::new();
+    let front = w.add(sphere());
+    let back = w.add(sphere());
+    w.get_mut(back)
+        .unwrap()
+        .set_transform(Matrix::from_translation(0.0, 0.0, 5.0));
+    w.get_mut(front)
+        .unwrap()
+        .set_transform(Matrix::from_translation(0.0, 0.0, -5.0));
+
+    let r = Ray::new(Point3::new(0.0, 0.0, -10.0), Vec3::unit_z());
+    let hit = w.interferences_with_ray(&r).next().unwrap();
+
+    assert_ne!(front, back);
+    assert_eq!(hit.handle, front);
+
+    let hits: HashSet<_> = w.interferences_with_ray(&r).map(|i| i.handle).collect();
+    assert_eq!(hits, [front, back].iter().copied().collect());
+}
+
 #[test]
 fn precomputing_the_state_of_an_intersection() {
     let mut w = World::new();
@@ -75,7 +302,7 @@ fn precomputing_the_state_of_an_intersection() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 4.).abs() < EPSILON)
+        .hit_within(4. - EPSILON..4. + EPSILON)
         .unwrap();
 
     assert_eq!(interference.handle, s);
@@ -94,7 +321,7 @@ fn the_hit_when_an_intersection_occurs_on_the_outside() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 4.).abs() < EPSILON)
+        .hit_within(4. - EPSILON..4. + EPSILON)
         .unwrap();
 
     assert!(!interference.inside);
@@ -109,7 +336,7 @@ fn the_hit_when_an_intersection_occurs_on_the_inside() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 1.).abs() < EPSILON)
+        .hit_within(1. - EPSILON..1. + EPSILON)
         .unwrap();
 
     assert_abs_diff!(interference.point, Point3::new(0.0, 0.0, 1.0));
@@ -120,12 +347,12 @@ fn the_hit_when_an_intersection_occurs_on_the_inside() {
 
 #[test]
 fn shading_an_intersection() {
-    let w = World::default();
+    let w = World::book_default();
     let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 4.).abs() < EPSILON)
+        .hit_within(4. - EPSILON..4. + EPSILON)
         .unwrap();
 
     assert_abs_diff!(
@@ -136,7 +363,7 @@ fn shading_an_intersection() {
 
 #[test]
 fn shading_an_intersection_from_the_inside() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     *w.lights_mut().next().unwrap() = PointLight {
         position: Point3::new(0.0, 0.25, 0.0),
@@ -147,7 +374,7 @@ fn shading_an_intersection_from_the_inside() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 0.5).abs() < EPSILON)
+        .hit_within(0.5 - EPSILON..0.5 + EPSILON)
         .unwrap();
 
     assert_abs_diff!(
@@ -158,7 +385,7 @@ fn shading_an_intersection_from_the_inside() {
 
 #[test]
 fn the_color_when_a_ray_misses() {
-    let w = World::default();
+    let w = World::book_default();
     let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_y());
 
     assert_abs_diff!(w.color_at(&r, DEFAULT_RECURSION_DEPTH), Color::BLACK);
@@ -166,7 +393,7 @@ fn the_color_when_a_ray_misses() {
 
 #[test]
 fn the_color_when_a_ray_hits() {
-    let w = World::default();
+    let w = World::book_default();
     let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
 
     assert_abs_diff!(
@@ -177,7 +404,7 @@ fn the_color_when_a_ray_hits() {
 
 #[test]
 fn the_color_with_an_intersection_behind_the_ray() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     let expected = {
         let mut objs = w.objects_mut();
@@ -205,7 +432,7 @@ fn the_transform_matrix_for_the_default_orientation() {
     let up = Vec3::unit_y();
 
     let t = Matrix::look_at(eye, center, up);
-    assert_abs_diff!(t, Matrix::identity(4));
+    assert_abs_diff!(t, Matrix::identity());
 }
 
 #[test]
@@ -238,13 +465,10 @@ fn an_arbitrary_view_transformation() {
 
     assert_abs_diff!(
         t,
-        Matrix::from_row_slice(
-            4,
-            [
-                -0.50709, 0.50709, 0.67612, -2.36643, 0.76772, 0.60609, 0.12122, -2.82843,
-                -0.35857, 0.59761, -0.71714, 0.00000, 0.00000, 0.00000, 0.00000, 1.00000,
-            ]
-        )
+        Matrix::<4>::from_row_slice([
+            -0.50709, 0.50709, 0.67612, -2.36643, 0.76772, 0.60609, 0.12122, -2.82843, -0.35857,
+            0.59761, -0.71714, 0.00000, 0.00000, 0.00000, 0.00000, 1.00000,
+        ])
     );
 }
 
@@ -255,7 +479,26 @@ fn constructing_a_camera() {
     assert_eq!(c.horizontal_size(), 160);
     assert_eq!(c.vertical_size(), 120);
     assert_f32!(c.fov(), PI / 2.);
-    assert_abs_diff!(c.view_transform(), Matrix::identity(4));
+    assert_abs_diff!(c.view_transform(), Matrix::identity());
+    assert_eq!(c.recursion_limit(), None);
+    assert_eq!(
+        c.recursion_limit_for(&World::new()),
+        DEFAULT_RECURSION_DEPTH
+    );
+    assert_eq!(c.integrator(), Integrator::Whitted);
+    assert_eq!(c.samples_per_pixel(), 1);
+}
+
+#[test]
+fn choosing_the_recursion_limit_of_a_render() {
+    let mut c = Camera::new(160, 120, PI / 2.0);
+    let mut w = World::new();
+
+    w.set_recursion_limit(10);
+    assert_eq!(c.recursion_limit_for(&w), 10);
+
+    c.set_recursion_limit(2);
+    assert_eq!(c.recursion_limit_for(&w), 2);
 }
 
 #[test]
@@ -288,6 +531,18 @@ fn constructing_a_ray_through_a_corner_of_the_canvas() {
     assert_abs_diff!(r.dir, Vec3::new(0.66519, 0.33259, -0.66851));
 }
 
+#[test]
+fn constructing_a_ray_through_an_arbitrary_point_of_the_canvas() {
+    let c = Camera::new(201, 101, PI / 2.0);
+
+    let r = c.ray_through(100.5, 50.5);
+    assert_abs_diff!(r.dir, c.ray_to(100, 50).dir);
+
+    let r = c.ray_through(0.0, 0.0);
+    assert_abs_diff!(r.origin, Point3::new(0.0, 0.0, 0.0));
+    assert_abs_diff!(r.dir, Vec3::new(0.66630, 0.33481, -0.66630));
+}
+
 #[test]
 fn constructing_a_ray_when_the_camera_is_transformed() {
     let c = Camera::new_with_transform(
@@ -303,9 +558,57 @@ fn constructing_a_ray_when_the_camera_is_transformed() {
     assert_abs_diff!(r.dir, Vec3::new(FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2));
 }
 
+#[test]
+fn constructing_the_rays_of_a_scanline() {
+    let c = Camera::new_with_transform(
+        201,
+        101,
+        PI / 2.0,
+        Matrix::from_rotation_y(PI / 4.0) * Matrix::from_translation(0.0, -2.0, 5.0),
+    );
+
+    let rays = c.rays_for_scanline(37).collect::<Vec<_>>();
+    assert_eq!(rays.len(), 201);
+
+    for (x, r) in rays.iter().enumerate() {
+        let expected = c.ray_to(x as u32, 37);
+
+        assert_abs_diff!(r.origin, expected.origin);
+        assert_abs_diff!(r.dir, expected.dir);
+    }
+}
+
+#[test]
+fn constructing_a_camera_with_an_invalid_view_transform() {
+    let flat = Matrix::from_scale(1.0, 0.0, 1.0);
+
+    assert!(matches!(
+        Camera::try_new(160, 120, PI / 2.0, flat.clone()),
+        Err(Error::InvalidCamera(_))
+    ));
+    assert!(matches!(
+        Camera::try_new(160, 120, PI, Matrix::identity()),
+        Err(Error::InvalidCamera(_))
+    ));
+
+    let mut c =
+        Camera::try_new(160, 120, PI / 2.0, Matrix::from_translation(0.0, 0.0, 5.0)).unwrap();
+    let expected = c.ray_to(80, 60);
+
+    assert!(matches!(
+        c.try_set_view_transform(flat),
+        Err(Error::InvalidCamera(_))
+    ));
+    assert_eq!(c.view_transform(), &Matrix::from_translation(0.0, 0.0, 5.0));
+    assert_eq!(c.ray_to(80, 60), expected);
+
+    assert!(c.try_set_view_transform(Matrix::identity()).is_ok());
+    assert_abs_diff!(c.ray_to(80, 60).origin, Point3::new(0.0, 0.0, 0.0));
+}
+
 #[test]
 fn rendering_a_world_with_a_camera() {
-    let w = World::default();
+    let w = World::book_default();
 
     let c = Camera::new_with_transform(
         11,
@@ -325,3 +628,442 @@ fn rendering_a_world_with_a_camera() {
         Color::new(0.38066, 0.47583, 0.2855)
     );
 }
+
+#[test]
+fn rendering_a_world_reports_its_phases_to_the_profiler() {
+    static COUNTERS: Counters = Counters::new();
+    profile::set_profiler(&COUNTERS).unwrap();
+
+    let w = World::book_default();
+    let c = Camera::new(11, 11, PI / 2.0);
+    c.render(&w);
+
+    // Other tests may render at the same time, so only check that events were received
+    for &phase in &[
+        Phase::Build,
+        Phase::PrimaryRays,
+        Phase::ShadowRays,
+        Phase::Shading,
+    ] {
+        assert_eq!(COUNTERS.calls(phase) > 0, cfg!(feature = "profiling"));
+    }
+}
+
+#[test]
+fn rendering_the_default_world_matches_its_golden_image() {
+    let w = World::book_default();
+
+    let c = Camera::new_with_transform(
+        64,
+        48,
+        PI / 3.0,
+        Matrix::look_at(
+            Point3::new(0.0, 1.5, -5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::unit_y(),
+        ),
+    );
+
+    GoldenImage::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/golden/default_world.ppm"
+    ))
+    .assert(&c.render(&w));
+}
+
+#[test]
+fn rendering_does_not_depend_on_the_number_of_threads() {
+    let w = World::book_default();
+    let c = Camera::new_with_transform(
+        33,
+        17,
+        PI / 3.0,
+        Matrix::look_at(
+            Point3::new(0.0, 1.5, -5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::unit_y(),
+        ),
+    );
+
+    let render_with = |threads| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| c.render(&w))
+    };
+
+    let single = render_with(1);
+    for &threads in &[2, 3, 8] {
+        let multi = render_with(threads);
+        assert!(single.iter().zip(multi.iter()).all(|(a, b)| a == b));
+    }
+}
+
+#[test]
+fn rendering_a_world_one_row_at_a_time() {
+    let w = World::book_default();
+    let c = Camera::new_with_transform(
+        21,
+        37,
+        PI / 3.0,
+        Matrix::look_at(
+            Point3::new(0.0, 1.5, -5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::unit_y(),
+        ),
+    );
+
+    let mut rows = Vec::new();
+    c.render_rows(&w, |row| {
+        rows.push(row.to_vec());
+        Ok::<_, ()>(())
+    })
+    .unwrap();
+
+    let canvas = c.render(&w);
+    assert_eq!(rows.len(), 37);
+    assert!(rows.iter().zip(canvas.scanlines()).all(|(a, b)| a == b));
+}
+
+#[test]
+fn rendering_rows_stops_at_the_first_error() {
+    let w = World::book_default();
+    let c = Camera::new(5, 40, PI / 2.0);
+
+    let mut rows = 0;
+    let res = c.render_rows(&w, |_| {
+        rows += 1;
+        if rows == 20 {
+            Err(rows)
+        } else {
+            Ok(())
+        }
+    });
+
+    assert_eq!(res, Err(20));
+    assert_eq!(rows, 20);
+}
+
+#[test]
+fn previewing_a_world_at_increasing_resolutions() {
+    let w = World::book_default();
+    let c = Camera::new_with_transform(
+        200,
+        90,
+        PI / 3.0,
+        Matrix::look_at(
+            Point3::new(0.0, 1.5, -5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::unit_y(),
+        ),
+    );
+
+    let canvas = c.render(&w);
+    let levels = c.preview(&w).collect::<Vec<_>>();
+
+    assert_eq!(
+        levels
+            .iter()
+            .map(|l| (l.width(), l.height()))
+            .collect::<Vec<_>>(),
+        vec![(50, 23), (100, 45), (200, 90)]
+    );
+
+    // Each level samples the full resolution image on a regular grid
+    for (level, stride) in levels.iter().zip(&[4, 2, 1]) {
+        for (x, y, c) in level.enumerate_pixels() {
+            assert_eq!(
+                Some(c),
+                canvas.get(x * stride, y * stride),
+                "pixel ({}, {}) of level with stride {}",
+                x,
+                y,
+                stride
+            );
+        }
+    }
+}
+
+#[test]
+fn previewing_a_small_world_renders_it_at_once() {
+    let w = World::book_default();
+    let c = Camera::new(PREVIEW_SIZE, 20, PI / 2.0);
+
+    let mut preview = c.preview(&w);
+    assert_eq!(preview.stride(), 1);
+    assert_eq!(preview.total_rows(), 20);
+
+    while preview.advance() {}
+
+    assert!(preview.is_finished());
+    assert_eq!(preview.completed_rows(), 20);
+    assert!(preview.canvas().iter().eq(c.render(&w).iter()));
+}
+
+#[test]
+fn streaming_a_world_reports_its_progress() {
+    let w = World::book_default();
+    let c = Camera::new(11, 11, PI / 2.0);
+
+    let mut stream = c.stream(&w);
+    assert_eq!(stream.completed_lines(), 0);
+    assert_eq!(stream.total_lines(), 11);
+
+    while stream.advance() {
+        assert!(stream.completed_lines() > 0);
+    }
+
+    assert_eq!(stream.completed_lines(), stream.total_lines());
+}
+
+#[test]
+fn streaming_a_world_renders_one_batch_per_pool_thread() {
+    let w = World::book_default();
+    let c = Camera::new(11, 11, PI / 2.0);
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .build()
+        .unwrap();
+
+    pool.install(|| {
+        let mut stream = c.stream(&w);
+
+        assert!(stream.advance());
+        assert_eq!(stream.completed_lines(), 2);
+    });
+}
+
+#[test]
+fn random_numbers_are_reproducible() {
+    let mut a = Rng::new(42);
+    let mut b = Rng::new(42);
+    let mut c = Rng::with_stream(42, 1);
+
+    let xs = (0..100).map(|_| a.next_f32()).collect::<Vec<_>>();
+
+    assert!(xs.iter().all(|x| (0.0..1.0).contains(x)));
+    assert!(xs.iter().all(|&x| x == b.next_f32()));
+    assert!(xs.iter().any(|&x| x != c.next_f32()));
+}
+
+#[test]
+fn the_traced_path_when_a_ray_misses() {
+    let w = World::book_default();
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_y());
+
+    assert_abs_diff!(
+        w.trace_path(&r, DEFAULT_RECURSION_DEPTH, &mut Rng::new(0)),
+        Color::BLACK
+    );
+}
+
+#[test]
+fn the_traced_path_without_bounces_is_lit_directly() {
+    let w = World::book_default();
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
+
+    // Same as `color_at`, without the ambient term
+    assert_abs_diff!(
+        w.trace_path(&r, 0, &mut Rng::new(0)),
+        Color::new(0.30066, 0.37583, 0.2255)
+    );
+}
+
+#[test]
+fn traced_paths_light_surfaces_in_shadow() {
+    let mut w = World::new();
+    let material = Material {
+        ambient: 0.0,
+        ..Default::default()
+    };
+
+    w.add_light(PointLight {
+        position: Point3::new(0.0, 10.0, 0.0),
+        ..Default::default()
+    });
+    w.add(Object::new_with_material(
+        Plane,
+        Matrix::identity(),
+        material.clone(),
+    ));
+    w.add(Object::new_with_material(
+        Sphere,
+        Matrix::from_translation(0.0, 1.0, 0.0),
+        material,
+    ));
+
+    // The floor under the sphere only receives the light bounced off the rest of the floor
+    let r = Ray::new(
+        Point3::new(3.0, 0.2, 0.0),
+        (Point3::new(0.5, 0.0, 0.0) - Point3::new(3.0, 0.2, 0.0)).normalize(),
+    );
+    assert_abs_diff!(w.color_at(&r, DEFAULT_RECURSION_DEPTH), Color::BLACK);
+
+    let mut rng = Rng::new(0);
+    let c = (0..256)
+        .map(|_| w.trace_path(&r, DEFAULT_RECURSION_DEPTH, &mut rng))
+        .fold(Color::BLACK, |acc, c| acc + c)
+        / 256.0;
+    assert!(c.r > 0.0 && c.g > 0.0 && c.b > 0.0);
+}
+
+#[test]
+fn choosing_the_integrator_of_a_camera() {
+    let mut c = Camera::new(160, 120, PI / 2.0);
+
+    c.set_integrator(Integrator::PathTracing);
+    c.set_samples_per_pixel(16);
+
+    assert_eq!(c.integrator(), Integrator::PathTracing);
+    assert_eq!(c.samples_per_pixel(), 16);
+}
+
+#[test]
+fn rendering_a_single_pixel_with_a_camera() {
+    let w = World::book_default();
+    let mut c = Camera::new_with_transform(
+        11,
+        11,
+        PI / 2.0,
+        Matrix::look_at(
+            Point3::new(0.0, 0.0, -5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::unit_y(),
+        ),
+    );
+
+    let canvas = c.render(&w);
+    assert_abs_diff!(c.render_pixel(&w, 5, 5).0, canvas.get(5, 5).unwrap());
+
+    c.set_samples_per_pixel(6);
+    let canvas = c.render(&w);
+    for (x, y) in [(5, 5), (0, 0), (3, 8)] {
+        assert_eq!(&c.render_pixel(&w, x, y).0, canvas.get(x, y).unwrap());
+    }
+}
+
+#[test]
+fn recording_the_aovs_of_a_render() {
+    let w = World::book_default();
+    let outer = w.object_handles().next().unwrap();
+    let mut c = Camera::new_with_transform(
+        11,
+        11,
+        PI / 2.0,
+        Matrix::look_at(
+            Point3::new(0.0, 0.0, -5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::unit_y(),
+        ),
+    );
+
+    for samples in [1, 6] {
+        c.set_samples_per_pixel(samples);
+
+        let mut preview = c.preview(&w);
+        while preview.advance() {}
+        let aovs = preview.aovs();
+
+        assert_eq!(aovs.len(), 11 * 11);
+        assert_eq!(aovs[5 * 11 + 5].object, Some(outer));
+        assert!((aovs[5 * 11 + 5].depth - 4.0).abs() < 0.25);
+        assert_eq!(aovs[0], Aov::default());
+
+        for (i, aov) in aovs.iter().enumerate() {
+            assert_eq!(c.render_pixel(&w, i as u32 % 11, i as u32 / 11).1, *aov);
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn a_camera_needs_at_least_one_sample_per_pixel() {
+    Camera::new(160, 120, PI / 2.0).set_samples_per_pixel(0);
+}
+
+#[test]
+fn path_tracing_does_not_depend_on_the_number_of_threads() {
+    let w = World::book_default();
+    let mut c = Camera::new_with_transform(
+        17,
+        9,
+        PI / 3.0,
+        Matrix::look_at(
+            Point3::new(0.0, 1.5, -5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::unit_y(),
+        ),
+    );
+    c.set_integrator(Integrator::PathTracing);
+    c.set_samples_per_pixel(4);
+
+    let render_with = |threads| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| c.render(&w))
+    };
+
+    let single = render_with(1);
+    let multi = render_with(4);
+    let preview = c.preview(&w).last().unwrap();
+
+    assert!(single.iter().zip(multi.iter()).all(|(a, b)| a == b));
+    assert!(single.iter().zip(preview.iter()).all(|(a, b)| a == b));
+}
+
+#[test]
+fn building_an_invalid_scene_prefab() {
+    let camera = CameraPrefab {
+        width: 160,
+        height: 120,
+        fov: 60.0,
+        from: Point3::new(0.0, 1.5, -5.0),
+        to: Point3::new(0.0, 1.0, 0.0),
+        up: Vec3::unit_y(),
+    };
+    let scene = |camera, objects| ScenePrefab {
+        camera,
+        lights: vec![PointLight::default()],
+        area_lights: vec![],
+        objects,
+        tolerances: Default::default(),
+        recursion_limit: None,
+        render_settings: Default::default(),
+        environment: Default::default(),
+    };
+
+    assert!(scene(camera, vec![sphere()]).try_build().is_ok());
+
+    let invalid_cameras = [
+        CameraPrefab { width: 0, ..camera },
+        CameraPrefab {
+            fov: 180.0,
+            ..camera
+        },
+        CameraPrefab {
+            to: camera.from,
+            ..camera
+        },
+        CameraPrefab {
+            up: Vec3::new(0.0, 0.0, 3.0),
+            to: Point3::new(0.0, 1.5, 0.0),
+            ..camera
+        },
+    ];
+    for camera in IntoIterator::into_iter(invalid_cameras) {
+        assert!(matches!(
+            scene(camera, vec![]).try_build(),
+            Err(Error::InvalidCamera(_))
+        ));
+    }
+
+    let flat = Object::new(Sphere, Matrix::from_scale(1.0, 0.0, 1.0));
+    assert!(matches!(
+        scene(camera, vec![sphere(), flat]).try_build(),
+        Err(Error::NonInvertibleTransform { object: 1 })
+    ));
+}
@@ -1,9 +1,13 @@
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
 use rendering::DEFAULT_RECURSION_DEPTH;
 use tracy::{
-    math::{Matrix, Point3, Vec3, EPSILON},
+    math::{Matrix, Point3, Tolerances, Vec3, EPSILON},
     query::{Object, Ray, World},
-    rendering::{self, Color, PointLight},
-    shape::Sphere,
+    rendering::{
+        self, AreaLight, Color, LightSource, Material, PointLight, RenderSettings, Sequence,
+    },
+    shape::{Plane, Sphere},
 };
 pub use utils::*;
 
@@ -24,7 +28,7 @@ fn lighting_with_the_surface_in_shadow() {
         &Point3::new(0.0, 0.0, 0.0),
         &eye,
         &normal,
-        true,
+        0.0,
     );
 
     assert_abs_diff!(result, Color::new(0.1, 0.1, 0.1));
@@ -32,28 +36,28 @@ fn lighting_with_the_surface_in_shadow() {
 
 #[test]
 fn there_is_no_shadow_when_nothing_is_collinear_with_point_and_light() {
-    let w = World::default();
+    let w = World::book_default();
     let p = Point3::new(0.0, 10.0, 0.0);
     assert!(!w.is_in_shadow(&p, w.lights().next().unwrap()));
 }
 
 #[test]
 fn the_shadow_when_an_object_is_between_the_point_and_the_light() {
-    let w = World::default();
+    let w = World::book_default();
     let p = Point3::new(10.0, -10.0, 10.0);
     assert!(w.is_in_shadow(&p, w.lights().next().unwrap()));
 }
 
 #[test]
 fn there_is_no_shadow_when_an_object_is_behind_the_light() {
-    let w = World::default();
+    let w = World::book_default();
     let p = Point3::new(-20.0, 20.0, -20.0);
     assert!(!w.is_in_shadow(&p, w.lights().next().unwrap()));
 }
 
 #[test]
 fn there_is_no_shadow_when_an_object_is_behind_the_point() {
-    let w = World::default();
+    let w = World::book_default();
     let p = Point3::new(-2.0, 2.0, -2.0);
     assert!(!w.is_in_shadow(&p, w.lights().next().unwrap()));
 }
@@ -77,7 +81,7 @@ fn shade_hit_is_given_an_intersection_in_shadow() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 4.0).abs() < EPSILON)
+        .hit_within(4.0 - EPSILON..4.0 + EPSILON)
         .unwrap();
 
     let c = w.shade_hit(&interference, DEFAULT_RECURSION_DEPTH);
@@ -94,9 +98,310 @@ fn the_hit_should_offset_the_point() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 5.0).abs() < EPSILON)
+        .hit_within(5.0 - EPSILON..5.0 + EPSILON)
         .unwrap();
 
     assert!(interference.over_point.z < -EPSILON / 2.0);
     assert!(interference.point.z > interference.over_point.z);
 }
+
+#[test]
+fn the_shadow_bias_of_a_world_offsets_the_point() {
+    let mut w = World::new();
+
+    w.add(Object::new(Sphere, Matrix::from_translation(0.0, 0.0, 1.0)));
+    w.set_tolerances(Tolerances {
+        shadow_bias: 0.5,
+        ..Default::default()
+    });
+
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
+    let interference = w.interferences_with_ray(&r).hit().unwrap();
+
+    assert_f32!(interference.over_point.z, -0.5);
+    assert_f32!(interference.under_point.z, 0.5);
+}
+
+#[test]
+fn the_hit_ignores_intersections_closer_than_the_tolerance() {
+    let mut w = World::new();
+
+    w.add(sphere());
+    w.set_tolerances(Tolerances {
+        intersection: 2.0,
+        ..Default::default()
+    });
+
+    let r = Ray::new(Point3::new(0.0, 0.0, -2.5), Vec3::unit_z());
+    let hit = w.interferences_with_ray(&r).hit().unwrap();
+    assert_f32!(hit.toi, 3.5);
+
+    let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::unit_z());
+    assert!(w.interferences_with_ray(&r).hit().is_none());
+}
+
+fn area_light(jitter: Sequence) -> AreaLight {
+    AreaLight {
+        corner: Point3::new(-0.5, -0.5, -5.0),
+        uvec: Vec3::unit_x(),
+        usteps: 2,
+        vvec: Vec3::unit_y(),
+        vsteps: 2,
+        jitter,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn creating_an_area_light() {
+    let light = AreaLight {
+        corner: Point3::new(0.0, 0.0, 0.0),
+        uvec: Vec3::new(2.0, 0.0, 0.0),
+        usteps: 4,
+        vvec: Vec3::new(0.0, 0.0, 1.0),
+        vsteps: 2,
+        ..Default::default()
+    };
+
+    assert_eq!(light.sample_count(), 8);
+    assert_abs_diff!(light.position(), Point3::new(1.0, 0.0, 0.5));
+    assert_abs_diff!(light.radiance(), Color::WHITE);
+}
+
+#[test]
+fn finding_a_single_point_on_an_area_light() {
+    let mut light = AreaLight {
+        corner: Point3::new(0.0, 0.0, 0.0),
+        uvec: Vec3::new(2.0, 0.0, 0.0),
+        usteps: 4,
+        vvec: Vec3::new(0.0, 0.0, 1.0),
+        vsteps: 2,
+        ..Default::default()
+    };
+
+    let cells = [(0, 0), (1, 0), (0, 1), (2, 0), (3, 1)];
+
+    let centered = [
+        Point3::new(0.25, 0.0, 0.25),
+        Point3::new(0.75, 0.0, 0.25),
+        Point3::new(0.25, 0.0, 0.75),
+        Point3::new(1.25, 0.0, 0.25),
+        Point3::new(1.75, 0.0, 0.75),
+    ];
+    for (&(u, v), &expected) in cells.iter().zip(centered.iter()) {
+        assert_abs_diff!(light.point_on(u, v), expected);
+    }
+
+    light.jitter = Sequence::new(vec![0.3, 0.7]);
+    let jittered = [
+        Point3::new(0.15, 0.0, 0.35),
+        Point3::new(0.65, 0.0, 0.35),
+        Point3::new(0.15, 0.0, 0.85),
+        Point3::new(1.15, 0.0, 0.35),
+        Point3::new(1.65, 0.0, 0.85),
+    ];
+    for (&(u, v), &expected) in cells.iter().zip(jittered.iter()) {
+        assert_abs_diff!(light.point_on(u, v), expected);
+    }
+}
+
+#[test]
+fn sequences_wrap_around() {
+    let seq = Sequence::new(vec![0.1, 0.5, 1.0]);
+    assert_f32!(seq.get(0), 0.1);
+    assert_f32!(seq.get(2), 1.0);
+    assert_f32!(seq.get(4), 0.5);
+
+    assert_eq!(Sequence::new(vec![]), Sequence::default());
+    assert_f32!(Sequence::default().get(7), 0.5);
+}
+
+#[test]
+fn the_area_light_intensity_function() {
+    let w = World::book_default();
+
+    let cases = [
+        (vec![], [0.0, 0.25, 0.5, 0.75, 1.0]),
+        (vec![0.7, 0.3, 0.9, 0.1, 0.5], [0.0, 0.5, 0.75, 0.75, 1.0]),
+    ];
+
+    for (jitter, expected) in IntoIterator::into_iter(cases) {
+        let light = area_light(Sequence::new(jitter));
+
+        for (p, &e) in [
+            Point3::new(0.0, 0.0, 2.0),
+            Point3::new(1.0, -1.0, 2.0),
+            Point3::new(1.5, 0.0, 2.0),
+            Point3::new(1.25, 1.25, 3.0),
+            Point3::new(0.0, 0.0, -2.0),
+        ]
+        .iter()
+        .zip(expected.iter())
+        {
+            assert_f32!(w.light_intensity_at(p, &light), e);
+        }
+    }
+}
+
+#[test]
+fn lighting_samples_the_area_light() {
+    let light = area_light(Sequence::default());
+    let obj = Object::new_with_material(
+        Sphere,
+        Matrix::identity(),
+        Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            ..Default::default()
+        },
+    );
+    let eye = Point3::new(0.0, 0.0, -5.0);
+
+    for &(p, expected) in &[
+        (Point3::new(0.0, 0.0, -1.0), 0.9965),
+        (Point3::new(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2), 0.62318),
+    ] {
+        let c = rendering::phong_lighting(
+            &obj,
+            &light,
+            &p,
+            &(eye - p).normalize(),
+            &Vec3::new(p.x, p.y, p.z),
+            1.0,
+        );
+        assert_abs_diff!(c, Color::new(expected, expected, expected));
+    }
+}
+
+#[test]
+fn area_lights_cast_soft_shadows() {
+    let mut w = World::new();
+    w.add(plane());
+    w.add(Object::new(
+        Sphere,
+        Matrix::from_translation(0.0, 1.0, 0.0) * Matrix::from_scale(0.5, 0.5, 0.5),
+    ));
+    let light = w.add_area_light(AreaLight {
+        corner: Point3::new(-1.0, 4.0, -1.0),
+        uvec: Vec3::new(2.0, 0.0, 0.0),
+        usteps: 4,
+        vvec: Vec3::new(0.0, 0.0, 2.0),
+        vsteps: 4,
+        ..Default::default()
+    });
+
+    let light = w.get_area_light(light).unwrap();
+    let umbra = w.light_intensity_at(&Point3::new(0.0, 0.01, 0.0), light);
+    let penumbra = w.light_intensity_at(&Point3::new(0.7, 0.01, 0.0), light);
+    let lit = w.light_intensity_at(&Point3::new(3.0, 0.01, 0.0), light);
+
+    assert_f32!(umbra, 0.0);
+    assert!(0.0 < penumbra && penumbra < 1.0);
+    assert_f32!(lit, 1.0);
+
+    let r = Ray::new(Point3::new(0.7, 5.0, 0.0), -Vec3::unit_y());
+    let hit = w.interferences_with_ray(&r).hit().unwrap();
+    let shaded = w.shade_hit(&hit, DEFAULT_RECURSION_DEPTH);
+    assert!(shaded.r > 0.1 && shaded.r < 1.0);
+}
+
+#[test]
+fn managing_area_lights() {
+    let mut w = World::new();
+    let a = w.add_area_light(AreaLight::default());
+    let b = w.add_area_light(area_light(Sequence::default()));
+
+    w.get_area_light_mut(a).unwrap().intensity = 0.5;
+    assert_f32!(w.get_area_light(a).unwrap().intensity, 0.5);
+
+    assert_eq!(w.remove_area_light(a).map(|l| l.intensity), Some(0.5));
+    assert!(w.get_area_light(a).is_none());
+    assert_eq!(w.area_light_handles().collect::<Vec<_>>(), [b]);
+    assert_eq!(w.area_lights().count(), 1);
+    assert_eq!(w.lights().count(), 0);
+}
+
+#[test]
+fn ambient_occlusion_is_disabled_by_default() {
+    let w = World::book_default();
+    assert_eq!(w.render_settings(), &RenderSettings::default());
+    assert_eq!(w.render_settings().ao_samples, 0);
+    assert_f32!(
+        w.ambient_visibility(&Point3::new(0.0, 0.0, 0.0), &Vec3::unit_y()),
+        1.0
+    );
+}
+
+#[test]
+fn objects_within_the_radius_occlude_ambient_light() {
+    let mut w = World::new();
+    w.add(Object::new(Sphere, Matrix::from_scale(5.0, 5.0, 5.0)));
+
+    let p = Point3::new(0.0, 0.0, 0.0);
+    let n = Vec3::new(1.0, 2.0, 3.0).normalize();
+
+    for &(radius, expected) in &[(1.0, 1.0), (10.0, 0.0)] {
+        w.set_render_settings(RenderSettings {
+            ao_samples: 16,
+            ao_radius: radius,
+            ..Default::default()
+        });
+        assert_f32!(w.ambient_visibility(&p, &n), expected);
+    }
+}
+
+#[test]
+fn corners_are_partially_occluded() {
+    let mut w = World::new();
+    w.add(plane());
+    w.add(Object::new(
+        Plane,
+        Matrix::from_translation(1.0, 0.0, 0.0) * Matrix::from_rotation_z(PI / 2.0),
+    ));
+    w.set_render_settings(RenderSettings {
+        ao_samples: 64,
+        ao_radius: 2.0,
+        ..Default::default()
+    });
+
+    let corner = w.ambient_visibility(&Point3::new(0.9, 0.01, 0.0), &Vec3::unit_y());
+    let open = w.ambient_visibility(&Point3::new(-5.0, 0.01, 0.0), &Vec3::unit_y());
+
+    assert!(0.0 < corner && corner < 1.0);
+    assert_f32!(open, 1.0);
+}
+
+#[test]
+fn shade_hit_darkens_the_ambient_light_of_occluded_points() {
+    let mut w = World::new();
+    w.add(plane());
+    w.add(Object::new(
+        Sphere,
+        Matrix::from_translation(0.0, 1.0, 0.0) * Matrix::from_scale(0.5, 0.5, 0.5),
+    ));
+    w.add_light(PointLight {
+        position: Point3::new(0.0, 10.0, 0.0),
+        casts_shadows: false,
+        ..Default::default()
+    });
+
+    let shade = |w: &World, x: f32| {
+        let r = Ray::new(Point3::new(x, 0.3, 0.0), -Vec3::unit_y());
+        let hit = w.interferences_with_ray(&r).hit().unwrap();
+        w.shade_hit(&hit, DEFAULT_RECURSION_DEPTH)
+    };
+
+    let (below, far) = (shade(&w, 0.0), shade(&w, 5.0));
+
+    w.set_render_settings(RenderSettings {
+        ao_samples: 32,
+        ao_radius: 2.0,
+        ..Default::default()
+    });
+
+    let occluded = shade(&w, 0.0);
+    assert!(occluded.r < below.r);
+    assert!(occluded.r > below.r - Material::default().ambient);
+    assert_abs_diff!(shade(&w, 5.0), far);
+}
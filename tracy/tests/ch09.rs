@@ -2,8 +2,9 @@ use std::f32::consts::{FRAC_1_SQRT_2, PI};
 
 use tracy::{
     math::{Matrix, Point3, Vec3},
-    query::Ray,
+    query::{Aabb, Object, Ray, RayCast, World},
     rendering::Material,
+    shape::{Cone, Cube, Cylinder, Plane, Quad, Shape, Sphere},
 };
 pub use utils::*;
 
@@ -12,7 +13,7 @@ mod utils;
 #[test]
 fn the_default_transformation() {
     let s = test_shape();
-    assert_abs_diff!(s.transform(), Matrix::identity(4));
+    assert_abs_diff!(s.transform(), Matrix::identity());
 }
 
 #[test]
@@ -22,6 +23,140 @@ fn assigning_a_transformation() {
     assert_abs_diff!(s.transform(), Matrix::from_translation(2.0, 3.0, 4.0));
 }
 
+#[test]
+fn the_inverse_transformation_follows_the_transformation() {
+    let mut s = test_shape();
+    assert_abs_diff!(s.inverse_transform().unwrap(), Matrix::identity());
+
+    let m = Matrix::from_translation(2.0, 3.0, 4.0) * Matrix::from_scale(1.0, 2.0, 1.0);
+    s.set_transform(m.clone());
+    assert_abs_diff!(s.inverse_transform().unwrap(), m.inverse().unwrap());
+    assert_abs_diff!(s.normal_matrix().unwrap(), m.inverse().unwrap().transpose());
+
+    s.set_transform(Matrix::from_scale(1.0, 0.0, 1.0));
+    assert!(s.inverse_transform().is_none());
+    assert!(s.normal_matrix().is_none());
+}
+
+#[test]
+fn building_an_object() {
+    let s = Object::builder(Sphere).build();
+    assert_abs_diff!(s.transform(), Matrix::identity());
+    assert_eq!(s.material(), &Material::default());
+    assert!(s.casts_shadow());
+    assert_eq!(s.name(), None);
+
+    let m = Material {
+        ambient: 1.0,
+        ..Default::default()
+    };
+    let s = Object::builder(Sphere)
+        .transform(Matrix::from_translation(2.0, 3.0, 4.0))
+        .material(m.clone())
+        .name("ball")
+        .casts_shadow(false)
+        .build();
+
+    assert_abs_diff!(s.transform(), Matrix::from_translation(2.0, 3.0, 4.0));
+    assert_eq!(s.material(), &m);
+    assert!(!s.casts_shadow());
+    assert_eq!(s.name(), Some("ball"));
+}
+
+#[test]
+fn comparing_shapes() {
+    let mut short = Cylinder::default();
+    short.set_top(1.0);
+
+    let shapes: [&dyn Shape; 4] = [&Sphere, &Cube, &Cylinder::default(), &short];
+
+    for (i, a) in shapes.iter().enumerate() {
+        for (j, b) in shapes.iter().enumerate() {
+            assert_eq!(a.same_shape(*b), i == j);
+            assert_eq!(*a == *b, i == j);
+        }
+    }
+}
+
+#[test]
+fn comparing_objects() {
+    let ball = Object::builder(Sphere).name("ball").build();
+
+    assert_eq!(ball, ball.clone());
+    assert_ne!(ball, sphere());
+    assert_ne!(ball, Object::builder(Cube).name("ball").build());
+    assert_ne!(
+        ball,
+        Object::builder(Sphere)
+            .name("ball")
+            .transform(Matrix::from_scale(2.0, 2.0, 2.0))
+            .build()
+    );
+}
+
+#[test]
+fn identifying_the_object_hit_by_a_ray() {
+    let ball = Object::builder(Sphere)
+        .transform(Matrix::from_translation(0.0, 0.0, 5.0))
+        .build();
+
+    let mut w = World::new();
+    w.add(plane());
+    w.add(ball.clone());
+
+    let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::unit_z());
+    let hit = w.interferences_with_ray(&r).hit().unwrap();
+
+    assert_eq!(w.get(hit.handle), Some(&ball));
+}
+
+#[test]
+fn tagging_an_object() {
+    let mut s = Object::builder(Sphere)
+        .tag("toys")
+        .tag("red")
+        .tag("toys")
+        .build();
+    assert_eq!(s.tags().collect::<Vec<_>>(), ["toys", "red"]);
+    assert!(s.has_tag("red"));
+    assert!(!s.has_tag("blue"));
+
+    s.add_tag("blue");
+    assert!(s.remove_tag("red"));
+    assert!(!s.remove_tag("red"));
+    assert_eq!(s.tags().collect::<Vec<_>>(), ["toys", "blue"]);
+
+    assert_ne!(s, Object::builder(Sphere).tag("toys").build());
+}
+
+#[test]
+fn renaming_an_object() {
+    let mut s = Object::builder(Sphere).name("ball").build();
+    s.set_name(Some("marble"));
+    assert_eq!(s.name(), Some("marble"));
+    s.set_name(None::<&str>);
+    assert_eq!(s.name(), None);
+}
+
+#[test]
+fn finding_objects_by_name_and_tag() {
+    let mut w = World::new();
+    let floor = w.add(Object::builder(Plane).name("floor").tag("static").build());
+    let ball = w.add(Object::builder(Sphere).name("ball").tag("toys").build());
+    let cube = w.add(Object::builder(Cube).tag("toys").tag("static").build());
+
+    assert_eq!(w.find_by_name("ball"), Some(ball));
+    assert_eq!(w.find_by_name("floor"), Some(floor));
+    assert_eq!(w.find_by_name("wall"), None);
+
+    assert_eq!(w.objects_with_tag("toys").collect::<Vec<_>>(), [ball, cube]);
+    assert_eq!(
+        w.objects_with_tag("static").collect::<Vec<_>>(),
+        [floor, cube]
+    );
+    assert_eq!(w.objects_with_tag("lights").count(), 0);
+}
+
 #[test]
 fn the_default_material() {
     assert_eq!(test_shape().material(), &Material::default());
@@ -102,6 +237,41 @@ fn computing_the_normal_on_a_transformed_shape() {
     );
 }
 
+#[test]
+fn computing_the_normal_of_an_object_at_a_point() {
+    let mut s = sphere();
+    s.set_transform(Matrix::from_translation(0.0, 1.0, 0.0));
+    assert_abs_diff!(
+        s.normal_at(&Point3::new(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2)),
+        Vec3::new(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2)
+    );
+
+    s.set_transform(Matrix::from_scale(1.0, 0.5, 1.0) * Matrix::from_rotation_z(PI / 5.0));
+    assert_abs_diff!(
+        s.normal_at(&Point3::new(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2)),
+        Vec3::new(0.0, 0.97014, -0.24254)
+    );
+}
+
+#[test]
+fn the_normal_of_an_object_matches_the_one_of_ray_queries() {
+    let objects = [
+        Object::new(Cube, Matrix::from_rotation_y(PI / 3.0)),
+        Object::new(Cylinder::default(), Matrix::from_scale(2.0, 1.0, 0.5)),
+        Object::new(Cone::default(), Matrix::from_translation(0.0, -2.0, 0.0)),
+        Object::new(Quad::default(), Matrix::from_rotation_x(PI / 2.0)),
+    ];
+    let r = Ray::new(
+        Point3::new(0.3, 0.2, -5.0),
+        Vec3::new(0.0, 0.1, 1.0).normalize(),
+    );
+
+    for obj in &objects {
+        let hit = obj.interferences_with_ray(&r).hit().unwrap();
+        assert_abs_diff!(obj.normal_at(&r.point_at(hit.toi)), hit.normal);
+    }
+}
+
 #[test]
 fn the_normal_of_a_plane_is_constant_everywhere() {
     let p = plane();
@@ -158,3 +328,131 @@ fn a_ray_intersecting_a_plane_from_below() {
     assert_eq!(xs.len(), 1);
     assert_f32!(xs[0].toi, 1.0);
 }
+
+#[test]
+fn the_bounding_box_of_each_shape() {
+    let unit = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+    assert_eq!(Sphere.local_bounds(), unit);
+    assert_eq!(Cube.local_bounds(), unit);
+
+    let plane = Plane.local_bounds();
+    assert_eq!((plane.min.y, plane.max.y), (0.0, 0.0));
+    assert_eq!(plane.min.x, f32::NEG_INFINITY);
+    assert_eq!(plane.max.z, f32::INFINITY);
+
+    let mut cyl = Cylinder::default();
+    assert_eq!(cyl.local_bounds().max.y, f32::INFINITY);
+    cyl.set_bottom(-2.0);
+    cyl.set_top(3.0);
+    assert_eq!(
+        cyl.local_bounds(),
+        Aabb::new(Point3::new(-1.0, -2.0, -1.0), Point3::new(1.0, 3.0, 1.0))
+    );
+
+    let mut cone = Cone::default();
+    cone.set_bottom(-5.0);
+    cone.set_top(3.0);
+    assert_eq!(
+        cone.local_bounds(),
+        Aabb::new(Point3::new(-5.0, -5.0, -5.0), Point3::new(5.0, 3.0, 5.0))
+    );
+
+    assert_eq!(test_shape().shape().local_bounds(), Aabb::infinite());
+}
+
+#[test]
+fn the_bounding_box_of_a_transformed_object() {
+    let s = Object::new(
+        Sphere,
+        Matrix::from_translation(1.0, 2.0, 3.0) * Matrix::from_scale(2.0, 1.0, 0.5),
+    );
+    assert_abs_diff!(
+        s.bounds(),
+        Aabb::new(Point3::new(-1.0, 1.0, 2.5), Point3::new(3.0, 3.0, 3.5))
+    );
+
+    let c = Object::new(Cube, Matrix::from_rotation_y(PI / 4.0));
+    let d = 2.0 * FRAC_1_SQRT_2;
+    assert_abs_diff!(
+        c.bounds(),
+        Aabb::new(Point3::new(-d, -1.0, -d), Point3::new(d, 1.0, d))
+    );
+
+    let p = Object::new(Plane, Matrix::from_translation(0.0, 2.0, 0.0));
+    assert_eq!((p.bounds().min.y, p.bounds().max.y), (2.0, 2.0));
+    assert!(!p.bounds().is_finite());
+}
+
+#[test]
+fn combining_bounding_boxes() {
+    let a = Aabb::new(Point3::new(1.0, 0.0, 0.0), Point3::new(-1.0, 2.0, 1.0));
+    assert_eq!(a.min, Point3::new(-1.0, 0.0, 0.0));
+
+    let b = Aabb::from_points(vec![Point3::new(3.0, -1.0, 0.5)]);
+    let u = a.union(&b);
+    assert_eq!(u.min, Point3::new(-1.0, -1.0, 0.0));
+    assert_eq!(u.max, Point3::new(3.0, 2.0, 1.0));
+    assert!(u.contains(&Point3::new(2.0, 1.0, 0.5)));
+    assert!(!u.contains(&Point3::new(2.0, 3.0, 0.5)));
+
+    assert!(Aabb::empty().is_empty());
+    assert_eq!(Aabb::empty().union(&a), a);
+    assert!(Aabb::from_points(vec![]).is_empty());
+}
+
+#[test]
+fn intersecting_a_ray_with_a_bounding_box() {
+    let b = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+
+    let r = Ray::new(Point3::new(0.0, 0.5, -5.0), Vec3::unit_z());
+    assert_eq!(b.intersect_ray(&r), Some((4.0, 6.0)));
+
+    let r = Ray::new(Point3::new(0.0, 2.0, -5.0), Vec3::unit_z());
+    assert_eq!(b.intersect_ray(&r), None);
+
+    let r = Ray::new(Point3::new(2.0, 0.0, 2.0), Vec3::new(-1.0, 0.0, -1.0));
+    assert_eq!(b.intersect_ray(&r), Some((1.0, 3.0)));
+
+    let plane = Object::new(Plane, Matrix::identity()).bounds();
+    let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+    assert_eq!(plane.intersect_ray(&r), Some((1.0, 1.0)));
+}
+
+#[test]
+fn a_ray_intersecting_a_quad() {
+    let q = Quad::new(4.0, 2.0);
+
+    for &(origin, count) in &[
+        (Point3::new(0.0, 1.0, 0.0), 1),
+        (Point3::new(1.9, 1.0, -0.9), 1),
+        (Point3::new(2.1, 1.0, 0.0), 0),
+        (Point3::new(0.0, 1.0, 1.1), 0),
+    ] {
+        let r = Ray::new(origin, -Vec3::unit_y());
+        assert_eq!(q.intersections_in_local_space(&r).count(), count);
+    }
+
+    let r = Ray::new(Point3::new(0.0, -1.0, 0.0), Vec3::unit_y());
+    let x = q.intersections_in_local_space(&r).next().unwrap();
+    assert_f32!(x.toi, 1.0);
+    assert_abs_diff!(x.normal, Vec3::unit_y());
+}
+
+#[test]
+fn intersecting_a_quad_with_a_parallel_ray() {
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
+    assert_eq!(Quad::default().intersections_in_local_space(&r).count(), 0);
+}
+
+#[test]
+fn resizing_a_quad() {
+    let mut q = Quad::default();
+    assert_eq!((q.width(), q.depth()), (2.0, 2.0));
+
+    q.set_width(-3.0);
+    q.set_depth(5.0);
+    assert_eq!(
+        q.local_bounds(),
+        Aabb::new(Point3::new(-1.5, 0.0, -2.5), Point3::new(1.5, 0.0, 2.5))
+    );
+}
@@ -1,8 +1,14 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
 use tracy::{
-    math::{Matrix, Point3, Vec3},
+    math::{Matrix, Noise, Point3, Vec3},
     query::Object,
-    rendering::{self, Color, Material, Pattern, PatternKind, PointLight},
-    shape::Sphere,
+    rendering::{
+        self, Canvas, Color, Material, Pattern, PatternKind, PointLight, TextureFilter,
+        TextureMapping,
+    },
+    shape::{Cone, Cube, Cylinder, Plane, Quad, Shape, Sphere, Triangle},
+    Error,
 };
 pub use utils::*;
 
@@ -69,7 +75,7 @@ fn a_stripe_pattern_alternates_in_x() {
 fn lighting_with_a_pattern_applied() {
     let obj = Object::new_with_material(
         Sphere,
-        Matrix::identity(4),
+        Matrix::identity(),
         Material {
             pattern: Pattern::new(PatternKind::Stripes(
                 Box::new(Pattern::new(Color::WHITE.into())),
@@ -96,7 +102,7 @@ fn lighting_with_a_pattern_applied() {
         &Point3::new(0.9, 0.0, 0.0),
         &eye,
         &normal,
-        false,
+        1.0,
     );
 
     let c2 = rendering::phong_lighting(
@@ -105,7 +111,7 @@ fn lighting_with_a_pattern_applied() {
         &Point3::new(1.1, 0.0, 0.0),
         &eye,
         &normal,
-        false,
+        1.0,
     );
 
     assert_eq!(c1, Color::WHITE);
@@ -135,7 +141,7 @@ fn stripes_with_an_object_transformation() {
         &Point3::new(1.5, 0.0, 0.0),
         &Vec3::default(),
         &Vec3::default(),
-        false,
+        1.0,
     );
 
     assert_abs_diff!(c, Color::WHITE);
@@ -145,7 +151,7 @@ fn stripes_with_an_object_transformation() {
 fn stripes_with_a_pattern_transformation() {
     let obj = Object::new_with_material(
         Sphere,
-        Matrix::identity(4),
+        Matrix::identity(),
         Material {
             pattern: Pattern::new_with_transform(
                 PatternKind::Stripes(
@@ -167,7 +173,7 @@ fn stripes_with_a_pattern_transformation() {
         &Point3::new(1.5, 0.0, 0.0),
         &Vec3::default(),
         &Vec3::default(),
-        false,
+        1.0,
     );
 
     assert_abs_diff!(c, Color::WHITE);
@@ -199,7 +205,7 @@ fn stripes_with_both_an_object_and_a_pattern_transformation() {
         &Point3::new(2.5, 0.0, 0.0),
         &Vec3::default(),
         &Vec3::default(),
-        false,
+        1.0,
     );
 
     assert_abs_diff!(c, Color::WHITE);
@@ -297,3 +303,326 @@ fn checkers_should_repeat_in_z() {
         assert_eq!(pattern.color_at(&Point3::new(0.0, 0.0, z)), exp);
     }
 }
+
+fn assert_uv(shape: &dyn Shape, p: Point3, (u, v): (f32, f32)) {
+    let uv = shape.uv_at(&p);
+    assert!(
+        (uv.0 - u).abs() < 1e-4 && (uv.1 - v).abs() < 1e-4,
+        "{:?} at {:?} maps to {:?} instead of {:?}",
+        shape,
+        p,
+        uv,
+        (u, v)
+    );
+}
+
+#[test]
+fn using_a_spherical_mapping_on_a_3d_point() {
+    for &(p, uv) in &[
+        (Point3::new(0.0, 0.0, -1.0), (0.0, 0.5)),
+        (Point3::new(1.0, 0.0, 0.0), (0.25, 0.5)),
+        (Point3::new(0.0, 0.0, 1.0), (0.5, 0.5)),
+        (Point3::new(-1.0, 0.0, 0.0), (0.75, 0.5)),
+        (Point3::new(0.0, 1.0, 0.0), (0.5, 1.0)),
+        (Point3::new(0.0, -1.0, 0.0), (0.5, 0.0)),
+        (Point3::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0), (0.25, 0.75)),
+    ] {
+        assert_uv(&Sphere, p, uv);
+    }
+}
+
+#[test]
+fn using_a_planar_mapping_on_a_3d_point() {
+    let triangle = Triangle::new(Point3::default(), Point3::default(), Point3::default());
+
+    for &(p, uv) in &[
+        (Point3::new(0.25, 0.0, 0.5), (0.25, 0.5)),
+        (Point3::new(0.25, 0.0, -0.25), (0.25, 0.75)),
+        (Point3::new(0.25, 0.5, -0.25), (0.25, 0.75)),
+        (Point3::new(1.25, 0.0, 0.5), (0.25, 0.5)),
+        (Point3::new(0.25, 0.0, -1.75), (0.25, 0.25)),
+        (Point3::new(1.0, 0.0, -1.0), (0.0, 0.0)),
+        (Point3::new(0.0, 0.0, 0.0), (0.0, 0.0)),
+    ] {
+        assert_uv(&Plane, p, uv);
+        assert_uv(&triangle, p, uv);
+    }
+}
+
+#[test]
+fn using_a_cylindrical_mapping_on_a_3d_point() {
+    for &(p, uv) in &[
+        (Point3::new(0.0, 0.0, -1.0), (0.0, 0.0)),
+        (Point3::new(0.0, 0.5, -1.0), (0.0, 0.5)),
+        (Point3::new(0.0, 1.0, -1.0), (0.0, 0.0)),
+        (
+            Point3::new(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2),
+            (0.125, 0.5),
+        ),
+        (Point3::new(1.0, 0.5, 0.0), (0.25, 0.5)),
+        (Point3::new(FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), (0.375, 0.5)),
+        (Point3::new(0.0, -0.25, 1.0), (0.5, 0.75)),
+        (
+            Point3::new(-FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2),
+            (0.625, 0.5),
+        ),
+        (Point3::new(-1.0, 1.25, 0.0), (0.75, 0.25)),
+        (
+            Point3::new(-FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2),
+            (0.875, 0.5),
+        ),
+    ] {
+        assert_uv(&Cylinder::default(), p, uv);
+        assert_uv(&Cone::default(), p, uv);
+    }
+}
+
+#[test]
+fn using_a_cube_mapping_on_a_3d_point() {
+    for &(p, uv) in &[
+        (Point3::new(-1.0, 0.5, -0.5), (0.0625, 0.58333)),
+        (Point3::new(-0.5, 0.5, 1.0), (0.3125, 0.58333)),
+        (Point3::new(1.0, 0.5, -0.5), (0.6875, 0.58333)),
+        (Point3::new(0.5, 0.5, -1.0), (0.8125, 0.58333)),
+        (Point3::new(-0.5, 1.0, -0.5), (0.3125, 0.91667)),
+        (Point3::new(-0.5, -1.0, 0.5), (0.3125, 0.25)),
+    ] {
+        assert_uv(&Cube, p, uv);
+    }
+}
+
+#[test]
+fn using_a_quad_mapping_on_a_3d_point() {
+    let q = Quad::new(4.0, 2.0);
+    assert_uv(&q, Point3::new(1.0, 0.0, -0.5), (0.75, 0.25));
+    assert_uv(&q, Point3::new(-2.0, 0.0, 1.0), (0.0, 1.0));
+}
+
+/// Creates a 2x2 image pattern, with red, green, blue and white pixels from the top left.
+fn checkered_image(mapping: TextureMapping, filter: TextureFilter) -> Pattern {
+    let mut canvas = Canvas::new(2, 2);
+    canvas.put(0, 0, Color::new(1.0, 0.0, 0.0));
+    canvas.put(1, 0, Color::new(0.0, 1.0, 0.0));
+    canvas.put(0, 1, Color::new(0.0, 0.0, 1.0));
+    canvas.put(1, 1, Color::WHITE);
+
+    Pattern::new(PatternKind::image(&canvas, mapping, filter))
+}
+
+#[test]
+fn an_image_pattern_with_nearest_filtering() {
+    let pattern = checkered_image(TextureMapping::Planar, TextureFilter::Nearest);
+
+    for &(p, exp) in &[
+        (Point3::new(0.1, 0.0, 0.9), Color::new(1.0, 0.0, 0.0)),
+        (Point3::new(0.6, 0.0, 0.9), Color::new(0.0, 1.0, 0.0)),
+        (Point3::new(0.1, 0.0, 0.4), Color::new(0.0, 0.0, 1.0)),
+        (Point3::new(0.6, 0.0, 0.4), Color::WHITE),
+        (Point3::new(1.6, 0.0, -0.1), Color::new(0.0, 1.0, 0.0)),
+    ] {
+        assert_abs_diff!(pattern.color_at(&p), exp);
+    }
+}
+
+#[test]
+fn an_image_pattern_with_bilinear_filtering() {
+    let pattern = checkered_image(TextureMapping::Planar, TextureFilter::Bilinear);
+
+    for &(p, exp) in &[
+        // Pixel centers and edges of the image
+        (Point3::new(0.25, 0.0, 0.75), Color::new(1.0, 0.0, 0.0)),
+        (Point3::new(0.0, 0.0, 0.25), Color::new(0.0, 0.0, 1.0)),
+        // Between pixels
+        (Point3::new(0.5, 0.0, 0.75), Color::new(0.5, 0.5, 0.0)),
+        (Point3::new(0.5, 0.0, 0.5), Color::new(0.5, 0.5, 0.5)),
+    ] {
+        assert_abs_diff!(pattern.color_at(&p), exp);
+    }
+}
+
+#[test]
+fn an_image_pattern_is_mapped_like_its_shape() {
+    let on_shape = checkered_image(TextureMapping::Shape, TextureFilter::Nearest);
+    let spherical = checkered_image(TextureMapping::Spherical, TextureFilter::Nearest);
+    let p = Point3::new(0.0, 1.0, 0.0);
+
+    // The north pole of a sphere is on the top edge of the texture
+    assert_abs_diff!(on_shape.color_on(&Sphere, &p), Color::new(0.0, 1.0, 0.0));
+    assert_abs_diff!(spherical.color_at(&p), Color::new(0.0, 1.0, 0.0));
+
+    // Without a shape, the planar mapping is used
+    assert_abs_diff!(on_shape.color_at(&p), Color::new(0.0, 0.0, 1.0));
+
+    let m = Material {
+        pattern: on_shape,
+        ..Default::default()
+    };
+    assert_abs_diff!(m.color_on(&Sphere, &p), Color::new(0.0, 1.0, 0.0));
+}
+
+#[test]
+#[cfg(feature = "image")]
+fn an_image_pattern_from_a_decoded_image() {
+    let image = image::RgbImage::from_raw(2, 1, vec![255, 0, 0, 0, 0, 255]).unwrap();
+    let pattern = Pattern::new(PatternKind::from_image(
+        &image::DynamicImage::ImageRgb8(image),
+        TextureMapping::Planar,
+        TextureFilter::Nearest,
+    ));
+
+    assert_abs_diff!(
+        pattern.color_at(&Point3::new(0.25, 0.0, 0.5)),
+        Color::new(1.0, 0.0, 0.0)
+    );
+    assert_abs_diff!(
+        pattern.color_at(&Point3::new(0.75, 0.0, 0.5)),
+        Color::new(0.0, 0.0, 1.0)
+    );
+}
+
+#[test]
+fn creating_an_image_pattern_with_mismatched_pixels() {
+    for &(width, height, len) in &[(0, 1, 1), (2, 2, 3), (1, 1, 2)] {
+        assert_eq!(
+            PatternKind::try_image(
+                vec![Color::WHITE; len],
+                width,
+                height,
+                TextureMapping::Planar,
+                TextureFilter::Nearest,
+            ),
+            Err(Error::ImageSize { width, height, len })
+        );
+    }
+
+    assert!(PatternKind::try_image(
+        vec![Color::WHITE; 6],
+        3,
+        2,
+        TextureMapping::Planar,
+        TextureFilter::Bilinear,
+    )
+    .is_ok());
+}
+
+#[test]
+fn an_image_pattern_with_mismatched_pixels_is_black() {
+    for &(width, height, len) in &[(0, 1, 1), (1, 0, 1), (2, 2, 3)] {
+        for &filter in &[TextureFilter::Nearest, TextureFilter::Bilinear] {
+            let pattern = Pattern::new(PatternKind::Image {
+                pixels: vec![Color::WHITE; len],
+                width,
+                height,
+                mapping: TextureMapping::Planar,
+                filter,
+            });
+
+            assert_eq!(
+                pattern.color_on(&Sphere, &Point3::new(0.75, 0.0, 0.75)),
+                Color::BLACK
+            );
+        }
+    }
+}
+
+#[test]
+fn noise_is_zero_on_the_lattice() {
+    let noise = Noise::new(7);
+
+    for &p in &[
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(3.0, -2.0, 1.0),
+        Point3::new(-300.0, 12.0, 255.0),
+    ] {
+        assert_f32!(noise.at(&p), 0.0);
+    }
+}
+
+#[test]
+fn noise_only_depends_on_its_seed() {
+    let points = (0..1000)
+        .map(|i| {
+            let t = i as f32 * 0.37;
+            Point3::new(t, -t * 0.71, t * 1.13 - 50.0)
+        })
+        .collect::<Vec<_>>();
+
+    let a = Noise::new(1);
+    let b = Noise::new(1);
+    let c = Noise::new(2);
+
+    assert_eq!(a, b);
+    assert_eq!(a.seed(), 1);
+    assert!(points.iter().all(|p| a.at(p) == b.at(p)));
+    assert!(points.iter().any(|p| a.at(p) != c.at(p)));
+    assert!(points.iter().all(|p| (-1.0..=1.0).contains(&a.at(p))));
+}
+
+#[test]
+fn noise_is_smooth() {
+    let noise = Noise::new(3);
+    let p = Point3::new(1.3, 2.7, -0.4);
+
+    let delta = noise.at(&p) - noise.at(&(p + Vec3::new(1e-3, 1e-3, 1e-3)));
+    assert!(delta.abs() < 1e-2);
+}
+
+#[test]
+fn fractal_noise_with_a_single_octave() {
+    let noise = Noise::new(3);
+    let p = Point3::new(1.3, 2.7, -0.4);
+
+    assert_f32!(noise.fractal_at(&p, 1), noise.at(&p));
+    assert_f32!(noise.fractal_at(&p, 0), noise.at(&p));
+}
+
+#[test]
+fn a_perturbed_pattern_is_displaced_by_noise() {
+    let stripes = Pattern::new(PatternKind::Stripes(
+        Box::new(Pattern::new(Color::WHITE.into())),
+        Box::new(Pattern::new(Color::BLACK.into())),
+    ));
+    let perturbed = |scale| {
+        Pattern::new(PatternKind::Perturbed {
+            pattern: Box::new(stripes.clone()),
+            noise: Noise::new(5),
+            scale,
+        })
+    };
+
+    let points = (0..100)
+        .map(|i| Point3::new(i as f32 * 0.1 + 0.05, 0.3, 0.7))
+        .collect::<Vec<_>>();
+
+    let unchanged = perturbed(0.0);
+    assert!(points
+        .iter()
+        .all(|p| unchanged.color_at(p) == stripes.color_at(p)));
+
+    let perturbed = perturbed(0.5);
+    assert!(points
+        .iter()
+        .any(|p| perturbed.color_at(p) != stripes.color_at(p)));
+}
+
+#[test]
+fn a_noise_pattern_blends_two_colors() {
+    let (low, high) = (Color::new(0.2, 0.0, 0.0), Color::new(1.0, 0.5, 0.0));
+    let pattern = Pattern::new(PatternKind::Noise {
+        low,
+        high,
+        noise: Noise::new(11),
+        octaves: 4,
+    });
+
+    // The noise is zero on the lattice, halfway through the gradient
+    assert_abs_diff!(
+        pattern.color_at(&Point3::new(1.0, 2.0, 3.0)),
+        Color::new(0.6, 0.25, 0.0)
+    );
+
+    for i in 0..100 {
+        let c = pattern.color_at(&Point3::new(i as f32 * 0.13, 0.5, -0.25));
+        assert!(c.r >= low.r && c.r <= high.r && c.g >= low.g && c.g <= high.g);
+    }
+}
@@ -1,9 +1,13 @@
 use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2};
 
 use tracy::{
-    math::{Matrix, Point3, Vec3, EPSILON},
+    math::{Matrix, Point3, Rng, Vec3, EPSILON},
     query::{Object, Ray, World},
-    rendering::{Color, Material, Pattern, PatternKind, PointLight, DEFAULT_RECURSION_DEPTH},
+    rendering::{
+        Canvas, Color, Environment, Fresnel, Material, Pattern, PatternKind, PbrMaterial,
+        PointLight, RenderSettings, ShadingModel, TextureFilter, TextureMapping,
+        DEFAULT_RECURSION_DEPTH,
+    },
     shape::{Plane, Sphere},
 };
 pub use utils::*;
@@ -27,7 +31,7 @@ fn precomputing_the_reflection_vector() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - SQRT_2).abs() < EPSILON)
+        .hit_within(SQRT_2 - EPSILON..SQRT_2 + EPSILON)
         .unwrap();
 
     assert_abs_diff!(
@@ -38,7 +42,7 @@ fn precomputing_the_reflection_vector() {
 
 #[test]
 fn the_reflected_color_for_a_nonreflective_material() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     w.objects_mut().nth(1).unwrap().material_mut().ambient = 1.0;
 
@@ -46,7 +50,7 @@ fn the_reflected_color_for_a_nonreflective_material() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 1.0).abs() < EPSILON)
+        .hit_within(1.0 - EPSILON..1.0 + EPSILON)
         .unwrap();
 
     assert_eq!(
@@ -57,7 +61,7 @@ fn the_reflected_color_for_a_nonreflective_material() {
 
 #[test]
 fn the_reflected_color_for_a_reflective_material() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     w.add(Object::new_with_material(
         Plane,
@@ -75,7 +79,7 @@ fn the_reflected_color_for_a_reflective_material() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - SQRT_2).abs() < EPSILON)
+        .hit_within(SQRT_2 - EPSILON..SQRT_2 + EPSILON)
         .unwrap();
 
     assert_abs_diff!(
@@ -86,7 +90,7 @@ fn the_reflected_color_for_a_reflective_material() {
 
 #[test]
 fn shade_hit_with_a_reflective_material() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     w.add(Object::new_with_material(
         Plane,
@@ -104,7 +108,7 @@ fn shade_hit_with_a_reflective_material() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - SQRT_2).abs() < EPSILON)
+        .hit_within(SQRT_2 - EPSILON..SQRT_2 + EPSILON)
         .unwrap();
 
     assert_abs_diff!(
@@ -143,7 +147,7 @@ fn color_at_with_mutually_reflective_surfaces() {
 
 #[test]
 fn the_reflected_color_at_the_maximum_recursive_depth() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     w.add(Object::new_with_material(
         Plane,
@@ -161,7 +165,7 @@ fn the_reflected_color_at_the_maximum_recursive_depth() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - SQRT_2).abs() < EPSILON)
+        .hit_within(SQRT_2 - EPSILON..SQRT_2 + EPSILON)
         .unwrap();
 
     assert_eq!(w.reflected_color(&interference, 0), Color::BLACK);
@@ -176,7 +180,7 @@ fn transparency_and_refractive_index_for_the_default_material() {
 #[test]
 fn a_helper_for_producing_a_sphere_with_a_glassy_material() {
     let s = glass_sphere();
-    assert_eq!(s.transform(), &Matrix::identity(4));
+    assert_eq!(s.transform(), &Matrix::identity());
     assert_f32!(s.material().transparency, 1.0);
     assert_f32!(s.material().refractive_index, 1.5);
 }
@@ -232,7 +236,7 @@ fn the_under_point_is_offset_below_the_surface() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 5.0).abs() < EPSILON)
+        .hit_within(5.0 - EPSILON..5.0 + EPSILON)
         .unwrap();
 
     assert!(interference.under_point.z > EPSILON / 2.0);
@@ -241,12 +245,12 @@ fn the_under_point_is_offset_below_the_surface() {
 
 #[test]
 fn the_refracted_color_with_an_opaque_surface() {
-    let w = World::default();
+    let w = World::book_default();
     let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 4.0).abs() < EPSILON)
+        .hit_within(4.0 - EPSILON..4.0 + EPSILON)
         .unwrap();
 
     assert_eq!(
@@ -257,7 +261,7 @@ fn the_refracted_color_with_an_opaque_surface() {
 
 #[test]
 fn the_refracted_color_at_the_maximum_recursive_depth() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     let shape = w.objects_mut().next().unwrap();
     shape.material_mut().transparency = 1.0;
@@ -267,7 +271,7 @@ fn the_refracted_color_at_the_maximum_recursive_depth() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - 4.0).abs() < EPSILON)
+        .hit_within(4.0 - EPSILON..4.0 + EPSILON)
         .unwrap();
 
     assert_eq!(w.refracted_color(&interference, 0), Color::BLACK);
@@ -275,7 +279,7 @@ fn the_refracted_color_at_the_maximum_recursive_depth() {
 
 #[test]
 fn the_refracted_color_under_total_internal_reflection() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     let shape = w.objects_mut().next().unwrap();
     shape.material_mut().transparency = 1.0;
@@ -285,7 +289,7 @@ fn the_refracted_color_under_total_internal_reflection() {
 
     let interference = w
         .interferences_with_ray(&r)
-        .find(|i| (i.toi - FRAC_1_SQRT_2).abs() < EPSILON)
+        .hit_within(FRAC_1_SQRT_2 - EPSILON..FRAC_1_SQRT_2 + EPSILON)
         .unwrap();
 
     assert_eq!(
@@ -296,7 +300,7 @@ fn the_refracted_color_under_total_internal_reflection() {
 
 #[test]
 fn the_refracted_color_with_a_refracted_ray() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     let a = w.objects_mut().next().unwrap();
     a.material_mut().ambient = 1.0;
@@ -318,7 +322,7 @@ fn the_refracted_color_with_a_refracted_ray() {
 
 #[test]
 fn shade_hit_with_a_transparent_material() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     w.add(Object::new_with_material(
         Plane,
@@ -391,7 +395,7 @@ fn the_schlick_approximation_with_small_angle_and_n2_greater_than_n1() {
 
 #[test]
 fn shade_hit_with_a_reflective_transparent_material() {
-    let mut w = World::default();
+    let mut w = World::book_default();
 
     w.add(Object::new_with_material(
         Plane,
@@ -426,3 +430,498 @@ fn shade_hit_with_a_reflective_transparent_material() {
         Color::new(0.93391, 0.69643, 0.69243)
     );
 }
+
+#[test]
+fn shading_a_packet_of_rays() {
+    let mut w = World::book_default();
+    w.set_environment(Environment::Gradient {
+        bottom: Color::BLACK,
+        top: Color::WHITE,
+    });
+    w.add(glass_sphere());
+    w.add(Object::new_with_material(
+        Plane,
+        Matrix::from_translation(0., -1., 0.),
+        Material {
+            reflective: 0.5,
+            transparency: 0.5,
+            refractive_index: 1.5,
+            ..Default::default()
+        },
+    ));
+
+    let rays = [
+        Ray::new(Point3::new(0., 0., -5.), Vec3::unit_z()),
+        Ray::new(
+            Point3::new(0., 0., -3.),
+            Vec3::new(0., -0.7, 0.7).normalize(),
+        ),
+        Ray::new(
+            Point3::new(0.5, 0.5, -5.),
+            Vec3::new(-0.1, 0., 1.).normalize(),
+        ),
+        Ray::new(Point3::new(0., 0., -5.), Vec3::new(0., 1., 0.)),
+    ];
+
+    let colors = w.color_at_packet(&rays, 5);
+
+    for (color, r) in colors.iter().zip(&rays) {
+        assert_eq!(*color, w.color_at(r, 5));
+    }
+}
+
+#[test]
+fn the_default_environment_is_black() {
+    let w = World::new();
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_z());
+
+    assert_eq!(w.environment(), &Environment::Solid(Color::BLACK));
+    assert_abs_diff!(w.color_at(&r, DEFAULT_RECURSION_DEPTH), Color::BLACK);
+}
+
+#[test]
+fn the_color_of_a_gradient_environment() {
+    let env = Environment::Gradient {
+        bottom: Color::new(0.0, 0.0, 0.2),
+        top: Color::new(0.0, 0.0, 1.0),
+    };
+
+    assert_abs_diff!(env.color_at(&-Vec3::unit_y()), Color::new(0.0, 0.0, 0.2));
+    assert_abs_diff!(env.color_at(&Vec3::unit_x()), Color::new(0.0, 0.0, 0.6));
+    assert_abs_diff!(
+        env.color_at(&Vec3::new(0.0, 2.0, 0.0)),
+        Color::new(0.0, 0.0, 1.0)
+    );
+}
+
+#[test]
+fn the_color_of_a_spherical_environment() {
+    let mut canvas = Canvas::new(1, 2);
+    canvas.put(0, 0, Color::WHITE);
+
+    let env = Environment::Spherical(Pattern::new(PatternKind::image(
+        &canvas,
+        TextureMapping::Shape,
+        TextureFilter::Nearest,
+    )));
+
+    assert_abs_diff!(env.color_at(&Vec3::new(0.1, 1.0, 0.2)), Color::WHITE);
+    assert_abs_diff!(env.color_at(&Vec3::new(0.1, -1.0, 0.2)), Color::BLACK);
+}
+
+#[test]
+fn the_color_of_a_cube_environment() {
+    let env = Environment::Cube(Pattern::new(PatternKind::Test));
+
+    // Directions are projected on the surface of the cube
+    assert_abs_diff!(
+        env.color_at(&Vec3::new(2.0, 1.0, 0.0)),
+        Color::new(1.0, 0.5, 0.0)
+    );
+    assert_abs_diff!(
+        env.color_at(&Vec3::new(0.0, -0.5, -2.0)),
+        Color::new(0.0, -0.25, -1.0)
+    );
+}
+
+#[test]
+fn rays_missing_all_objects_see_the_environment() {
+    let mut w = World::book_default();
+    w.set_environment(Environment::Solid(Color::new(0.5, 0.7, 1.0)));
+
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::unit_y());
+
+    assert_abs_diff!(
+        w.color_at(&r, DEFAULT_RECURSION_DEPTH),
+        Color::new(0.5, 0.7, 1.0)
+    );
+    assert_abs_diff!(
+        w.trace_path(&r, DEFAULT_RECURSION_DEPTH, &mut Rng::new(0)),
+        Color::new(0.5, 0.7, 1.0)
+    );
+}
+
+#[test]
+fn a_mirror_reflects_the_environment() {
+    let mut w = World::new();
+    w.set_environment(Environment::Gradient {
+        bottom: Color::BLACK,
+        top: Color::WHITE,
+    });
+    w.add(Object::new_with_material(
+        Plane,
+        Matrix::identity(),
+        Material {
+            reflective: 1.0,
+            ..Default::default()
+        },
+    ));
+
+    let r = Ray::new(
+        Point3::new(0.0, 1.0, -1.0),
+        Vec3::new(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    );
+
+    // The reflected ray goes up at 45 degrees
+    assert_abs_diff!(
+        w.color_at(&r, DEFAULT_RECURSION_DEPTH),
+        Color::new(0.85355, 0.85355, 0.85355)
+    );
+}
+
+/// Creates a world made of a mirror floor lit by a white environment, seen at 45 degrees.
+fn mirror_floor(material: Material) -> (World, Ray) {
+    let mut w = World::new();
+    w.set_environment(Environment::Solid(Color::WHITE));
+    w.add(Object::new_with_material(
+        Plane,
+        Matrix::identity(),
+        Material {
+            reflective: 1.0,
+            ..material
+        },
+    ));
+
+    let r = Ray::new(
+        Point3::new(0.0, 1.0, -1.0),
+        Vec3::new(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    );
+
+    (w, r)
+}
+
+#[test]
+fn fresnel_model_and_metalness_for_the_default_material() {
+    let m = Material::default();
+
+    assert_eq!(m.fresnel, Fresnel::Transparent);
+    assert!(!m.metallic);
+}
+
+#[test]
+fn opaque_reflections_are_not_weighted_by_default() {
+    let (w, r) = mirror_floor(Material {
+        refractive_index: 1.5,
+        ..Default::default()
+    });
+
+    assert_abs_diff!(w.color_at(&r, DEFAULT_RECURSION_DEPTH), Color::WHITE);
+}
+
+#[test]
+fn weighting_opaque_reflections_with_the_schlick_approximation() {
+    let (w, r) = mirror_floor(Material {
+        refractive_index: 1.5,
+        fresnel: Fresnel::Schlick,
+        ..Default::default()
+    });
+
+    let hit = w.interferences_with_ray(&r).hit().unwrap();
+    assert_f32!(hit.schlick(), 0.04207);
+
+    assert_abs_diff!(
+        w.color_at(&r, DEFAULT_RECURSION_DEPTH),
+        Color::WHITE * hit.schlick()
+    );
+}
+
+#[test]
+fn shade_hit_without_the_fresnel_effect() {
+    let mut w = World::book_default();
+
+    let floor = w.add(Object::new_with_material(
+        Plane,
+        Matrix::from_translation(0.0, -1.0, 0.0),
+        Material {
+            reflective: 0.5,
+            transparency: 0.5,
+            refractive_index: 1.5,
+            fresnel: Fresnel::None,
+            ..Default::default()
+        },
+    ));
+
+    w.add(Object::new_with_material(
+        Sphere,
+        Matrix::from_translation(0.0, -3.5, -0.5),
+        Material {
+            pattern: Pattern::new(PatternKind::Solid(Color::new(1.0, 0.0, 0.0))),
+            ambient: 0.5,
+            ..Default::default()
+        },
+    ));
+
+    let r = Ray::new(
+        Point3::new(0.0, 0.0, -3.0),
+        Vec3::new(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    );
+
+    let hit = w
+        .interferences_with_ray(&r)
+        .find(|i| i.handle == floor)
+        .unwrap();
+
+    let mut surface = w.clone();
+    surface.get_mut(floor).unwrap().material_mut().reflective = 0.0;
+    surface.get_mut(floor).unwrap().material_mut().transparency = 0.0;
+
+    assert_abs_diff!(
+        w.shade_hit(&hit, DEFAULT_RECURSION_DEPTH),
+        surface.shade_hit(&hit, DEFAULT_RECURSION_DEPTH)
+            + w.reflected_color(&hit, DEFAULT_RECURSION_DEPTH)
+            + w.refracted_color(&hit, DEFAULT_RECURSION_DEPTH)
+    );
+}
+
+#[test]
+fn metallic_reflections_are_tinted_by_the_surface() {
+    let gold = Pattern::new(PatternKind::Solid(Color::new(1.0, 0.8, 0.2)));
+
+    let (w, r) = mirror_floor(Material {
+        pattern: gold.clone(),
+        metallic: true,
+        ..Default::default()
+    });
+    assert_abs_diff!(
+        w.color_at(&r, DEFAULT_RECURSION_DEPTH),
+        Color::new(1.0, 0.8, 0.2)
+    );
+
+    // Towards grazing angles, metals reflect more of every color
+    let (w, r) = mirror_floor(Material {
+        pattern: gold,
+        metallic: true,
+        fresnel: Fresnel::Schlick,
+        ..Default::default()
+    });
+    assert_abs_diff!(
+        w.color_at(&r, DEFAULT_RECURSION_DEPTH),
+        Color::new(1.0, 0.80043, 0.20172)
+    );
+}
+
+#[test]
+fn pbr_materials_tint_and_blur_reflections_with_their_own_parameters() {
+    let pbr = |metallic, roughness| {
+        ShadingModel::Pbr(PbrMaterial {
+            metallic,
+            roughness,
+        })
+    };
+    let gold = Material {
+        pattern: Pattern::new(PatternKind::Solid(Color::new(1.0, 0.8, 0.2))),
+        ..Default::default()
+    };
+
+    let m = Material {
+        metallic: true,
+        reflection_roughness: 0.3,
+        shading: pbr(0.5, 0.7),
+        ..gold.clone()
+    };
+    assert_f32!(m.metalness(), 0.5);
+    assert_f32!(m.reflection_blur(), 0.7);
+
+    // The metallic flag of the material is ignored
+    let (w, r) = mirror_floor(Material {
+        shading: pbr(0.5, 0.0),
+        ..m
+    });
+    assert_abs_diff!(
+        w.color_at(&r, DEFAULT_RECURSION_DEPTH),
+        Color::new(1.0, 0.9, 0.6)
+    );
+
+    let (w, r) = mirror_floor(Material {
+        shading: pbr(1.0, 0.0),
+        ..gold.clone()
+    });
+    assert_abs_diff!(
+        w.color_at(&r, DEFAULT_RECURSION_DEPTH),
+        Color::new(1.0, 0.8, 0.2)
+    );
+
+    // The roughness of the model blurs reflections instead of that of the material
+    let sky = Environment::Gradient {
+        bottom: Color::BLACK,
+        top: Color::WHITE,
+    };
+    let color_of = |material| {
+        let (mut w, r) = mirror_floor(material);
+        w.set_environment(sky.clone());
+        w.color_at(&r, DEFAULT_RECURSION_DEPTH)
+    };
+
+    assert_abs_diff!(
+        color_of(Material {
+            reflection_roughness: 0.3,
+            shading: pbr(0.0, 0.0),
+            ..Default::default()
+        }),
+        color_of(Material::default())
+    );
+    assert_abs_diff!(
+        color_of(Material {
+            shading: pbr(0.0, 0.3),
+            ..Default::default()
+        }),
+        color_of(Material {
+            reflection_roughness: 0.3,
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn reflections_are_sharp_by_default() {
+    assert_eq!(Material::default().reflection_roughness, 0.0);
+    assert_eq!(RenderSettings::default().glossy_samples, 8);
+}
+
+#[test]
+fn rough_reflections_of_a_uniform_environment() {
+    let (w, r) = mirror_floor(Material {
+        reflection_roughness: 0.5,
+        ..Default::default()
+    });
+
+    assert_abs_diff!(w.color_at(&r, DEFAULT_RECURSION_DEPTH), Color::WHITE);
+}
+
+#[test]
+fn rough_reflections_are_blurred() {
+    let sky = Environment::Gradient {
+        bottom: Color::BLACK,
+        top: Color::WHITE,
+    };
+
+    let (mut sharp, r) = mirror_floor(Material::default());
+    sharp.set_environment(sky.clone());
+    let sharp = sharp.color_at(&r, DEFAULT_RECURSION_DEPTH);
+
+    let (mut w, r) = mirror_floor(Material {
+        reflection_roughness: 0.3,
+        ..Default::default()
+    });
+    w.set_environment(sky);
+
+    let glossy = w.color_at(&r, DEFAULT_RECURSION_DEPTH);
+    assert_not_abs_diff!(glossy, sharp);
+    assert_eq!(glossy, w.color_at(&r, DEFAULT_RECURSION_DEPTH));
+
+    // A single sample disables the blur
+    w.set_render_settings(RenderSettings {
+        glossy_samples: 1,
+        ..Default::default()
+    });
+    assert_abs_diff!(w.color_at(&r, DEFAULT_RECURSION_DEPTH), sharp);
+}
+
+#[test]
+fn rough_reflections_stay_above_the_surface() {
+    let (mut w, r) = mirror_floor(Material {
+        reflection_roughness: 1.0,
+        ..Default::default()
+    });
+    w.set_environment(Environment::Gradient {
+        bottom: Color::BLACK,
+        top: Color::WHITE,
+    });
+    w.set_render_settings(RenderSettings {
+        glossy_samples: 64,
+        ..Default::default()
+    });
+
+    // Only the upper half of the sky is reflected
+    assert!(w.color_at(&r, DEFAULT_RECURSION_DEPTH).r > 0.5);
+}
+
+/// Creates a world made of a transparent floor under a sky, seen at 45 degrees.
+fn transparent_floor(material: Material) -> (World, Ray) {
+    let mut w = World::new();
+    w.set_environment(Environment::Gradient {
+        bottom: Color::BLACK,
+        top: Color::WHITE,
+    });
+    w.add(Object::new_with_material(
+        Plane,
+        Matrix::identity(),
+        Material {
+            transparency: 1.0,
+            ..material
+        },
+    ));
+
+    let r = Ray::new(
+        Point3::new(0.0, 1.0, -1.0),
+        Vec3::new(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    );
+
+    (w, r)
+}
+
+#[test]
+fn refractions_are_sharp_by_default() {
+    assert_eq!(Material::default().refraction_roughness, 0.0);
+    assert_eq!(RenderSettings::default().translucency_samples, 8);
+}
+
+#[test]
+fn rough_refractions_are_blurred() {
+    let (sharp, r) = transparent_floor(Material::default());
+    let sharp = sharp.color_at(&r, DEFAULT_RECURSION_DEPTH);
+
+    // The refracted ray goes straight through the floor
+    assert_abs_diff!(sharp, Color::new(0.14645, 0.14645, 0.14645));
+
+    let (mut w, r) = transparent_floor(Material {
+        refraction_roughness: 0.3,
+        ..Default::default()
+    });
+
+    let frosted = w.color_at(&r, DEFAULT_RECURSION_DEPTH);
+    assert_not_abs_diff!(frosted, sharp);
+    assert_eq!(frosted, w.color_at(&r, DEFAULT_RECURSION_DEPTH));
+
+    // A single sample disables the blur
+    w.set_render_settings(RenderSettings {
+        translucency_samples: 1,
+        ..Default::default()
+    });
+    assert_abs_diff!(w.color_at(&r, DEFAULT_RECURSION_DEPTH), sharp);
+}
+
+#[test]
+fn rough_refractions_stay_below_the_surface() {
+    let (mut w, r) = transparent_floor(Material {
+        refraction_roughness: 1.0,
+        ..Default::default()
+    });
+    w.set_render_settings(RenderSettings {
+        translucency_samples: 64,
+        ..Default::default()
+    });
+
+    // Only the lower half of the sky is seen through the floor
+    assert!(w.color_at(&r, DEFAULT_RECURSION_DEPTH).r < 0.5);
+}
+
+#[test]
+fn shade_hit_blends_rough_reflections_and_refractions() {
+    let (w, r) = transparent_floor(Material {
+        reflective: 0.5,
+        reflection_roughness: 0.2,
+        refraction_roughness: 0.4,
+        refractive_index: 1.5,
+        ..Default::default()
+    });
+
+    let hit = w.interferences_with_ray(&r).hit().unwrap();
+    let reflectance = hit.schlick();
+
+    assert_abs_diff!(
+        w.shade_hit(&hit, DEFAULT_RECURSION_DEPTH),
+        w.reflected_color(&hit, DEFAULT_RECURSION_DEPTH) * reflectance
+            + w.refracted_color(&hit, DEFAULT_RECURSION_DEPTH) * (1.0 - reflectance)
+    );
+}
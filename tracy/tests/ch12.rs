@@ -1,6 +1,6 @@
 use tracy::{
     math::{Point3, Vec3, EPSILON},
-    query::{Ray, World},
+    query::{intersect_aabb, Ray, RayPacket, World},
 };
 pub use utils::*;
 
@@ -48,6 +48,69 @@ fn a_ray_misses_a_cube() {
     }
 }
 
+#[test]
+fn a_packet_of_rays_intersects_a_box() {
+    let rays = [
+        Ray::new(Point3::new(5., 0.5, 0.), Vec3::new(-1., 0., 0.)),
+        Ray::new(Point3::new(0.5, -5., 0.), Vec3::new(0., 1., 0.)),
+        Ray::new(Point3::new(0.5, 0., 5.), Vec3::new(0., 0., -1.)),
+        Ray::new(Point3::new(0., 0.5, 0.), Vec3::new(0., 0., 1.)),
+        Ray::new(Point3::new(-2., 0., 0.), Vec3::new(0.2673, 0.5345, 0.8018)),
+        Ray::new(Point3::new(2., 0., 2.), Vec3::new(0., 0., -1.)),
+        Ray::new(Point3::new(0., 2., 2.), Vec3::new(0., -1., 0.)),
+        Ray::new(Point3::new(3., 0.5, 0.), Vec3::new(-1., 0., 0.)),
+    ];
+
+    let hits = intersect_aabb(
+        &RayPacket::new(&rays),
+        &Point3::new(-1., -1., -1.),
+        &Point3::new(1., 1., 3.),
+    );
+
+    assert_eq!(
+        hits.mask,
+        [true, true, true, true, false, false, true, true]
+    );
+    for &(lane, near, far) in &[
+        (0, 4., 6.),
+        (1, 4., 6.),
+        (2, 2., 6.),
+        (3, -1., 3.),
+        (6, 1., 3.),
+        (7, 2., 4.),
+    ] {
+        assert_f32!(hits.near[lane], near);
+        assert_f32!(hits.far[lane], far);
+    }
+}
+
+#[test]
+fn intersecting_a_cube_with_a_packet_of_rays() {
+    let mut w = World::new();
+    w.add(cube());
+
+    let rays = [
+        Ray::new(Point3::new(5., 0.5, 0.), Vec3::new(-1., 0., 0.)),
+        Ray::new(Point3::new(0.5, 0., -5.), Vec3::new(0., 0., 1.)),
+        Ray::new(Point3::new(0., -2., 0.), Vec3::new(0.8018, 0.2673, 0.5345)),
+        Ray::new(
+            Point3::new(-3., 2., -1.),
+            Vec3::new(1., -0.5, 0.3).normalize(),
+        ),
+    ];
+
+    for (xs, r) in w.interferences_with_packet(&rays).iter_mut().zip(&rays) {
+        let expected = w.interferences_with_ray(r).collect::<Vec<_>>();
+        let actual = xs.collect::<Vec<_>>();
+
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(&expected) {
+            assert_eq!(a.toi, e.toi);
+            assert_eq!(a.normal, e.normal);
+        }
+    }
+}
+
 #[test]
 fn the_normal_on_the_surface_of_a_cube() {
     for &(point, normal) in &[
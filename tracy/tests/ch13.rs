@@ -1,7 +1,7 @@
 use tracy::{
     math::{Point3, Vec3, EPSILON},
     query::{Ray, RayCast},
-    shape::Cylinder,
+    shape::{Cone, Cylinder},
 };
 pub use utils::*;
 
@@ -135,3 +135,102 @@ fn the_normal_vector_on_a_cylinder_end_caps() {
             .any(|x| x.normal.abs_diff_eq(&normal, EPSILON)));
     }
 }
+
+#[test]
+fn intersecting_a_cone_with_a_ray() {
+    for &(origin, dir, t1, t2) in &[
+        (Point3::new(0.0, 0.0, -5.0), Vec3::unit_z(), 5.0, 5.0),
+        (
+            Point3::new(0.0, 0.0, -5.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            8.66025,
+            8.66025,
+        ),
+        (
+            Point3::new(1.0, 1.0, -5.0),
+            Vec3::new(-0.5, -1.0, 1.0),
+            4.55006,
+            49.44994,
+        ),
+    ] {
+        let cone = Cone::default();
+        let r = Ray::new(origin, dir.normalize());
+
+        let mut xs = cone.intersections_in_local_space(&r);
+        assert!((xs.next().unwrap().toi - t1).abs() < 1e-3);
+        assert!((xs.next().unwrap().toi - t2).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn intersecting_a_cone_with_a_ray_parallel_to_one_of_its_halves() {
+    let cone = Cone::default();
+    let r = Ray::new(
+        Point3::new(0.0, 0.0, -1.0),
+        Vec3::new(0.0, 1.0, 1.0).normalize(),
+    );
+
+    let mut xs = cone.intersections_in_local_space(&r);
+    assert!((xs.next().unwrap().toi - 0.35355).abs() < 1e-3);
+    assert!(xs.next().is_none());
+}
+
+#[test]
+fn intersecting_the_caps_of_a_closed_cone() {
+    let mut cone = Cone::default();
+    cone.set_bottom(-0.5);
+    cone.set_top(0.5);
+    cone.set_closed(true);
+
+    for &(point, dir, count) in &[
+        (Point3::new(0.0, 0.0, -5.0), Vec3::unit_y(), 0),
+        (Point3::new(0.0, 0.0, -0.25), Vec3::new(0.0, 1.0, 1.0), 2),
+        (Point3::new(0.0, 0.0, -0.25), Vec3::unit_y(), 4),
+    ] {
+        let r = Ray::new(point, dir.normalize());
+
+        assert_eq!(cone.intersections_in_local_space(&r).count(), count)
+    }
+}
+
+#[test]
+fn computing_the_normal_vector_on_a_cone() {
+    let mut cone = Cone::default();
+    cone.set_bottom(-2.0);
+    cone.set_top(2.0);
+    cone.set_closed(true);
+
+    for &(origin, dir, normal) in &[
+        (
+            Point3::new(0.0, 1.0, -5.0),
+            Vec3::unit_z(),
+            Vec3::new(0.0, -1.0, -1.0),
+        ),
+        (
+            Point3::new(-5.0, -1.0, 0.0),
+            Vec3::unit_x(),
+            Vec3::new(-1.0, 1.0, 0.0),
+        ),
+        (Point3::new(0.5, 5.0, 0.0), -Vec3::unit_y(), Vec3::unit_y()),
+        (Point3::new(0.5, -5.0, 0.0), Vec3::unit_y(), -Vec3::unit_y()),
+    ] {
+        let r = Ray::new(origin, dir);
+
+        let x = cone
+            .intersections_in_local_space(&r)
+            .min_by(|a, b| a.toi.total_cmp(&b.toi))
+            .unwrap();
+        assert_abs_diff!(x.normal.normalize(), normal.normalize());
+    }
+}
+
+#[test]
+fn the_normal_vector_at_the_apex_of_a_cone() {
+    let cone = Cone::default();
+    let r = Ray::new(Point3::new(0.0, -5.0, 0.0), Vec3::unit_y());
+
+    for x in cone.intersections_in_local_space(&r) {
+        assert_f32!(x.toi, 5.0);
+        assert_abs_diff!(x.normal, Vec3::unit_y());
+    }
+}
@@ -0,0 +1,315 @@
+use std::io;
+
+use tracy::{
+    io::{obj, stl},
+    math::{Point3, Vec3},
+    query::{Aabb, Ray, RayCast},
+    shape::{Mesh, Shape, Triangle},
+};
+pub use utils::*;
+
+mod utils;
+
+fn triangle() -> Triangle {
+    Triangle::new(
+        Point3::new(0.0, 1.0, 0.0),
+        Point3::new(-1.0, 0.0, 0.0),
+        Point3::new(1.0, 0.0, 0.0),
+    )
+}
+
+#[test]
+fn constructing_a_triangle() {
+    let t = triangle();
+    assert_eq!(t.vertices()[0], Point3::new(0.0, 1.0, 0.0));
+    assert_abs_diff!(t.normal(), Vec3::new(0.0, 0.0, -1.0));
+    assert!(!t.is_degenerate());
+}
+
+#[test]
+fn finding_the_normal_on_a_triangle() {
+    let t = triangle();
+    let r = Ray::new(Point3::new(0.0, 0.5, -2.0), Vec3::unit_z());
+
+    let x = t.intersections_in_local_space(&r).next().unwrap();
+    assert_abs_diff!(x.normal, t.normal());
+}
+
+#[test]
+fn intersecting_a_ray_parallel_to_the_triangle() {
+    let r = Ray::new(Point3::new(0.0, -1.0, -2.0), Vec3::unit_y());
+    assert_eq!(triangle().intersections_in_local_space(&r).count(), 0);
+}
+
+#[test]
+fn a_ray_misses_the_edges_of_a_triangle() {
+    for &origin in &[
+        Point3::new(1.0, 1.0, -2.0),
+        Point3::new(-1.0, 1.0, -2.0),
+        Point3::new(0.0, -1.0, -2.0),
+    ] {
+        let r = Ray::new(origin, Vec3::unit_z());
+        assert_eq!(triangle().intersections_in_local_space(&r).count(), 0);
+    }
+}
+
+#[test]
+fn a_ray_strikes_a_triangle() {
+    let r = Ray::new(Point3::new(0.0, 0.5, -2.0), Vec3::unit_z());
+
+    let mut xs = triangle().intersections_in_local_space(&r);
+    assert_f32!(xs.next().unwrap().toi, 2.0);
+    assert!(xs.next().is_none());
+}
+
+#[test]
+fn intersecting_a_mesh() {
+    let back = Triangle::new(
+        Point3::new(0.0, 1.0, 1.0),
+        Point3::new(-1.0, 0.0, 1.0),
+        Point3::new(1.0, 0.0, 1.0),
+    );
+    let flat = Triangle::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(1.0, 0.0, 0.0),
+        Point3::new(2.0, 0.0, 0.0),
+    );
+    let mesh = Mesh::new(vec![triangle(), back, flat]);
+
+    assert_eq!(mesh.triangles().len(), 2);
+    assert_eq!(
+        mesh.local_bounds(),
+        Aabb::new(Point3::new(-1.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0))
+    );
+
+    let r = Ray::new(Point3::new(0.0, 0.5, -2.0), Vec3::unit_z());
+    let mut tois = mesh
+        .intersections_in_local_space(&r)
+        .map(|x| x.toi)
+        .collect::<Vec<_>>();
+    tois.sort_by(f32::total_cmp);
+    assert_eq!(tois, vec![2.0, 3.0]);
+
+    let r = Ray::new(Point3::new(5.0, 0.5, -2.0), Vec3::unit_z());
+    assert_eq!(mesh.intersections_in_local_space(&r).count(), 0);
+}
+
+#[test]
+fn finding_the_normal_on_a_mesh() {
+    let floor = Triangle::new(
+        Point3::new(-1.0, 0.0, 0.0),
+        Point3::new(0.0, 0.0, 1.0),
+        Point3::new(1.0, 0.0, 0.0),
+    );
+    let mesh = Mesh::new(vec![triangle(), floor]);
+
+    assert_abs_diff!(
+        mesh.local_normal_at(&Point3::new(0.0, 0.5, 0.0)),
+        Vec3::new(0.0, 0.0, -1.0)
+    );
+    assert_abs_diff!(
+        mesh.local_normal_at(&Point3::new(0.2, 0.0, 0.5)),
+        Vec3::new(0.0, -1.0, 0.0)
+    );
+    assert_abs_diff!(
+        mesh.local_normal_at(&Point3::new(0.0, 0.2, -0.5)),
+        Vec3::new(0.0, 0.0, -1.0)
+    );
+}
+
+#[test]
+fn querying_a_large_mesh() {
+    let triangles = (0..16)
+        .flat_map(|x| (0..16).map(move |z| (x as f32, z as f32)))
+        .map(|(x, z)| {
+            Triangle::new(
+                Point3::new(x, 0.0, z),
+                Point3::new(x + 1.0, 0.0, z),
+                Point3::new(x, 1.0 + x * 0.1, z + 0.5),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mesh = Mesh::new(triangles.clone());
+
+    let tois_of = |xs: &mut dyn Iterator<Item = f32>| {
+        let mut tois = xs.collect::<Vec<_>>();
+        tois.sort_by(f32::total_cmp);
+        tois
+    };
+
+    for &(origin, dir) in &[
+        (Point3::new(3.2, 0.3, -1.0), Vec3::unit_z()),
+        (Point3::new(-1.0, 0.2, 7.1), Vec3::new(1.0, 0.0, 0.1)),
+        (Point3::new(8.3, 5.0, 4.2), Vec3::new(0.1, -1.0, 0.2)),
+        (Point3::new(20.0, 0.5, 0.0), Vec3::unit_z()),
+    ] {
+        let r = Ray::new(origin, dir);
+
+        assert_eq!(
+            tois_of(&mut mesh.intersections_in_local_space(&r).map(|x| x.toi)),
+            tois_of(
+                &mut triangles
+                    .iter()
+                    .flat_map(|t| t.intersections_in_local_space(&r))
+                    .map(|x| x.toi)
+            )
+        );
+    }
+
+    for t in triangles.iter().step_by(37) {
+        let [a, b, c] = t.vertices();
+        let centroid = Point3::new(
+            (a.x + b.x + c.x) / 3.0,
+            (a.y + b.y + c.y) / 3.0,
+            (a.z + b.z + c.z) / 3.0,
+        );
+
+        assert_abs_diff!(mesh.local_normal_at(&centroid), t.normal());
+    }
+}
+
+const ASCII_STL: &str = "solid pyramid
+  facet normal 0 0 0
+    outer loop
+      vertex 0 1 0
+      vertex -1 0 0
+      vertex 1 0 0
+    endloop
+  endfacet
+  facet normal 0 0 0
+    outer loop
+      vertex 0 1 1
+      vertex -1 0 1
+      vertex 1.0e0 0 1
+    endloop
+  endfacet
+endsolid pyramid
+";
+
+#[test]
+fn parsing_an_ascii_stl_file() {
+    let mesh = stl::parse(ASCII_STL.as_bytes()).unwrap();
+
+    assert_eq!(mesh.triangles().len(), 2);
+    assert_eq!(mesh.triangles()[0], triangle());
+    assert_eq!(
+        mesh.triangles()[1].vertices()[2],
+        Point3::new(1.0, 0.0, 1.0)
+    );
+}
+
+#[test]
+fn parsing_a_binary_stl_file() {
+    // Binary files may start with `solid` too
+    let mut data = b"solid but binary".to_vec();
+    data.resize(80, 0);
+    data.extend_from_slice(&1u32.to_le_bytes());
+    data.extend_from_slice(&[0; 12]);
+    for v in triangle().vertices() {
+        for c in &[v.x, v.y, v.z] {
+            data.extend_from_slice(&c.to_le_bytes());
+        }
+    }
+    data.extend_from_slice(&[0; 2]);
+
+    let mesh = stl::parse(&data).unwrap();
+    assert_eq!(mesh.triangles(), &[triangle()]);
+}
+
+#[test]
+fn parsing_invalid_stl_files() {
+    for data in &[
+        &b"not a model"[..],
+        ASCII_STL.replace("vertex 0 1 1", "vertex 0 1").as_bytes(),
+        ASCII_STL.replace("vertex 0 1 0", "").as_bytes(),
+        &[0; 90][..],
+    ] {
+        assert_eq!(
+            stl::parse(data).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
+
+#[test]
+fn loading_an_stl_file() {
+    let path = std::env::temp_dir().join(format!("tracy-{}.stl", std::process::id()));
+    std::fs::write(&path, ASCII_STL).unwrap();
+
+    let obj = stl::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(obj.shape().local_bounds().max, Point3::new(1.0, 1.0, 1.0));
+    assert!(stl::load(&path).is_err());
+}
+
+const OBJ: &str = "# A pyramid and a quad
+v 0 1 0
+v -1 0 0
+v 1 0 0
+vt 0 0
+vn 0 0 -1
+
+g quad
+v -1 0 1
+v 1 0 1
+v 1 1 1
+v -1 1 1
+f 1 2 3
+f 4/1 5/1/1 6//1 -1
+";
+
+#[test]
+fn parsing_an_obj_file() {
+    let mesh = obj::parse(OBJ).unwrap();
+
+    assert_eq!(mesh.triangles().len(), 3);
+    assert_eq!(mesh.triangles()[0], triangle());
+    assert_eq!(
+        mesh.triangles()[1],
+        Triangle::new(
+            Point3::new(-1.0, 0.0, 1.0),
+            Point3::new(1.0, 0.0, 1.0),
+            Point3::new(1.0, 1.0, 1.0),
+        )
+    );
+    assert_eq!(
+        mesh.triangles()[2],
+        Triangle::new(
+            Point3::new(-1.0, 0.0, 1.0),
+            Point3::new(1.0, 1.0, 1.0),
+            Point3::new(-1.0, 1.0, 1.0),
+        )
+    );
+}
+
+#[test]
+fn parsing_invalid_obj_files() {
+    for data in &[
+        OBJ.replace("v 1 0 0", "v 1 0"),
+        OBJ.replace("f 1 2 3", "f 1 2"),
+        OBJ.replace("f 1 2 3", "f 1 2 8"),
+        OBJ.replace("f 1 2 3", "f 1 2 -8"),
+        OBJ.replace("f 1 2 3", "f 1 2 x"),
+    ] {
+        assert_eq!(
+            obj::parse(data).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
+
+#[test]
+fn loading_an_obj_file() {
+    let path = std::env::temp_dir().join(format!("tracy-{}.obj", std::process::id()));
+    std::fs::write(&path, OBJ).unwrap();
+
+    let object = obj::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        object.shape().local_bounds().max,
+        Point3::new(1.0, 1.0, 1.0)
+    );
+    assert!(obj::load(&path).is_err());
+}
@@ -1,7 +1,7 @@
 use std::sync::Mutex;
 
 use tracy::{
-    math::Matrix,
+    math::{Matrix, Point3, Vec3},
     query::{Object, Ray, RayCast, RayIntersection, RayIntersections},
     rendering::Material,
     shape::{Cube, Plane, Shape, Sphere},
@@ -19,7 +19,18 @@ pub struct TestShape {
 }
 
 #[typetag::serde]
-impl Shape for TestShape {}
+impl Shape for TestShape {
+    fn local_normal_at(&self, p: &Point3) -> Vec3 {
+        (*p).into()
+    }
+}
+
+// The saved ray is only a probe of the last query, not part of the shape itself
+impl PartialEq for TestShape {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
 
 impl RayCast for TestShape {
     fn intersections_in_local_space(&self, ray: &Ray) -> RayIntersections {
@@ -58,14 +69,14 @@ macro_rules! assert_not_abs_diff {
 
 /// Creates a default unit sphere centered in the origin.
 pub fn sphere() -> Object {
-    Object::new(Sphere, Matrix::identity(4))
+    Object::new(Sphere, Matrix::identity())
 }
 
 /// Creates a sphere with a glassy texture.
 pub fn glass_sphere() -> Object {
     Object::new_with_material(
         Sphere,
-        Matrix::identity(4),
+        Matrix::identity(),
         Material {
             transparency: 1.0,
             refractive_index: 1.5,
@@ -76,12 +87,12 @@ pub fn glass_sphere() -> Object {
 
 /// Creates a default plane.
 pub fn plane() -> Object {
-    Object::new(Plane, Matrix::identity(4))
+    Object::new(Plane, Matrix::identity())
 }
 
 /// Creates a default unit cube centered in the origin.
 pub fn cube() -> Object {
-    Object::new(Cube, Matrix::identity(4))
+    Object::new(Cube, Matrix::identity())
 }
 
 /// Creates a test shape centered in the origin.
@@ -90,6 +101,6 @@ pub fn test_shape() -> Object {
         TestShape {
             saved_ray: Mutex::new(None),
         },
-        Matrix::identity(4),
+        Matrix::identity(),
     )
 }